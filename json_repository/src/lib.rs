@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
+use soda_core::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+
+mod product_catalog;
+mod sales_ledger;
+pub use product_catalog::JsonFileProductCatalog;
+pub use sales_ledger::JsonFileSalesLedger;
+
+/// Version of the file layout, bumped whenever a stored machine can no longer be read as is
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// What is written to each machine's file
+#[derive(Serialize, Deserialize)]
+struct MachineFile {
+    schema_version: u32,
+    machine: SodaMachine,
+}
+
+/// Stores every machine as a JSON file in a directory, one file per machine
+///
+/// Files are written to a temporary file first and then renamed over the old one,
+/// so a crash never leaves a half-written machine behind.
+pub struct JsonFileSodaMachineRepository {
+    directory: PathBuf,
+    /// Serializes load-compare-write so two saves cannot both pass the version check
+    write_lock: Mutex<()>,
+}
+
+impl JsonFileSodaMachineRepository {
+    /// Opens the repository, creating the directory if needed
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self, RepositoryError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory).map_err(Self::io_error)?;
+
+        Ok(JsonFileSodaMachineRepository { directory, write_lock: Mutex::new(()) })
+    }
+
+    /// Gets the directory the machines are stored in
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Gets the file a machine is stored in
+    pub fn path_for(&self, id: SodaMachineId) -> PathBuf {
+        self.directory.join(format!("machine-{}.json", id.value()))
+    }
+
+    /// Gets the IDs of all stored machines from the file names, ordered by ID
+    fn stored_ids(&self) -> Result<Vec<SodaMachineId>, RepositoryError> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.directory).map_err(Self::io_error)? {
+            let file_name = entry.map_err(Self::io_error)?.file_name();
+            let id = file_name.to_str()
+                .and_then(|name| name.strip_prefix("machine-"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|id| id.parse().ok());
+
+            if let Some(id) = id {
+                ids.push(SodaMachineId::new(id));
+            }
+        }
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, ()>, RepositoryError> {
+        self.write_lock.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+
+    fn read(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
+        let contents = match fs::read_to_string(self.path_for(id)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Self::io_error(e)),
+        };
+
+        let file: MachineFile = serde_json::from_str(&contents)
+            .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+
+        if file.schema_version != JSON_SCHEMA_VERSION {
+            return Err(RepositoryError::Other(Box::new(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Machine {} is stored with schema version {}, expected {}",
+                    id, file.schema_version, JSON_SCHEMA_VERSION
+                ),
+            ))));
+        }
+
+        Ok(Some(file.machine))
+    }
+
+    /// Writes the machine with its events committed, replacing the old file atomically
+    fn write(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let mut stored = machine.clone();
+        stored.mark_events_committed();
+
+        let contents = serde_json::to_vec_pretty(&MachineFile { schema_version: JSON_SCHEMA_VERSION, machine: stored })
+            .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+
+        let path = self.path_for(machine.id());
+        let temporary_path = path.with_extension("json.tmp");
+
+        let mut file = fs::File::create(&temporary_path).map_err(Self::io_error)?;
+        file.write_all(&contents).map_err(Self::io_error)?;
+        file.sync_all().map_err(Self::io_error)?;
+        fs::rename(&temporary_path, &path).map_err(Self::io_error)?;
+
+        Ok(())
+    }
+
+    fn io_error(err: io::Error) -> RepositoryError {
+        RepositoryError::ConnectionError(format!("File system error: {}", err))
+    }
+}
+
+#[async_trait]
+impl SodaMachineRepository for JsonFileSodaMachineRepository {
+    async fn find_by_id(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
+        self.read(id)
+    }
+
+    async fn save(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let _guard = self.lock()?;
+
+        if let Some(stored) = self.read(machine.id())?
+            && stored.version() != machine.committed_version()
+        {
+            return Err(RepositoryError::ConcurrencyConflict {
+                machine_id: machine.id(),
+                expected_version: machine.committed_version(),
+                actual_version: stored.version(),
+            });
+        }
+
+        self.write(machine)
+    }
+
+    async fn create(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let _guard = self.lock()?;
+
+        if self.path_for(machine.id()).exists() {
+            return Err(RepositoryError::Other(Box::new(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "Machine already exists",
+            ))));
+        }
+
+        self.write(machine)
+    }
+
+    async fn list(&self) -> Result<Vec<SodaMachine>, RepositoryError> {
+        let mut machines = Vec::new();
+        for id in self.stored_ids()? {
+            machines.extend(self.read(id)?);
+        }
+
+        Ok(machines)
+    }
+
+    async fn delete(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let _guard = self.lock()?;
+
+        match fs::remove_file(self.path_for(id)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(Self::io_error(e)),
+        }
+    }
+
+    async fn exists(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        Ok(self.path_for(id).exists())
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use soda_core::domain::entities::product::{Product, Sku};
+use soda_core::ports::driven::product_catalog_port::ProductCatalogRepository;
+use soda_core::ports::driven::soda_machine_repository_port::RepositoryError;
+
+use crate::JSON_SCHEMA_VERSION;
+
+/// What is written to the catalog file
+#[derive(Serialize, Deserialize)]
+struct CatalogFile {
+    schema_version: u32,
+    products: Vec<Product>,
+}
+
+/// Stores the whole product catalog in one JSON file, ordered by SKU
+///
+/// Like the machine files, the catalog is written to a temporary file first and then renamed.
+pub struct JsonFileProductCatalog {
+    path: PathBuf,
+    /// Serializes read-modify-write so two saves cannot overwrite each other's products
+    write_lock: Mutex<()>,
+}
+
+impl JsonFileProductCatalog {
+    /// Opens the catalog file, creating its directory if needed, the file itself is written on the first save
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, RepositoryError> {
+        let path = path.into();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(io_error)?;
+        }
+
+        Ok(JsonFileProductCatalog { path, write_lock: Mutex::new(()) })
+    }
+
+    /// Gets the file the catalog is stored in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, ()>, RepositoryError> {
+        self.write_lock.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+
+    fn read(&self) -> Result<Vec<Product>, RepositoryError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        };
+
+        let file: CatalogFile = serde_json::from_str(&contents)
+            .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+
+        if file.schema_version != JSON_SCHEMA_VERSION {
+            return Err(RepositoryError::Other(Box::new(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "The catalog is stored with schema version {}, expected {}",
+                    file.schema_version, JSON_SCHEMA_VERSION
+                ),
+            ))));
+        }
+
+        Ok(file.products)
+    }
+
+    /// Writes the products ordered by SKU, replacing the old file atomically
+    fn write(&self, mut products: Vec<Product>) -> Result<(), RepositoryError> {
+        products.sort_by(|a, b| a.sku().cmp(b.sku()));
+
+        let contents = serde_json::to_vec_pretty(&CatalogFile { schema_version: JSON_SCHEMA_VERSION, products })
+            .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+
+        let temporary_path = self.path.with_extension("json.tmp");
+        let mut file = fs::File::create(&temporary_path).map_err(io_error)?;
+        file.write_all(&contents).map_err(io_error)?;
+        file.sync_all().map_err(io_error)?;
+        fs::rename(&temporary_path, &self.path).map_err(io_error)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ProductCatalogRepository for JsonFileProductCatalog {
+    async fn find_product(&self, sku: &Sku) -> Result<Option<Product>, RepositoryError> {
+        Ok(self.read()?.into_iter().find(|product| product.sku() == sku))
+    }
+
+    async fn save_product(&self, product: &Product) -> Result<(), RepositoryError> {
+        let _guard = self.lock()?;
+
+        let mut products = self.read()?;
+        products.retain(|stored| stored.sku() != product.sku());
+        products.push(product.clone());
+
+        self.write(products)
+    }
+
+    async fn list_products(&self) -> Result<Vec<Product>, RepositoryError> {
+        self.read()
+    }
+
+    async fn delete_product(&self, sku: &Sku) -> Result<bool, RepositoryError> {
+        let _guard = self.lock()?;
+
+        let mut products = self.read()?;
+        let count = products.len();
+        products.retain(|stored| stored.sku() != sku);
+        if products.len() == count {
+            return Ok(false);
+        }
+
+        self.write(products)?;
+        Ok(true)
+    }
+}
+
+fn io_error(err: io::Error) -> RepositoryError {
+    RepositoryError::ConnectionError(format!("File system error: {}", err))
+}
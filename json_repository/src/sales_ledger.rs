@@ -0,0 +1,121 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use soda_core::domain::entities::sale::Sale;
+use soda_core::domain::value_objects::timestamp::Timestamp;
+use soda_core::ports::driven::sales_ledger_port::SalesLedger;
+use soda_core::ports::driven::soda_machine_repository_port::RepositoryError;
+
+use crate::JSON_SCHEMA_VERSION;
+
+/// What is written to each line of the ledger file
+#[derive(Serialize, Deserialize)]
+struct SaleLine {
+    schema_version: u32,
+    sale: Sale,
+}
+
+/// Appends the sales ledger to a JSON Lines file, one sale per line
+///
+/// Sales are never rewritten, so the file only grows and a crash can at most lose the last line.
+pub struct JsonFileSalesLedger {
+    path: PathBuf,
+    /// Serializes the duplicate check and the append
+    write_lock: Mutex<()>,
+}
+
+impl JsonFileSalesLedger {
+    /// Opens the ledger file, creating its directory if needed, the file itself is written on the first sale
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, RepositoryError> {
+        let path = path.into();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(io_error)?;
+        }
+
+        Ok(JsonFileSalesLedger { path, write_lock: Mutex::new(()) })
+    }
+
+    /// Gets the file the ledger is stored in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, ()>, RepositoryError> {
+        self.write_lock.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+
+    fn read(&self) -> Result<Vec<Sale>, RepositoryError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        };
+
+        let mut sales = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let line: SaleLine = serde_json::from_str(line)
+                .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+
+            if line.schema_version != JSON_SCHEMA_VERSION {
+                return Err(RepositoryError::Other(Box::new(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "A sale is stored with schema version {}, expected {}",
+                        line.schema_version, JSON_SCHEMA_VERSION
+                    ),
+                ))));
+            }
+            sales.push(line.sale);
+        }
+
+        Ok(sales)
+    }
+}
+
+#[async_trait]
+impl SalesLedger for JsonFileSalesLedger {
+    async fn record(&self, sales: &[Sale]) -> Result<(), RepositoryError> {
+        let _guard = self.lock()?;
+
+        let mut known: HashSet<_> = self.read()?.iter()
+            .map(|sale| (sale.machine_id, sale.sequence, sale.sold_at))
+            .collect();
+
+        let mut contents = Vec::new();
+        for sale in sales {
+            if !known.insert((sale.machine_id, sale.sequence, sale.sold_at)) {
+                continue;
+            }
+            serde_json::to_writer(&mut contents, &SaleLine { schema_version: JSON_SCHEMA_VERSION, sale: sale.clone() })
+                .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+            contents.push(b'\n');
+        }
+
+        if contents.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(io_error)?;
+        file.write_all(&contents).map_err(io_error)?;
+        file.sync_all().map_err(io_error)
+    }
+
+    async fn sales_between(&self, from: Timestamp, until: Timestamp) -> Result<Vec<Sale>, RepositoryError> {
+        let mut sales: Vec<Sale> = self.read()?.into_iter()
+            .filter(|sale| from <= sale.sold_at && sale.sold_at < until)
+            .collect();
+        sales.sort_by_key(|sale| sale.sold_at);
+        Ok(sales)
+    }
+}
+
+fn io_error(err: io::Error) -> RepositoryError {
+    RepositoryError::ConnectionError(format!("File system error: {}", err))
+}
@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use soda_core::ports::driven::notification_port::{DeliveryFailure, NotificationError, NotificationPort, StockAlert};
+
+/// Appends every stock alert as one line to a local log file, and every reported failure as a line starting with `FAILED`
+pub struct LogFileNotifier {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl LogFileNotifier {
+    /// Opens the log file for appending, creating it and its directory if needed
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, NotificationError> {
+        let path = path.into();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(Self::io_error)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(Self::io_error)?;
+
+        Ok(LogFileNotifier { path, file: Mutex::new(file) })
+    }
+
+    /// Gets the file the alerts are written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write_line(&self, line: &str) -> Result<(), NotificationError> {
+        let mut file = self.file.lock().map_err(|e| {
+            NotificationError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })?;
+
+        writeln!(file, "{}", line).map_err(Self::io_error)?;
+        file.flush().map_err(Self::io_error)
+    }
+
+    fn io_error(err: io::Error) -> NotificationError {
+        NotificationError::ConnectionError(format!("File system error: {}", err))
+    }
+}
+
+#[async_trait]
+impl NotificationPort for LogFileNotifier {
+    async fn notify(&self, alert: &StockAlert) -> Result<(), NotificationError> {
+        self.write_line(&alert.to_string())
+    }
+
+    async fn report(&self, failure: &DeliveryFailure) {
+        // A log file that cannot be written to has nowhere left to report that
+        let _ = self.write_line(&format!("FAILED {}", failure));
+    }
+}
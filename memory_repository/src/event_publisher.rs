@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+use soda_core::domain::events::event_envelope::EventEnvelope;
+use soda_core::ports::driven::event_publisher_port::{EventPublisher, PublishError};
+
+type SharedEvents = Arc<Mutex<Vec<EventEnvelope>>>;
+
+/// Keeps every published event in memory, in publishing order
+pub struct InMemoryEventPublisher {
+    events: SharedEvents,
+}
+
+impl InMemoryEventPublisher {
+    pub fn new() -> Self {
+        InMemoryEventPublisher {
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Gets a copy of everything published so far
+    pub fn published(&self) -> Vec<EventEnvelope> {
+        self.events.lock().map(|events| events.clone()).unwrap_or_default()
+    }
+}
+
+impl Default for InMemoryEventPublisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EventPublisher for InMemoryEventPublisher {
+    async fn publish(&self, events: &[EventEnvelope]) -> Result<(), PublishError> {
+        let mut published = self.events.lock().map_err(|e| {
+            PublishError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })?;
+        published.extend_from_slice(events);
+        Ok(())
+    }
+}
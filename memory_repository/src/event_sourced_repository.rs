@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineEvent, SodaMachineId};
+use soda_core::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+
+/// How many events are stored between two snapshots unless configured otherwise
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 50;
+
+/// Everything stored for one machine: its full event history and the latest snapshot
+#[derive(Default)]
+struct EventStream {
+    events: Vec<(u64, SodaMachineEvent)>,
+    snapshot: Option<SodaMachine>,
+    deleted: bool,
+}
+
+impl EventStream {
+    /// The sequence number of the last stored event
+    fn version(&self) -> u64 {
+        self.events.last().map(|(sequence, _)| *sequence).unwrap_or(0)
+    }
+
+    /// The sequence number the snapshot was taken at
+    fn snapshot_version(&self) -> u64 {
+        self.snapshot.as_ref().map(|machine| machine.version()).unwrap_or(0)
+    }
+}
+
+/// Every stream ever started under an ID, oldest first, only the last one can still be in use
+type Streams = HashMap<SodaMachineId, Vec<EventStream>>;
+type SharedStreams = Arc<Mutex<Streams>>;
+
+/// Stores each machine as an append-only stream of its events
+///
+/// Machines are rebuilt by replaying their events on top of the latest snapshot, so loading
+/// never replays more than the snapshot interval. The full history stays available for auditing,
+/// even after a machine is deleted: its stream is closed with a `MachineDecommissioned` event
+/// and a machine created again under the same ID starts a stream of its own.
+pub struct EventSourcedSodaMachineRepository {
+    streams: SharedStreams,
+    snapshot_interval: u64,
+}
+
+impl EventSourcedSodaMachineRepository {
+    pub fn new() -> Self {
+        EventSourcedSodaMachineRepository {
+            streams: Arc::new(Mutex::new(HashMap::new())),
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+        }
+    }
+
+    /// Takes a snapshot every `interval` events, 0 turns snapshots off
+    pub fn with_snapshot_interval(mut self, interval: u64) -> Self {
+        self.snapshot_interval = interval;
+        self
+    }
+
+    /// Gets the full event history of the last machine stored under an ID, oldest first
+    ///
+    /// The history of a deleted machine ends with `MachineDecommissioned`.
+    pub fn events_for(&self, id: SodaMachineId) -> Result<Vec<(u64, SodaMachineEvent)>, RepositoryError> {
+        let streams = self.lock()?;
+        Ok(streams.get(&id).and_then(|history| history.last()).map(|stream| stream.events.clone()).unwrap_or_default())
+    }
+
+    /// Gets the histories of the machines deleted under an ID, oldest first
+    pub fn deleted_histories(&self, id: SodaMachineId) -> Result<Vec<Vec<(u64, SodaMachineEvent)>>, RepositoryError> {
+        let streams = self.lock()?;
+        Ok(streams.get(&id)
+            .map(|history| history.iter().filter(|stream| stream.deleted).map(|stream| stream.events.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    /// Gets the version of a machine's latest snapshot, if one was taken
+    pub fn snapshot_version(&self, id: SodaMachineId) -> Result<Option<u64>, RepositoryError> {
+        let streams = self.lock()?;
+        Ok(Self::live_stream(&streams, id).and_then(|stream| stream.snapshot.as_ref()).map(|machine| machine.version()))
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Streams>, RepositoryError> {
+        self.streams.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+
+    /// Gets the stream of the machine currently stored under an ID, if it was not deleted
+    fn live_stream(streams: &Streams, id: SodaMachineId) -> Option<&EventStream> {
+        streams.get(&id)
+            .and_then(|history| history.last())
+            .filter(|stream| !stream.deleted)
+    }
+
+    fn live_stream_mut(streams: &mut Streams, id: SodaMachineId) -> Option<&mut EventStream> {
+        streams.get_mut(&id)
+            .and_then(|history| history.last_mut())
+            .filter(|stream| !stream.deleted)
+    }
+
+    /// Appends the machine's new events, refusing them if the stream moved on since the machine was loaded
+    fn append(&self, stream: &mut EventStream, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        if machine.committed_version() != stream.version() {
+            return Err(RepositoryError::ConcurrencyConflict {
+                machine_id: machine.id(),
+                expected_version: machine.committed_version(),
+                actual_version: stream.version(),
+            });
+        }
+
+        stream.events.extend_from_slice(machine.uncommitted_events());
+
+        if self.snapshot_interval > 0 && stream.version() - stream.snapshot_version() >= self.snapshot_interval {
+            let mut snapshot = machine.clone();
+            snapshot.mark_events_committed();
+            stream.snapshot = Some(snapshot);
+        }
+
+        Ok(())
+    }
+
+    /// Stores the first events of a machine, only keeping the stream if they are accepted
+    fn start_stream(
+        &self,
+        streams: &mut Streams,
+        machine: &SodaMachine,
+    ) -> Result<(), RepositoryError> {
+        let mut stream = EventStream::default();
+        self.append(&mut stream, machine)?;
+        streams.entry(machine.id()).or_default().push(stream);
+        Ok(())
+    }
+
+    /// Rebuilds a machine from its latest snapshot and the events stored after it
+    fn rehydrate(stream: &EventStream) -> Result<SodaMachine, RepositoryError> {
+        let snapshot_version = stream.snapshot_version();
+        let later_events = stream.events.iter()
+            .filter(|(sequence, _)| *sequence > snapshot_version);
+
+        let machine = match &stream.snapshot {
+            Some(snapshot) => {
+                let mut machine = snapshot.clone();
+                for (sequence, event) in later_events {
+                    machine.replay(*sequence, event)
+                        .map_err(|e| RepositoryError::Other(Box::new(e)))?;
+                }
+                machine
+            },
+            None => SodaMachine::from_history(later_events.cloned())
+                .map_err(|e| RepositoryError::Other(Box::new(e)))?,
+        };
+
+        Ok(machine)
+    }
+}
+
+impl Default for EventSourcedSodaMachineRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SodaMachineRepository for EventSourcedSodaMachineRepository {
+    async fn find_by_id(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
+        let streams = self.lock()?;
+        Self::live_stream(&streams, id).map(Self::rehydrate).transpose()
+    }
+
+    async fn save(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let mut streams = self.lock()?;
+        match Self::live_stream_mut(&mut streams, machine.id()) {
+            Some(stream) => self.append(stream, machine),
+            None => self.start_stream(&mut streams, machine),
+        }
+    }
+
+    async fn create(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let mut streams = self.lock()?;
+        if Self::live_stream(&streams, machine.id()).is_some() {
+            return Err(RepositoryError::Other(Box::new(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "Machine already exists",
+            ))));
+        }
+
+        self.start_stream(&mut streams, machine)
+    }
+
+    async fn list(&self) -> Result<Vec<SodaMachine>, RepositoryError> {
+        let streams = self.lock()?;
+        let mut machines = streams.keys()
+            .filter_map(|id| Self::live_stream(&streams, *id))
+            .map(Self::rehydrate)
+            .collect::<Result<Vec<_>, _>>()?;
+        machines.sort_by_key(|machine| machine.id());
+
+        Ok(machines)
+    }
+
+    async fn delete(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let mut streams = self.lock()?;
+        let Some(stream) = Self::live_stream_mut(&mut streams, id) else {
+            return Ok(false);
+        };
+
+        // The history is kept, a machine deleted without being decommissioned first still gets its tombstone
+        if !matches!(stream.events.last(), Some((_, SodaMachineEvent::MachineDecommissioned))) {
+            let sequence = stream.version() + 1;
+            stream.events.push((sequence, SodaMachineEvent::MachineDecommissioned));
+        }
+        stream.deleted = true;
+
+        Ok(true)
+    }
+
+    async fn exists(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let streams = self.lock()?;
+        Ok(Self::live_stream(&streams, id).is_some())
+    }
+}
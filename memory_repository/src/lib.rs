@@ -4,7 +4,16 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
-use soda_core::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+use soda_core::ports::driven::soda_machine_repository_port::{MachineFilter, SodaMachineRepository, RepositoryError};
+
+mod event_publisher;
+mod event_sourced_repository;
+mod product_catalog;
+mod sales_ledger;
+pub use event_publisher::InMemoryEventPublisher;
+pub use event_sourced_repository::{EventSourcedSodaMachineRepository, DEFAULT_SNAPSHOT_INTERVAL};
+pub use product_catalog::InMemoryProductCatalog;
+pub use sales_ledger::InMemorySalesLedger;
 
 type SharedMachines = Arc<Mutex<HashMap<SodaMachineId, SodaMachine>>>;
 
@@ -20,6 +29,21 @@ impl InMemorySodaMachineRepository {
     }
 }
 
+impl InMemorySodaMachineRepository {
+    /// The stored copy has its events committed, so they are not handed over twice
+    fn committed_copy(machine: &SodaMachine) -> SodaMachine {
+        let mut stored = machine.clone();
+        stored.mark_events_committed();
+        stored
+    }
+}
+
+impl Default for InMemorySodaMachineRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[async_trait]
 impl SodaMachineRepository for InMemorySodaMachineRepository {
     async fn find_by_id(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
@@ -36,7 +60,16 @@ impl SodaMachineRepository for InMemorySodaMachineRepository {
         let mut machines = self.machines.lock().map_err(|e| {
             RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
         })?;
-        machines.insert(machine.id(), machine.clone());
+        if let Some(stored) = machines.get(&machine.id())
+            && stored.version() != machine.committed_version()
+        {
+            return Err(RepositoryError::ConcurrencyConflict {
+                machine_id: machine.id(),
+                expected_version: machine.committed_version(),
+                actual_version: stored.version(),
+            });
+        }
+        machines.insert(machine.id(), Self::committed_copy(machine));
         Ok(())
     }
 
@@ -50,7 +83,38 @@ impl SodaMachineRepository for InMemorySodaMachineRepository {
                 "Machine already exists",
             ))));
         }
-        machines.insert(machine.id(), machine.clone());
+        machines.insert(machine.id(), Self::committed_copy(machine));
         Ok(())
     }
+
+    async fn list(&self) -> Result<Vec<SodaMachine>, RepositoryError> {
+        self.query(&MachineFilter::new()).await
+    }
+
+    async fn delete(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let mut machines = self.machines.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })?;
+        Ok(machines.remove(&id).is_some())
+    }
+
+    async fn exists(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let machines = self.machines.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })?;
+        Ok(machines.contains_key(&id))
+    }
+
+    async fn query(&self, filter: &MachineFilter) -> Result<Vec<SodaMachine>, RepositoryError> {
+        let machines = self.machines.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })?;
+        let mut matching: Vec<SodaMachine> = machines.values()
+            .filter(|machine| filter.matches(machine))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|machine| machine.id());
+
+        Ok(matching)
+    }
 }
@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use soda_core::domain::entities::product::{Product, Sku};
+use soda_core::ports::driven::product_catalog_port::ProductCatalogRepository;
+use soda_core::ports::driven::soda_machine_repository_port::RepositoryError;
+
+type SharedProducts = Arc<Mutex<BTreeMap<Sku, Product>>>;
+
+/// Keeps the product catalog in memory, ordered by SKU
+pub struct InMemoryProductCatalog {
+    products: SharedProducts,
+}
+
+impl InMemoryProductCatalog {
+    pub fn new() -> Self {
+        InMemoryProductCatalog {
+            products: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, BTreeMap<Sku, Product>>, RepositoryError> {
+        self.products.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+}
+
+impl Default for InMemoryProductCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ProductCatalogRepository for InMemoryProductCatalog {
+    async fn find_product(&self, sku: &Sku) -> Result<Option<Product>, RepositoryError> {
+        Ok(self.lock()?.get(sku).cloned())
+    }
+
+    async fn save_product(&self, product: &Product) -> Result<(), RepositoryError> {
+        self.lock()?.insert(product.sku().clone(), product.clone());
+        Ok(())
+    }
+
+    async fn list_products(&self) -> Result<Vec<Product>, RepositoryError> {
+        Ok(self.lock()?.values().cloned().collect())
+    }
+
+    async fn delete_product(&self, sku: &Sku) -> Result<bool, RepositoryError> {
+        Ok(self.lock()?.remove(sku).is_some())
+    }
+}
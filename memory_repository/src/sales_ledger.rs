@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use soda_core::domain::entities::sale::Sale;
+use soda_core::domain::value_objects::timestamp::Timestamp;
+use soda_core::ports::driven::sales_ledger_port::SalesLedger;
+use soda_core::ports::driven::soda_machine_repository_port::RepositoryError;
+
+type SharedSales = Arc<Mutex<Vec<Sale>>>;
+
+/// Keeps the sales ledger in memory, in the order the sales were recorded
+pub struct InMemorySalesLedger {
+    sales: SharedSales,
+}
+
+impl InMemorySalesLedger {
+    pub fn new() -> Self {
+        InMemorySalesLedger {
+            sales: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Gets every sale recorded so far
+    pub fn sales(&self) -> Vec<Sale> {
+        self.sales.lock().map(|sales| sales.clone()).unwrap_or_default()
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<Sale>>, RepositoryError> {
+        self.sales.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+}
+
+impl Default for InMemorySalesLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SalesLedger for InMemorySalesLedger {
+    async fn record(&self, sales: &[Sale]) -> Result<(), RepositoryError> {
+        let mut recorded = self.lock()?;
+        for sale in sales {
+            let is_known = recorded.iter().any(|known| {
+                known.machine_id == sale.machine_id && known.sequence == sale.sequence && known.sold_at == sale.sold_at
+            });
+            if !is_known {
+                recorded.push(sale.clone());
+            }
+        }
+        Ok(())
+    }
+
+    async fn sales_between(&self, from: Timestamp, until: Timestamp) -> Result<Vec<Sale>, RepositoryError> {
+        let mut sales: Vec<Sale> = self.lock()?.iter()
+            .filter(|sale| from <= sale.sold_at && sale.sold_at < until)
+            .cloned()
+            .collect();
+        sales.sort_by_key(|sale| sale.sold_at);
+        Ok(sales)
+    }
+}
@@ -0,0 +1,222 @@
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use soda_core::domain::aggregates::soda_machine::SodaMachineId;
+use soda_core::domain::entities::slot::SlotId;
+use soda_core::domain::entities::vend::VendFailure;
+use soda_core::domain::value_objects::money::Money;
+use soda_core::ports::driven::hardware_port::{
+    Acceptance, BillValidator, CoinAcceptor, CustomerDisplay, DoorSensor, HardwareError, VendOutcome, VendingMotor,
+};
+
+/// Pretends to be the boards of every machine, so the controller logic can run without them
+///
+/// Each vend jams or hits a motor fault, and each coin or bill is rejected, at the configured
+/// rates. The rolls come from a seeded generator, so the same seed fails the same vends.
+pub struct SimulatedHardware {
+    jam_rate: f64,
+    motor_fault_rate: f64,
+    coin_reject_rate: f64,
+    bill_reject_rate: f64,
+    motor_latency: Duration,
+    acceptor_latency: Duration,
+    state: Mutex<SimulatedState>,
+}
+
+struct SimulatedState {
+    seed: u64,
+    open_doors: HashSet<SodaMachineId>,
+    messages: Vec<(SodaMachineId, String)>,
+    ejected: Vec<(SodaMachineId, Money)>,
+}
+
+impl SimulatedHardware {
+    /// Creates hardware that never fails and answers straight away
+    pub fn new() -> Self {
+        SimulatedHardware {
+            jam_rate: 0.0,
+            motor_fault_rate: 0.0,
+            coin_reject_rate: 0.0,
+            bill_reject_rate: 0.0,
+            motor_latency: Duration::ZERO,
+            acceptor_latency: Duration::ZERO,
+            state: Mutex::new(SimulatedState {
+                seed: 0,
+                open_doors: HashSet::new(),
+                messages: Vec::new(),
+                ejected: Vec::new(),
+            }),
+        }
+    }
+
+    /// Sets the share of vends, from 0.0 to 1.0, that jam in the spiral
+    pub fn with_jam_rate(mut self, rate: f64) -> Self {
+        self.jam_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the share of vends, from 0.0 to 1.0, whose motor does not turn
+    pub fn with_motor_fault_rate(mut self, rate: f64) -> Self {
+        self.motor_fault_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the share of coins, from 0.0 to 1.0, the coin acceptor rejects
+    pub fn with_coin_reject_rate(mut self, rate: f64) -> Self {
+        self.coin_reject_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the share of bills, from 0.0 to 1.0, the bill validator rejects
+    pub fn with_bill_reject_rate(mut self, rate: f64) -> Self {
+        self.bill_reject_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets how long a motor takes to turn
+    pub fn with_motor_latency(mut self, latency: Duration) -> Self {
+        self.motor_latency = latency;
+        self
+    }
+
+    /// Sets how long the coin acceptor and bill validator take to check the money
+    pub fn with_acceptor_latency(mut self, latency: Duration) -> Self {
+        self.acceptor_latency = latency;
+        self
+    }
+
+    /// Seeds the generator the failures are rolled with
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        if let Ok(state) = self.state.get_mut() {
+            state.seed = seed;
+        }
+        self
+    }
+
+    /// Opens the front door of a machine, as an operator does to service it
+    pub fn open_door(&self, machine_id: SodaMachineId) {
+        if let Ok(mut state) = self.state.lock() {
+            state.open_doors.insert(machine_id);
+        }
+    }
+
+    pub fn close_door(&self, machine_id: SodaMachineId) {
+        if let Ok(mut state) = self.state.lock() {
+            state.open_doors.remove(&machine_id);
+        }
+    }
+
+    /// Gets every message shown on the displays so far, oldest first
+    pub fn messages(&self) -> Vec<(SodaMachineId, String)> {
+        self.state.lock().map(|state| state.messages.clone()).unwrap_or_default()
+    }
+
+    /// Gets the messages shown since the last call and forgets them
+    pub fn take_messages(&self) -> Vec<(SodaMachineId, String)> {
+        self.state.lock().map(|mut state| std::mem::take(&mut state.messages)).unwrap_or_default()
+    }
+
+    /// Gets every coin and bill handed back from escrow so far
+    pub fn ejected(&self) -> Vec<(SodaMachineId, Money)> {
+        self.state.lock().map(|state| state.ejected.clone()).unwrap_or_default()
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, SimulatedState>, HardwareError> {
+        self.state.lock().map_err(|e| {
+            HardwareError::DeviceFault(format!("Mutex poisoned: {}", e))
+        })
+    }
+
+    /// Rolls a number from 0.0 up to 1.0 with SplitMix64
+    fn roll(&self) -> Result<f64, HardwareError> {
+        let mut state = self.lock()?;
+        state.seed = state.seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state.seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Ok((z >> 11) as f64 / (1u64 << 53) as f64)
+    }
+
+    /// Checks money with the given reject rate after the acceptor's latency
+    async fn check(&self, reject_rate: f64, reason: &str) -> Result<Acceptance, HardwareError> {
+        wait(self.acceptor_latency).await;
+        if self.roll()? < reject_rate {
+            return Ok(Acceptance::Rejected { reason: reason.to_string() });
+        }
+        Ok(Acceptance::Accepted)
+    }
+
+    fn hand_back(&self, machine_id: SodaMachineId, amount: Money) -> Result<(), HardwareError> {
+        self.lock()?.ejected.push((machine_id, amount));
+        Ok(())
+    }
+}
+
+impl Default for SimulatedHardware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn wait(latency: Duration) {
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+}
+
+#[async_trait]
+impl VendingMotor for SimulatedHardware {
+    async fn vend(&self, _machine_id: SodaMachineId, _slot_id: SlotId) -> Result<VendOutcome, HardwareError> {
+        wait(self.motor_latency).await;
+
+        let roll = self.roll()?;
+        if roll < self.motor_fault_rate {
+            return Ok(VendOutcome::Failed(VendFailure::MotorFault));
+        }
+        if roll < self.motor_fault_rate + self.jam_rate {
+            return Ok(VendOutcome::Failed(VendFailure::Jammed));
+        }
+        Ok(VendOutcome::Dropped)
+    }
+}
+
+#[async_trait]
+impl CoinAcceptor for SimulatedHardware {
+    async fn accept(&self, _machine_id: SodaMachineId, _coin: Money) -> Result<Acceptance, HardwareError> {
+        self.check(self.coin_reject_rate, "coin not recognized").await
+    }
+
+    async fn eject(&self, machine_id: SodaMachineId, coin: Money) -> Result<(), HardwareError> {
+        self.hand_back(machine_id, coin)
+    }
+}
+
+#[async_trait]
+impl BillValidator for SimulatedHardware {
+    async fn validate(&self, _machine_id: SodaMachineId, _bill: Money) -> Result<Acceptance, HardwareError> {
+        self.check(self.bill_reject_rate, "bill not recognized").await
+    }
+
+    async fn eject(&self, machine_id: SodaMachineId, bill: Money) -> Result<(), HardwareError> {
+        self.hand_back(machine_id, bill)
+    }
+}
+
+#[async_trait]
+impl CustomerDisplay for SimulatedHardware {
+    async fn show(&self, machine_id: SodaMachineId, message: &str) -> Result<(), HardwareError> {
+        self.lock()?.messages.push((machine_id, message.to_string()));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DoorSensor for SimulatedHardware {
+    async fn is_open(&self, machine_id: SodaMachineId) -> Result<bool, HardwareError> {
+        Ok(self.lock()?.open_doors.contains(&machine_id))
+    }
+}
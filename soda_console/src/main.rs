@@ -1,18 +1,50 @@
+use std::fs;
 use std::io::{self, Write};
 use std::sync::Arc;
+use std::time::Duration;
 
-use memory_repository::InMemorySodaMachineRepository;
+use json_repository::{JsonFileProductCatalog, JsonFileSalesLedger, JsonFileSodaMachineRepository};
+use log_notifier::LogFileNotifier;
+use simulated_hardware::SimulatedHardware;
+use soda_core::application::acceptor_service::AcceptorService;
+use soda_core::application::catalog_service::CatalogService;
 use soda_core::application::customer_service::CustomerService;
 use soda_core::application::operator_service::OperatorService;
+use soda_core::application::reporting_service::ReportingService;
+use soda_core::ports::driving::acceptor_port::{AcceptorOutcomeDTO, AcceptorPort};
+use soda_core::ports::driving::catalog_port::CatalogPort;
 use soda_core::ports::driving::customer_port::CustomerPort;
-use soda_core::ports::driving::operator_port::OperatorPort;
+use soda_core::ports::driving::operator_port::{DiscountDTO, MachineSummaryDTO, OperatorError, OperatorPort, PlanogramDTO, PromotionDTO};
+use soda_core::ports::driving::reporting_port::ReportingPort;
+use soda_core::ports::driven::hardware_port::VendingHardware;
+use soda_core::ports::driven::soda_machine_repository_port::MachineFilter;
+use soda_core::domain::entities::product::{Product, Sku};
+use soda_core::domain::entities::vend::VendFailure;
+use soda_core::domain::services::sales_report::{ReportDimension, ReportPeriod};
 use soda_core::domain::value_objects::soda::{Soda,SodaFlavor,SodaSize};
-use soda_core::domain::value_objects::money::Money;
+use soda_core::domain::value_objects::denomination::AcceptedDenominations;
+use soda_core::domain::value_objects::money::{Currency, Money};
+use soda_core::domain::value_objects::timestamp::Timestamp;
+
+/// Directory the machines are saved in, so they survive a restart
+const DATA_DIR: &str = "soda_data";
+/// File the low stock and sold out alerts are appended to
+const ALERT_LOG: &str = "soda_data/alerts.log";
+/// File the product catalog is saved in
+const CATALOG_FILE: &str = "soda_data/catalog.json";
+/// File every soda sold is appended to
+const SALES_FILE: &str = "soda_data/sales.jsonl";
 
 async fn seed(operator_service: &OperatorService) {
     // Add a soda machine with ID 1 and max 5 slots
     let machine_id = 1u32;
     let max_slots = 5u32;
+
+    // Machines saved by an earlier run are kept as they are
+    if !matches!(operator_service.get_machine_status(machine_id).await, Err(OperatorError::SodaMachineNotFound(_))) {
+        return;
+    }
+
     if let Err(e) = operator_service.create_new_machine(machine_id, max_slots).await {
         println!("Failed to add machine: {:?}", e);
     }
@@ -35,13 +67,48 @@ async fn seed(operator_service: &OperatorService) {
     if let Err(e) = operator_service.refill_slot(machine_id, slot_id, 3).await {
         println!("Failed to refill slot: {:?}", e);
     }
+
+    // Report the slot as low on stock when only one soda is left
+    if let Err(e) = operator_service.set_low_stock_threshold(machine_id, slot_id, 1).await {
+        println!("Failed to set low stock threshold: {:?}", e);
+    }
+
+    // Load coins so the machine can give change
+    for (denomination, count) in [(25, 20), (10, 20), (5, 20)] {
+        if let Err(e) = operator_service.load_cash(machine_id, Money::from_cents(denomination), count).await {
+            println!("Failed to load cash: {:?}", e);
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    let repo = Arc::new(InMemorySodaMachineRepository::new());
-    let customer_service = Arc::new(CustomerService::new(repo.clone()));
-    let operator_service = Arc::new(OperatorService::new(repo.clone()));
+    let repo = Arc::new(JsonFileSodaMachineRepository::new(DATA_DIR).expect("Failed to open the data directory"));
+    let notifier = Arc::new(LogFileNotifier::new(ALERT_LOG).expect("Failed to open the alert log"));
+    let ledger = Arc::new(JsonFileSalesLedger::new(SALES_FILE).expect("Failed to open the sales ledger"));
+    // Every twentieth vend jams, so the failure flow can be tried without a real machine
+    let hardware = Arc::new(
+        SimulatedHardware::new()
+            .with_seed(Timestamp::now().as_millis() as u64)
+            .with_jam_rate(0.05)
+            .with_motor_latency(Duration::from_millis(300))
+            .with_acceptor_latency(Duration::from_millis(100))
+    );
+    let customer_service = Arc::new(
+        CustomerService::new(repo.clone())
+            .with_notifier(notifier.clone())
+            .with_sales_ledger(ledger.clone())
+            .with_hardware(VendingHardware::from_driver(hardware.clone()))
+    );
+    let acceptor_service = Arc::new(AcceptorService::new(customer_service.clone(), Currency::USD));
+    let operator_service = Arc::new(
+        OperatorService::new(repo.clone())
+            .with_notifier(notifier.clone())
+            .with_sales_ledger(ledger.clone())
+    );
+    let catalog = Arc::new(JsonFileProductCatalog::new(CATALOG_FILE).expect("Failed to open the product catalog"));
+    let catalog_service = Arc::new(CatalogService::new(catalog, repo.clone()).with_notifier(notifier));
+    let reporting_service = Arc::new(ReportingService::new(ledger));
 
     seed(&operator_service).await;
     
@@ -49,7 +116,9 @@ async fn main() {
         println!("\nWelcome to Soda Console!");
         println!("1. Soda Consumer");
         println!("2. Soda Operator");
-        println!("3. Exit");
+        println!("3. Merchandiser");
+        println!("4. Category Manager");
+        println!("5. Exit");
         print!("Select your role: ");
         io::stdout().flush().unwrap();
 
@@ -58,9 +127,16 @@ async fn main() {
         let role = role.trim();
 
         match role {
-            "1" => soda_consumer_menu(customer_service.clone()).await,
+            "1" => {
+                soda_consumer_menu(customer_service.clone(), acceptor_service.clone()).await;
+                for (machine_id, message) in hardware.take_messages() {
+                    println!("[Machine {} display] {}", machine_id, message);
+                }
+            }
             "2" => soda_operator_menu(operator_service.clone()).await,
-            "3" => {
+            "3" => merchandiser_menu(catalog_service.clone()).await,
+            "4" => category_manager_menu(reporting_service.clone()).await,
+            "5" => {
                 println!("Goodbye!");
                 break;
             }
@@ -69,12 +145,14 @@ async fn main() {
     }
 }
 
-async fn soda_consumer_menu(customer_service: Arc<CustomerService>) {
+async fn soda_consumer_menu(customer_service: Arc<CustomerService>, acceptor_service: Arc<AcceptorService>) {
     println!("\n--- Soda Consumer ---");
     println!("1. Available Sodas");
     println!("2. Insert Money");
     println!("3. Buy Soda");
     println!("4. Request Money Back");
+    println!("5. Enter Coupon");
+    println!("6. Send Coin Acceptor Message");
     print!("Select an option: ");
     io::stdout().flush().unwrap();
 
@@ -96,10 +174,11 @@ async fn soda_consumer_menu(customer_service: Arc<CustomerService>) {
                         println!("Available Sodas:");
                         for soda in sodas {
                             println!(
-                                "Slot {}: {} - ${}",
+                                "Slot {}: {} - {} {}",
                                 soda.slot_id,
                                 soda.soda_name,
-                                soda.price
+                                soda.price,
+                                soda.currency
                             );
                         }
                     }
@@ -112,14 +191,9 @@ async fn soda_consumer_menu(customer_service: Arc<CustomerService>) {
             let id = prompt("Enter Soda Machine ID: ");
             let id: u32 = id.parse().unwrap_or(0);
 
-            let amount = prompt("Enter amount to insert (e.g., 2.50): ");
-            let amount: f64 = amount.parse().unwrap_or(0.0);
+            let amount = prompt("Enter amount to insert (e.g., 2.50 or 2 EUR): ");
 
-            // Convert to cents to avoid floating point issues
-            let dollars = amount.trunc() as i64;
-            let cents = ((amount.fract() * 100.0).round()) as u8;
-
-            match soda_core::domain::value_objects::money::Money::from_dollars_cents(dollars, cents) {
+            match amount.parse::<Money>() {
                 Ok(money) => {
                     match customer_service.insert_money(id, money).await {
                         Ok(_) => println!("Money inserted successfully."),
@@ -149,11 +223,32 @@ async fn soda_consumer_menu(customer_service: Arc<CustomerService>) {
 
             match customer_service.request_money_back(id).await {
                 Ok(money) => {
-                    println!("Returned: ${:.2}", money.as_decimal());
+                    println!("Returned: {}", money);
                 }
                 Err(e) => println!("Error: {}", e),
             }
         }
+        "5" => {
+            let id = prompt("Enter Soda Machine ID: ");
+            let id: u32 = id.parse().unwrap_or(0);
+            let code = prompt("Enter coupon code: ");
+
+            match customer_service.enter_coupon(id, &code).await {
+                Ok(_) => println!("Coupon accepted, it is taken off your next soda."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "6" => {
+            let id = prompt("Enter Soda Machine ID: ");
+            let id: u32 = id.parse().unwrap_or(0);
+            let message = prompt("Enter the device message (e.g., COIN 3, BILL 2 COUNTERFEIT, COIN REJECT): ");
+
+            match acceptor_service.handle_device_message(id, &message).await {
+                Ok(AcceptorOutcomeDTO::Credited { amount }) => println!("{} credited.", amount),
+                Ok(AcceptorOutcomeDTO::Returned { reason }) => println!("Money handed back: {}", reason),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
         _ => {
             println!("Invalid option. Please try again.");
         }
@@ -166,6 +261,27 @@ async fn soda_operator_menu(operator_service: Arc<OperatorService>) {
     println!("2. View Soda Machine");
     println!("3. Add Slot to Soda Machine");
     println!("4. Refill Slot in Soda Machine");
+    println!("5. Load Change into Soda Machine");
+    println!("6. Set Soda Machine Location");
+    println!("7. Find Soda Machines");
+    println!("8. Delete Soda Machine");
+    println!("9. Fleet Dashboard");
+    println!("10. Set Low Stock Threshold");
+    println!("11. Plan Restock Route");
+    println!("12. Restock Soda Machine");
+    println!("13. Export Planogram");
+    println!("14. Import Planogram");
+    println!("15. Set Machine Price");
+    println!("16. Schedule Price Change");
+    println!("17. Machine Prices");
+    println!("18. Launch Promotions");
+    println!("19. Promotions");
+    println!("20. End Promotion");
+    println!("21. Collect Cash");
+    println!("22. Issue Refund");
+    println!("23. Strict Denominations");
+    println!("24. Resolve Pending Vend");
+    println!("25. Unload Change from Soda Machine");
     print!("Select an option: ");
     io::stdout().flush().unwrap();
 
@@ -184,11 +300,23 @@ async fn soda_operator_menu(operator_service: Arc<OperatorService>) {
             }
         }
         "2" => {
+            match operator_service.list_machines().await {
+                Ok(machines) if machines.is_empty() => {
+                    println!("No soda machines yet.");
+                    return;
+                }
+                Ok(machines) => print_machines(&machines),
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            }
+
             let id = prompt("Enter Soda Machine ID to view: ");
             let id: u32 = id.parse().unwrap_or(0);
 
             match operator_service.get_machine_status(id).await {
-                Ok(machine) => println!("Soda Machine: {}", machine),
+                Ok(status) => println!("{}", status),
                 Err(e) => println!("Error: {}", e),
             }
         }
@@ -231,13 +359,11 @@ async fn soda_operator_menu(operator_service: Arc<OperatorService>) {
                     SodaSize::Medium
                 }
             };
-            let price = prompt("Enter Soda Price (cents): ");
-            let price = match price.parse::<i64>() {
-                Ok(val) => {
-                    Money::from_cents(val) 
-                }
-                Err(_) => {
-                    println!("Invalid price format, defaulting to $1.00.");
+            let price = prompt("Enter Soda Price (e.g., 1.25): ");
+            let price = match price.parse::<Money>() {
+                Ok(val) => val,
+                Err(e) => {
+                    println!("Invalid price ({}), defaulting to $1.00.", e);
                     Money::from_cents(100)
                 }
             };
@@ -279,10 +405,613 @@ async fn soda_operator_menu(operator_service: Arc<OperatorService>) {
                 Err(e) => println!("Error: {}", e),
             }
         }
+        "5" => {
+            let id = prompt("Enter Soda Machine ID: ");
+            let id = id.parse::<u32>().unwrap_or(1);
+            let denomination = prompt("Enter coin/bill value (e.g., 0.25): ");
+            let denomination = match denomination.parse::<Money>() {
+                Ok(val) => val,
+                Err(e) => {
+                    println!("Invalid amount: {}", e);
+                    return;
+                }
+            };
+            let count = prompt("Enter number of coins/bills: ");
+            let count = count.parse::<u32>().unwrap_or(0);
+
+            match operator_service.load_cash(id, denomination, count).await {
+                Ok(_) => println!("Cash loaded."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "6" => {
+            let id = prompt("Enter Soda Machine ID: ");
+            let id = id.parse::<u32>().unwrap_or(0);
+            let location = prompt("Enter location (leave empty to clear): ");
+
+            match operator_service.set_machine_location(id, Some(location)).await {
+                Ok(_) => println!("Location updated."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "7" => {
+            println!("1. Low on stock");
+            println!("2. Out of service");
+            println!("3. By location");
+            let filter = match prompt("Select a search: ").as_str() {
+                "1" => {
+                    let threshold = prompt("Show slots with at most how many sodas? ");
+                    MachineFilter::new().low_stock(threshold.parse::<u32>().unwrap_or(2))
+                }
+                "2" => MachineFilter::new().operational(false),
+                "3" => MachineFilter::new().location(prompt("Enter part of the location: ")),
+                _ => {
+                    println!("Invalid option.");
+                    return;
+                }
+            };
+
+            match operator_service.find_machines(filter).await {
+                Ok(machines) if machines.is_empty() => println!("No soda machines match."),
+                Ok(machines) => print_machines(&machines),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "8" => {
+            let id = prompt("Enter Soda Machine ID to delete: ");
+            let id = id.parse::<u32>().unwrap_or(0);
+
+            match operator_service.delete_machine(id).await {
+                Ok(_) => println!("Soda Machine deleted."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "9" => {
+            let threshold = prompt("Count slots as low with at most how many sodas? ");
+            let threshold = threshold.parse::<u32>().unwrap_or(2);
+
+            match operator_service.get_fleet_dashboard(threshold).await {
+                Ok(dashboard) => {
+                    for totals in &dashboard.totals {
+                        println!(
+                            "{} ({} machines): {} collected, {} in stock, {} credit held",
+                            totals.currency.code(),
+                            totals.machine_count,
+                            totals.total_collected,
+                            totals.inventory_value,
+                            totals.inserted_money,
+                        );
+                    }
+                    for machine in dashboard.machines.iter().filter(|machine| machine.needs_visit()) {
+                        println!(
+                            "  Visit #{} {}:{} empty slots {:?}, low slots {:?}",
+                            machine.machine_id,
+                            machine.location.as_deref().unwrap_or("(no location)"),
+                            if machine.is_operational { "" } else { " out of service," },
+                            machine.empty_slots,
+                            machine.low_slots,
+                        );
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "10" => {
+            let id = prompt("Enter Soda Machine ID: ");
+            let id = id.parse::<u32>().unwrap_or(1);
+            let slot_id = prompt("Enter Slot ID: ");
+            let slot_id = slot_id.parse::<u32>().unwrap_or(1);
+            let threshold = prompt("Report low stock at how many sodas? ");
+            let threshold = threshold.parse::<u32>().unwrap_or(0);
+
+            match operator_service.set_low_stock_threshold(id, slot_id, threshold).await {
+                Ok(_) => println!("Threshold set, alerts go to {}.", ALERT_LOG),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "11" => {
+            let ids = prompt("Enter the Soda Machine IDs of the route (e.g., 1,3,2): ");
+            let ids: Vec<u32> = ids.split(',').filter_map(|id| id.trim().parse().ok()).collect();
+
+            match operator_service.plan_restock(&ids).await {
+                Ok(plan) => {
+                    println!("Load on the truck:");
+                    for item in &plan.pick_list {
+                        println!("  {} x {}", item.units, item.product);
+                    }
+                    for machine in &plan.machines {
+                        println!("Machine #{}: {} sodas", machine.machine_id, machine.total_units());
+                        for slot in &machine.slots {
+                            println!("  Slot {}: {} x {}", slot.slot_id, slot.units, slot.product);
+                        }
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "12" => {
+            let id = prompt("Enter Soda Machine ID to restock: ");
+            let id = id.parse::<u32>().unwrap_or(1);
+
+            match operator_service.restock_machine(id).await {
+                Ok(report) => {
+                    println!("Added {} sodas.", report.total_units());
+                    for slot in &report.slots {
+                        println!("  Slot {}: {} x {}", slot.slot_id, slot.units, slot.product);
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "13" => {
+            let id = prompt("Enter Soda Machine ID to export: ");
+            let id = id.parse::<u32>().unwrap_or(1);
+            let path = prompt("Write planogram to file: ");
+
+            let planogram = match operator_service.export_planogram(id).await {
+                Ok(planogram) => planogram,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            };
+
+            let written = serde_json::to_string_pretty(&planogram)
+                .map_err(|e| e.to_string())
+                .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+            match written {
+                Ok(()) => println!("Exported {} slots to {}.", planogram.slots.len(), path),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "14" => {
+            let path = prompt("Read planogram from file: ");
+            let planogram: PlanogramDTO = match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(planogram) => planogram,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            };
+
+            let ids = prompt("Apply to Soda Machine IDs (e.g., 1,3,2): ");
+            for id in ids.split(',').filter_map(|id| id.trim().parse::<u32>().ok()) {
+                match operator_service.import_planogram(id, planogram.clone()).await {
+                    Ok(_) => println!("Machine #{}: planogram applied.", id),
+                    Err(e) => println!("Machine #{}: {}", id, e),
+                }
+            }
+        }
+        "15" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let sku = prompt("Enter SKU: ");
+            let price = prompt("Enter the machine's price (leave empty for the slot price): ");
+            let price = match price.as_str() {
+                "" => None,
+                price => match price.parse::<Money>() {
+                    Ok(price) => Some(price),
+                    Err(e) => {
+                        println!("Invalid price: {}", e);
+                        return;
+                    }
+                },
+            };
+
+            match operator_service.set_price_override(id, &sku, price).await {
+                Ok(_) => println!("Price set."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "16" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let sku = prompt("Enter SKU: ");
+            let price = prompt("Enter the new price (leave empty to go back to the slot price): ");
+            let price = match price.as_str() {
+                "" => None,
+                price => match price.parse::<Money>() {
+                    Ok(price) => Some(price),
+                    Err(e) => {
+                        println!("Invalid price: {}", e);
+                        return;
+                    }
+                },
+            };
+            let minutes = prompt("Take effect in how many minutes? ").parse::<u64>().unwrap_or(60);
+            let effective_at = Timestamp::now().plus(Duration::from_secs(minutes * 60));
+
+            match operator_service.schedule_price_change(id, &sku, price, effective_at).await {
+                Ok(_) => println!("Price change scheduled for {}.", effective_at),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "17" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+
+            match operator_service.list_price_changes(id).await {
+                Ok(changes) if changes.is_empty() => println!("Every slot sells at its own price."),
+                Ok(changes) => {
+                    for change in changes {
+                        let price = change.price.map(|price| price.to_string()).unwrap_or("slot price".to_string());
+                        if change.is_upcoming {
+                            println!("  {} - {} from {}", change.sku, price, change.effective_at);
+                        } else {
+                            println!("  {} - {}", change.sku, price);
+                        }
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "18" => {
+            let path = prompt("Read promotions from file: ");
+            let campaign: Vec<PromotionDTO> = match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(campaign) => campaign,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            };
+
+            let ids = prompt("Launch on Soda Machine IDs (e.g., 1,3,2): ");
+            for id in ids.split(',').filter_map(|id| id.trim().parse::<u32>().ok()) {
+                for promotion in &campaign {
+                    match operator_service.add_promotion(id, promotion.clone()).await {
+                        Ok(_) => println!("Machine #{}: {} launched.", id, promotion.id),
+                        Err(e) => println!("Machine #{}: {}", id, e),
+                    }
+                }
+            }
+        }
+        "19" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+
+            match operator_service.list_promotions(id).await {
+                Ok(promotions) if promotions.is_empty() => println!("No promotions run on this machine."),
+                Ok(promotions) => {
+                    for promotion in promotions {
+                        let discount = match &promotion.discount {
+                            DiscountDTO::PercentOff(percent) => format!("{}% off", percent),
+                            DiscountDTO::AmountOff(amount) => format!("{} off", amount),
+                            DiscountDTO::BuyNGetOneFree(n) => format!("buy {} get 1 free", n),
+                        };
+                        println!(
+                            "  {} {} - {}{}{}{}{}",
+                            promotion.id,
+                            promotion.name,
+                            discount,
+                            promotion.flavor.or(promotion.sku).map(|on| format!(" on {}", on)).unwrap_or_default(),
+                            promotion.hours.map(|hours| format!(", {} UTC", hours)).unwrap_or_default(),
+                            if promotion.is_coupon { " (coupon)" } else { "" },
+                            if promotion.is_active { "" } else { " [not running now]" },
+                        );
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "20" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let promotion_id = prompt("Enter Promotion ID: ");
+
+            match operator_service.remove_promotion(id, &promotion_id).await {
+                Ok(_) => println!("Promotion ended."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "21" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let operator_id = prompt("Enter your operator ID: ");
+            let counted = match prompt("Enter the amount counted (e.g., 42.75): ").parse::<Money>() {
+                Ok(counted) => counted,
+                Err(e) => {
+                    println!("Invalid amount: {}", e);
+                    return;
+                }
+            };
+
+            match operator_service.collect_cash(id, &operator_id, counted).await {
+                Ok(report) => {
+                    println!("{}", report);
+                    println!("The cash box is empty, load change before leaving.");
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "22" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let operator_id = prompt("Enter your operator ID: ");
+            let amount = match prompt("Enter the amount to refund (e.g., 1.50): ").parse::<Money>() {
+                Ok(amount) => amount,
+                Err(e) => {
+                    println!("Invalid amount: {}", e);
+                    return;
+                }
+            };
+            let reason = prompt("Enter the reason: ");
+
+            match operator_service.issue_refund(id, &operator_id, amount, &reason).await {
+                Ok(refund) => {
+                    println!("Refunded {} to the customer. Hand over:", refund.amount);
+                    for (coin, count) in &refund.coins {
+                        println!("  {} x {}", count, coin);
+                    }
+                    println!("Revenue since the last collection: {}", refund.total_collected);
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "23" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let strict = prompt("Only take the standard coins and bills? (y/n): ");
+
+            let result = if strict.trim().eq_ignore_ascii_case("y") {
+                match operator_service.get_machine_status(id).await {
+                    Ok(status) => {
+                        let standard = AcceptedDenominations::standard(status.currency);
+                        let coins: Vec<Money> = standard.coins().iter().copied().collect();
+                        let bills: Vec<Money> = standard.bills().iter().copied().collect();
+                        operator_service.set_accepted_denominations(id, &coins, &bills).await
+                            .map(|_| format!("Strict mode on, taking {}.", standard))
+                    }
+                    Err(e) => Err(e),
+                }
+            } else {
+                operator_service.accept_any_denomination(id).await.map(|_| "Strict mode off, any coin or bill is taken.".to_string())
+            };
+
+            match result {
+                Ok(message) => println!("{}", message),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "24" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            match operator_service.get_machine_status(id).await {
+                Ok(status) => match status.pending_vend {
+                    Some(vend) => println!("{} from slot {} for {} is pending since {}.", vend.soda_name, vend.slot_id, vend.price, vend.reserved_at),
+                    None => {
+                        println!("No vend is pending.");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            }
+
+            println!("1. The soda was delivered, charge the customer");
+            println!("2. The motor never turned, the can is still in its place");
+            println!("3. The can is stuck, give the customer their credit back");
+            let failure = match prompt("Select an option: ").as_str() {
+                "1" => None,
+                "2" => Some(VendFailure::MotorFault),
+                "3" => Some(VendFailure::Jammed),
+                _ => {
+                    println!("Invalid option.");
+                    return;
+                }
+            };
+
+            match operator_service.resolve_pending_vend(id, failure).await {
+                Ok(_) => println!("The vend is resolved, customers can buy again."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "25" => {
+            let id = prompt("Enter Soda Machine ID: ");
+            let id = id.parse::<u32>().unwrap_or(1);
+            let denomination = prompt("Enter coin/bill value (e.g., 0.25): ");
+            let denomination = match denomination.parse::<Money>() {
+                Ok(val) => val,
+                Err(e) => {
+                    println!("Invalid amount: {}", e);
+                    return;
+                }
+            };
+            let count = prompt("Enter number of coins/bills: ");
+            let count = count.parse::<u32>().unwrap_or(0);
+
+            match operator_service.unload_cash(id, denomination, count).await {
+                Ok(_) => println!("Cash unloaded."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
         _ => println!("Invalid option."),
     }
 }
 
+async fn merchandiser_menu(catalog_service: Arc<CatalogService>) {
+    println!("\n--- Merchandiser ---");
+    println!("1. List Products");
+    println!("2. Add Product");
+    println!("3. Update Product Price");
+    println!("4. Assign Product to Slot");
+    println!("5. Remove Product");
+    print!("Select an option: ");
+    io::stdout().flush().unwrap();
+
+    let mut op = String::new();
+    io::stdin().read_line(&mut op).unwrap();
+    let op = op.trim();
+
+    match op {
+        "1" => {
+            match catalog_service.list_products().await {
+                Ok(products) if products.is_empty() => println!("The catalog is empty."),
+                Ok(products) => {
+                    for product in products {
+                        println!(
+                            "  {} {} - {}{}",
+                            product.sku,
+                            product.description,
+                            product.default_price,
+                            product.barcode.map(|barcode| format!(" [{}]", barcode)).unwrap_or_default(),
+                        );
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "2" => {
+            let sku = match Sku::new(&prompt("Enter SKU (e.g., COLA-12OZ): ")) {
+                Ok(sku) => sku,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            };
+            let name = prompt("Enter Product Name: ");
+            let Some(flavor) = SodaFlavor::from_string(&prompt("Enter Flavor (e.g., Cola, Orange, Lemon): ")) else {
+                println!("Unknown flavor.");
+                return;
+            };
+            let Some(size) = SodaSize::from_string(&prompt("Enter Size (Small, Medium, Large, XLarge): ")) else {
+                println!("Unknown size.");
+                return;
+            };
+            let price = match prompt("Enter Default Price (e.g., 1.25): ").parse::<Money>() {
+                Ok(price) => price,
+                Err(e) => {
+                    println!("Invalid price: {}", e);
+                    return;
+                }
+            };
+            let is_diet = matches!(prompt("Is the product diet? (y/n): ").to_lowercase().as_str(), "y" | "yes");
+            let is_caffeinated = matches!(prompt("Is the product caffeinated? (y/n): ").to_lowercase().as_str(), "y" | "yes");
+            let barcode = prompt("Enter Barcode (leave empty if none): ");
+
+            let product = Soda::new(name, flavor, size, price, is_diet, is_caffeinated)
+                .map_err(|e| e.to_string())
+                .and_then(|soda| Product::new(sku, soda, Some(barcode)).map_err(|e| e.to_string()));
+            match product {
+                Ok(product) => match catalog_service.add_product(product).await {
+                    Ok(_) => println!("Product added."),
+                    Err(e) => println!("Error: {}", e),
+                },
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "3" => {
+            let sku = prompt("Enter SKU: ");
+            let price = match prompt("Enter New Price (e.g., 1.50): ").parse::<Money>() {
+                Ok(price) => price,
+                Err(e) => {
+                    println!("Invalid price: {}", e);
+                    return;
+                }
+            };
+
+            match catalog_service.update_price(&sku, price).await {
+                Ok(update) => println!(
+                    "{} now sells for {}, {} slots repriced on {} machines.",
+                    update.sku,
+                    update.price,
+                    update.slots_repriced,
+                    update.machines.len(),
+                ),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "4" => {
+            let id = prompt("Enter Soda Machine ID: ").parse::<u32>().unwrap_or(1);
+            let slot_id = prompt("Enter Slot ID: ").parse::<u32>().unwrap_or(1);
+            let capacity = prompt("Enter Slot Capacity (used if the slot is new): ").parse::<u32>().unwrap_or(10);
+            let sku = prompt("Enter SKU: ");
+
+            match catalog_service.assign_product(id, slot_id, capacity, &sku).await {
+                Ok(_) => println!("Slot {} now sells {}.", slot_id, sku),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        "5" => {
+            let sku = prompt("Enter SKU to remove: ");
+
+            match catalog_service.remove_product(&sku).await {
+                Ok(_) => println!("Product removed."),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        _ => println!("Invalid option."),
+    }
+}
+
+async fn category_manager_menu(reporting_service: Arc<ReportingService>) {
+    println!("\n--- Category Manager ---");
+    println!("1. Sales Report");
+    print!("Select an option: ");
+    io::stdout().flush().unwrap();
+
+    let mut op = String::new();
+    io::stdin().read_line(&mut op).unwrap();
+    let op = op.trim();
+
+    match op {
+        "1" => {
+            let dimension = prompt("Group by (product, flavor, size, machine, slot): ");
+            let Some(dimension) = ReportDimension::from_string(&dimension) else {
+                println!("Unknown grouping: {}", dimension);
+                return;
+            };
+            let period = prompt("Period (daily, weekly, monthly): ");
+            let Some(period) = ReportPeriod::from_string(&period) else {
+                println!("Unknown period: {}", period);
+                return;
+            };
+
+            // Without dates the report covers the last 30 days
+            let now = Timestamp::now();
+            let from = match prompt("From (e.g. 2024-03-01T00:00:00Z, leave empty for 30 days ago): ").as_str() {
+                "" => Timestamp::from_millis(now.as_millis() - 30 * 86_400_000),
+                from => match from.parse::<Timestamp>() {
+                    Ok(from) => from,
+                    Err(e) => {
+                        println!("Invalid time: {}", e);
+                        return;
+                    }
+                },
+            };
+            let until = match prompt("Until (leave empty for now): ").as_str() {
+                "" => now,
+                until => match until.parse::<Timestamp>() {
+                    Ok(until) => until,
+                    Err(e) => {
+                        println!("Invalid time: {}", e);
+                        return;
+                    }
+                },
+            };
+
+            match reporting_service.sales_report(dimension, period, from, until).await {
+                Ok(report) if report.lines.is_empty() => println!("Nothing was sold in that time."),
+                Ok(report) => println!("{}", report),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+        _ => println!("Invalid option."),
+    }
+}
+
+fn print_machines(machines: &[MachineSummaryDTO]) {
+    for machine in machines {
+        println!(
+            "  #{} {} - {} slots, {} sodas, {} ({})",
+            machine.machine_id,
+            machine.location.as_deref().unwrap_or("(no location)"),
+            machine.slot_count,
+            machine.total_sodas,
+            if machine.is_operational { "operational" } else { "out of service" },
+            machine.currency.code(),
+        );
+    }
+}
+
 fn prompt(msg: &str) -> String {
     print!("{}", msg);
     io::stdout().flush().unwrap();
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::services::acceptor_protocol::{AcceptorDecision, AcceptorEvent, AcceptorProtocolError, ChannelTable, MoneyAcceptor};
+use crate::domain::value_objects::denomination::AcceptedDenominations;
+use crate::domain::value_objects::money::Currency;
+use crate::ports::driving::acceptor_port::{AcceptorError, AcceptorOutcomeDTO, AcceptorPort};
+use crate::ports::driving::customer_port::{CustomerError, CustomerPort};
+
+/// Reads the messages of the coin acceptors and bill validators and credits the money they took
+///
+/// The money is checked once, against the machine: its currency must be the one the channels are
+/// programmed for, and a genuine coin or bill reaches `insert_denomination` only if the machine's
+/// own denominations take it, as a coin or a bill, or the configured ones if it is not in strict mode.
+pub struct AcceptorService {
+    customers: Arc<dyn CustomerPort + Send + Sync>,
+    acceptor: MoneyAcceptor,
+}
+
+impl AcceptorService {
+    /// Credits money through the customer service, with the standard channels and denominations of the currency
+    pub fn new(customers: Arc<dyn CustomerPort + Send + Sync>, currency: Currency) -> Self {
+        Self {
+            customers,
+            acceptor: MoneyAcceptor::standard(currency),
+        }
+    }
+
+    /// Replaces the standard channels, e.g. for a device programmed differently
+    pub fn with_channels(mut self, channels: ChannelTable) -> Self {
+        self.acceptor = MoneyAcceptor::new(channels, self.acceptor.accepted().clone());
+        self
+    }
+
+    /// Replaces the standard denominations, e.g. to refuse bills a site does not want to take
+    pub fn with_accepted_denominations(mut self, accepted: AcceptedDenominations) -> Self {
+        self.acceptor = MoneyAcceptor::new(self.acceptor.channels().clone(), accepted);
+        self
+    }
+}
+
+#[async_trait]
+impl AcceptorPort for AcceptorService {
+    async fn handle_device_message(&self, machine_id: u32, message: &str) -> Result<AcceptorOutcomeDTO, AcceptorError> {
+        let event: AcceptorEvent = message.parse()
+            .map_err(|e: AcceptorProtocolError| AcceptorError::InvalidMessage(e.to_string()))?;
+
+        let machine = self.customers.accepted_money(machine_id).await.map_err(AcceptorError::CreditFailure)?;
+        let currency = self.acceptor.channels().currency();
+        if machine.currency != currency {
+            return Err(AcceptorError::CurrencyMismatch { machine: machine.currency, acceptor: currency });
+        }
+
+        let decision = match &machine.denominations {
+            Some(denominations) => self.acceptor.decide_with(&event, denominations),
+            None => self.acceptor.decide(&event),
+        };
+        let (kind, amount) = match decision {
+            AcceptorDecision::Credit { kind, amount } => (kind, amount),
+            AcceptorDecision::Return { reason } => return Ok(AcceptorOutcomeDTO::Returned { reason }),
+        };
+
+        match self.customers.insert_denomination(machine_id, kind, amount).await {
+            Ok(()) => Ok(AcceptorOutcomeDTO::Credited { amount }),
+            // The machine itself refused the money, e.g. it is out of service
+            Err(CustomerError::MachineError(e)) => Ok(AcceptorOutcomeDTO::Returned { reason: e.to_string() }),
+            Err(e) => Err(AcceptorError::CreditFailure(e)),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::aggregates::soda_machine::{SodaMachine, SodaMachineError, SodaMachineId};
+use crate::domain::entities::product::{Product, ProductError, Sku};
+use crate::domain::entities::slot::SlotId;
+use crate::domain::events::event_envelope::{CorrelationId, EventEnvelope};
+use crate::domain::value_objects::money::Money;
+use crate::domain::value_objects::timestamp::Timestamp;
+use crate::application::outbox::Outbox;
+use crate::ports::driving::catalog_port::{CatalogError, CatalogPort, PriceUpdateDTO, ProductDTO};
+use crate::ports::driven::product_catalog_port::ProductCatalogRepository;
+use crate::ports::driven::soda_machine_repository_port::{MachineFilter, SodaMachineRepository, RepositoryError};
+use crate::ports::driven::clock_port::{Clock, SystemClock};
+use crate::ports::driven::event_publisher_port::{EventPublisher, NullEventPublisher};
+use crate::ports::driven::notification_port::{NotificationPort, NullNotifier};
+
+/// How many times a command is tried before a conflicting save is reported to the merchandiser
+const MAX_SAVE_ATTEMPTS: u32 = 3;
+
+impl From<RepositoryError> for CatalogError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::ConnectionError(msg) => CatalogError::RepositoryUnavailable(msg),
+            RepositoryError::ConcurrencyConflict { machine_id, .. } => CatalogError::ConcurrencyConflict(machine_id),
+            RepositoryError::Other(e) => CatalogError::RepositoryFailure(e.to_string()),
+        }
+    }
+}
+
+impl From<ProductError> for CatalogError {
+    fn from(err: ProductError) -> Self {
+        CatalogError::ProductError(err)
+    }
+}
+
+/// Manages the product catalog and keeps the slots selling its products in line with it
+pub struct CatalogService {
+    catalog: Arc<dyn ProductCatalogRepository>,
+    repository: Arc<dyn SodaMachineRepository>,
+    clock: Arc<dyn Clock>,
+    publisher: Arc<dyn EventPublisher>,
+    notifier: Arc<dyn NotificationPort>,
+    outbox: Outbox,
+}
+
+impl CatalogService {
+    pub fn new(catalog: Arc<dyn ProductCatalogRepository>, repository: Arc<dyn SodaMachineRepository>) -> Self {
+        Self {
+            catalog,
+            repository,
+            clock: Arc::new(SystemClock),
+            publisher: Arc::new(NullEventPublisher),
+            notifier: Arc::new(NullNotifier),
+            outbox: Outbox::new(),
+        }
+    }
+
+    /// Replaces the system clock, e.g. with a fake one in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publishes the events of every command through the given publisher
+    pub fn with_event_publisher(mut self, publisher: Arc<dyn EventPublisher>) -> Self {
+        self.publisher = publisher;
+        self
+    }
+
+    /// Reports the events the publisher could not take through the given notifier
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationPort>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Saves the machine, then publishes the events the command recorded
+    ///
+    /// Only the save can fail the command, events the publisher could not take are kept in the outbox for the next command and reported
+    async fn save_and_publish(&self, machine: &mut SodaMachine, now: Timestamp, correlation_id: &CorrelationId) -> Result<(), CatalogError> {
+        if machine.uncommitted_events().is_empty() {
+            return Ok(());
+        }
+
+        self.repository.save(machine).await.map_err(CatalogError::from)?;
+
+        let events = machine.take_uncommitted_events();
+        let envelopes = EventEnvelope::wrap_all(machine.id(), events, now, correlation_id);
+        if let Err(failure) = self.outbox.publish(self.publisher.as_ref(), envelopes).await {
+            self.notifier.report(&failure).await;
+        }
+        Ok(())
+    }
+
+    /// Runs a command against the latest state of a machine and saves it
+    ///
+    /// If another terminal saved the machine in the meantime, the machine is loaded again
+    /// and the command retried, up to `MAX_SAVE_ATTEMPTS` times.
+    async fn execute<T, F>(&self, machine_id: u32, correlation_id: &CorrelationId, command: F) -> Result<T, CatalogError>
+    where
+        T: Send,
+        F: Fn(&mut SodaMachine) -> Result<T, SodaMachineError> + Send + Sync,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let mut machine = self.repository
+                .find_by_id(SodaMachineId::new(machine_id))
+                .await
+                .map_err(CatalogError::from)?
+                .ok_or(CatalogError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
+
+            let now = self.clock.now();
+            let output = command(&mut machine).map_err(CatalogError::MachineError)?;
+
+            match self.save_and_publish(&mut machine, now, correlation_id).await {
+                Err(CatalogError::ConcurrencyConflict(_)) if attempt < MAX_SAVE_ATTEMPTS => attempt += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(output),
+            }
+        }
+    }
+
+    async fn find(&self, sku: &Sku) -> Result<Product, CatalogError> {
+        self.catalog.find_product(sku).await
+            .map_err(CatalogError::from)?
+            .ok_or_else(|| CatalogError::ProductNotFound(sku.clone()))
+    }
+
+    fn product_dto(product: &Product) -> ProductDTO {
+        ProductDTO {
+            sku: product.sku().to_string(),
+            name: product.soda().name().to_string(),
+            description: product.soda().description(),
+            barcode: product.barcode().map(str::to_string),
+            default_price: product.default_price(),
+        }
+    }
+}
+
+#[async_trait]
+impl CatalogPort for CatalogService {
+    async fn add_product(&self, product: Product) -> Result<(), CatalogError> {
+        if self.catalog.find_product(product.sku()).await.map_err(CatalogError::from)?.is_some() {
+            return Err(CatalogError::ProductAlreadyExists(product.sku().clone()));
+        }
+
+        self.catalog.save_product(&product).await.map_err(CatalogError::from)
+    }
+
+    async fn get_product(&self, sku: &str) -> Result<ProductDTO, CatalogError> {
+        let product = self.find(&Sku::new(sku)?).await?;
+
+        Ok(Self::product_dto(&product))
+    }
+
+    async fn list_products(&self) -> Result<Vec<ProductDTO>, CatalogError> {
+        let products = self.catalog.list_products().await.map_err(CatalogError::from)?;
+
+        Ok(products.iter().map(Self::product_dto).collect())
+    }
+
+    async fn remove_product(&self, sku: &str) -> Result<(), CatalogError> {
+        let sku = Sku::new(sku)?;
+
+        let machines = self.repository.query(&MachineFilter::new().sells(sku.clone())).await
+            .map_err(CatalogError::from)?;
+        if !machines.is_empty() {
+            let machines = machines.iter().map(|machine| machine.id()).collect();
+            return Err(CatalogError::ProductInUse { sku, machines });
+        }
+
+        if self.catalog.delete_product(&sku).await.map_err(CatalogError::from)? {
+            Ok(())
+        } else {
+            Err(CatalogError::ProductNotFound(sku))
+        }
+    }
+
+    /// The catalog is updated first, so if a machine cannot be repriced, running the update
+    /// again passes the price on to the machines that were missed
+    async fn update_price(&self, sku: &str, price: Money) -> Result<PriceUpdateDTO, CatalogError> {
+        let mut product = self.find(&Sku::new(sku)?).await?;
+        product.set_default_price(price)?;
+        self.catalog.save_product(&product).await.map_err(CatalogError::from)?;
+
+        let correlation_id = CorrelationId::generate();
+        let machines = self.repository.query(&MachineFilter::new().sells(product.sku().clone())).await
+            .map_err(CatalogError::from)?;
+
+        let mut update = PriceUpdateDTO {
+            sku: product.sku().to_string(),
+            price,
+            machines: Vec::new(),
+            slots_repriced: 0,
+        };
+        for machine in machines {
+            let repriced = self.execute(machine.id().value(), &correlation_id, |machine| {
+                machine.reprice_product(product.sku(), price).map(|events| events.len())
+            }).await?;
+
+            if repriced > 0 {
+                update.machines.push(machine.id().value());
+                update.slots_repriced += repriced;
+            }
+        }
+
+        Ok(update)
+    }
+
+    async fn assign_product(&self, machine_id: u32, slot_id: u32, capacity: u32, sku: &str) -> Result<(), CatalogError> {
+        let product = self.find(&Sku::new(sku)?).await?;
+
+        self.execute(machine_id, &CorrelationId::generate(), |machine| {
+            if machine.get_slot(SlotId::new(slot_id)).is_none() {
+                machine.add_slot(SlotId::new(slot_id), capacity)?;
+            }
+
+            machine.assign_product(SlotId::new(slot_id), &product).map(|_| ())
+        }).await
+    }
+}
@@ -1,27 +1,257 @@
 use std::sync::Arc;
 use async_trait::async_trait;
-use crate::domain::aggregates::soda_machine::{SodaMachineId};
+use crate::domain::aggregates::soda_machine::{SodaMachine, SodaMachineError, SodaMachineEvent, SodaMachineId};
+use crate::domain::entities::promotion::PromotionId;
+use crate::domain::entities::sale::Sale;
 use crate::domain::entities::slot::SlotId;
+use crate::domain::entities::vend::VendFailure;
+use crate::domain::events::event_envelope::{CorrelationId, EventEnvelope};
+use crate::domain::value_objects::denomination::DenominationKind;
 use crate::domain::value_objects::money::Money;
-use crate::ports::driving::customer_port::{CustomerPort, AvailableSodaDTO, CustomerError};
+use crate::domain::value_objects::timestamp::Timestamp;
+use crate::application::outbox::Outbox;
+use crate::ports::driving::customer_port::{CustomerPort, AcceptedMoneyDTO, AvailableSodaDTO, CustomerError};
 use crate::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+use crate::ports::driven::clock_port::{Clock, SystemClock};
+use crate::ports::driven::event_publisher_port::{EventPublisher, NullEventPublisher};
+use crate::ports::driven::notification_port::{DeliveryFailure, NotificationPort, NullNotifier, StockAlert};
+use crate::ports::driven::sales_ledger_port::{NullSalesLedger, SalesLedger};
+use crate::ports::driven::hardware_port::{Acceptance, HardwareError, VendOutcome, VendingHardware};
+
+/// How many times a command is tried before a conflicting save is reported to the customer
+const MAX_SAVE_ATTEMPTS: u32 = 3;
 
 impl From<RepositoryError> for CustomerError {
     fn from(err: RepositoryError) -> Self {
         match err {
             RepositoryError::ConnectionError(msg) => CustomerError::RepositoryUnavailable(msg),
+            RepositoryError::ConcurrencyConflict { machine_id, .. } => CustomerError::ConcurrencyConflict(machine_id),
             RepositoryError::Other(e) => CustomerError::RepositoryFailure(e.to_string()),
         }
     }
 }
 
+impl From<HardwareError> for CustomerError {
+    fn from(err: HardwareError) -> Self {
+        CustomerError::HardwareFailure(err.to_string())
+    }
+}
+
 pub struct CustomerService {
     repository: Arc<dyn SodaMachineRepository>,
+    clock: Arc<dyn Clock>,
+    publisher: Arc<dyn EventPublisher>,
+    notifier: Arc<dyn NotificationPort>,
+    ledger: Arc<dyn SalesLedger>,
+    outbox: Outbox,
+    /// None when nothing physical is attached, e.g. a kiosk that only keeps the books
+    hardware: Option<VendingHardware>,
 }
 
 impl CustomerService {
     pub fn new(repository: Arc<dyn SodaMachineRepository>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            clock: Arc::new(SystemClock),
+            publisher: Arc::new(NullEventPublisher),
+            notifier: Arc::new(NullNotifier),
+            ledger: Arc::new(NullSalesLedger),
+            outbox: Outbox::new(),
+            hardware: None,
+        }
+    }
+
+    /// Replaces the system clock, e.g. with a fake one in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publishes the events of every command through the given publisher
+    pub fn with_event_publisher(mut self, publisher: Arc<dyn EventPublisher>) -> Self {
+        self.publisher = publisher;
+        self
+    }
+
+    /// Sends the stock alerts of every command through the given notifier, which also hears what could not be delivered
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationPort>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Records the sodas sold by every command in the given sales ledger
+    pub fn with_sales_ledger(mut self, ledger: Arc<dyn SalesLedger>) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// Drives the given devices: money is credited once the acceptor took it and sodas are
+    /// charged once the motor saw them drop
+    pub fn with_hardware(mut self, hardware: VendingHardware) -> Self {
+        self.hardware = Some(hardware);
+        self
+    }
+
+    /// Refuses customers while an operator has the door open
+    async fn ensure_door_closed(&self, hardware: &VendingHardware, machine_id: u32) -> Result<(), CustomerError> {
+        let machine_id = SodaMachineId::new(machine_id);
+        if hardware.door.is_open(machine_id).await? {
+            return Err(CustomerError::DoorOpen(machine_id));
+        }
+        Ok(())
+    }
+
+    /// Takes money through the coin acceptor or bill validator and credits it, ejecting it if the machine refuses it
+    ///
+    /// The money goes to the bill validator from the smallest bill of its currency up.
+    async fn insert_money_through(&self, hardware: &VendingHardware, machine_id: u32, amount: Money) -> Result<(), CustomerError> {
+        self.ensure_door_closed(hardware, machine_id).await?;
+
+        let device_id = SodaMachineId::new(machine_id);
+        let device = if amount >= amount.currency().smallest_bill() {
+            DenominationKind::Bill
+        } else {
+            DenominationKind::Coin
+        };
+        let acceptance = match device {
+            DenominationKind::Bill => hardware.bill_validator.validate(device_id, amount).await?,
+            DenominationKind::Coin => hardware.coin_acceptor.accept(device_id, amount).await?,
+        };
+
+        if let Acceptance::Rejected { reason } = acceptance {
+            hardware.display.show(device_id, &format!("{} rejected, please try another", amount)).await?;
+            return Err(CustomerError::MoneyRejected { amount, reason });
+        }
+
+        self.credit_through(hardware, machine_id, device, None, amount).await
+    }
+
+    /// Credits money the coin acceptor or bill validator took, ejecting it through that device if the machine refuses it
+    async fn credit_through(&self, hardware: &VendingHardware, machine_id: u32, device: DenominationKind, kind: Option<DenominationKind>, amount: Money) -> Result<(), CustomerError> {
+        let device_id = SodaMachineId::new(machine_id);
+        let credited = self.execute(machine_id, |machine, now, _| {
+            match kind {
+                Some(kind) => machine.insert_denomination(kind, amount, now)?,
+                None => machine.insert_money(amount, now)?,
+            };
+            Ok(machine.inserted_money())
+        }).await;
+
+        match credited {
+            Ok(credit) => {
+                hardware.display.show(device_id, &format!("Credit: {}", credit)).await?;
+                Ok(())
+            }
+            Err(err) => {
+                match device {
+                    DenominationKind::Bill => hardware.bill_validator.eject(device_id, amount).await?,
+                    DenominationKind::Coin => hardware.coin_acceptor.eject(device_id, amount).await?,
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Reserves the soda, turns the motor of its slot and charges the customer only if the soda dropped
+    async fn vend_through(&self, hardware: &VendingHardware, machine_id: u32, slot_id: u32) -> Result<(), CustomerError> {
+        self.ensure_door_closed(hardware, machine_id).await?;
+
+        let device_id = SodaMachineId::new(machine_id);
+        let vend = self.execute(machine_id, |machine, now, _| {
+            machine.reserve_vend(SlotId::new(slot_id), now)
+        }).await?;
+
+        let (failure, error) = match hardware.motor.vend(device_id, vend.slot_id).await {
+            Ok(VendOutcome::Dropped) => {
+                self.execute(machine_id, |machine, now, _| machine.confirm_vend(now).map(|_| ())).await?;
+                hardware.display.show(device_id, &format!("Enjoy your {}", vend.soda.name())).await?;
+                return Ok(());
+            }
+            Ok(VendOutcome::Failed(failure)) => (failure, CustomerError::VendFailed(failure)),
+            // Nobody knows whether the soda dropped, so the unit is quarantined rather than charged
+            Err(err) => (VendFailure::DropNotDetected, CustomerError::from(err)),
+        };
+
+        self.execute(machine_id, |machine, now, _| machine.fail_vend(failure, now).map(|_| ())).await?;
+        hardware.display.show(device_id, &format!("Sorry, {} could not be vended, please choose again", vend.soda.name())).await?;
+
+        Err(error)
+    }
+
+    /// Saves the machine, then records its sales, publishes the events the command recorded and sends its stock alerts
+    ///
+    /// Only the save can fail the command. Sales and events the ledger or publisher could not take
+    /// are kept in the outbox for the next command; every failed handover is reported to the notifier.
+    async fn save_and_publish(&self, machine: &mut SodaMachine, now: Timestamp, correlation_id: &CorrelationId) -> Result<(), CustomerError> {
+        if machine.uncommitted_events().is_empty() {
+            return Ok(());
+        }
+
+        self.repository.save(machine).await.map_err(CustomerError::from)?;
+
+        let events = machine.take_uncommitted_events();
+        let sales: Vec<Sale> = events.iter()
+            .filter_map(|(sequence, event)| Sale::from_event(machine.id(), *sequence, event))
+            .collect();
+        let alerts: Vec<StockAlert> = events.iter()
+            .filter_map(|(_, event)| StockAlert::from_event(machine.id(), event, now))
+            .collect();
+
+        if let Err(failure) = self.outbox.record_sales(self.ledger.as_ref(), sales).await {
+            self.notifier.report(&failure).await;
+        }
+
+        let envelopes = EventEnvelope::wrap_all(machine.id(), events, now, correlation_id);
+        if let Err(failure) = self.outbox.publish(self.publisher.as_ref(), envelopes).await {
+            self.notifier.report(&failure).await;
+        }
+
+        // The command already took effect, so a notifier that is down must not fail it
+        for alert in alerts {
+            if let Err(err) = self.notifier.notify(&alert).await {
+                self.notifier.report(&DeliveryFailure::StockAlert { alert, reason: err.to_string() }).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a command against the latest state of a machine and saves it
+    /// 
+    /// A session the previous customer walked away from is refunded and saved first, the command
+    /// then gets the events of that expiry. If another terminal saved the machine in the meantime,
+    /// the machine is loaded again and the command retried, up to `MAX_SAVE_ATTEMPTS` times.
+    async fn execute<T, F>(&self, machine_id: u32, command: F) -> Result<T, CustomerError>
+    where
+        T: Send,
+        F: Fn(&mut SodaMachine, Timestamp, &[SodaMachineEvent]) -> Result<T, SodaMachineError> + Send + Sync,
+    {
+        let correlation_id = CorrelationId::generate();
+        let mut attempt = 1;
+
+        loop {
+            let mut machine = self.repository
+                .find_by_id(SodaMachineId::new(machine_id))
+                .await
+                .map_err(CustomerError::from)?
+                .ok_or(CustomerError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
+
+            let now = self.clock.now();
+            let result = async {
+                let expired = machine.expire_idle_session(now).map_err(CustomerError::MachineError)?;
+                self.save_and_publish(&mut machine, now, &correlation_id).await?;
+
+                let output = command(&mut machine, now, &expired).map_err(CustomerError::MachineError)?;
+                self.save_and_publish(&mut machine, now, &correlation_id).await?;
+
+                Ok(output)
+            }.await;
+
+            match result {
+                Err(CustomerError::ConcurrencyConflict(_)) if attempt < MAX_SAVE_ATTEMPTS => attempt += 1,
+                result => return result,
+            }
+        }
     }
 }
 
@@ -34,11 +264,12 @@ impl CustomerPort for CustomerService {
             .map_err(CustomerError::from)?
             .ok_or(CustomerError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
 
-        let available_sodas = machine.get_available_sodas().into_iter().map(|(slot_id, soda)| {
+        let available_sodas = machine.get_available_sodas(self.clock.now()).into_iter().map(|(slot_id, soda, price)| {
             AvailableSodaDTO {
                 slot_id: slot_id.value(),
                 soda_name: soda.name().to_string(),
-                price: format!("{:.2}", soda.price().as_decimal()),
+                price: price.to_decimal_string(),
+                currency: price.currency(),
             }
         }).collect();
        
@@ -46,45 +277,93 @@ impl CustomerPort for CustomerService {
     }
 
     async fn insert_money(&self, machine_id: u32, amount: Money) -> Result<(), CustomerError> {
-        let mut machine = self.repository
+        if let Some(hardware) = &self.hardware {
+            return self.insert_money_through(hardware, machine_id, amount).await;
+        }
+
+        self.execute(machine_id, |machine, now, _| {
+            machine.insert_money(amount, now).map(|_| ())
+        }).await
+    }
+
+    async fn accepted_money(&self, machine_id: u32) -> Result<AcceptedMoneyDTO, CustomerError> {
+        let machine = self.repository
             .find_by_id(SodaMachineId::new(machine_id))
             .await
             .map_err(CustomerError::from)?
             .ok_or(CustomerError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
 
-        machine.insert_money(amount).map_err(CustomerError::MachineError)?;
-       
-        self.repository.save(&machine).await.map_err(CustomerError::from)?;
+        Ok(AcceptedMoneyDTO {
+            currency: machine.currency(),
+            denominations: machine.accepted_denominations().cloned(),
+        })
+    }
 
-        Ok(())
+    async fn insert_denomination(&self, machine_id: u32, kind: DenominationKind, amount: Money) -> Result<(), CustomerError> {
+        // The device already took the money, so it is not asked to accept it again
+        if let Some(hardware) = &self.hardware {
+            self.ensure_door_closed(hardware, machine_id).await?;
+            return self.credit_through(hardware, machine_id, kind, Some(kind), amount).await;
+        }
+
+        self.execute(machine_id, |machine, now, _| {
+            machine.insert_denomination(kind, amount, now).map(|_| ())
+        }).await
+    }
+
+    async fn enter_coupon(&self, machine_id: u32, code: &str) -> Result<(), CustomerError> {
+        let code = PromotionId::new(code).map_err(|e| CustomerError::Validation(e.to_string()))?;
+
+        self.execute(machine_id, |machine, now, _| {
+            machine.enter_coupon(&code, now).map(|_| ())
+        }).await
     }
 
     async fn buy_soda(&self, machine_id: u32, slot_id: u32) -> Result<(), CustomerError> {
-        let mut machine = self.repository
-            .find_by_id(SodaMachineId::new(machine_id))
-            .await
-            .map_err(CustomerError::from)?
-            .ok_or(CustomerError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
-       
-        machine.dispense_soda(SlotId::new(slot_id)).map_err(CustomerError::MachineError)?;
+        if let Some(hardware) = &self.hardware {
+            return self.vend_through(hardware, machine_id, slot_id).await;
+        }
 
-        self.repository.save(&machine).await.map_err(CustomerError::from)?;
-       
-        Ok(())
+        self.execute(machine_id, |machine, now, _| {
+            machine.dispense_soda(SlotId::new(slot_id), now).map(|_| ())
+        }).await
+    }
+
+    async fn reserve_soda(&self, machine_id: u32, slot_id: u32) -> Result<(), CustomerError> {
+        self.execute(machine_id, |machine, now, _| {
+            machine.reserve_vend(SlotId::new(slot_id), now).map(|_| ())
+        }).await
+    }
+
+    async fn confirm_vend(&self, machine_id: u32) -> Result<(), CustomerError> {
+        self.execute(machine_id, |machine, now, _| {
+            machine.confirm_vend(now).map(|_| ())
+        }).await
+    }
+
+    async fn report_vend_failure(&self, machine_id: u32, failure: VendFailure) -> Result<(), CustomerError> {
+        self.execute(machine_id, |machine, now, _| {
+            machine.fail_vend(failure, now).map(|_| ())
+        }).await
     }
    
     async fn request_money_back(&self, machine_id: u32) -> Result<Money, CustomerError> {
-        let mut machine = self.repository
-            .find_by_id(SodaMachineId::new(machine_id))
-            .await
-            .map_err(CustomerError::from)?
-            .ok_or(CustomerError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
-       
-        let inserted_money = machine.inserted_money();
-        machine.return_money().map_err(CustomerError::MachineError)?;
-       
-        self.repository.save(&machine).await.map_err(CustomerError::from)?;
+        self.execute(machine_id, |machine, now, expired| {
+            // A session that already timed out has been refunded by the expiry itself
+            let events = if expired.is_empty() {
+                machine.return_money(now)?
+            } else {
+                expired.to_vec()
+            };
+
+            let returned = events.iter()
+                .find_map(|event| match event {
+                    SodaMachineEvent::MoneyReturned { amount, .. } => Some(*amount),
+                    _ => None,
+                })
+                .unwrap_or(Money::zero_in(machine.currency()));
 
-        Ok(inserted_money)
+            Ok(returned)
+        }).await
     }
 }
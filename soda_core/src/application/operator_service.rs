@@ -1,15 +1,40 @@
 use std::sync::Arc;
+use std::time::Duration;
 use async_trait::async_trait;
-use crate::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
+use crate::domain::aggregates::soda_machine::{SodaMachine, SodaMachineError, SodaMachineEvent, SodaMachineId};
+use crate::domain::entities::cash_collection::{CashCollection, OperatorId};
+use crate::domain::entities::price_schedule::PriceChange;
+use crate::domain::entities::promotion::{DailyHours, Discount, Promotion, PromotionId, PromotionScope};
+use crate::domain::entities::product::Sku;
+use crate::domain::entities::sale::Sale;
 use crate::domain::entities::slot::SlotId;
-use crate::domain::value_objects::soda::Soda;
-use crate::ports::driving::operator_port::{OperatorPort, OperatorError};
-use crate::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+use crate::domain::entities::vend::VendFailure;
+use crate::domain::events::event_envelope::{CorrelationId, EventEnvelope};
+use crate::domain::value_objects::denomination::AcceptedDenominations;
+use crate::domain::value_objects::planogram::{Planogram, PlanogramSlot};
+use crate::domain::value_objects::soda::{Soda, SodaFlavor, SodaSize};
+use crate::domain::value_objects::money::{Currency, Money, MoneyError};
+use crate::domain::value_objects::timestamp::Timestamp;
+use crate::application::outbox::Outbox;
+use crate::ports::driving::operator_port::{
+    CashReconciliationDTO, CurrencyTotalsDTO, FleetDashboardDTO, MachineDashboardDTO, MachineRestockDTO, MachineStatusDTO, MachineSummaryDTO,
+    DiscountDTO, OperatorPort, OperatorError, PickListItemDTO, PlanogramDTO, PlanogramProductDTO, PlanogramSlotDTO,
+    PendingVendDTO, PriceChangeDTO, PromotionDTO, RefundDTO, RestockPlanDTO, SlotRestockDTO, SlotStatusDTO,
+};
+use crate::ports::driven::soda_machine_repository_port::{MachineFilter, SodaMachineRepository, RepositoryError};
+use crate::ports::driven::clock_port::{Clock, SystemClock};
+use crate::ports::driven::event_publisher_port::{EventPublisher, NullEventPublisher};
+use crate::ports::driven::notification_port::{DeliveryFailure, NotificationPort, NullNotifier, StockAlert};
+use crate::ports::driven::sales_ledger_port::{NullSalesLedger, SalesLedger};
+
+/// How many times a command is tried before a conflicting save is reported to the operator
+const MAX_SAVE_ATTEMPTS: u32 = 3;
 
 impl From<RepositoryError> for OperatorError {
     fn from(err: RepositoryError) -> Self {
         match err {
             RepositoryError::ConnectionError(msg) => OperatorError::RepositoryUnavailable(msg),
+            RepositoryError::ConcurrencyConflict { machine_id, .. } => OperatorError::ConcurrencyConflict(machine_id),
             RepositoryError::Other(e) => OperatorError::RepositoryFailure(e.to_string()),
         }
     }
@@ -17,21 +42,452 @@ impl From<RepositoryError> for OperatorError {
 
 pub struct OperatorService {
     repository: Arc<dyn SodaMachineRepository>,
+    clock: Arc<dyn Clock>,
+    publisher: Arc<dyn EventPublisher>,
+    notifier: Arc<dyn NotificationPort>,
+    ledger: Arc<dyn SalesLedger>,
+    outbox: Outbox,
 }
 
 impl OperatorService {
     pub fn new(repository: Arc<dyn SodaMachineRepository>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            clock: Arc::new(SystemClock),
+            publisher: Arc::new(NullEventPublisher),
+            notifier: Arc::new(NullNotifier),
+            ledger: Arc::new(NullSalesLedger),
+            outbox: Outbox::new(),
+        }
+    }
+
+    /// Replaces the system clock, e.g. with a fake one in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publishes the events of every command through the given publisher
+    pub fn with_event_publisher(mut self, publisher: Arc<dyn EventPublisher>) -> Self {
+        self.publisher = publisher;
+        self
+    }
+
+    /// Sends the stock alerts of every command through the given notifier, which also hears what could not be delivered
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationPort>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Records the sodas sold by every command in the given sales ledger, e.g. a vend the operator resolved as delivered
+    pub fn with_sales_ledger(mut self, ledger: Arc<dyn SalesLedger>) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// Saves the machine, then records its sales and publishes the events the command recorded
+    async fn save_and_publish(&self, machine: &mut SodaMachine, now: Timestamp, correlation_id: &CorrelationId) -> Result<(), OperatorError> {
+        if machine.uncommitted_events().is_empty() {
+            return Ok(());
+        }
+
+        self.repository.save(machine).await.map_err(OperatorError::from)?;
+        self.publish_events(machine, now, correlation_id).await;
+        Ok(())
+    }
+
+    /// Records the sales and publishes the events recorded since the machine was last saved, and sends their stock alerts
+    ///
+    /// The machine is already saved, so nothing here fails the command: sales and events the ledger or
+    /// publisher could not take are kept in the outbox for the next command; every failed handover is
+    /// reported to the notifier.
+    async fn publish_events(&self, machine: &mut SodaMachine, now: Timestamp, correlation_id: &CorrelationId) {
+        let events = machine.take_uncommitted_events();
+        let sales: Vec<Sale> = events.iter()
+            .filter_map(|(sequence, event)| Sale::from_event(machine.id(), *sequence, event))
+            .collect();
+        let alerts: Vec<StockAlert> = events.iter()
+            .filter_map(|(_, event)| StockAlert::from_event(machine.id(), event, now))
+            .collect();
+
+        if let Err(failure) = self.outbox.record_sales(self.ledger.as_ref(), sales).await {
+            self.notifier.report(&failure).await;
+        }
+
+        let envelopes = EventEnvelope::wrap_all(machine.id(), events, now, correlation_id);
+        if let Err(failure) = self.outbox.publish(self.publisher.as_ref(), envelopes).await {
+            self.notifier.report(&failure).await;
+        }
+
+        // The command already took effect, so a notifier that is down must not fail it
+        for alert in alerts {
+            if let Err(err) = self.notifier.notify(&alert).await {
+                self.notifier.report(&DeliveryFailure::StockAlert { alert, reason: err.to_string() }).await;
+            }
+        }
+    }
+
+    /// Runs a command against the latest state of a machine and saves it
+    /// 
+    /// If another terminal saved the machine in the meantime, the machine is loaded again
+    /// and the command retried, up to `MAX_SAVE_ATTEMPTS` times.
+    async fn execute<T, F>(&self, machine_id: u32, command: F) -> Result<T, OperatorError>
+    where
+        T: Send,
+        F: Fn(&mut SodaMachine, Timestamp) -> Result<T, SodaMachineError> + Send + Sync,
+    {
+        let correlation_id = CorrelationId::generate();
+        let mut attempt = 1;
+
+        loop {
+            let mut machine = self.repository
+                .find_by_id(SodaMachineId::new(machine_id))
+                .await
+                .map_err(OperatorError::from)?
+                .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
+
+            let now = self.clock.now();
+            let output = command(&mut machine, now).map_err(OperatorError::MachineError)?;
+
+            match self.save_and_publish(&mut machine, now, &correlation_id).await {
+                Err(OperatorError::ConcurrencyConflict(_)) if attempt < MAX_SAVE_ATTEMPTS => attempt += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(output),
+            }
+        }
+    }
+
+    fn summarize(machine: &SodaMachine) -> MachineSummaryDTO {
+        MachineSummaryDTO {
+            machine_id: machine.id().value(),
+            location: machine.location().map(str::to_string),
+            is_operational: machine.is_operational(),
+            slot_count: machine.slot_count(),
+            total_sodas: machine.get_all_slots().values().map(|slot| slot.quantity()).sum(),
+            currency: machine.currency(),
+        }
+    }
+
+    fn status(machine: &SodaMachine, now: Timestamp) -> MachineStatusDTO {
+        let mut slots: Vec<SlotStatusDTO> = machine.get_all_slots().values()
+            .map(|slot| SlotStatusDTO {
+                slot_id: slot.id().value(),
+                soda_name: slot.soda_type().map(|soda| soda.name().to_string()),
+                sku: slot.sku().map(|sku| sku.to_string()),
+                price: machine.price_at(slot.id(), now),
+                quantity: slot.quantity(),
+                max_capacity: slot.max_capacity(),
+                fill_percentage: slot.fill_percentage(),
+                low_stock_threshold: slot.low_stock_threshold(),
+                is_enabled: slot.is_enabled(),
+            })
+            .collect();
+        slots.sort_by_key(|slot| slot.slot_id);
+
+        MachineStatusDTO {
+            machine_id: machine.id().value(),
+            location: machine.location().map(str::to_string),
+            currency: machine.currency(),
+            is_operational: machine.is_operational(),
+            slots,
+            available_sodas: machine.get_available_sodas(now).len(),
+            total_sodas: machine.total_soda_count(),
+            inventory_value: machine.total_inventory_value(),
+            inserted_money: machine.inserted_money(),
+            total_collected: machine.total_collected(),
+            cash_in_box: machine.cash_box().total(),
+            pending_vend: machine.pending_vend().map(|vend| PendingVendDTO {
+                slot_id: vend.slot_id.value(),
+                soda_name: vend.soda.name().to_string(),
+                price: vend.price,
+                reserved_at: vend.reserved_at,
+            }),
+        }
+    }
+
+    /// Gets what every stocked slot still fits, as `SodaMachine::restock` would fill it
+    fn restock_needs(machine: &SodaMachine) -> MachineRestockDTO {
+        let mut slots: Vec<SlotRestockDTO> = machine.get_all_slots().values()
+            .filter(|slot| slot.is_enabled() && slot.remaining_capacity() > 0)
+            .filter_map(|slot| slot.soda_type().map(|soda| SlotRestockDTO {
+                slot_id: slot.id().value(),
+                product: soda.description(),
+                units: slot.remaining_capacity(),
+            }))
+            .collect();
+        slots.sort_by_key(|slot| slot.slot_id);
+
+        MachineRestockDTO {
+            machine_id: machine.id().value(),
+            location: machine.location().map(str::to_string),
+            slots,
+        }
+    }
+
+    /// Adds up the units of each product over all machines
+    fn pick_list(machines: &[MachineRestockDTO]) -> Vec<PickListItemDTO> {
+        let mut pick_list: Vec<PickListItemDTO> = Vec::new();
+
+        for slot in machines.iter().flat_map(|machine| &machine.slots) {
+            match pick_list.iter_mut().find(|item| item.product == slot.product) {
+                Some(item) => item.units += slot.units,
+                None => pick_list.push(PickListItemDTO { product: slot.product.clone(), units: slot.units }),
+            }
+        }
+        pick_list.sort_by(|a, b| a.product.cmp(&b.product));
+
+        pick_list
+    }
+
+    fn sku_from(sku: &str) -> Result<Sku, OperatorError> {
+        Sku::new(sku).map_err(|e| OperatorError::Validation(e.to_string()))
+    }
+
+    /// Gets the price in effect for each product plus the changes still to come
+    fn price_changes(machine: &SodaMachine, now: Timestamp) -> Vec<PriceChangeDTO> {
+        let changes = machine.price_schedule().changes();
+
+        changes.iter()
+            .enumerate()
+            .filter(|(index, change)| {
+                // Of the changes that took effect, only the latest one of each product still counts
+                let superseded = changes.get(index + 1)
+                    .is_some_and(|next| next.sku == change.sku && next.effective_at <= now);
+                change.effective_at > now || (change.price.is_some() && !superseded)
+            })
+            .map(|(_, change)| PriceChangeDTO {
+                sku: change.sku.to_string(),
+                price: change.price,
+                effective_at: change.effective_at,
+                is_upcoming: change.effective_at > now,
+            })
+            .collect()
+    }
+
+    /// Reads the slots of a planogram file, checking every product with the same rules as `Soda::new`
+    fn planogram_from(planogram: &PlanogramDTO) -> Result<Planogram, OperatorError> {
+        let slots = planogram.slots.iter()
+            .map(|slot| Ok(PlanogramSlot {
+                slot_id: SlotId::new(slot.slot_id),
+                capacity: slot.capacity,
+                low_stock_threshold: slot.low_stock_threshold,
+                sku: slot.product.as_ref()
+                    .and_then(|product| product.sku.as_deref())
+                    .map(|sku| Sku::new(sku).map_err(|e| OperatorError::Validation(format!("Slot {}: {}", slot.slot_id, e))))
+                    .transpose()?,
+                soda: slot.product.as_ref()
+                    .map(|product| Self::soda_from(slot.slot_id, product, planogram.currency))
+                    .transpose()?,
+            }))
+            .collect::<Result<Vec<_>, OperatorError>>()?;
+
+        Planogram::new(slots).map_err(|e| OperatorError::Validation(e.to_string()))
+    }
+
+    fn soda_from(slot_id: u32, product: &PlanogramProductDTO, currency: Currency) -> Result<Soda, OperatorError> {
+        let invalid = |reason: String| OperatorError::Validation(format!("Slot {}: {}", slot_id, reason));
+
+        let flavor = SodaFlavor::from_string(&product.flavor)
+            .ok_or_else(|| invalid(format!("unknown flavor '{}'", product.flavor)))?;
+        let size = SodaSize::from_string(&product.size)
+            .ok_or_else(|| invalid(format!("unknown size '{}'", product.size)))?;
+        let price = Money::parse_in(&product.price, currency)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Soda::new(product.name.clone(), flavor, size, price, product.is_diet, product.is_caffeinated)
+            .map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Reads a promotion of a campaign file, with amounts in the currency of the machine it is launched on
+    fn promotion_from(promotion: &PromotionDTO, currency: Currency) -> Result<Promotion, OperatorError> {
+        let invalid = |reason: String| OperatorError::Validation(format!("Promotion {}: {}", promotion.id, reason));
+
+        let discount = match &promotion.discount {
+            DiscountDTO::PercentOff(percent) => Discount::PercentOff(*percent),
+            DiscountDTO::AmountOff(amount) => Discount::AmountOff(
+                Money::parse_in(amount, currency).map_err(|e| invalid(e.to_string()))?
+            ),
+            DiscountDTO::BuyNGetOneFree(n) => Discount::BuyNGetOneFree(*n),
+        };
+        let time = |time: &Option<String>| {
+            time.as_deref()
+                .map(|time| time.parse::<Timestamp>().map_err(|e| invalid(e.to_string())))
+                .transpose()
+        };
+
+        let (valid_from, valid_until) = (time(&promotion.valid_from)?, time(&promotion.valid_until)?);
+
+        let id = PromotionId::new(&promotion.id).map_err(|e| invalid(e.to_string()))?;
+        let mut result = Promotion::new(id, promotion.name.clone(), discount)
+            .and_then(|result| result.valid_between(valid_from, valid_until))
+            .map_err(|e| invalid(e.to_string()))?;
+
+        match (&promotion.flavor, &promotion.sku) {
+            (Some(_), Some(_)) => return Err(invalid("a promotion is either for a flavor or for a product".to_string())),
+            (Some(flavor), None) => {
+                let flavor = SodaFlavor::from_string(flavor)
+                    .ok_or_else(|| invalid(format!("unknown flavor '{}'", flavor)))?;
+                result = result.for_flavor(flavor);
+            },
+            (None, Some(sku)) => result = result.for_product(Sku::new(sku).map_err(|e| invalid(e.to_string()))?),
+            (None, None) => {},
+        }
+
+        if let Some(hours) = &promotion.hours {
+            result = result.during(Self::hours_from(hours).ok_or_else(|| {
+                invalid(format!("'{}' is not a time of day like 16:00-18:00", hours))
+            })?);
+        }
+
+        Ok(if promotion.is_coupon { result.as_coupon() } else { result })
+    }
+
+    /// Reads a time of day like "16:00-18:00"
+    fn hours_from(hours: &str) -> Option<DailyHours> {
+        let minute = |time: &str| {
+            let (hour, minute) = time.trim().split_once(':')?;
+            let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+            (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+        };
+
+        let (from, until) = hours.split_once('-')?;
+        DailyHours::new(minute(from)?, minute(until)?).ok()
+    }
+
+    fn promotion_dto(promotion: &Promotion, now: Timestamp) -> PromotionDTO {
+        let (flavor, sku) = match promotion.scope() {
+            PromotionScope::AllProducts => (None, None),
+            PromotionScope::Flavor(flavor) => (Some(flavor.code().to_string()), None),
+            PromotionScope::Product(sku) => (None, Some(sku.to_string())),
+        };
+
+        PromotionDTO {
+            id: promotion.id().to_string(),
+            name: promotion.name().to_string(),
+            discount: match promotion.discount() {
+                Discount::PercentOff(percent) => DiscountDTO::PercentOff(percent),
+                Discount::AmountOff(amount) => DiscountDTO::AmountOff(amount.to_decimal_string()),
+                Discount::BuyNGetOneFree(n) => DiscountDTO::BuyNGetOneFree(n),
+            },
+            flavor,
+            sku,
+            valid_from: promotion.valid_from().map(|time| time.to_string()),
+            valid_until: promotion.valid_until().map(|time| time.to_string()),
+            hours: promotion.daily_hours().map(|hours| hours.to_string()),
+            is_coupon: promotion.is_coupon(),
+            is_active: promotion.is_active_at(now),
+        }
+    }
+
+    fn reconciliation(machine_id: u32, collection: &CashCollection) -> Result<CashReconciliationDTO, OperatorError> {
+        let invalid = |e: MoneyError| OperatorError::Validation(e.to_string());
+
+        Ok(CashReconciliationDTO {
+            machine_id,
+            operator_id: collection.operator_id.to_string(),
+            collected_at: collection.collected_at,
+            period_start: collection.period_start,
+            revenue: collection.revenue,
+            float: collection.float,
+            kept: collection.kept,
+            expected: collection.expected().map_err(invalid)?,
+            counted: collection.counted,
+            variance: collection.variance().map_err(invalid)?,
+        })
+    }
+
+    fn planogram_dto(machine: &SodaMachine) -> PlanogramDTO {
+        let slots = machine.planogram().slots().iter()
+            .map(|slot| PlanogramSlotDTO {
+                slot_id: slot.slot_id.value(),
+                capacity: slot.capacity,
+                low_stock_threshold: slot.low_stock_threshold,
+                product: slot.soda.as_ref().map(|soda| PlanogramProductDTO {
+                    sku: slot.sku.as_ref().map(|sku| sku.to_string()),
+                    name: soda.name().to_string(),
+                    flavor: soda.flavor().code().to_string(),
+                    size: soda.size().code().to_string(),
+                    price: soda.price().to_decimal_string(),
+                    is_diet: soda.is_diet(),
+                    is_caffeinated: soda.is_caffeinated(),
+                }),
+            })
+            .collect();
+
+        PlanogramDTO { currency: machine.currency(), slots }
+    }
+
+    fn dashboard(machine: &SodaMachine, low_stock_threshold: u32) -> MachineDashboardDTO {
+        let mut stocked_slots: Vec<_> = machine.get_all_slots().values()
+            .filter(|slot| slot.soda_type().is_some())
+            .collect();
+        stocked_slots.sort_by_key(|slot| slot.id());
+
+        MachineDashboardDTO {
+            machine_id: machine.id().value(),
+            location: machine.location().map(str::to_string),
+            is_operational: machine.is_operational(),
+            total_collected: machine.total_collected(),
+            inventory_value: machine.total_inventory_value(),
+            inserted_money: machine.inserted_money(),
+            empty_slots: stocked_slots.iter()
+                .filter(|slot| slot.is_empty())
+                .map(|slot| slot.id().value())
+                .collect(),
+            low_slots: stocked_slots.iter()
+                .filter(|slot| !slot.is_empty() && slot.quantity() <= low_stock_threshold)
+                .map(|slot| slot.id().value())
+                .collect(),
+        }
+    }
+
+    /// Adds up the machines per currency, in currency order
+    fn currency_totals(machines: &[MachineDashboardDTO]) -> Result<Vec<CurrencyTotalsDTO>, OperatorError> {
+        let mut totals: Vec<CurrencyTotalsDTO> = Vec::new();
+
+        for machine in machines {
+            let currency = machine.total_collected.currency();
+            let index = match totals.iter().position(|totals| totals.currency == currency) {
+                Some(index) => index,
+                None => {
+                    totals.push(CurrencyTotalsDTO {
+                        currency,
+                        machine_count: 0,
+                        total_collected: Money::zero_in(currency),
+                        inventory_value: Money::zero_in(currency),
+                        inserted_money: Money::zero_in(currency),
+                    });
+                    totals.len() - 1
+                }
+            };
+
+            let add = |total: Money, amount: Money| {
+                (total + amount).map_err(|e| OperatorError::MachineError(e.into()))
+            };
+            let entry = &mut totals[index];
+            entry.machine_count += 1;
+            entry.total_collected = add(entry.total_collected, machine.total_collected)?;
+            entry.inventory_value = add(entry.inventory_value, machine.inventory_value)?;
+            entry.inserted_money = add(entry.inserted_money, machine.inserted_money)?;
+        }
+        totals.sort_by_key(|totals| totals.currency);
+
+        Ok(totals)
     }
 }
 
 #[async_trait]
 impl OperatorPort for OperatorService {
     async fn create_new_machine(&self, machine_id: u32, max_slots: u32) -> Result<(), OperatorError> {
-        let machine = SodaMachine::new(SodaMachineId::new(machine_id), max_slots)
+        self.create_new_machine_with_currency(machine_id, max_slots, Currency::USD).await
+    }
+
+    async fn create_new_machine_with_currency(&self, machine_id: u32, max_slots: u32, currency: Currency) -> Result<(), OperatorError> {
+        let mut machine = SodaMachine::new_with_currency(SodaMachineId::new(machine_id), max_slots, currency)
             .map_err(OperatorError::MachineError)?;
        
         self.repository.create(&machine).await.map_err(OperatorError::from)?;
+        self.publish_events(&mut machine, self.clock.now(), &CorrelationId::generate()).await;
        
         Ok(())
     }
@@ -43,45 +499,311 @@ impl OperatorPort for OperatorService {
         capacity: u32,
         soda: Soda
     ) -> Result<(), OperatorError> {
-        let mut machine = self.repository
+        self.execute(machine_id, |machine, _| {
+            if machine.get_slot(SlotId::new(slot_id)).is_none() {
+                machine.add_slot(SlotId::new(slot_id), capacity)?;
+            }
+
+            machine.configure_slot(SlotId::new(slot_id), soda.clone()).map(|_| ())
+        }).await
+    }
+
+    async fn refill_slot(&self, machine_id: u32, slot_id: u32, quantity: u32) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.refill_slot(SlotId::new(slot_id), quantity).map(|_| ())
+        }).await
+    }
+
+    async fn set_low_stock_threshold(&self, machine_id: u32, slot_id: u32, threshold: u32) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.set_low_stock_threshold(SlotId::new(slot_id), threshold).map(|_| ())
+        }).await
+    }
+
+    async fn import_planogram(&self, machine_id: u32, planogram: PlanogramDTO) -> Result<(), OperatorError> {
+        let currency = planogram.currency;
+        let planogram = Self::planogram_from(&planogram)?;
+
+        self.execute(machine_id, |machine, _| {
+            if currency != machine.currency() {
+                return Err(SodaMachineError::CurrencyMismatch { expected: machine.currency(), actual: currency });
+            }
+
+            machine.apply_planogram(&planogram).map(|_| ())
+        }).await
+    }
+
+    async fn export_planogram(&self, machine_id: u32) -> Result<PlanogramDTO, OperatorError> {
+        let machine = self.repository
             .find_by_id(SodaMachineId::new(machine_id))
             .await
             .map_err(OperatorError::from)?
             .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
 
-        if machine.get_slot(SlotId::new(slot_id)).is_none() {
-            machine.add_slot(SlotId::new(slot_id), capacity).map_err(OperatorError::MachineError)?;
-        }
-       
-        machine.configure_slot(SlotId::new(slot_id), soda).map_err(OperatorError::MachineError)?;
+        Ok(Self::planogram_dto(&machine))
+    }
 
-        self.repository.save(&machine).await.map_err(OperatorError::from)?;
+    async fn set_price_override(&self, machine_id: u32, sku: &str, price: Option<Money>) -> Result<(), OperatorError> {
+        let sku = Self::sku_from(sku)?;
 
-        Ok(())
+        self.execute(machine_id, |machine, now| {
+            machine.override_price(&sku, price, now).map(|_| ())
+        }).await
     }
 
-    async fn refill_slot(&self, machine_id: u32, slot_id: u32, quantity: u32) -> Result<(), OperatorError> {
-        let mut machine = self.repository
+    async fn schedule_price_change(&self, machine_id: u32, sku: &str, price: Option<Money>, effective_at: Timestamp) -> Result<(), OperatorError> {
+        let change = PriceChange { sku: Self::sku_from(sku)?, price, effective_at };
+
+        self.execute(machine_id, |machine, now| {
+            machine.schedule_price_change(change.clone(), now).map(|_| ())
+        }).await
+    }
+
+    async fn cancel_price_change(&self, machine_id: u32, sku: &str, effective_at: Timestamp) -> Result<(), OperatorError> {
+        let sku = Self::sku_from(sku)?;
+
+        self.execute(machine_id, |machine, now| {
+            machine.cancel_price_change(&sku, effective_at, now).map(|_| ())
+        }).await
+    }
+
+    async fn list_price_changes(&self, machine_id: u32) -> Result<Vec<PriceChangeDTO>, OperatorError> {
+        let machine = self.repository
             .find_by_id(SodaMachineId::new(machine_id))
             .await
             .map_err(OperatorError::from)?
             .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
 
-        machine.refill_slot(SlotId::new(slot_id), quantity).map_err(OperatorError::MachineError)?;
-       
-        self.repository.save(&machine).await.map_err(OperatorError::from)?;
-       
-        Ok(())
+        Ok(Self::price_changes(&machine, self.clock.now()))
+    }
+
+    async fn add_promotion(&self, machine_id: u32, promotion: PromotionDTO) -> Result<(), OperatorError> {
+        let currency = self.repository
+            .find_by_id(SodaMachineId::new(machine_id))
+            .await
+            .map_err(OperatorError::from)?
+            .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?
+            .currency();
+        let promotion = Self::promotion_from(&promotion, currency)?;
+
+        self.execute(machine_id, |machine, _| {
+            machine.add_promotion(promotion.clone()).map(|_| ())
+        }).await
+    }
+
+    async fn remove_promotion(&self, machine_id: u32, promotion_id: &str) -> Result<(), OperatorError> {
+        let promotion_id = PromotionId::new(promotion_id).map_err(|e| OperatorError::Validation(e.to_string()))?;
+
+        self.execute(machine_id, |machine, _| {
+            machine.remove_promotion(&promotion_id).map(|_| ())
+        }).await
+    }
+
+    async fn list_promotions(&self, machine_id: u32) -> Result<Vec<PromotionDTO>, OperatorError> {
+        let machine = self.repository
+            .find_by_id(SodaMachineId::new(machine_id))
+            .await
+            .map_err(OperatorError::from)?
+            .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
+
+        let now = self.clock.now();
+        Ok(machine.promotions().iter().map(|promotion| Self::promotion_dto(promotion, now)).collect())
+    }
+
+    async fn plan_restock(&self, machine_ids: &[u32]) -> Result<RestockPlanDTO, OperatorError> {
+        let mut machines = Vec::with_capacity(machine_ids.len());
+        for machine_id in machine_ids {
+            let machine = self.repository
+                .find_by_id(SodaMachineId::new(*machine_id))
+                .await
+                .map_err(OperatorError::from)?
+                .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(*machine_id)))?;
+
+            machines.push(Self::restock_needs(&machine));
+        }
+
+        Ok(RestockPlanDTO { pick_list: Self::pick_list(&machines), machines })
     }
 
-    async fn get_machine_status(&self, machine_id: u32) -> Result<String, OperatorError> {
+    async fn restock_machine(&self, machine_id: u32) -> Result<MachineRestockDTO, OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            let events = machine.restock()?;
+
+            // Reported from the events, so a retried restock reports what the last attempt added
+            let slots = events.iter()
+                .filter_map(|event| match event {
+                    SodaMachineEvent::SlotRefilled { slot_id, quantity_added } => Some(SlotRestockDTO {
+                        slot_id: slot_id.value(),
+                        product: machine.get_slot(*slot_id)
+                            .and_then(|slot| slot.soda_type())
+                            .map(|soda| soda.description())
+                            .unwrap_or_default(),
+                        units: *quantity_added,
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            Ok(MachineRestockDTO {
+                machine_id: machine.id().value(),
+                location: machine.location().map(str::to_string),
+                slots,
+            })
+        }).await
+    }
+
+    async fn load_cash(&self, machine_id: u32, denomination: Money, count: u32) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.load_cash(denomination, count).map(|_| ())
+        }).await
+    }
+
+    async fn unload_cash(&self, machine_id: u32, denomination: Money, count: u32) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.unload_cash(denomination, count).map(|_| ())
+        }).await
+    }
+
+    async fn collect_cash(&self, machine_id: u32, operator_id: &str, counted: Money) -> Result<CashReconciliationDTO, OperatorError> {
+        let operator_id = OperatorId::new(operator_id).map_err(|e| OperatorError::Validation(e.to_string()))?;
+
+        let collection = self.execute(machine_id, |machine, now| {
+            machine.collect_cash(operator_id.clone(), counted, now)
+        }).await?;
+
+        Self::reconciliation(machine_id, &collection)
+    }
+
+    async fn issue_refund(&self, machine_id: u32, operator_id: &str, amount: Money, reason: &str) -> Result<RefundDTO, OperatorError> {
+        let operator_id = OperatorId::new(operator_id).map_err(|e| OperatorError::Validation(e.to_string()))?;
+
+        self.execute(machine_id, |machine, now| {
+            let refund = machine.issue_refund(operator_id.clone(), amount, reason, now)?;
+            Ok(RefundDTO {
+                machine_id,
+                operator_id: refund.operator_id.to_string(),
+                amount: refund.amount,
+                reason: refund.reason,
+                issued_at: refund.issued_at,
+                coins: refund.breakdown.items().to_vec(),
+                total_collected: machine.total_collected(),
+            })
+        }).await
+    }
+
+    async fn resolve_pending_vend(&self, machine_id: u32, failure: Option<VendFailure>) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, now| {
+            match failure {
+                None => machine.confirm_vend(now).map(|_| ()),
+                Some(failure) => machine.fail_vend(failure, now).map(|_| ()),
+            }
+        }).await
+    }
+
+    async fn set_session_timeout(&self, machine_id: u32, timeout: Duration) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.set_session_timeout(timeout).map(|_| ())
+        }).await
+    }
+
+    async fn set_accepted_denominations(&self, machine_id: u32, coins: &[Money], bills: &[Money]) -> Result<(), OperatorError> {
+        let currency = coins.iter().chain(bills).next().map(|denomination| denomination.currency()).unwrap_or_default();
+        let denominations = AcceptedDenominations::new(currency, coins, bills)
+            .map_err(|e| OperatorError::Validation(e.to_string()))?;
+
+        self.execute(machine_id, |machine, _| {
+            machine.set_accepted_denominations(Some(denominations.clone())).map(|_| ())
+        }).await
+    }
+
+    async fn accept_any_denomination(&self, machine_id: u32) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.set_accepted_denominations(None).map(|_| ())
+        }).await
+    }
+
+    async fn expire_idle_session(&self, machine_id: u32) -> Result<Money, OperatorError> {
+        self.execute(machine_id, |machine, now| {
+            let events = machine.expire_idle_session(now)?;
+
+            let refunded = events.iter()
+                .find_map(|event| match event {
+                    SodaMachineEvent::MoneyReturned { amount, .. } => Some(*amount),
+                    _ => None,
+                })
+                .unwrap_or(Money::zero_in(machine.currency()));
+
+            Ok(refunded)
+        }).await
+    }
+
+    async fn get_machine_status(&self, machine_id: u32) -> Result<MachineStatusDTO, OperatorError> {
         let machine = self.repository
             .find_by_id(SodaMachineId::new(machine_id))
             .await
             .map_err(OperatorError::from)?
             .ok_or(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))?;
 
-        Ok(machine.status_summary())
+        Ok(Self::status(&machine, self.clock.now()))
+    }
+
+    async fn get_fleet_dashboard(&self, low_stock_threshold: u32) -> Result<FleetDashboardDTO, OperatorError> {
+        let machines: Vec<MachineDashboardDTO> = self.repository.list().await
+            .map_err(OperatorError::from)?
+            .iter()
+            .map(|machine| Self::dashboard(machine, low_stock_threshold))
+            .collect();
+
+        Ok(FleetDashboardDTO {
+            totals: Self::currency_totals(&machines)?,
+            out_of_service: machines.iter()
+                .filter(|machine| !machine.is_operational)
+                .map(|machine| machine.machine_id)
+                .collect(),
+            needs_refill: machines.iter()
+                .filter(|machine| !machine.empty_slots.is_empty() || !machine.low_slots.is_empty())
+                .map(|machine| machine.machine_id)
+                .collect(),
+            machines,
+        })
+    }
+
+    async fn list_machines(&self) -> Result<Vec<MachineSummaryDTO>, OperatorError> {
+        let machines = self.repository.list().await.map_err(OperatorError::from)?;
+
+        Ok(machines.iter().map(Self::summarize).collect())
+    }
+
+    async fn find_machines(&self, filter: MachineFilter) -> Result<Vec<MachineSummaryDTO>, OperatorError> {
+        let machines = self.repository.query(&filter).await.map_err(OperatorError::from)?;
+
+        Ok(machines.iter().map(Self::summarize).collect())
+    }
+
+    async fn machine_exists(&self, machine_id: u32) -> Result<bool, OperatorError> {
+        self.repository.exists(SodaMachineId::new(machine_id)).await.map_err(OperatorError::from)
+    }
+
+    async fn delete_machine(&self, machine_id: u32) -> Result<(), OperatorError> {
+        // Saved and published first, so the machine's history and subscribers learn it is gone
+        self.execute(machine_id, |machine, _| {
+            machine.decommission().map(|_| ())
+        }).await?;
+
+        let deleted = self.repository.delete(SodaMachineId::new(machine_id)).await.map_err(OperatorError::from)?;
+
+        if deleted {
+            Ok(())
+        } else {
+            Err(OperatorError::SodaMachineNotFound(SodaMachineId::new(machine_id)))
+        }
+    }
+
+    async fn set_machine_location(&self, machine_id: u32, location: Option<String>) -> Result<(), OperatorError> {
+        self.execute(machine_id, |machine, _| {
+            machine.set_location(location.clone());
+            Ok(())
+        }).await
     }
 }
 
@@ -0,0 +1,81 @@
+use std::mem;
+use std::sync::{Mutex, PoisonError};
+use crate::domain::entities::sale::Sale;
+use crate::domain::events::event_envelope::EventEnvelope;
+use crate::ports::driven::event_publisher_port::EventPublisher;
+use crate::ports::driven::notification_port::DeliveryFailure;
+use crate::ports::driven::sales_ledger_port::SalesLedger;
+
+/// The sales and events of saved commands that still have to reach the sales ledger and the publisher
+///
+/// They are handed over after the machine was saved, when the command already took effect, so a
+/// ledger or publisher that is down must not fail it. Whatever could not be handed over is kept and
+/// sent again, ahead of anything newer, after the next command. The ledger skips sales it already
+/// holds; subscribers may see an event again if the publisher failed halfway through a batch.
+///
+/// The outbox lives in the memory of its service, each service has its own. Nothing else sends
+/// what it keeps: whatever is still waiting when the process stops is lost, so a sale or event the
+/// ledger or publisher missed is delivered at most once. Each failed handover is reported so the
+/// gap can be noticed.
+pub(crate) struct Outbox {
+    sales: Mutex<Vec<Sale>>,
+    envelopes: Mutex<Vec<EventEnvelope>>,
+}
+
+impl Outbox {
+    pub(crate) fn new() -> Self {
+        Outbox { sales: Mutex::new(Vec::new()), envelopes: Mutex::new(Vec::new()) }
+    }
+
+    /// Records the sales still waiting and the given ones, keeping them all if the ledger fails
+    ///
+    /// # Returns
+    /// * `Result<(), DeliveryFailure>` - Ok if the ledger took every sale, Err with the batch kept for the next command
+    pub(crate) async fn record_sales(&self, ledger: &dyn SalesLedger, sales: Vec<Sale>) -> Result<(), DeliveryFailure> {
+        let batch = take_with(&self.sales, sales);
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(err) = ledger.record(&batch).await {
+            let failure = DeliveryFailure::Sales { count: batch.len(), reason: err.to_string() };
+            put_back(&self.sales, batch);
+            return Err(failure);
+        }
+
+        Ok(())
+    }
+
+    /// Publishes the events still waiting and the given ones, keeping them all if the publisher fails
+    ///
+    /// # Returns
+    /// * `Result<(), DeliveryFailure>` - Ok if the publisher took every event, Err with the batch kept for the next command
+    pub(crate) async fn publish(&self, publisher: &dyn EventPublisher, envelopes: Vec<EventEnvelope>) -> Result<(), DeliveryFailure> {
+        let batch = take_with(&self.envelopes, envelopes);
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(err) = publisher.publish(&batch).await {
+            let failure = DeliveryFailure::Events { count: batch.len(), reason: err.to_string() };
+            put_back(&self.envelopes, batch);
+            return Err(failure);
+        }
+
+        Ok(())
+    }
+}
+
+/// Takes everything waiting in the queue, followed by the newer items
+fn take_with<T>(queue: &Mutex<Vec<T>>, newer: Vec<T>) -> Vec<T> {
+    let mut batch = mem::take(&mut *queue.lock().unwrap_or_else(PoisonError::into_inner));
+    batch.extend(newer);
+    batch
+}
+
+/// Puts an undelivered batch back in front of whatever other commands queued in the meantime
+fn put_back<T>(queue: &Mutex<Vec<T>>, mut batch: Vec<T>) {
+    let mut waiting = queue.lock().unwrap_or_else(PoisonError::into_inner);
+    batch.append(&mut waiting);
+    *waiting = batch;
+}
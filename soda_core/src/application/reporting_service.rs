@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::services::sales_report::{ReportDimension, ReportPeriod, SalesReporter};
+use crate::domain::value_objects::money::{Currency, Money};
+use crate::domain::value_objects::timestamp::Timestamp;
+use crate::ports::driving::reporting_port::{ReportingError, ReportingPort, SalesReportDTO, SalesReportLineDTO, SalesTotalsDTO};
+use crate::ports::driven::sales_ledger_port::SalesLedger;
+use crate::ports::driven::soda_machine_repository_port::RepositoryError;
+
+impl From<RepositoryError> for ReportingError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::ConnectionError(msg) => ReportingError::RepositoryUnavailable(msg),
+            RepositoryError::ConcurrencyConflict { machine_id, .. } => {
+                ReportingError::RepositoryFailure(format!("Machine {} changed while reading the ledger", machine_id))
+            },
+            RepositoryError::Other(e) => ReportingError::RepositoryFailure(e.to_string()),
+        }
+    }
+}
+
+/// Reports on the sales ledger for the category managers deciding what to stock
+pub struct ReportingService {
+    ledger: Arc<dyn SalesLedger>,
+}
+
+impl ReportingService {
+    pub fn new(ledger: Arc<dyn SalesLedger>) -> Self {
+        Self { ledger }
+    }
+}
+
+#[async_trait]
+impl ReportingPort for ReportingService {
+    async fn sales_report(
+        &self,
+        dimension: ReportDimension,
+        period: ReportPeriod,
+        from: Timestamp,
+        until: Timestamp,
+    ) -> Result<SalesReportDTO, ReportingError> {
+        if until <= from {
+            return Err(ReportingError::InvalidWindow { from, until });
+        }
+
+        let sales = self.ledger.sales_between(from, until).await.map_err(ReportingError::from)?;
+        let lines = SalesReporter::summarize(&sales, period, dimension)
+            .map_err(|e| ReportingError::Validation(e.to_string()))?;
+
+        let mut totals: BTreeMap<Currency, (u32, Money)> = BTreeMap::new();
+        for line in &lines {
+            let currency = line.revenue.currency();
+            let (units, revenue) = totals.entry(currency).or_insert((0, Money::zero_in(currency)));
+            *units += line.units;
+            *revenue = (*revenue + line.revenue).map_err(|e| ReportingError::Validation(e.to_string()))?;
+        }
+
+        Ok(SalesReportDTO {
+            from,
+            until,
+            period,
+            dimension,
+            lines: lines.into_iter()
+                .map(|line| SalesReportLineDTO {
+                    period_start: line.period_start,
+                    period_end: line.period_end,
+                    key: line.key,
+                    units: line.units,
+                    revenue: line.revenue,
+                })
+                .collect(),
+            totals: totals.into_iter()
+                .map(|(currency, (units, revenue))| SalesTotalsDTO { currency, units, revenue })
+                .collect(),
+        })
+    }
+}
@@ -1,42 +1,141 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::time::Duration;
 use crate::domain::entities::slot::{Slot, SlotId, SlotError};
+use crate::domain::entities::product::{Product, Sku};
+use crate::domain::entities::cash_box::{CashBox, CashBoxError};
+use crate::domain::entities::cash_collection::{CashCollection, OperatorId};
+use crate::domain::entities::price_schedule::{PriceChange, PriceSchedule};
+use crate::domain::entities::promotion::{AppliedPromotion, Discount, Promotion, PromotionId};
+use crate::domain::entities::purchase_session::{PurchaseSession, SessionError, SessionId, SessionSummary};
+use crate::domain::entities::refund::Refund;
+use crate::domain::entities::vend::{PendingVend, VendFailure};
+use crate::domain::services::change_calculator::ChangeBreakdown;
+use crate::domain::value_objects::denomination::{AcceptedDenominations, DenominationKind};
+use crate::domain::value_objects::planogram::{Planogram, PlanogramSlot};
 use crate::domain::value_objects::soda::Soda;
-use crate::domain::value_objects::money::{Money, MoneyError};
+use crate::domain::value_objects::money::{Currency, Money, MoneyError};
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// How long a customer session may stay idle before its credit is refunded
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(120);
 
 /// Represents a soda machine aggregate that orchestrates all soda machine operations
 /// This is the main aggregate that maintains consistency across the entire domain
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SodaMachine {
     /// Unique identifier for the soda machine
     id: SodaMachineId,
     /// Collection of slots in the machine
     slots: HashMap<SlotId, Slot>,
-    /// Current amount of money inserted by the customer
-    inserted_money: Money,
-    /// Total amount of money collected by the machine
+    /// Where the machine is installed, e.g. "Building A, 2nd floor"
+    #[cfg_attr(feature = "serde", serde(default))]
+    location: Option<String>,
+    /// The only currency the machine accepts and prices sodas in
+    currency: Currency,
+    /// The customer session in progress, if someone has inserted money
+    session: Option<PurchaseSession>,
+    /// ID the next customer session will get
+    next_session_id: SessionId,
+    /// How long a session may stay idle before it expires
+    session_timeout: Duration,
+    /// Revenue of the sodas sold since the cash was last collected
     total_collected: Money,
+    /// Physical coins and bills held by the machine, used to pay out change
+    cash_box: CashBox,
+    /// When an operator last emptied the cash box
+    #[cfg_attr(feature = "serde", serde(default))]
+    last_collected_at: Option<Timestamp>,
+    /// Prices this machine charges for catalog products instead of the slot prices
+    #[cfg_attr(feature = "serde", serde(default))]
+    price_schedule: PriceSchedule,
+    /// Promotions the machine runs, in the order they were added
+    #[cfg_attr(feature = "serde", serde(default))]
+    promotions: Vec<Promotion>,
+    /// The soda the vending mechanism is delivering, until it confirms or reports a failure
+    #[cfg_attr(feature = "serde", serde(default))]
+    pending_vend: Option<PendingVend>,
+    /// The only coins and bills customers may insert, None takes any amount
+    #[cfg_attr(feature = "serde", serde(default))]
+    accepted_denominations: Option<AcceptedDenominations>,
     /// Whether the machine is currently operational
     is_operational: bool,
     /// Maximum number of slots this machine can have
     max_slots: u32,
+    /// Sequence number of the last event the machine recorded
+    version: u64,
+    /// Events recorded since the machine was last saved, with their sequence numbers
+    #[cfg_attr(feature = "serde", serde(skip))]
+    uncommitted_events: Vec<(u64, SodaMachineEvent)>,
 }
 
 /// Unique identifier for a soda machine
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SodaMachineId(u32);
 
 /// Events that can occur in the soda machine
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SodaMachineEvent {
-    MoneyInserted { amount: Money, total_inserted: Money },
-    MoneyReturned { amount: Money },
-    SodaDispensed { slot_id: SlotId, soda: Soda },
+    MachineCreated { machine_id: SodaMachineId, max_slots: u32, currency: Currency },
+    SlotAdded { slot_id: SlotId, capacity: u32 },
+    SlotResized { slot_id: SlotId, capacity: u32 },
+    MoneyInserted { amount: Money, total_inserted: Money, inserted_at: Timestamp },
+    MoneyReturned { amount: Money, breakdown: ChangeBreakdown },
+    /// A soda was sold, `price` is what the customer paid after the promotion
+    SodaDispensed {
+        slot_id: SlotId,
+        soda: Soda,
+        price: Money,
+        dispensed_at: Timestamp,
+        #[cfg_attr(feature = "serde", serde(default))]
+        promotion: Option<AppliedPromotion>,
+    },
     SlotConfigured { slot_id: SlotId, soda_type: Soda },
+    ProductAssigned { slot_id: SlotId, product: Product },
+    SlotRepriced { slot_id: SlotId, price: Money },
+    /// The machine charges its own price for a product from now on, None goes back to the slot price
+    PriceOverridden { sku: Sku, price: Option<Money>, overridden_at: Timestamp },
+    PriceChangeScheduled { change: PriceChange },
+    PriceChangeCancelled { sku: Sku, effective_at: Timestamp },
+    PromotionAdded { promotion: Promotion },
+    PromotionRemoved { promotion_id: PromotionId },
+    /// The customer entered a coupon code for the purchases of their session
+    CouponEntered { code: PromotionId, entered_at: Timestamp },
     SlotRefilled { slot_id: SlotId, quantity_added: u32 },
     MachineEnabled,
     MachineDisabled,
-    ChangeReturned { amount: Money },
+    /// The machine was taken out of the fleet, the last event of its history
+    MachineDecommissioned,
+    ChangeReturned { amount: Money, breakdown: ChangeBreakdown, returned_at: Timestamp },
+    CashLoaded { denomination: Money, count: u32 },
+    /// An operator took loaded change back out of the cash box, e.g. before decommissioning the machine
+    CashUnloaded { denomination: Money, count: u32 },
+    /// An operator took the takings out of the cash box and left the float, the revenue count starts over
+    CashCollected { collection: CashCollection },
+    /// An operator paid money back to a customer, taken off the revenue
+    RefundIssued { refund: Refund },
+    /// The vending mechanism started delivering a soda, its price is held from the credit
+    VendReserved { vend: PendingVend },
+    /// The soda did not reach the customer, who keeps their credit
+    VendFailed { vend: PendingVend, failure: VendFailure, failed_at: Timestamp },
+    SessionStarted { session_id: SessionId, started_at: Timestamp },
+    SessionCompleted { summary: SessionSummary },
+    SessionCancelled { summary: SessionSummary },
+    SessionExpired { summary: SessionSummary },
+    SessionTimeoutChanged { timeout: Duration },
+    /// The machine only takes these coins and bills from now on, None takes any amount
+    AcceptedDenominationsChanged { denominations: Option<AcceptedDenominations> },
+    LocationChanged { location: Option<String> },
+    LowStockThresholdChanged { slot_id: SlotId, threshold: u32 },
+    /// A sale brought the slot down to its low stock threshold
+    SlotLowStock { slot_id: SlotId, quantity: u32, threshold: u32 },
+    /// A sale took the last soda out of the slot
+    SlotSoldOut { slot_id: SlotId },
+    /// A refill brought a low or sold out slot back above its threshold
+    SlotRestocked { slot_id: SlotId, quantity: u32 },
 }
 
 /// Errors that can occur during soda machine operations
@@ -51,6 +150,62 @@ pub enum SodaMachineError {
     SlotAlreadyExists(SlotId),
     TooManySlots,
     InvalidAmount,
+    CurrencyMismatch { expected: Currency, actual: Currency },
+    CashBoxError(CashBoxError),
+    ExactChangeUnavailable(Money),
+    NoActiveSession,
+    SessionExpired(SessionId),
+    /// A customer still has credit in the machine
+    SessionInProgress(SessionId),
+    PriceChangeInPast(Timestamp),
+    PriceChangeNotFound { sku: Sku, effective_at: Timestamp },
+    PromotionAlreadyExists(PromotionId),
+    PromotionNotFound(PromotionId),
+    /// The code is not a coupon the machine currently accepts
+    InvalidCoupon(PromotionId),
+    /// The vending mechanism is still delivering a soda from the slot
+    VendInProgress(SlotId),
+    NoPendingVend,
+    RefundExceedsRevenue { requested: Money, revenue: Money },
+    RefundReasonMissing,
+    /// Only the float may be unloaded, the revenue has to be collected first
+    RevenueNotCollected(Money),
+    /// The amount is not a single coin or bill the machine takes
+    DenominationNotAccepted(Money),
+    /// The machine still holds something a customer or the operator is owed, e.g. cash in its box
+    MachineInUse(String),
+    InvalidHistory(String),
+}
+
+/// Why a customer session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionEnd {
+    Completed,
+    Cancelled,
+    Expired,
+}
+
+/// The complete state of a machine, for repositories that store it field by field
+/// instead of keeping the aggregate as a whole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SodaMachineState {
+    pub id: SodaMachineId,
+    pub currency: Currency,
+    pub max_slots: u32,
+    pub location: Option<String>,
+    pub slots: Vec<Slot>,
+    pub session: Option<PurchaseSession>,
+    pub next_session_id: SessionId,
+    pub session_timeout: Duration,
+    pub total_collected: Money,
+    pub cash_box: CashBox,
+    pub last_collected_at: Option<Timestamp>,
+    pub price_schedule: PriceSchedule,
+    pub promotions: Vec<Promotion>,
+    pub pending_vend: Option<PendingVend>,
+    pub accepted_denominations: Option<AcceptedDenominations>,
+    pub is_operational: bool,
+    pub version: u64,
 }
 
 impl SodaMachine {
@@ -70,18 +225,193 @@ impl SodaMachine {
     /// let machine = SodaMachine::new(SodaMachineId::new(1), 10).unwrap();
     /// ```
     pub fn new(id: SodaMachineId, max_slots: u32) -> Result<Self, SodaMachineError> {
+        Self::new_with_currency(id, max_slots, Currency::USD)
+    }
+
+    /// Creates a new soda machine bound to a currency
+    /// 
+    /// # Arguments
+    /// * `id` - Unique identifier for the soda machine
+    /// * `max_slots` - Maximum number of slots the machine can have
+    /// * `currency` - The currency the machine accepts and prices sodas in
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachine, SodaMachineError>` - Ok(SodaMachine) if valid, Err if invalid
+    /// 
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
+    /// use soda_core::domain::value_objects::money::Currency;
+    /// 
+    /// let machine = SodaMachine::new_with_currency(SodaMachineId::new(1), 10, Currency::EUR).unwrap();
+    /// ```
+    pub fn new_with_currency(id: SodaMachineId, max_slots: u32, currency: Currency) -> Result<Self, SodaMachineError> {
         if max_slots == 0 {
             return Err(SodaMachineError::InvalidAmount);
         }
 
+        let mut machine = Self::blank(id, currency);
+        machine.raise(SodaMachineEvent::MachineCreated { machine_id: id, max_slots, currency })?;
+        Ok(machine)
+    }
+
+    /// Rebuilds a machine by replaying its stored events
+    /// 
+    /// # Arguments
+    /// * `history` - The machine's events with their sequence numbers, starting with `MachineCreated`
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachine, SodaMachineError>` - Ok(SodaMachine) in the state after the last event,
+    ///   Err if the history is empty, out of order or does not fit together
+    /// 
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
+    /// 
+    /// let mut machine = SodaMachine::new(SodaMachineId::new(1), 10).unwrap();
+    /// machine.disable();
+    /// 
+    /// let rebuilt = SodaMachine::from_history(machine.take_uncommitted_events()).unwrap();
+    /// assert!(!rebuilt.is_operational());
+    /// assert_eq!(rebuilt.version(), 2);
+    /// ```
+    pub fn from_history<I>(history: I) -> Result<Self, SodaMachineError>
+    where
+        I: IntoIterator<Item = (u64, SodaMachineEvent)>,
+    {
+        let mut history = history.into_iter();
+
+        let mut machine = match history.next() {
+            Some((sequence, event)) => {
+                let SodaMachineEvent::MachineCreated { machine_id, currency, .. } = event else {
+                    return Err(SodaMachineError::InvalidHistory("History must start with MachineCreated".to_string()));
+                };
+                let mut machine = Self::blank(machine_id, currency);
+                machine.replay(sequence, &event)?;
+                machine
+            },
+            None => return Err(SodaMachineError::InvalidHistory("History is empty".to_string())),
+        };
+
+        for (sequence, event) in history {
+            machine.replay(sequence, &event)?;
+        }
+
+        Ok(machine)
+    }
+
+    /// Brings a machine up to date with an event that was already stored, e.g. on top of a snapshot
+    /// 
+    /// # Arguments
+    /// * `sequence` - The event's sequence number, must follow the machine's version
+    /// * `event` - The stored event
+    /// 
+    /// # Returns
+    /// * `Result<(), SodaMachineError>` - Ok if applied, Err if out of order or the event does not fit
+    pub fn replay(&mut self, sequence: u64, event: &SodaMachineEvent) -> Result<(), SodaMachineError> {
+        if sequence != self.version + 1 {
+            return Err(SodaMachineError::InvalidHistory(format!(
+                "Expected event {} but got event {}",
+                self.version + 1,
+                sequence
+            )));
+        }
+
+        self.apply(event)?;
+        self.version = sequence;
+        Ok(())
+    }
+
+    /// Rebuilds a machine from state a repository stored
+    /// 
+    /// # Arguments
+    /// * `state` - The stored state, at the version it was saved with
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachine, SodaMachineError>` - Ok(SodaMachine) with no uncommitted events,
+    ///   Err if the slots do not fit the machine
+    pub fn from_state(state: SodaMachineState) -> Result<Self, SodaMachineError> {
+        if state.slots.len() > state.max_slots as usize {
+            return Err(SodaMachineError::TooManySlots);
+        }
+
+        let mut slots = HashMap::new();
+        for slot in state.slots {
+            if let Some(duplicate) = slots.insert(slot.id(), slot) {
+                return Err(SodaMachineError::SlotAlreadyExists(duplicate.id()));
+            }
+        }
+
         Ok(SodaMachine {
+            id: state.id,
+            slots,
+            location: state.location,
+            currency: state.currency,
+            session: state.session,
+            next_session_id: state.next_session_id,
+            session_timeout: state.session_timeout,
+            total_collected: state.total_collected,
+            cash_box: state.cash_box,
+            last_collected_at: state.last_collected_at,
+            price_schedule: state.price_schedule,
+            promotions: state.promotions,
+            pending_vend: state.pending_vend,
+            accepted_denominations: state.accepted_denominations,
+            is_operational: state.is_operational,
+            max_slots: state.max_slots,
+            version: state.version,
+            uncommitted_events: Vec::new(),
+        })
+    }
+
+    /// Gets the complete state of the machine, with slots ordered by ID
+    pub fn state(&self) -> SodaMachineState {
+        let mut slots: Vec<Slot> = self.slots.values().cloned().collect();
+        slots.sort_by_key(|slot| slot.id());
+
+        SodaMachineState {
+            id: self.id,
+            currency: self.currency,
+            max_slots: self.max_slots,
+            location: self.location.clone(),
+            slots,
+            session: self.session.clone(),
+            next_session_id: self.next_session_id,
+            session_timeout: self.session_timeout,
+            total_collected: self.total_collected,
+            cash_box: self.cash_box.clone(),
+            last_collected_at: self.last_collected_at,
+            price_schedule: self.price_schedule.clone(),
+            promotions: self.promotions.clone(),
+            pending_vend: self.pending_vend.clone(),
+            accepted_denominations: self.accepted_denominations.clone(),
+            is_operational: self.is_operational,
+            version: self.version,
+        }
+    }
+
+    /// A machine with no history yet, the starting point for `MachineCreated`
+    fn blank(id: SodaMachineId, currency: Currency) -> Self {
+        SodaMachine {
             id,
             slots: HashMap::new(),
-            inserted_money: Money::zero(),
-            total_collected: Money::zero(),
+            location: None,
+            currency,
+            session: None,
+            next_session_id: SessionId::new(1),
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            total_collected: Money::zero_in(currency),
+            cash_box: CashBox::new(currency),
+            last_collected_at: None,
+            price_schedule: PriceSchedule::new(),
+            promotions: Vec::new(),
+            pending_vend: None,
+            accepted_denominations: None,
             is_operational: true,
-            max_slots,
-        })
+            max_slots: 0,
+            version: 0,
+            uncommitted_events: Vec::new(),
+        }
     }
 
     /// Gets the machine ID
@@ -89,16 +419,53 @@ impl SodaMachine {
         self.id
     }
 
-    /// Gets the current amount of money inserted
+    /// Gets the currency the machine operates in
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Gets the credit of the current customer session, or zero if there is none
     pub fn inserted_money(&self) -> Money {
-        self.inserted_money
+        self.session.as_ref()
+            .map(|session| session.credit())
+            .unwrap_or(Money::zero_in(self.currency))
+    }
+
+    /// Gets the customer session in progress
+    pub fn current_session(&self) -> Option<&PurchaseSession> {
+        self.session.as_ref()
+    }
+
+    /// Gets how long a customer session may stay idle before it expires
+    pub fn session_timeout(&self) -> Duration {
+        self.session_timeout
     }
 
-    /// Gets the total amount of money collected
+    /// Gets the revenue of the sodas sold since the cash was last collected
     pub fn total_collected(&self) -> Money {
         self.total_collected
     }
 
+    /// Gets the coins and bills currently held by the machine
+    pub fn cash_box(&self) -> &CashBox {
+        &self.cash_box
+    }
+
+    /// Gets when an operator last emptied the cash box
+    pub fn last_collected_at(&self) -> Option<Timestamp> {
+        self.last_collected_at
+    }
+
+    /// Gets the soda the vending mechanism is still delivering
+    pub fn pending_vend(&self) -> Option<&PendingVend> {
+        self.pending_vend.as_ref()
+    }
+
+    /// Gets the only coins and bills customers may insert, None if the machine takes any amount
+    pub fn accepted_denominations(&self) -> Option<&AcceptedDenominations> {
+        self.accepted_denominations.as_ref()
+    }
+
     /// Checks if the machine is operational
     pub fn is_operational(&self) -> bool {
         self.is_operational
@@ -109,11 +476,141 @@ impl SodaMachine {
         self.slots.len()
     }
 
+    /// Gets where the machine is installed, if known
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Checks if any stocked slot is down to `threshold` sodas or fewer
+    /// 
+    /// # Arguments
+    /// * `threshold` - The highest quantity that still counts as low
+    /// 
+    /// # Returns
+    /// * `bool` - True if a slot with a soda configured has at most `threshold` sodas left
+    pub fn has_low_stock(&self, threshold: u32) -> bool {
+        self.slots.values()
+            .any(|slot| slot.soda_type().is_some() && slot.quantity() <= threshold)
+    }
+
+    /// Checks if any slot sells a catalog product
+    pub fn sells(&self, sku: &Sku) -> bool {
+        self.slots.values().any(|slot| slot.sells(sku))
+    }
+
+    /// Gets the prices the machine charges for catalog products instead of the slot prices
+    pub fn price_schedule(&self) -> &PriceSchedule {
+        &self.price_schedule
+    }
+
+    /// Gets the price a slot sells its soda for at a point in time
+    /// 
+    /// # Arguments
+    /// * `slot_id` - The slot
+    /// * `at` - The point in time, e.g. when a soda is bought
+    /// 
+    /// # Returns
+    /// * `Option<Money>` - The machine's own price for the slot's product if it has one,
+    ///   otherwise the price the slot was configured with, None if the slot sells nothing
+    pub fn price_at(&self, slot_id: SlotId, at: Timestamp) -> Option<Money> {
+        self.slots.get(&slot_id).and_then(|slot| self.price_of(slot, at))
+    }
+
+    fn price_of(&self, slot: &Slot, at: Timestamp) -> Option<Money> {
+        let soda = slot.soda_type()?;
+
+        slot.sku()
+            .and_then(|sku| self.price_schedule.price_at(sku, at))
+            .or(Some(soda.price()))
+    }
+
+    /// Gets the promotions the machine runs, in the order they were added
+    pub fn promotions(&self) -> &[Promotion] {
+        &self.promotions
+    }
+
+    /// Gets a promotion the machine runs
+    pub fn get_promotion(&self, promotion_id: &PromotionId) -> Option<&Promotion> {
+        self.promotions.iter().find(|promotion| promotion.id() == promotion_id)
+    }
+
+    /// Picks the promotion that takes the most off a sale from a slot
+    /// 
+    /// Promotions do not stack, and a coupon only counts if the customer entered it
+    /// during their session and has not used it yet.
+    /// 
+    /// # Arguments
+    /// * `slot` - The slot the soda is bought from
+    /// * `session` - The customer's session, with what they bought so far
+    /// * `price` - The machine's price for the soda
+    /// * `now` - When the soda is bought
+    /// 
+    /// # Returns
+    /// * `Option<AppliedPromotion>` - The best promotion and its discount, None if no promotion lowers the price
+    fn best_promotion(&self, slot: &Slot, session: Option<&PurchaseSession>, price: Money, now: Timestamp) -> Option<AppliedPromotion> {
+        let soda = slot.soda_type()?;
+        let purchases = session.map(|session| session.purchases()).unwrap_or_default();
+
+        let mut best: Option<AppliedPromotion> = None;
+        for promotion in &self.promotions {
+            if !promotion.is_active_at(now) || !promotion.applies_to(soda, slot.sku()) {
+                continue;
+            }
+
+            if promotion.is_coupon() && !session.is_some_and(|session| session.has_unused_coupon(promotion.id())) {
+                continue;
+            }
+
+            let bought_before = purchases.iter()
+                .filter(|purchase| {
+                    let sku = self.slots.get(&purchase.slot_id).and_then(|slot| slot.sku());
+                    promotion.applies_to(&purchase.soda, sku)
+                })
+                .count() as u32;
+            let discount = promotion.discount_on(price, bought_before);
+
+            if discount.is_positive() && best.as_ref().is_none_or(|best| discount > best.discount) {
+                best = Some(AppliedPromotion { promotion_id: promotion.id().clone(), discount });
+            }
+        }
+
+        best
+    }
+
     /// Gets the maximum number of slots
     pub fn max_slots(&self) -> u32 {
         self.max_slots
     }
 
+    /// Gets the sequence number of the last event the machine recorded
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Gets the version the machine had when it was loaded or last saved,
+    /// which repositories compare against the stored version to detect lost updates
+    pub fn committed_version(&self) -> u64 {
+        self.version - self.uncommitted_events.len() as u64
+    }
+
+    /// Gets the events recorded since the machine was last saved, with their sequence numbers
+    pub fn uncommitted_events(&self) -> &[(u64, SodaMachineEvent)] {
+        &self.uncommitted_events
+    }
+
+    /// Hands over the events recorded since the machine was last saved, so they can be published
+    /// 
+    /// # Returns
+    /// * `Vec<(u64, SodaMachineEvent)>` - The events with their sequence numbers, oldest first
+    pub fn take_uncommitted_events(&mut self) -> Vec<(u64, SodaMachineEvent)> {
+        std::mem::take(&mut self.uncommitted_events)
+    }
+
+    /// Forgets the uncommitted events once they have been stored
+    pub fn mark_events_committed(&mut self) {
+        self.uncommitted_events.clear();
+    }
+
     /// Gets a slot by ID
     /// 
     /// # Arguments
@@ -151,583 +648,2700 @@ impl SodaMachine {
             return Err(SodaMachineError::SlotAlreadyExists(slot_id));
         }
 
-        let slot = Slot::new(slot_id, capacity)
-            .map_err(SodaMachineError::SlotError)?;
-
-        self.slots.insert(slot_id, slot);
-        Ok(SodaMachineEvent::SlotConfigured { slot_id, soda_type: Soda::new("Empty".to_string(), crate::domain::value_objects::soda::SodaFlavor::Cola, crate::domain::value_objects::soda::SodaSize::Medium, Money::zero(), false, false).unwrap() })
+        self.raise(SodaMachineEvent::SlotAdded { slot_id, capacity })
     }
 
-    /// Configures a slot to hold a specific type of soda
+    /// Changes how many sodas a slot can hold
     /// 
     /// # Arguments
-    /// * `slot_id` - The ID of the slot to configure
-    /// * `soda_type` - The type of soda to configure
+    /// * `slot_id` - The ID of the slot to resize
+    /// * `capacity` - The new capacity, at least the slot's current quantity and above its threshold
     /// 
     /// # Returns
     /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
-    pub fn configure_slot(&mut self, slot_id: SlotId, soda_type: Soda) -> Result<SodaMachineEvent, SodaMachineError> {
+    pub fn resize_slot(&mut self, slot_id: SlotId, capacity: u32) -> Result<SodaMachineEvent, SodaMachineError> {
         if !self.is_operational {
             return Err(SodaMachineError::MachineNotOperational);
         }
 
-        let slot = self.slots.get_mut(&slot_id)
+        let slot = self.slots.get(&slot_id)
             .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
 
-        slot.configure_soda_type(soda_type.clone())
+        slot.clone().set_capacity(capacity)
             .map_err(SodaMachineError::SlotError)?;
 
-        Ok(SodaMachineEvent::SlotConfigured { slot_id, soda_type })
+        self.raise(SodaMachineEvent::SlotResized { slot_id, capacity })
     }
 
-    /// Refills a slot with sodas
+    /// Configures a slot to hold a specific type of soda
     /// 
     /// # Arguments
-    /// * `slot_id` - The ID of the slot to refill
-    /// * `quantity` - The number of sodas to add
+    /// * `slot_id` - The ID of the slot to configure
+    /// * `soda_type` - The type of soda to configure
     /// 
     /// # Returns
     /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
-    pub fn refill_slot(&mut self, slot_id: SlotId, quantity: u32) -> Result<SodaMachineEvent, SodaMachineError> {
+    pub fn configure_slot(&mut self, slot_id: SlotId, soda_type: Soda) -> Result<SodaMachineEvent, SodaMachineError> {
         if !self.is_operational {
             return Err(SodaMachineError::MachineNotOperational);
         }
 
-        let slot = self.slots.get_mut(&slot_id)
-            .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
+        self.ensure_currency(soda_type.price())?;
 
-        let added = slot.add_sodas(quantity)
-            .map_err(SodaMachineError::SlotError)?;
+        if !self.slots.contains_key(&slot_id) {
+            return Err(SodaMachineError::SlotNotFound(slot_id));
+        }
 
-        Ok(SodaMachineEvent::SlotRefilled { slot_id, quantity_added: added })
+        self.raise(SodaMachineEvent::SlotConfigured { slot_id, soda_type })
     }
 
-    /// Inserts money into the machine
+    /// Configures a slot to sell a catalog product at the product's default price
+    /// 
+    /// Assigning the product a slot already sells only updates its price, even while the slot is stocked.
     /// 
     /// # Arguments
-    /// * `amount` - The amount of money to insert
+    /// * `slot_id` - The ID of the slot to configure
+    /// * `product` - The catalog product to sell
     /// 
     /// # Returns
     /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
-    pub fn insert_money(&mut self, amount: Money) -> Result<SodaMachineEvent, SodaMachineError> {
+    pub fn assign_product(&mut self, slot_id: SlotId, product: &Product) -> Result<SodaMachineEvent, SodaMachineError> {
         if !self.is_operational {
             return Err(SodaMachineError::MachineNotOperational);
         }
 
-        if amount.is_negative() || amount.is_zero() {
+        self.ensure_currency(product.default_price())?;
+
+        if !self.slots.contains_key(&slot_id) {
+            return Err(SodaMachineError::SlotNotFound(slot_id));
+        }
+
+        self.raise(SodaMachineEvent::ProductAssigned { slot_id, product: product.clone() })
+    }
+
+    /// Changes the price of every slot selling a catalog product, works even while the machine is out of service
+    /// 
+    /// # Arguments
+    /// * `sku` - The catalog product whose price changed
+    /// * `price` - The new price
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) with one `SlotRepriced` per slot whose price changed
+    pub fn reprice_product(&mut self, sku: &Sku, price: Money) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        self.ensure_currency(price)?;
+
+        if price.is_negative() {
             return Err(SodaMachineError::InvalidAmount);
         }
 
-        self.inserted_money = (self.inserted_money + amount)
-            .map_err(SodaMachineError::MoneyError)?;
+        let mut slot_ids: Vec<SlotId> = self.slots.values()
+            .filter(|slot| slot.sells(sku) && slot.soda_type().is_some_and(|soda| soda.price() != price))
+            .map(|slot| slot.id())
+            .collect();
+        slot_ids.sort();
 
-        Ok(SodaMachineEvent::MoneyInserted { 
-            amount, 
-            total_inserted: self.inserted_money 
-        })
+        let events = slot_ids.into_iter()
+            .map(|slot_id| SodaMachineEvent::SlotRepriced { slot_id, price })
+            .collect();
+
+        self.raise_all(events)
     }
 
-    /// Dispenses a soda from a specific slot
+    /// Makes the machine charge its own price for a catalog product, works even while it is out of service
+    /// 
+    /// The price replaces the one the product's slots were configured with, e.g. so an airport
+    /// machine charges more than the catalog price. Price changes scheduled for later still take effect.
     /// 
     /// # Arguments
-    /// * `slot_id` - The ID of the slot to dispense from
+    /// * `sku` - The product
+    /// * `price` - The machine's price, None goes back to the slot price
+    /// * `now` - When the price changes
     /// 
     /// # Returns
-    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
-    pub fn dispense_soda(&mut self, slot_id: SlotId) -> Result<SodaMachineEvent, SodaMachineError> {
-        if !self.is_operational {
-            return Err(SodaMachineError::MachineNotOperational);
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if the price is invalid
+    pub fn override_price(&mut self, sku: &Sku, price: Option<Money>, now: Timestamp) -> Result<SodaMachineEvent, SodaMachineError> {
+        if let Some(price) = price {
+            self.ensure_price(price)?;
         }
 
-        let slot = self.slots.get(&slot_id)
-            .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
+        self.raise(SodaMachineEvent::PriceOverridden { sku: sku.clone(), price, overridden_at: now })
+    }
 
-        let soda = slot.soda_type()
-            .ok_or(SodaMachineError::SlotError(SlotError::SlotEmpty))?;
+    /// Sets up a change of the machine's price for a catalog product, taking effect at a later time
+    /// 
+    /// # Arguments
+    /// * `change` - The product, its price and when it takes effect, replacing a change set up for the same time
+    /// * `now` - The current time
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if the price is invalid or the time has passed
+    pub fn schedule_price_change(&mut self, change: PriceChange, now: Timestamp) -> Result<SodaMachineEvent, SodaMachineError> {
+        if let Some(price) = change.price {
+            self.ensure_price(price)?;
+        }
 
-        if !slot.can_dispense(soda) {
-            return Err(SodaMachineError::SlotError(SlotError::SlotEmpty));
+        if change.effective_at <= now {
+            return Err(SodaMachineError::PriceChangeInPast(change.effective_at));
         }
 
-        // Check if customer has enough money
-        if self.inserted_money < soda.price() {
-            return Err(SodaMachineError::InsufficientFunds {
-                required: soda.price(),
-                available: self.inserted_money,
-            });
+        self.raise(SodaMachineEvent::PriceChangeScheduled { change })
+    }
+
+    /// Drops a price change that has not taken effect yet
+    /// 
+    /// # Arguments
+    /// * `sku` - The product
+    /// * `effective_at` - When the change was going to take effect
+    /// * `now` - The current time
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if there is no such change or it already took effect
+    pub fn cancel_price_change(&mut self, sku: &Sku, effective_at: Timestamp, now: Timestamp) -> Result<SodaMachineEvent, SodaMachineError> {
+        if !self.price_schedule.contains(sku, effective_at) {
+            return Err(SodaMachineError::PriceChangeNotFound { sku: sku.clone(), effective_at });
         }
 
-        // Dispense the soda
-        let slot = self.slots.get_mut(&slot_id).unwrap();
-        let dispensed_soda = slot.dispense_soda()
-            .map_err(SodaMachineError::SlotError)?;
+        if effective_at <= now {
+            return Err(SodaMachineError::PriceChangeInPast(effective_at));
+        }
 
-        // Calculate change
-        let change = (self.inserted_money - dispensed_soda.price())
-            .map_err(SodaMachineError::MoneyError)?;
+        self.raise(SodaMachineEvent::PriceChangeCancelled { sku: sku.clone(), effective_at })
+    }
 
-        // Update machine state
-        self.total_collected = (self.total_collected + dispensed_soda.price())
-            .map_err(SodaMachineError::MoneyError)?;
-        self.inserted_money = change;
+    /// Starts running a promotion
+    /// 
+    /// # Arguments
+    /// * `promotion` - The promotion, with an ID no other promotion of the machine has
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if the ID is taken or the discount is in another currency
+    pub fn add_promotion(&mut self, promotion: Promotion) -> Result<SodaMachineEvent, SodaMachineError> {
+        if self.get_promotion(promotion.id()).is_some() {
+            return Err(SodaMachineError::PromotionAlreadyExists(promotion.id().clone()));
+        }
+
+        if let Discount::AmountOff(amount) = promotion.discount() {
+            self.ensure_currency(amount)?;
+        }
 
-        Ok(SodaMachineEvent::SodaDispensed { slot_id, soda: dispensed_soda })
+        self.raise(SodaMachineEvent::PromotionAdded { promotion })
     }
 
-    /// Returns all inserted money to the customer
+    /// Stops running a promotion
+    /// 
+    /// Purchases that already got the promotion keep their discount.
+    /// 
+    /// # Arguments
+    /// * `promotion_id` - The promotion
     /// 
     /// # Returns
-    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
-    pub fn return_money(&mut self) -> Result<SodaMachineEvent, SodaMachineError> {
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if the machine does not run the promotion
+    pub fn remove_promotion(&mut self, promotion_id: &PromotionId) -> Result<SodaMachineEvent, SodaMachineError> {
+        if self.get_promotion(promotion_id).is_none() {
+            return Err(SodaMachineError::PromotionNotFound(promotion_id.clone()));
+        }
+
+        self.raise(SodaMachineEvent::PromotionRemoved { promotion_id: promotion_id.clone() })
+    }
+
+    /// Lets the customer of the current session use a coupon on their next purchase it applies to
+    /// 
+    /// # Arguments
+    /// * `code` - The coupon code the customer entered
+    /// * `now` - When the code was entered
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if there is no session or the code is not a coupon running now
+    pub fn enter_coupon(&mut self, code: &PromotionId, now: Timestamp) -> Result<SodaMachineEvent, SodaMachineError> {
         if !self.is_operational {
             return Err(SodaMachineError::MachineNotOperational);
         }
 
-        if self.inserted_money.is_zero() {
-            return Err(SodaMachineError::InvalidAmount);
+        self.ensure_session_not_idle(now)?;
+
+        if self.session.is_none() {
+            return Err(SodaMachineError::NoActiveSession);
         }
 
-        let returned_amount = self.inserted_money;
-        self.inserted_money = Money::zero();
+        if !self.get_promotion(code).is_some_and(|promotion| promotion.is_coupon() && promotion.is_active_at(now)) {
+            return Err(SodaMachineError::InvalidCoupon(code.clone()));
+        }
 
-        Ok(SodaMachineEvent::MoneyReturned { amount: returned_amount })
+        self.raise(SodaMachineEvent::CouponEntered { code: code.clone(), entered_at: now })
     }
 
-    /// Returns change to the customer (partial money return)
+    /// Refills a slot with sodas
     /// 
     /// # Arguments
-    /// * `amount` - The amount of change to return
+    /// * `slot_id` - The ID of the slot to refill
+    /// * `quantity` - The number of sodas to add
+    /// 
+    /// Reports `SlotRestocked` if the refill brings a low or sold out slot back above its threshold.
     /// 
     /// # Returns
-    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
-    pub fn return_change(&mut self, amount: Money) -> Result<SodaMachineEvent, SodaMachineError> {
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn refill_slot(&mut self, slot_id: SlotId, quantity: u32) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
         if !self.is_operational {
             return Err(SodaMachineError::MachineNotOperational);
         }
 
-        if amount.is_negative() || amount.is_zero() {
-            return Err(SodaMachineError::InvalidAmount);
-        }
+        let slot = self.slots.get(&slot_id)
+            .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
 
-        if amount > self.inserted_money {
-            return Err(SodaMachineError::InsufficientFunds {
-                required: amount,
-                available: self.inserted_money,
-            });
-        }
+        // Refuse the whole refill rather than half-filling the slot
+        let mut refilled = slot.clone();
+        let added = refilled.add_sodas(quantity)
+            .map_err(SodaMachineError::SlotError)?;
 
-        self.inserted_money = (self.inserted_money - amount)
-            .map_err(SodaMachineError::MoneyError)?;
+        let mut events = vec![SodaMachineEvent::SlotRefilled { slot_id, quantity_added: added }];
+        if slot.is_low_on_stock() && !refilled.is_low_on_stock() {
+            events.push(SodaMachineEvent::SlotRestocked { slot_id, quantity: refilled.quantity() });
+        }
 
-        Ok(SodaMachineEvent::ChangeReturned { amount })
+        self.raise_all(events)
     }
 
-    /// Enables the soda machine
+    /// Fills every enabled slot that has a soda configured up to its capacity in one command
+    /// 
+    /// Each slot only gets what still fits, so a slot that sold less than expected does not
+    /// fail the restock. Slots brought back above their threshold also report `SlotRestocked`.
     /// 
     /// # Returns
-    /// * `SodaMachineEvent` - The event that occurred
-    pub fn enable(&mut self) -> SodaMachineEvent {
-        self.is_operational = true;
-        SodaMachineEvent::MachineEnabled
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) with one `SlotRefilled` per slot that got sodas
+    pub fn restock(&mut self) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        let mut slots: Vec<&Slot> = self.slots.values()
+            .filter(|slot| slot.soda_type().is_some() && slot.is_enabled() && slot.remaining_capacity() > 0)
+            .collect();
+        slots.sort_by_key(|slot| slot.id());
+
+        let mut events = Vec::new();
+        for slot in slots {
+            events.push(SodaMachineEvent::SlotRefilled { slot_id: slot.id(), quantity_added: slot.remaining_capacity() });
+            if slot.is_low_on_stock() {
+                events.push(SodaMachineEvent::SlotRestocked { slot_id: slot.id(), quantity: slot.max_capacity() });
+            }
+        }
+
+        self.raise_all(events)
     }
 
-    /// Disables the soda machine
+    /// Sets how many sodas a slot may hold before it counts as low on stock
+    /// 
+    /// # Arguments
+    /// * `slot_id` - The ID of the slot
+    /// * `threshold` - The reorder level, must be below the slot's capacity, 0 only reports sold out
     /// 
     /// # Returns
-    /// * `SodaMachineEvent` - The event that occurred
-    pub fn disable(&mut self) -> SodaMachineEvent {
-        self.is_operational = false;
-        SodaMachineEvent::MachineDisabled
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
+    pub fn set_low_stock_threshold(&mut self, slot_id: SlotId, threshold: u32) -> Result<SodaMachineEvent, SodaMachineError> {
+        let slot = self.slots.get(&slot_id)
+            .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
+
+        slot.clone().set_low_stock_threshold(threshold)
+            .map_err(SodaMachineError::SlotError)?;
+
+        self.raise(SodaMachineEvent::LowStockThresholdChanged { slot_id, threshold })
     }
 
-    /// Gets the total value of all sodas in the machine
+    /// Lays the machine out as the planogram describes, applying every slot or none of them
+    /// 
+    /// Missing slots are added, existing slots are resized, configured and given the planogram's
+    /// threshold. Slots the planogram does not list are left alone, and so is the soda of a slot the
+    /// planogram leaves unconfigured. A stocked slot cannot switch to a different soda or price.
+    /// 
+    /// # Arguments
+    /// * `planogram` - The layout to apply
     /// 
     /// # Returns
-    /// * `Money` - The total value of all sodas
-    pub fn total_inventory_value(&self) -> Money {
-        self.slots.values()
-            .filter_map(|slot| slot.total_value())
-            .fold(Money::zero(), |acc, value| {
-                (acc + value).unwrap_or(Money::zero())
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) for what had to change, Err if any slot does not fit
+    pub fn apply_planogram(&mut self, planogram: &Planogram) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        // Work on a copy so a slot failing halfway through leaves the machine untouched
+        let mut draft = self.clone();
+        let mut events = Vec::new();
+        for slot in planogram.slots() {
+            events.extend(draft.lay_out_slot(slot)?);
+        }
+
+        *self = draft;
+        Ok(events)
+    }
+
+    /// Brings one slot in line with its planogram entry
+    fn lay_out_slot(&mut self, planned: &PlanogramSlot) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        let slot_id = planned.slot_id;
+        let Some(slot) = self.slots.get(&slot_id) else {
+            let mut events = vec![self.add_slot(slot_id, planned.capacity)?];
+            if planned.low_stock_threshold > 0 {
+                events.push(self.set_low_stock_threshold(slot_id, planned.low_stock_threshold)?);
+            }
+            if let Some(soda) = &planned.soda {
+                events.push(self.configure_planned_soda(planned, soda)?);
+            }
+            return Ok(events);
+        };
+
+        let (capacity, threshold) = (slot.max_capacity(), slot.low_stock_threshold());
+        let configured = (slot.sku().cloned(), slot.soda_type().cloned());
+        let mut events = Vec::new();
+
+        // Lower the threshold before shrinking the slot and raise it after growing it, so both fit in between
+        if planned.low_stock_threshold != threshold && planned.low_stock_threshold < capacity {
+            events.push(self.set_low_stock_threshold(slot_id, planned.low_stock_threshold)?);
+        }
+        if planned.capacity != capacity {
+            events.push(self.resize_slot(slot_id, planned.capacity)?);
+        }
+        if planned.low_stock_threshold >= capacity {
+            events.push(self.set_low_stock_threshold(slot_id, planned.low_stock_threshold)?);
+        }
+
+        if let Some(planned_soda) = &planned.soda
+            && configured != (planned.sku.clone(), Some(planned_soda.clone()))
+        {
+            events.push(self.configure_planned_soda(planned, planned_soda)?);
+        }
+
+        Ok(events)
+    }
+
+    /// Configures a planogram slot's soda, as a catalog product if the planogram names its SKU
+    fn configure_planned_soda(&mut self, planned: &PlanogramSlot, soda: &Soda) -> Result<SodaMachineEvent, SodaMachineError> {
+        match &planned.sku {
+            Some(sku) => {
+                let product = Product::new(sku.clone(), soda.clone(), None)
+                    .expect("a product without a barcode is always valid");
+                self.assign_product(planned.slot_id, &product)
+            },
+            None => self.configure_slot(planned.slot_id, soda.clone()),
+        }
+    }
+
+    /// Gets the current layout of the machine, for rolling it out to other machines
+    /// 
+    /// # Returns
+    /// * `Planogram` - Every slot with its capacity, threshold and soda
+    pub fn planogram(&self) -> Planogram {
+        let slots = self.slots.values()
+            .map(|slot| PlanogramSlot {
+                slot_id: slot.id(),
+                capacity: slot.max_capacity(),
+                low_stock_threshold: slot.low_stock_threshold(),
+                sku: slot.sku().cloned(),
+                soda: slot.soda_type().cloned(),
             })
+            .collect();
+
+        Planogram::new(slots).expect("a machine's own slots always form a valid planogram")
     }
 
-    /// Gets the total number of sodas in the machine
+    /// Sets how long a customer session may stay idle before it expires
+    /// 
+    /// # Arguments
+    /// * `timeout` - The idle timeout, must be greater than zero
     /// 
     /// # Returns
-    /// * `u32` - The total number of sodas
-    pub fn total_soda_count(&self) -> u32 {
-        self.slots.values()
-            .map(|slot| slot.quantity())
-            .sum()
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
+    pub fn set_session_timeout(&mut self, timeout: Duration) -> Result<SodaMachineEvent, SodaMachineError> {
+        if timeout.is_zero() {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        self.raise(SodaMachineEvent::SessionTimeoutChanged { timeout })
     }
 
-    /// Gets available sodas (slots that can dispense)
+    /// Puts the machine in strict mode, where customers may only insert single coins and bills it takes
+    /// 
+    /// # Arguments
+    /// * `denominations` - The coins and bills taken, None leaves strict mode and takes any amount
     /// 
     /// # Returns
-    /// * `Vec<(SlotId, &Soda)>` - List of available sodas with their slot IDs
-    pub fn get_available_sodas(&self) -> Vec<(SlotId, &Soda)> {
-        self.slots.iter()
-            .filter_map(|(slot_id, slot)| {
-                slot.soda_type()
-                    .filter(|soda| slot.can_dispense(soda))
-                    .map(|soda| (*slot_id, soda))
-            })
-            .collect()
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if the
+    ///   denominations are in another currency
+    pub fn set_accepted_denominations(&mut self, denominations: Option<AcceptedDenominations>) -> Result<SodaMachineEvent, SodaMachineError> {
+        if let Some(denominations) = &denominations
+            && denominations.currency() != self.currency
+        {
+            return Err(SodaMachineError::CurrencyMismatch { expected: self.currency, actual: denominations.currency() });
+        }
+
+        self.raise(SodaMachineEvent::AcceptedDenominationsChanged { denominations })
     }
 
-    /// Checks if a specific soda is available
+    /// Inserts a coin or a bill into the machine, starting a customer session if none is in progress
+    /// 
+    /// The amount must be a single coin or bill of the machine's currency, which goes into the cash box
+    /// as it is. In strict mode it must also be one the machine takes. Use `insert_denomination` when
+    /// the device that took the money is known.
     /// 
     /// # Arguments
-    /// * `soda` - The soda to check for availability
+    /// * `amount` - The value of the coin or bill
+    /// * `now` - When the money was inserted
     /// 
     /// # Returns
-    /// * `Option<SlotId>` - Some(slot_id) if available, None if not available
-    pub fn find_available_soda(&self, soda: &Soda) -> Option<SlotId> {
-        self.slots.iter()
-            .find(|(_, slot)| slot.can_dispense(soda))
-            .map(|(slot_id, _)| *slot_id)
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn insert_money(&mut self, amount: Money, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        self.credit(amount, None, now)
     }
 
-    /// Gets the machine status summary
+    /// Inserts a coin or a bill into the machine, starting a customer session if none is in progress
+    /// 
+    /// In strict mode the money must be taken as the kind it came in as, e.g. a $1 bill is refused
+    /// by a machine that only takes $1 coins.
+    /// 
+    /// # Arguments
+    /// * `kind` - Whether the coin acceptor or the bill validator took the money
+    /// * `amount` - The value of the coin or bill
+    /// * `now` - When the money was inserted
     /// 
     /// # Returns
-    /// * `String` - A summary of the machine's current status
-    pub fn status_summary(&self) -> String {
-        let available_sodas = self.get_available_sodas().len();
-        let total_sodas = self.total_soda_count();
-        let total_value = self.total_inventory_value();
-        
-        format!(
-            "Machine {}: {} slots, {} available sodas ({} total), ${:.2} inventory value, ${:.2} inserted, ${:.2} collected - {}",
-            self.id.value(),
-            self.slot_count(),
-            available_sodas,
-            total_sodas,
-            total_value.as_decimal(),
-            self.inserted_money.as_decimal(),
-            self.total_collected.as_decimal(),
-            if self.is_operational { "Operational" } else { "Out of Service" }
-        )
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn insert_denomination(&mut self, kind: DenominationKind, amount: Money, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        self.credit(amount, Some(kind), now)
     }
-}
 
-impl SodaMachineId {
-    /// Creates a new soda machine ID
+    /// Credits inserted money, checked against strict mode as the given kind, or as either kind if unknown
+    fn credit(&mut self, amount: Money, kind: Option<DenominationKind>, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        self.ensure_currency(amount)?;
+
+        if amount.is_negative() || amount.is_zero() {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        if !self.currency.has_denomination(amount) {
+            return Err(SodaMachineError::DenominationNotAccepted(amount));
+        }
+
+        if let Some(denominations) = &self.accepted_denominations
+            && !kind.map_or(denominations.accepts(amount), |kind| denominations.accepts_as(kind, amount))
+        {
+            return Err(SodaMachineError::DenominationNotAccepted(amount));
+        }
+
+        self.ensure_session_not_idle(now)?;
+
+        let mut events = Vec::new();
+        let mut session = match &self.session {
+            Some(session) => session.clone(),
+            None => {
+                events.push(SodaMachineEvent::SessionStarted { session_id: self.next_session_id, started_at: now });
+                PurchaseSession::start(self.next_session_id, self.currency, now)
+            },
+        };
+        let total_inserted = session.add_credit(amount, now)?;
+
+        // The inserted coin or bill is physically kept in the cash box
+        self.cash_box.clone().deposit(amount, 1)
+            .map_err(SodaMachineError::CashBoxError)?;
+
+        events.push(SodaMachineEvent::MoneyInserted { amount, total_inserted, inserted_at: now });
+        self.raise_all(events)
+    }
+
+    /// Dispenses a soda from a specific slot, paid from the current session's credit
+    /// 
+    /// For mechanisms that cannot report whether the soda dropped. The session completes on its own
+    /// once its credit is used up, unless the customer earned a free soda they have yet to take. Reports `SlotLowStock` when the sale brings the slot down to its
+    /// threshold and `SlotSoldOut` when it takes the last soda.
     /// 
     /// # Arguments
-    /// * `id` - The numeric ID
+    /// * `slot_id` - The ID of the slot to dispense from
+    /// * `now` - When the soda is bought
     /// 
     /// # Returns
-    /// * `SodaMachineId` - The soda machine ID
-    pub fn new(id: u32) -> Self {
-        SodaMachineId(id)
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn dispense_soda(&mut self, slot_id: SlotId, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        let vend = self.plan_vend(slot_id, now)?;
+        let events = self.vend_events(vend, now)?;
+        self.raise_all(events)
     }
 
-    /// Gets the numeric value of the soda machine ID
-    pub fn value(&self) -> u32 {
-        self.0
+    /// Holds the price of a soda from the session's credit while the vending mechanism delivers it
+    /// 
+    /// Runs the same checks as `dispense_soda`. Nothing is charged until `confirm_vend`, and
+    /// `fail_vend` gives the customer their credit back. The session cannot end in the meantime.
+    /// 
+    /// # Arguments
+    /// * `slot_id` - The ID of the slot to dispense from
+    /// * `now` - When the soda is bought
+    /// 
+    /// # Returns
+    /// * `Result<PendingVend, SodaMachineError>` - Ok(vend) as the `VendReserved` event records it, Err if invalid
+    pub fn reserve_vend(&mut self, slot_id: SlotId, now: Timestamp) -> Result<PendingVend, SodaMachineError> {
+        let vend = self.plan_vend(slot_id, now)?;
+        self.raise(SodaMachineEvent::VendReserved { vend: vend.clone() })?;
+        Ok(vend)
     }
-}
 
-impl fmt::Display for SodaMachine {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.status_summary())
+    /// Charges the customer for the pending vend once the mechanism saw the soda drop
+    /// 
+    /// # Arguments
+    /// * `now` - When the soda dropped
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) starting with `SodaDispensed`, Err if no soda is being delivered
+    pub fn confirm_vend(&mut self, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        let vend = self.pending_vend.clone()
+            .ok_or(SodaMachineError::NoPendingVend)?;
+        let events = self.vend_events(vend, now)?;
+        self.raise_all(events)
     }
-}
 
-impl fmt::Display for SodaMachineId {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+    /// Rolls back the pending vend after the mechanism reported a failure
+    /// 
+    /// The customer keeps their credit. A can that is still in its place stays for sale,
+    /// any other failure quarantines it: it leaves the stock, which can report the slot low or sold out.
+    /// 
+    /// # Arguments
+    /// * `failure` - What the mechanism reported
+    /// * `now` - When the failure was reported
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) starting with `VendFailed`, Err if no soda is being delivered
+    pub fn fail_vend(&mut self, failure: VendFailure, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        let vend = self.pending_vend.clone()
+            .ok_or(SodaMachineError::NoPendingVend)?;
+        let slot_id = vend.slot_id;
+        let mut events = vec![SodaMachineEvent::VendFailed { vend, failure, failed_at: now }];
+
+        if !failure.keeps_unit_in_slot() {
+            events.extend(self.stock_alert_after_removing_one(slot_id)?);
+        }
+
+        self.raise_all(events)
     }
-}
 
-impl fmt::Display for SodaMachineError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SodaMachineError::SlotNotFound(slot_id) => write!(f, "Slot {} not found", slot_id),
-            SodaMachineError::SlotError(err) => write!(f, "Slot error: {}", err),
-            SodaMachineError::MoneyError(err) => write!(f, "Money error: {}", err),
-            SodaMachineError::InsufficientFunds { required, available } => {
-                write!(f, "Insufficient funds: need {}, have {}", required, available)
-            },
-            SodaMachineError::MachineNotOperational => write!(f, "Machine is not operational"),
-            SodaMachineError::InvalidSlotId => write!(f, "Invalid slot ID"),
-            SodaMachineError::SlotAlreadyExists(slot_id) => write!(f, "Slot {} already exists", slot_id),
-            SodaMachineError::TooManySlots => write!(f, "Too many slots"),
-            SodaMachineError::InvalidAmount => write!(f, "Invalid amount"),
+    /// Checks that the session can buy a soda from the slot and works out what it costs
+    fn plan_vend(&self, slot_id: SlotId, now: Timestamp) -> Result<PendingVend, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        if let Some(vend) = &self.pending_vend {
+            return Err(SodaMachineError::VendInProgress(vend.slot_id));
+        }
+
+        self.ensure_session_not_idle(now)?;
+
+        let slot = self.slots.get(&slot_id)
+            .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
+
+        let soda = slot.soda_type()
+            .ok_or(SodaMachineError::SlotError(SlotError::SlotEmpty))?;
+
+        if !slot.can_dispense(soda) {
+            return Err(SodaMachineError::SlotError(SlotError::SlotEmpty));
+        }
+
+        // The machine's own price for the product wins over the slot price,
+        // then the best promotion is taken off it
+        let price = self.price_of(slot, now)
+            .unwrap_or(soda.price());
+        let promotion = self.best_promotion(slot, self.session.as_ref(), price, now);
+        let price = match &promotion {
+            Some(promotion) => (price - promotion.discount)?,
+            None => price,
+        };
+
+        // Check if customer has enough money
+        let credit = self.inserted_money();
+        if credit < price {
+            return Err(SodaMachineError::InsufficientFunds {
+                required: price,
+                available: credit,
+            });
+        }
+
+        // Refuse the sale if the remaining credit could not be paid back as exact change
+        let change = (credit - price)
+            .map_err(SodaMachineError::MoneyError)?;
+        if !self.cash_box.can_make_change(change) {
+            return Err(SodaMachineError::ExactChangeUnavailable(change));
         }
+
+        Ok(PendingVend { slot_id, soda: soda.clone(), price, promotion, reserved_at: now })
     }
-}
 
-impl std::error::Error for SodaMachineError {}
+    /// Gets the events of a soda reaching the customer: the sale, its stock alerts and the end of a spent session
+    fn vend_events(&self, vend: PendingVend, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        let mut session = self.session.clone()
+            .ok_or(SodaMachineError::NoActiveSession)?;
+        session.record_purchase(vend.slot_id, vend.soda.clone(), vend.price, vend.promotion.clone(), now)?;
+
+        let slot_id = vend.slot_id;
+        let mut events = vec![SodaMachineEvent::SodaDispensed {
+            slot_id,
+            soda: vend.soda,
+            price: vend.price,
+            dispensed_at: now,
+            promotion: vend.promotion,
+        }];
+        events.extend(self.stock_alert_after_removing_one(slot_id)?);
+
+        // A customer who spent their credit exactly may still have earned a free soda, e.g. the third
+        // of a buy 2 get 1 free, so the session stays open for it until it is taken or times out
+        if session.credit().is_zero() && !self.has_free_soda(&session, slot_id, now) {
+            let summary = session.end(Money::zero_in(self.currency), now);
+            events.push(SodaMachineEvent::SessionCompleted { summary });
+        }
 
-impl From<SlotError> for SodaMachineError {
-    fn from(err: SlotError) -> Self {
-        SodaMachineError::SlotError(err)
+        Ok(events)
     }
-}
 
-impl From<MoneyError> for SodaMachineError {
-    fn from(err: MoneyError) -> Self {
-        SodaMachineError::MoneyError(err)
+    /// Checks if a session would get its next soda for free from any slot still stocked after a sale
+    fn has_free_soda(&self, session: &PurchaseSession, sold_from: SlotId, now: Timestamp) -> bool {
+        self.slots.values()
+            .filter(|slot| {
+                let sold = if slot.id() == sold_from { 1 } else { 0 };
+                slot.quantity() > sold && slot.soda_type().is_some_and(|soda| slot.can_dispense(soda))
+            })
+            .any(|slot| {
+                self.price_of(slot, now).is_some_and(|price| {
+                    price.is_positive() && self.best_promotion(slot, Some(session), price, now)
+                        .is_some_and(|promotion| promotion.discount == price)
+                })
+            })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::domain::value_objects::soda::{Soda, SodaFlavor, SodaSize};
+    /// Gets the stock alert for a slot that is about to lose one soda, if it has to report one
+    fn stock_alert_after_removing_one(&self, slot_id: SlotId) -> Result<Option<SodaMachineEvent>, SodaMachineError> {
+        let slot = self.slots.get(&slot_id)
+            .ok_or(SodaMachineError::SlotNotFound(slot_id))?;
 
-    fn create_test_soda() -> Soda {
-        Soda::new(
-            "Coca-Cola".to_string(),
-            SodaFlavor::Cola,
-            SodaSize::Medium,
-            Money::from_dollars_cents(1, 50).unwrap(),
-            false,
-            true,
-        ).unwrap()
+        let remaining = slot.quantity().saturating_sub(1);
+        let threshold = slot.low_stock_threshold();
+        Ok(if remaining == 0 {
+            Some(SodaMachineEvent::SlotSoldOut { slot_id })
+        } else if remaining == threshold {
+            Some(SodaMachineEvent::SlotLowStock { slot_id, quantity: remaining, threshold })
+        } else {
+            None
+        })
     }
 
-    fn create_test_machine() -> SodaMachine {
-        SodaMachine::new(SodaMachineId::new(1), 10).unwrap()
+    /// Returns all remaining credit to the customer and ends their session
+    /// 
+    /// The session counts as completed if the customer bought something, cancelled otherwise.
+    /// 
+    /// # Arguments
+    /// * `now` - When the money is returned
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn return_money(&mut self, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        let session = self.session.as_ref()
+            .ok_or(SodaMachineError::InvalidAmount)?;
+
+        if session.purchases().is_empty() {
+            self.cancel_session(now)
+        } else {
+            self.complete_session(now)
+        }
+    }
+
+    /// Ends the current session after a purchase, refunding any remaining credit
+    /// 
+    /// # Arguments
+    /// * `now` - When the session ends
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn complete_session(&mut self, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        self.ensure_session_not_idle(now)?;
+        self.end_session(SessionEnd::Completed, now)
+    }
+
+    /// Cancels the current session, refunding all remaining credit
+    /// 
+    /// # Arguments
+    /// * `now` - When the session is cancelled
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events) if successful, Err if invalid
+    pub fn cancel_session(&mut self, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        self.ensure_session_not_idle(now)?;
+        self.end_session(SessionEnd::Cancelled, now)
+    }
+
+    /// Expires the current session if it has been idle for longer than the session timeout,
+    /// refunding any remaining credit
+    /// 
+    /// # Arguments
+    /// * `now` - The current time
+    /// 
+    /// # Returns
+    /// * `Result<Vec<SodaMachineEvent>, SodaMachineError>` - Ok(events), empty if nothing expired
+    pub fn expire_idle_session(&mut self, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        match &self.session {
+            // The credit is held until the mechanism says whether the soda dropped
+            _ if self.pending_vend.is_some() => Ok(Vec::new()),
+            Some(session) if session.is_idle(now, self.session_timeout) => {
+                self.end_session(SessionEnd::Expired, now)
+            },
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns change to the customer (partial money return)
+    /// 
+    /// # Arguments
+    /// * `amount` - The amount of change to return
+    /// * `now` - When the change is returned
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
+    pub fn return_change(&mut self, amount: Money, now: Timestamp) -> Result<SodaMachineEvent, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        self.ensure_currency(amount)?;
+
+        if amount.is_negative() || amount.is_zero() {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        self.ensure_session_not_idle(now)?;
+        self.ensure_no_pending_vend()?;
+
+        let credit = self.inserted_money();
+        if self.session.is_none() || amount > credit {
+            return Err(SodaMachineError::InsufficientFunds { required: amount, available: credit });
+        }
+
+        let breakdown = self.cash_box.plan_change(amount)
+            .map_err(SodaMachineError::from)?;
+
+        self.raise(SodaMachineEvent::ChangeReturned { amount, breakdown, returned_at: now })
+    }
+
+    /// Loads coins or bills into the cash box so the machine can give change
+    /// 
+    /// # Arguments
+    /// * `denomination` - The face value of the coin or bill
+    /// * `count` - The number of coins/bills to load
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful, Err if invalid
+    pub fn load_cash(&mut self, denomination: Money, count: u32) -> Result<SodaMachineEvent, SodaMachineError> {
+        if !self.is_operational {
+            return Err(SodaMachineError::MachineNotOperational);
+        }
+
+        self.ensure_currency(denomination)?;
+
+        if count == 0 {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        self.cash_box.clone().deposit(denomination, count)
+            .map_err(SodaMachineError::CashBoxError)?;
+
+        self.raise(SodaMachineEvent::CashLoaded { denomination, count })
+    }
+
+    /// Takes loaded change back out of the cash box, e.g. to empty it before the machine is decommissioned
+    /// 
+    /// Only the float can be unloaded, the revenue is taken out by `collect_cash`.
+    /// 
+    /// # Arguments
+    /// * `denomination` - The face value of the coin or bill
+    /// * `count` - The number of coins/bills to take out
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if successful,
+    ///   Err if a customer is using the machine, the box does not hold them or they are revenue
+    pub fn unload_cash(&mut self, denomination: Money, count: u32) -> Result<SodaMachineEvent, SodaMachineError> {
+        self.ensure_currency(denomination)?;
+
+        if count == 0 {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        self.ensure_no_pending_vend()?;
+        if let Some(session) = &self.session {
+            return Err(SodaMachineError::SessionInProgress(session.id()));
+        }
+
+        let mut remaining = self.cash_box.clone();
+        remaining.withdraw(denomination, count)
+            .map_err(SodaMachineError::CashBoxError)?;
+        if remaining.total() < self.total_collected {
+            return Err(SodaMachineError::RevenueNotCollected(self.total_collected));
+        }
+
+        self.raise(SodaMachineEvent::CashUnloaded { denomination, count })
+    }
+
+    /// Records an operator taking the takings out of the cash box and counting them
+    /// 
+    /// The float, the change operators loaded, stays in the cash box as far as its coins are
+    /// still there, smallest coins first, so the machine can keep giving change. Everything
+    /// else is taken out: the revenue since the last collection and what is left of the float.
+    /// 
+    /// # Arguments
+    /// * `operator_id` - The operator collecting the cash
+    /// * `counted` - What the operator counted
+    /// * `now` - When the cash was collected
+    /// 
+    /// # Returns
+    /// * `Result<CashCollection, SodaMachineError>` - Ok(collection) as the `CashCollected` event records it,
+    ///   Err if a soda is being delivered, a customer still has credit or the amount is invalid
+    pub fn collect_cash(&mut self, operator_id: OperatorId, counted: Money, now: Timestamp) -> Result<CashCollection, SodaMachineError> {
+        self.ensure_currency(counted)?;
+
+        if counted.is_negative() {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        self.ensure_no_pending_vend()?;
+        if let Some(session) = &self.session {
+            return Err(SodaMachineError::SessionInProgress(session.id()));
+        }
+
+        let float = (self.cash_box.total() - self.total_collected)?
+            .max(Money::zero_in(self.currency));
+        let collection = CashCollection {
+            operator_id,
+            collected_at: now,
+            period_start: self.last_collected_at,
+            revenue: self.total_collected,
+            float,
+            kept: self.cash_box.float_up_to(float).total(),
+            counted,
+        };
+
+        self.raise(SodaMachineEvent::CashCollected { collection: collection.clone() })?;
+        Ok(collection)
+    }
+
+    /// Pays money back to a customer from the cash box, e.g. for a soda that never dropped
+    /// 
+    /// The refund is taken off the revenue since the last collection, so it cannot be larger
+    /// than that revenue, and no customer may be using the machine.
+    /// 
+    /// # Arguments
+    /// * `operator_id` - The operator issuing the refund
+    /// * `amount` - What the customer gets back
+    /// * `reason` - Why, e.g. "Can stuck in slot 3"
+    /// * `now` - When the refund is paid out
+    /// 
+    /// # Returns
+    /// * `Result<Refund, SodaMachineError>` - Ok(refund) with the coins paid out, as the `RefundIssued` event records it, Err if invalid
+    pub fn issue_refund(&mut self, operator_id: OperatorId, amount: Money, reason: &str, now: Timestamp) -> Result<Refund, SodaMachineError> {
+        self.ensure_currency(amount)?;
+
+        if !amount.is_positive() {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(SodaMachineError::RefundReasonMissing);
+        }
+
+        if let Some(session) = &self.session {
+            return Err(SodaMachineError::SessionInProgress(session.id()));
+        }
+
+        if amount > self.total_collected {
+            return Err(SodaMachineError::RefundExceedsRevenue { requested: amount, revenue: self.total_collected });
+        }
+
+        let breakdown = self.cash_box.plan_change(amount)
+            .map_err(SodaMachineError::from)?;
+
+        let refund = Refund { operator_id, amount, reason: reason.to_string(), breakdown, issued_at: now };
+        self.raise(SodaMachineEvent::RefundIssued { refund: refund.clone() })?;
+        Ok(refund)
+    }
+
+    /// Pays back the session's credit and closes it
+    fn end_session(&mut self, reason: SessionEnd, now: Timestamp) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        self.ensure_no_pending_vend()?;
+        let session = self.session.clone()
+            .ok_or(SodaMachineError::NoActiveSession)?;
+        let refund = session.credit();
+
+        let mut events = Vec::new();
+        if !refund.is_zero() {
+            let breakdown = self.cash_box.plan_change(refund)
+                .map_err(SodaMachineError::from)?;
+            events.push(SodaMachineEvent::MoneyReturned { amount: refund, breakdown });
+        }
+
+        let summary = session.end(refund, now);
+        events.push(match reason {
+            SessionEnd::Completed => SodaMachineEvent::SessionCompleted { summary },
+            SessionEnd::Cancelled => SodaMachineEvent::SessionCancelled { summary },
+            SessionEnd::Expired => SodaMachineEvent::SessionExpired { summary },
+        });
+
+        self.raise_all(events)
+    }
+
+    /// Rejects commands that would pay out credit held for a soda still being delivered
+    fn ensure_no_pending_vend(&self) -> Result<(), SodaMachineError> {
+        match &self.pending_vend {
+            Some(vend) => Err(SodaMachineError::VendInProgress(vend.slot_id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects commands against a session that has been idle past the timeout
+    fn ensure_session_not_idle(&self, now: Timestamp) -> Result<(), SodaMachineError> {
+        match &self.session {
+            Some(session) if session.is_idle(now, self.session_timeout) => {
+                Err(SodaMachineError::SessionExpired(session.id()))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies an event that a command decided on, then numbers it and keeps it until the machine is saved
+    fn raise(&mut self, event: SodaMachineEvent) -> Result<SodaMachineEvent, SodaMachineError> {
+        self.apply(&event)?;
+        self.version += 1;
+        self.uncommitted_events.push((self.version, event.clone()));
+        Ok(event)
+    }
+
+    /// Raises several events in order
+    fn raise_all(&mut self, events: Vec<SodaMachineEvent>) -> Result<Vec<SodaMachineEvent>, SodaMachineError> {
+        events.into_iter().map(|event| self.raise(event)).collect()
+    }
+
+    /// Changes the machine's state to reflect an event
+    /// 
+    /// This is the only place state changes. Commands check business rules before raising an event,
+    /// and replaying stored events goes through here without checking them again.
+    /// 
+    /// # Arguments
+    /// * `event` - The event to apply
+    /// 
+    /// # Returns
+    /// * `Result<(), SodaMachineError>` - Ok if applied, Err if the event does not fit the current state
+    pub fn apply(&mut self, event: &SodaMachineEvent) -> Result<(), SodaMachineError> {
+        match event {
+            SodaMachineEvent::MachineCreated { machine_id, max_slots, currency } => {
+                *self = SodaMachine {
+                    max_slots: *max_slots,
+                    version: self.version,
+                    uncommitted_events: std::mem::take(&mut self.uncommitted_events),
+                    ..Self::blank(*machine_id, *currency)
+                };
+            },
+            SodaMachineEvent::SlotAdded { slot_id, capacity } => {
+                let slot = Slot::new(*slot_id, *capacity)?;
+                self.slots.insert(*slot_id, slot);
+            },
+            SodaMachineEvent::SlotResized { slot_id, capacity } => {
+                self.slot_mut(*slot_id)?.set_capacity(*capacity)?;
+            },
+            SodaMachineEvent::SlotConfigured { slot_id, soda_type } => {
+                self.slot_mut(*slot_id)?.configure_soda_type(soda_type.clone())?;
+            },
+            SodaMachineEvent::ProductAssigned { slot_id, product } => {
+                self.slot_mut(*slot_id)?.assign_product(product)?;
+            },
+            SodaMachineEvent::SlotRepriced { slot_id, price } => {
+                self.slot_mut(*slot_id)?.reprice(*price)?;
+            },
+            SodaMachineEvent::PriceOverridden { sku, price, overridden_at } => {
+                self.price_schedule.set(sku, *price, *overridden_at);
+            },
+            SodaMachineEvent::PriceChangeScheduled { change } => {
+                self.price_schedule.schedule(change.clone());
+            },
+            SodaMachineEvent::PriceChangeCancelled { sku, effective_at } => {
+                self.price_schedule.cancel(sku, *effective_at);
+            },
+            SodaMachineEvent::PromotionAdded { promotion } => {
+                self.promotions.push(promotion.clone());
+            },
+            SodaMachineEvent::PromotionRemoved { promotion_id } => {
+                self.promotions.retain(|promotion| promotion.id() != promotion_id);
+            },
+            SodaMachineEvent::CouponEntered { code, entered_at } => {
+                self.session_mut()?.enter_coupon(code.clone(), *entered_at);
+            },
+            SodaMachineEvent::SlotRefilled { slot_id, quantity_added } => {
+                self.slot_mut(*slot_id)?.add_sodas(*quantity_added)?;
+            },
+            SodaMachineEvent::SessionStarted { session_id, started_at } => {
+                self.session = Some(PurchaseSession::start(*session_id, self.currency, *started_at));
+                self.next_session_id = session_id.next();
+            },
+            SodaMachineEvent::MoneyInserted { amount, inserted_at, .. } => {
+                self.cash_box.deposit(*amount, 1)?;
+                self.session_mut()?.add_credit(*amount, *inserted_at)?;
+            },
+            SodaMachineEvent::SodaDispensed { slot_id, soda, price, dispensed_at, promotion } => {
+                self.pending_vend = None;
+                self.slot_mut(*slot_id)?.dispense_soda()?;
+                self.total_collected = (self.total_collected + *price)?;
+                self.session_mut()?.record_purchase(*slot_id, soda.clone(), *price, promotion.clone(), *dispensed_at)?;
+            },
+            SodaMachineEvent::MoneyReturned { breakdown, .. } => {
+                self.cash_box.withdraw_breakdown(breakdown)?;
+            },
+            SodaMachineEvent::ChangeReturned { amount, breakdown, returned_at } => {
+                self.cash_box.withdraw_breakdown(breakdown)?;
+                self.session_mut()?.refund(*amount, *returned_at)?;
+            },
+            SodaMachineEvent::SessionCompleted { .. }
+            | SodaMachineEvent::SessionCancelled { .. }
+            | SodaMachineEvent::SessionExpired { .. } => {
+                self.session = None;
+            },
+            SodaMachineEvent::SessionTimeoutChanged { timeout } => {
+                self.session_timeout = *timeout;
+            },
+            SodaMachineEvent::AcceptedDenominationsChanged { denominations } => {
+                self.accepted_denominations = denominations.clone();
+            },
+            SodaMachineEvent::LocationChanged { location } => {
+                self.location = location.clone();
+            },
+            SodaMachineEvent::LowStockThresholdChanged { slot_id, threshold } => {
+                self.slot_mut(*slot_id)?.set_low_stock_threshold(*threshold)?;
+            },
+            // Stock alerts only report what the sale or refill before them did
+            SodaMachineEvent::SlotLowStock { slot_id, .. }
+            | SodaMachineEvent::SlotSoldOut { slot_id }
+            | SodaMachineEvent::SlotRestocked { slot_id, .. } => {
+                self.slot_mut(*slot_id)?;
+            },
+            SodaMachineEvent::CashLoaded { denomination, count } => {
+                self.cash_box.deposit(*denomination, *count)?;
+            },
+            SodaMachineEvent::CashUnloaded { denomination, count } => {
+                self.cash_box.withdraw(*denomination, *count)?;
+            },
+            SodaMachineEvent::VendReserved { vend } => {
+                self.session_mut()?;
+                self.pending_vend = Some(vend.clone());
+            },
+            SodaMachineEvent::VendFailed { vend, failure, .. } => {
+                self.pending_vend = None;
+                // A stuck can leaves the stock without being sold
+                if !failure.keeps_unit_in_slot() {
+                    self.slot_mut(vend.slot_id)?.dispense_soda()?;
+                }
+            },
+            SodaMachineEvent::RefundIssued { refund } => {
+                self.cash_box.withdraw_breakdown(&refund.breakdown)?;
+                self.total_collected = (self.total_collected - refund.amount)?;
+            },
+            SodaMachineEvent::CashCollected { collection } => {
+                self.cash_box = self.cash_box.float_up_to(collection.kept);
+                self.total_collected = Money::zero_in(self.currency);
+                self.last_collected_at = Some(collection.collected_at);
+            },
+            SodaMachineEvent::MachineEnabled => self.is_operational = true,
+            SodaMachineEvent::MachineDisabled
+            | SodaMachineEvent::MachineDecommissioned => self.is_operational = false,
+        }
+
+        Ok(())
+    }
+
+    /// Gets a slot for an event to change
+    fn slot_mut(&mut self, slot_id: SlotId) -> Result<&mut Slot, SodaMachineError> {
+        self.slots.get_mut(&slot_id)
+            .ok_or(SodaMachineError::SlotNotFound(slot_id))
+    }
+
+    /// Gets the current session for an event to change
+    fn session_mut(&mut self) -> Result<&mut PurchaseSession, SodaMachineError> {
+        self.session.as_mut()
+            .ok_or(SodaMachineError::NoActiveSession)
+    }
+
+    /// Checks that an amount is in the machine's currency
+    fn ensure_currency(&self, amount: Money) -> Result<(), SodaMachineError> {
+        if amount.currency() != self.currency {
+            return Err(SodaMachineError::CurrencyMismatch {
+                expected: self.currency,
+                actual: amount.currency(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a price is in the machine's currency and not negative
+    fn ensure_price(&self, price: Money) -> Result<(), SodaMachineError> {
+        self.ensure_currency(price)?;
+
+        if price.is_negative() {
+            return Err(SodaMachineError::InvalidAmount);
+        }
+
+        Ok(())
+    }
+
+    /// Enables the soda machine
+    /// 
+    /// # Returns
+    /// * `SodaMachineEvent` - The event that occurred
+    pub fn enable(&mut self) -> SodaMachineEvent {
+        self.raise(SodaMachineEvent::MachineEnabled)
+            .expect("enabling a machine always applies")
+    }
+
+    /// Records where the machine is installed, works even while it is out of service
+    /// 
+    /// # Arguments
+    /// * `location` - The new location, None or a blank name clears it
+    /// 
+    /// # Returns
+    /// * `SodaMachineEvent` - The event that occurred
+    pub fn set_location(&mut self, location: Option<String>) -> SodaMachineEvent {
+        let location = location
+            .map(|location| location.trim().to_string())
+            .filter(|location| !location.is_empty());
+
+        self.raise(SodaMachineEvent::LocationChanged { location })
+            .expect("changing the location always applies")
+    }
+
+    /// Disables the soda machine
+    /// 
+    /// # Returns
+    /// * `SodaMachineEvent` - The event that occurred
+    pub fn disable(&mut self) -> SodaMachineEvent {
+        self.raise(SodaMachineEvent::MachineDisabled)
+            .expect("disabling a machine always applies")
+    }
+
+    /// Takes the machine out of the fleet for good, before a repository deletes it
+    /// 
+    /// Nothing the machine holds may be lost with it, so a soda still being delivered,
+    /// a customer's credit and the cash in the box all have to be dealt with first.
+    /// 
+    /// # Returns
+    /// * `Result<SodaMachineEvent, SodaMachineError>` - Ok(event) if the machine is empty, Err(MachineInUse) otherwise
+    pub fn decommission(&mut self) -> Result<SodaMachineEvent, SodaMachineError> {
+        if let Some(vend) = &self.pending_vend {
+            return Err(SodaMachineError::MachineInUse(format!("a soda from slot {} is still being delivered", vend.slot_id.value())));
+        }
+
+        if let Some(session) = &self.session {
+            return Err(SodaMachineError::MachineInUse(format!("a customer still has {} in credit", session.credit())));
+        }
+
+        if !self.cash_box.is_empty() {
+            return Err(SodaMachineError::MachineInUse(format!("the cash box still holds {}", self.cash_box.total())));
+        }
+
+        self.raise(SodaMachineEvent::MachineDecommissioned)
+    }
+
+    /// Gets the total value of all sodas in the machine
+    /// 
+    /// # Returns
+    /// * `Money` - The total value of all sodas
+    pub fn total_inventory_value(&self) -> Money {
+        self.slots.values()
+            .filter_map(|slot| slot.total_value())
+            .fold(Money::zero_in(self.currency), |acc, value| {
+                (acc + value).unwrap_or(Money::zero_in(self.currency))
+            })
+    }
+
+    /// Gets the total number of sodas in the machine
+    /// 
+    /// # Returns
+    /// * `u32` - The total number of sodas
+    pub fn total_soda_count(&self) -> u32 {
+        self.slots.values()
+            .map(|slot| slot.quantity())
+            .sum()
+    }
+
+    /// Gets available sodas (slots that can dispense)
+    /// 
+    /// # Arguments
+    /// * `now` - The current time, which decides the prices
+    /// 
+    /// # Returns
+    /// * `Vec<(SlotId, &Soda, Money)>` - List of available sodas with their slot IDs and the price they sell for now
+    pub fn get_available_sodas(&self, now: Timestamp) -> Vec<(SlotId, &Soda, Money)> {
+        self.slots.iter()
+            .filter_map(|(slot_id, slot)| {
+                slot.soda_type()
+                    .filter(|soda| slot.can_dispense(soda))
+                    .map(|soda| (*slot_id, soda, self.price_of(slot, now).unwrap_or(soda.price())))
+            })
+            .collect()
+    }
+
+    /// Checks if a specific soda is available
+    /// 
+    /// # Arguments
+    /// * `soda` - The soda to check for availability
+    /// 
+    /// # Returns
+    /// * `Option<SlotId>` - Some(slot_id) if available, None if not available
+    pub fn find_available_soda(&self, soda: &Soda) -> Option<SlotId> {
+        self.slots.iter()
+            .find(|(_, slot)| slot.can_dispense(soda))
+            .map(|(slot_id, _)| *slot_id)
+    }
+
+    /// Gets the machine status summary
+    /// 
+    /// # Returns
+    /// * `String` - A summary of the machine's current status
+    pub fn status_summary(&self) -> String {
+        let available_sodas = self.slots.values()
+            .filter(|slot| slot.soda_type().is_some_and(|soda| slot.can_dispense(soda)))
+            .count();
+        let total_sodas = self.total_soda_count();
+        let total_value = self.total_inventory_value();
+        
+        format!(
+            "Machine {}: {} slots, {} available sodas ({} total), {} inventory value, {} inserted, {} collected - {}",
+            self.id.value(),
+            self.slot_count(),
+            available_sodas,
+            total_sodas,
+            total_value,
+            self.inserted_money(),
+            self.total_collected,
+            if self.is_operational { "Operational" } else { "Out of Service" }
+        )
+    }
+}
+
+impl SodaMachineId {
+    /// Creates a new soda machine ID
+    /// 
+    /// # Arguments
+    /// * `id` - The numeric ID
+    /// 
+    /// # Returns
+    /// * `SodaMachineId` - The soda machine ID
+    pub fn new(id: u32) -> Self {
+        SodaMachineId(id)
+    }
+
+    /// Gets the numeric value of the soda machine ID
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for SodaMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status_summary())
+    }
+}
+
+impl fmt::Display for SodaMachineId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for SodaMachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SodaMachineError::SlotNotFound(slot_id) => write!(f, "Slot {} not found", slot_id),
+            SodaMachineError::SlotError(err) => write!(f, "Slot error: {}", err),
+            SodaMachineError::MoneyError(err) => write!(f, "Money error: {}", err),
+            SodaMachineError::InsufficientFunds { required, available } => {
+                write!(f, "Insufficient funds: need {}, have {}", required, available)
+            },
+            SodaMachineError::MachineNotOperational => write!(f, "Machine is not operational"),
+            SodaMachineError::InvalidSlotId => write!(f, "Invalid slot ID"),
+            SodaMachineError::SlotAlreadyExists(slot_id) => write!(f, "Slot {} already exists", slot_id),
+            SodaMachineError::TooManySlots => write!(f, "Too many slots"),
+            SodaMachineError::InvalidAmount => write!(f, "Invalid amount"),
+            SodaMachineError::CurrencyMismatch { expected, actual } => {
+                write!(f, "Currency mismatch: machine accepts {}, got {}", expected, actual)
+            },
+            SodaMachineError::CashBoxError(err) => write!(f, "Cash box error: {}", err),
+            SodaMachineError::ExactChangeUnavailable(amount) => {
+                write!(f, "Exact change unavailable: cannot return {}", amount)
+            },
+            SodaMachineError::NoActiveSession => write!(f, "No customer session in progress"),
+            SodaMachineError::SessionExpired(session_id) => {
+                write!(f, "Session {} expired and must be closed first", session_id)
+            },
+            SodaMachineError::SessionInProgress(session_id) => {
+                write!(f, "Session {} still has credit and must be closed first", session_id)
+            },
+            SodaMachineError::PriceChangeInPast(effective_at) => {
+                write!(f, "Price change at {} has already taken effect", effective_at)
+            },
+            SodaMachineError::PriceChangeNotFound { sku, effective_at } => {
+                write!(f, "No price change of {} scheduled at {}", sku, effective_at)
+            },
+            SodaMachineError::PromotionAlreadyExists(id) => write!(f, "Promotion {} already runs", id),
+            SodaMachineError::PromotionNotFound(id) => write!(f, "Promotion {} not found", id),
+            SodaMachineError::InvalidCoupon(code) => write!(f, "Coupon {} is not valid right now", code),
+            SodaMachineError::VendInProgress(slot_id) => {
+                write!(f, "A soda from slot {} is still being delivered", slot_id.value())
+            },
+            SodaMachineError::NoPendingVend => write!(f, "No soda is being delivered"),
+            SodaMachineError::RefundExceedsRevenue { requested, revenue } => {
+                write!(f, "Cannot refund {}, only {} was taken since the last collection", requested, revenue)
+            },
+            SodaMachineError::RefundReasonMissing => write!(f, "A refund needs a reason"),
+            SodaMachineError::RevenueNotCollected(revenue) => {
+                write!(f, "The {} revenue has to be collected before the float is unloaded", revenue)
+            },
+            SodaMachineError::DenominationNotAccepted(amount) => {
+                write!(f, "{} is not a coin or bill this machine takes", amount)
+            },
+            SodaMachineError::MachineInUse(reason) => write!(f, "Machine is still in use: {}", reason),
+            SodaMachineError::InvalidHistory(reason) => write!(f, "Invalid event history: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SodaMachineError {}
+
+impl From<SlotError> for SodaMachineError {
+    fn from(err: SlotError) -> Self {
+        SodaMachineError::SlotError(err)
+    }
+}
+
+impl From<CashBoxError> for SodaMachineError {
+    fn from(err: CashBoxError) -> Self {
+        match err {
+            CashBoxError::ExactChangeUnavailable(amount) => SodaMachineError::ExactChangeUnavailable(amount),
+            other => SodaMachineError::CashBoxError(other),
+        }
+    }
+}
+
+impl From<SessionError> for SodaMachineError {
+    fn from(err: SessionError) -> Self {
+        match err {
+            SessionError::InsufficientCredit { required, available } => {
+                SodaMachineError::InsufficientFunds { required, available }
+            },
+            SessionError::MoneyError(err) => SodaMachineError::MoneyError(err),
+        }
+    }
+}
+
+impl From<MoneyError> for SodaMachineError {
+    fn from(err: MoneyError) -> Self {
+        SodaMachineError::MoneyError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::soda::{Soda, SodaFlavor, SodaSize};
+
+    fn create_test_soda() -> Soda {
+        Soda::new(
+            "Coca-Cola".to_string(),
+            SodaFlavor::Cola,
+            SodaSize::Medium,
+            Money::from_dollars_cents(1, 50).unwrap(),
+            false,
+            true,
+        ).unwrap()
+    }
+
+    fn now() -> Timestamp {
+        Timestamp::from_secs(1_000)
+    }
+
+    fn create_test_machine() -> SodaMachine {
+        SodaMachine::new(SodaMachineId::new(1), 10).unwrap()
+    }
+
+    #[test]
+    fn test_machine_creation() {
+        let machine = create_test_machine();
+        
+        assert_eq!(machine.id().value(), 1);
+        assert_eq!(machine.slot_count(), 0);
+        assert_eq!(machine.max_slots(), 10);
+        assert!(machine.is_operational());
+        assert_eq!(machine.inserted_money(), Money::zero());
+        assert_eq!(machine.total_collected(), Money::zero());
+    }
+
+    #[test]
+    fn test_machine_creation_zero_max_slots() {
+        let result = SodaMachine::new(SodaMachineId::new(1), 0);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
+    }
+
+    #[test]
+    fn test_add_slot() {
+        let mut machine = create_test_machine();
+        let event = machine.add_slot(SlotId::new(1), 20).unwrap();
+        
+        assert_eq!(machine.slot_count(), 1);
+        assert!(machine.get_slot(SlotId::new(1)).is_some());
+        
+        assert_eq!(event, SodaMachineEvent::SlotAdded { slot_id: SlotId::new(1), capacity: 20 });
+    }
+
+    #[test]
+    fn test_add_slot_duplicate() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        
+        let result = machine.add_slot(SlotId::new(1), 15);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::SlotAlreadyExists(SlotId::new(1)));
+    }
+
+    #[test]
+    fn test_add_slot_too_many() {
+        let mut machine = SodaMachine::new(SodaMachineId::new(1), 1).unwrap();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        
+        let result = machine.add_slot(SlotId::new(2), 15);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::TooManySlots);
+    }
+
+    #[test]
+    fn test_configure_slot() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        
+        let soda = create_test_soda();
+        let event = machine.configure_slot(SlotId::new(1), soda.clone()).unwrap();
+        
+        let slot = machine.get_slot(SlotId::new(1)).unwrap();
+        assert!(slot.soda_type().is_some());
+        assert_eq!(slot.soda_type().unwrap().name(), "Coca-Cola");
+        
+        match event {
+            SodaMachineEvent::SlotConfigured { slot_id, soda_type } => {
+                assert_eq!(slot_id, SlotId::new(1));
+                assert_eq!(soda_type.name(), "Coca-Cola");
+            },
+            _ => panic!("Expected SlotConfigured event"),
+        }
+    }
+
+    #[test]
+    fn test_configure_slot_not_found() {
+        let mut machine = create_test_machine();
+        let soda = create_test_soda();
+        
+        let result = machine.configure_slot(SlotId::new(1), soda);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::SlotNotFound(SlotId::new(1)));
+    }
+
+    #[test]
+    fn test_refill_slot() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        
+        let events = machine.refill_slot(SlotId::new(1), 10).unwrap();
+        
+        let slot = machine.get_slot(SlotId::new(1)).unwrap();
+        assert_eq!(slot.quantity(), 10);
+        
+        match &events[0] {
+            SodaMachineEvent::SlotRefilled { slot_id, quantity_added } => {
+                assert_eq!(*slot_id, SlotId::new(1));
+                assert_eq!(*quantity_added, 10);
+            },
+            _ => panic!("Expected SlotRefilled event"),
+        }
+        // The slot was sold out before
+        assert_eq!(events[1], SodaMachineEvent::SlotRestocked { slot_id: SlotId::new(1), quantity: 10 });
+    }
+
+    #[test]
+    fn test_restock_fills_every_stocked_slot() {
+        let mut machine = create_test_machine();
+        for (slot_id, capacity, quantity) in [(1, 10, 4), (2, 6, 6), (3, 8, 0)] {
+            machine.add_slot(SlotId::new(slot_id), capacity).unwrap();
+            machine.configure_slot(SlotId::new(slot_id), create_test_soda()).unwrap();
+            if quantity > 0 {
+                machine.refill_slot(SlotId::new(slot_id), quantity).unwrap();
+            }
+        }
+        machine.add_slot(SlotId::new(4), 5).unwrap();
+        machine.set_low_stock_threshold(SlotId::new(1), 2).unwrap();
+
+        let events = machine.restock().unwrap();
+
+        assert_eq!(events, vec![
+            SodaMachineEvent::SlotRefilled { slot_id: SlotId::new(1), quantity_added: 6 },
+            SodaMachineEvent::SlotRefilled { slot_id: SlotId::new(3), quantity_added: 8 },
+            SodaMachineEvent::SlotRestocked { slot_id: SlotId::new(3), quantity: 8 },
+        ]);
+        assert!(machine.get_slot(SlotId::new(1)).unwrap().is_full());
+        assert!(machine.get_slot(SlotId::new(3)).unwrap().is_full());
+        assert_eq!(machine.get_slot(SlotId::new(4)).unwrap().quantity(), 0, "Slots without a soda are left alone");
+        assert!(machine.restock().unwrap().is_empty());
+    }
+
+    fn create_test_product() -> Product {
+        Product::new(Sku::new("COLA-12OZ").unwrap(), create_test_soda(), None).unwrap()
+    }
+
+    #[test]
+    fn test_assign_product() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+
+        let event = machine.assign_product(SlotId::new(1), &create_test_product()).unwrap();
+
+        assert_eq!(event, SodaMachineEvent::ProductAssigned { slot_id: SlotId::new(1), product: create_test_product() });
+        assert!(machine.sells(create_test_product().sku()));
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().soda_type(), Some(&create_test_soda()));
+    }
+
+    #[test]
+    fn test_reprice_product_reaches_every_slot_selling_it() {
+        let mut machine = create_test_machine();
+        let product = create_test_product();
+        for slot_id in [3, 1] {
+            machine.add_slot(SlotId::new(slot_id), 10).unwrap();
+            machine.assign_product(SlotId::new(slot_id), &product).unwrap();
+            machine.refill_slot(SlotId::new(slot_id), 5).unwrap();
+        }
+        machine.add_slot(SlotId::new(2), 10).unwrap();
+        machine.configure_slot(SlotId::new(2), create_test_soda()).unwrap();
+        machine.disable();
+
+        let events = machine.reprice_product(product.sku(), Money::from_cents(175)).unwrap();
+
+        assert_eq!(events, vec![
+            SodaMachineEvent::SlotRepriced { slot_id: SlotId::new(1), price: Money::from_cents(175) },
+            SodaMachineEvent::SlotRepriced { slot_id: SlotId::new(3), price: Money::from_cents(175) },
+        ]);
+        assert_eq!(machine.get_slot(SlotId::new(2)).unwrap().soda_type().unwrap().price(), Money::from_cents(150),
+            "A soda configured by hand keeps its price");
+        assert!(machine.reprice_product(product.sku(), Money::from_cents(175)).unwrap().is_empty());
+        assert!(matches!(
+            machine.reprice_product(product.sku(), Money::from_minor_units(175, Currency::EUR)),
+            Err(SodaMachineError::CurrencyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_price_override_is_charged_when_the_soda_is_bought() {
+        let mut machine = create_test_machine();
+        let product = create_test_product();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.assign_product(SlotId::new(1), &product).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.add_slot(SlotId::new(2), 10).unwrap();
+        machine.configure_slot(SlotId::new(2), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(2), 5).unwrap();
+
+        machine.override_price(product.sku(), Some(Money::from_cents(200)), now()).unwrap();
+
+        let mut available = machine.get_available_sodas(now());
+        available.sort_by_key(|(slot_id, _, _)| *slot_id);
+        assert_eq!(available[0].2, Money::from_cents(200));
+        assert_eq!(available[1].2, Money::from_cents(150), "A soda configured by hand keeps its price");
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+        assert_eq!(machine.dispense_soda(SlotId::new(1), now()).unwrap_err(), SodaMachineError::InsufficientFunds {
+            required: Money::from_cents(200),
+            available: Money::from_cents(150),
+        });
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+        let events = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        assert!(matches!(events[0], SodaMachineEvent::SodaDispensed { price, .. } if price == Money::from_cents(200)));
+        assert_eq!(machine.total_collected(), Money::from_cents(200));
+
+        machine.override_price(product.sku(), None, now()).unwrap();
+        assert_eq!(machine.price_at(SlotId::new(1), now()), Some(Money::from_cents(150)));
+        assert!(machine.price_schedule().is_empty());
+        assert!(matches!(
+            machine.override_price(product.sku(), Some(Money::from_cents(-1)), now()),
+            Err(SodaMachineError::InvalidAmount)
+        ));
+    }
+
+    #[test]
+    fn test_scheduled_price_change_takes_effect_at_its_time() {
+        let mut machine = create_test_machine();
+        let product = create_test_product();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.assign_product(SlotId::new(1), &product).unwrap();
+        let later = now().plus(Duration::from_secs(3600));
+        let change = PriceChange { sku: product.sku().clone(), price: Some(Money::from_cents(100)), effective_at: later };
+
+        machine.schedule_price_change(change.clone(), now()).unwrap();
+
+        assert_eq!(machine.price_at(SlotId::new(1), now()), Some(Money::from_cents(150)));
+        assert_eq!(machine.price_at(SlotId::new(1), later), Some(Money::from_cents(100)));
+        assert_eq!(machine.price_schedule().upcoming(now()), vec![&change]);
+        assert_eq!(
+            machine.schedule_price_change(PriceChange { effective_at: now(), ..change.clone() }, now()).unwrap_err(),
+            SodaMachineError::PriceChangeInPast(now())
+        );
+        assert_eq!(
+            machine.cancel_price_change(product.sku(), later, later).unwrap_err(),
+            SodaMachineError::PriceChangeInPast(later),
+            "A change that took effect is part of the price history"
+        );
+
+        let rebuilt = SodaMachine::from_history(machine.uncommitted_events().to_vec()).unwrap();
+        assert_eq!(rebuilt.price_schedule(), machine.price_schedule());
+
+        machine.cancel_price_change(product.sku(), later, now()).unwrap();
+        assert_eq!(machine.price_at(SlotId::new(1), later), Some(Money::from_cents(150)));
+        assert_eq!(
+            machine.cancel_price_change(product.sku(), later, now()).unwrap_err(),
+            SodaMachineError::PriceChangeNotFound { sku: product.sku().clone(), effective_at: later }
+        );
+    }
+
+    fn create_test_promotion(id: &str, discount: Discount) -> Promotion {
+        Promotion::new(PromotionId::new(id).unwrap(), id.to_string(), discount).unwrap()
+    }
+
+    #[test]
+    fn test_best_promotion_is_taken_off_the_price() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 10).unwrap();
+        machine.load_cash(Money::from_cents(25), 20).unwrap();
+        machine.load_cash(Money::from_cents(5), 10).unwrap();
+        machine.add_promotion(create_test_promotion("TEN-OFF", Discount::PercentOff(10))).unwrap();
+        machine.add_promotion(create_test_promotion("CHERRY", Discount::PercentOff(50)).for_flavor(SodaFlavor::Cherry)).unwrap();
+        machine.add_promotion(create_test_promotion("THIRD-FREE", Discount::BuyNGetOneFree(2))).unwrap();
+
+        machine.insert_money(Money::from_cents(500), now()).unwrap();
+        let mut paid = Vec::new();
+        for _ in 0..3 {
+            match machine.dispense_soda(SlotId::new(1), now()).unwrap().as_slice() {
+                [SodaMachineEvent::SodaDispensed { price, promotion: Some(promotion), .. }, ..] => {
+                    paid.push((*price, promotion.promotion_id.to_string()));
+                },
+                events => panic!("Unexpected events: {:?}", events),
+            }
+        }
+
+        assert_eq!(paid, vec![
+            (Money::from_cents(135), "TEN-OFF".to_string()),
+            (Money::from_cents(135), "TEN-OFF".to_string()),
+            (Money::zero(), "THIRD-FREE".to_string()),
+        ]);
+        assert_eq!(machine.total_collected(), Money::from_cents(270));
+        assert_eq!(machine.inserted_money(), Money::from_cents(230));
+
+        let rebuilt = SodaMachine::from_history(machine.uncommitted_events().to_vec()).unwrap();
+        assert_eq!(rebuilt.current_session(), machine.current_session());
+        assert_eq!(rebuilt.promotions(), machine.promotions());
+    }
+
+    #[test]
+    fn test_free_soda_is_earned_when_the_credit_is_spent_exactly() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 10).unwrap();
+        machine.add_promotion(create_test_promotion("THIRD-FREE", Discount::BuyNGetOneFree(2))).unwrap();
+        for _ in 0..6 {
+            machine.insert_money(Money::from_cents(50), now()).unwrap();
+        }
+
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        let second = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        let session_after_second = machine.current_session().cloned();
+        let third = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+
+        assert!(!second.iter().any(|event| matches!(event, SodaMachineEvent::SessionCompleted { .. })));
+        assert!(session_after_second.is_some_and(|session| session.credit().is_zero()), "The session stays open for the free soda");
+        assert!(matches!(
+            third.as_slice(),
+            [SodaMachineEvent::SodaDispensed { price, promotion: Some(_), .. }, SodaMachineEvent::SessionCompleted { .. }] if price.is_zero()
+        ));
+        assert!(machine.current_session().is_none());
+        assert_eq!(machine.total_collected(), Money::from_cents(300));
+    }
+
+    #[test]
+    fn test_promotions_are_managed_by_id() {
+        let mut machine = create_test_machine();
+        machine.add_promotion(create_test_promotion("SUMMER", Discount::PercentOff(10))).unwrap();
+
+        assert_eq!(
+            machine.add_promotion(create_test_promotion("summer", Discount::PercentOff(20))).unwrap_err(),
+            SodaMachineError::PromotionAlreadyExists(PromotionId::new("SUMMER").unwrap())
+        );
+        assert!(matches!(
+            machine.add_promotion(create_test_promotion("EURO", Discount::AmountOff(Money::from_minor_units(50, Currency::EUR)))),
+            Err(SodaMachineError::CurrencyMismatch { .. })
+        ));
+
+        machine.remove_promotion(&PromotionId::new("SUMMER").unwrap()).unwrap();
+        assert!(machine.promotions().is_empty());
+        assert_eq!(
+            machine.remove_promotion(&PromotionId::new("SUMMER").unwrap()).unwrap_err(),
+            SodaMachineError::PromotionNotFound(PromotionId::new("SUMMER").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_coupon_applies_once_it_is_entered() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 10).unwrap();
+        let code = PromotionId::new("SAVE50").unwrap();
+        machine.add_promotion(create_test_promotion("SAVE50", Discount::AmountOff(Money::from_cents(50))).as_coupon()).unwrap();
+
+        assert_eq!(machine.enter_coupon(&code, now()).unwrap_err(), SodaMachineError::NoActiveSession);
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        assert!(matches!(
+            machine.dispense_soda(SlotId::new(1), now()),
+            Err(SodaMachineError::InsufficientFunds { .. })
+        ), "The coupon was not entered yet");
+        assert_eq!(
+            machine.enter_coupon(&PromotionId::new("SAVE99").unwrap(), now()).unwrap_err(),
+            SodaMachineError::InvalidCoupon(PromotionId::new("SAVE99").unwrap())
+        );
+        machine.enter_coupon(&code, now()).unwrap();
+
+        let events = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        assert!(matches!(
+            &events[0],
+            SodaMachineEvent::SodaDispensed { price, promotion: Some(promotion), .. }
+                if *price == Money::from_cents(100) && promotion.discount == Money::from_cents(50)
+        ));
+        assert!(machine.current_session().is_none(), "The coupon paid for the rest of the soda");
+    }
+
+    fn planogram_slot(id: u32, capacity: u32, low_stock_threshold: u32, soda: Option<Soda>) -> PlanogramSlot {
+        PlanogramSlot { slot_id: SlotId::new(id), capacity, low_stock_threshold, sku: None, soda }
+    }
+
+    #[test]
+    fn test_apply_planogram_lays_out_every_slot() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 5).unwrap();
+        machine.set_low_stock_threshold(SlotId::new(1), 4).unwrap();
+        let planogram = Planogram::new(vec![
+            planogram_slot(1, 3, 1, Some(create_test_soda())),
+            planogram_slot(2, 12, 2, None),
+        ]).unwrap();
+
+        let events = machine.apply_planogram(&planogram).unwrap();
+
+        assert_eq!(events, vec![
+            SodaMachineEvent::LowStockThresholdChanged { slot_id: SlotId::new(1), threshold: 1 },
+            SodaMachineEvent::SlotResized { slot_id: SlotId::new(1), capacity: 3 },
+            SodaMachineEvent::SlotConfigured { slot_id: SlotId::new(1), soda_type: create_test_soda() },
+            SodaMachineEvent::SlotAdded { slot_id: SlotId::new(2), capacity: 12 },
+            SodaMachineEvent::LowStockThresholdChanged { slot_id: SlotId::new(2), threshold: 2 },
+        ]);
+        assert_eq!(machine.planogram(), planogram);
+        assert!(machine.apply_planogram(&planogram).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_planogram_is_all_or_nothing() {
+        let mut machine = SodaMachine::new(SodaMachineId::new(1), 2).unwrap();
+        machine.add_slot(SlotId::new(1), 5).unwrap();
+        let before = machine.planogram();
+        let version = machine.version();
+
+        let too_many = Planogram::new(vec![
+            planogram_slot(1, 8, 0, Some(create_test_soda())),
+            planogram_slot(2, 8, 0, None),
+            planogram_slot(3, 8, 0, None),
+        ]).unwrap();
+        assert_eq!(machine.apply_planogram(&too_many).unwrap_err(), SodaMachineError::TooManySlots);
+
+        let euros = Soda::new(
+            "Fanta".to_string(),
+            SodaFlavor::Orange,
+            SodaSize::Medium,
+            Money::from_minor_units(150, Currency::EUR),
+            false,
+            false,
+        ).unwrap();
+        let wrong_currency = Planogram::new(vec![planogram_slot(1, 8, 0, Some(euros))]).unwrap();
+        assert!(matches!(
+            machine.apply_planogram(&wrong_currency).unwrap_err(),
+            SodaMachineError::CurrencyMismatch { .. }
+        ));
+
+        assert_eq!(machine.planogram(), before);
+        assert_eq!(machine.version(), version);
+    }
+
+    #[test]
+    fn test_apply_planogram_keeps_stocked_soda() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 4).unwrap();
+
+        let sprite = Soda::new(
+            "Sprite".to_string(),
+            SodaFlavor::LemonLime,
+            SodaSize::Medium,
+            Money::from_cents(125),
+            false,
+            false,
+        ).unwrap();
+        let planogram = Planogram::new(vec![planogram_slot(1, 10, 0, Some(sprite))]).unwrap();
+
+        assert_eq!(
+            machine.apply_planogram(&planogram).unwrap_err(),
+            SodaMachineError::SlotError(SlotError::SodaTypeMismatch)
+        );
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().soda_type(), Some(&create_test_soda()));
+    }
+
+    #[test]
+    fn test_set_low_stock_threshold() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+
+        let event = machine.set_low_stock_threshold(SlotId::new(1), 3).unwrap();
+
+        assert_eq!(event, SodaMachineEvent::LowStockThresholdChanged { slot_id: SlotId::new(1), threshold: 3 });
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().low_stock_threshold(), 3);
+        assert!(matches!(
+            machine.set_low_stock_threshold(SlotId::new(1), 10),
+            Err(SodaMachineError::SlotError(SlotError::InvalidQuantity(_)))
+        ));
+        assert_eq!(
+            machine.set_low_stock_threshold(SlotId::new(2), 1),
+            Err(SodaMachineError::SlotNotFound(SlotId::new(2)))
+        );
+    }
+
+    #[test]
+    fn test_sales_report_low_stock_and_sold_out_once() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.set_low_stock_threshold(SlotId::new(1), 1).unwrap();
+        machine.refill_slot(SlotId::new(1), 3).unwrap();
+
+        let mut alerts = Vec::new();
+        for _ in 0..3 {
+            machine.insert_money(Money::from_cents(100), now()).unwrap();
+            machine.insert_money(Money::from_cents(50), now()).unwrap();
+            let events = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+            alerts.push(events.into_iter()
+                .filter(|event| matches!(event, SodaMachineEvent::SlotLowStock { .. } | SodaMachineEvent::SlotSoldOut { .. }))
+                .collect::<Vec<_>>());
+        }
+
+        assert!(alerts[0].is_empty());
+        assert_eq!(alerts[1], vec![SodaMachineEvent::SlotLowStock { slot_id: SlotId::new(1), quantity: 1, threshold: 1 }]);
+        assert_eq!(alerts[2], vec![SodaMachineEvent::SlotSoldOut { slot_id: SlotId::new(1) }]);
+    }
+
+    #[test]
+    fn test_refill_reports_restocked_only_above_threshold() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.set_low_stock_threshold(SlotId::new(1), 4).unwrap();
+
+        let still_low = machine.refill_slot(SlotId::new(1), 4).unwrap();
+        let restocked = machine.refill_slot(SlotId::new(1), 2).unwrap();
+        let topped_up = machine.refill_slot(SlotId::new(1), 2).unwrap();
+
+        assert_eq!(still_low.len(), 1);
+        assert_eq!(restocked[1], SodaMachineEvent::SlotRestocked { slot_id: SlotId::new(1), quantity: 6 });
+        assert_eq!(topped_up.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_money() {
+        let mut machine = create_test_machine();
+        let amount = Money::from_dollars_cents(2, 00).unwrap();
+        
+        let events = machine.insert_money(amount, now()).unwrap();
+        
+        assert_eq!(machine.inserted_money(), amount);
+        
+        match &events[..] {
+            [
+                SodaMachineEvent::SessionStarted { session_id, started_at },
+                SodaMachineEvent::MoneyInserted { amount: event_amount, total_inserted, inserted_at },
+            ] => {
+                assert_eq!(*session_id, SessionId::new(1));
+                assert_eq!(*started_at, now());
+                assert_eq!(*event_amount, amount);
+                assert_eq!(*total_inserted, amount);
+                assert_eq!(*inserted_at, now());
+            },
+            _ => panic!("Expected SessionStarted and MoneyInserted events"),
+        }
+    }
+
+    #[test]
+    fn test_insert_money_negative() {
+        let mut machine = create_test_machine();
+        let amount = Money::from_dollars_cents(-1, 00).unwrap();
+        
+        let result = machine.insert_money(amount, now());
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
+    }
+
+    #[test]
+    fn test_dispense_soda() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.load_cash(Money::from_cents(25), 4).unwrap();
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        
+        let events = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        
+        assert_eq!(machine.inserted_money(), Money::from_dollars_cents(0, 50).unwrap()); // $2.00 - $1.50
+        assert_eq!(machine.total_collected(), Money::from_dollars_cents(1, 50).unwrap());
+        
+        let slot = machine.get_slot(SlotId::new(1)).unwrap();
+        assert_eq!(slot.quantity(), 4); // 5 - 1
+        
+        // Credit is left over, so the session stays open
+        match &events[..] {
+            [SodaMachineEvent::SodaDispensed { slot_id, soda, price, dispensed_at, .. }] => {
+                assert_eq!(*slot_id, SlotId::new(1));
+                assert_eq!(*price, Money::from_cents(150));
+                assert_eq!(*dispensed_at, now());
+                assert_eq!(soda.name(), "Coca-Cola");
+            },
+            _ => panic!("Expected SodaDispensed event"),
+        }
+    }
+
+    #[test]
+    fn test_dispense_soda_exact_change_unavailable() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.load_cash(Money::from_cents(100), 2).unwrap(); // No quarters left
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        
+        let result = machine.dispense_soda(SlotId::new(1), now());
+        assert_eq!(result.unwrap_err(), SodaMachineError::ExactChangeUnavailable(Money::from_cents(50)));
+        
+        // Nothing was sold and the customer still has their credit
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().quantity(), 5);
+        assert_eq!(machine.inserted_money(), Money::from_dollars_cents(2, 00).unwrap());
+        assert_eq!(machine.total_collected(), Money::zero());
+    }
+
+    #[test]
+    fn test_dispense_soda_exact_amount_needs_no_change() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(25), now()).unwrap();
+        machine.insert_money(Money::from_cents(25), now()).unwrap();
+        
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        
+        assert_eq!(machine.inserted_money(), Money::zero());
+        assert_eq!(machine.cash_box().total(), Money::from_dollars_cents(1, 50).unwrap());
+    }
+
+    #[test]
+    fn test_dispense_soda_insufficient_funds() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.insert_money(Money::from_dollars_cents(1, 00).unwrap(), now()).unwrap(); // Not enough
+        
+        let result = machine.dispense_soda(SlotId::new(1), now());
+        assert!(result.is_err());
+        
+        match result.unwrap_err() {
+            SodaMachineError::InsufficientFunds { required, available } => {
+                assert_eq!(required, Money::from_dollars_cents(1, 50).unwrap());
+                assert_eq!(available, Money::from_dollars_cents(1, 00).unwrap());
+            },
+            _ => panic!("Expected InsufficientFunds error"),
+        }
+    }
+
+    #[test]
+    fn test_dispense_soda_empty_slot() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        // Don't refill the slot
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        
+        let result = machine.dispense_soda(SlotId::new(1), now());
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::SlotError(SlotError::SlotEmpty));
+    }
+
+    #[test]
+    fn test_return_money() {
+        let mut machine = create_test_machine();
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        
+        let events = machine.return_money(now()).unwrap();
+        
+        assert_eq!(machine.inserted_money(), Money::zero());
+        assert!(machine.current_session().is_none());
+        
+        match &events[..] {
+            [
+                SodaMachineEvent::MoneyReturned { amount, breakdown },
+                SodaMachineEvent::SessionCancelled { summary },
+            ] => {
+                assert_eq!(*amount, Money::from_dollars_cents(2, 00).unwrap());
+                assert_eq!(breakdown.items(), &[(Money::from_dollars_cents(2, 00).unwrap(), 1)]);
+                assert_eq!(summary.refunded, *amount);
+            },
+            _ => panic!("Expected MoneyReturned and SessionCancelled events"),
+        }
+    }
+
+    #[test]
+    fn test_return_money_drains_cash_box() {
+        let mut machine = create_test_machine();
+        machine.load_cash(Money::from_cents(25), 4).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        
+        machine.return_money(now()).unwrap();
+        
+        // The customer's own coin is handed back, the float is untouched
+        assert_eq!(machine.cash_box().count(Money::from_cents(100)), 0);
+        assert_eq!(machine.cash_box().count(Money::from_cents(25)), 4);
+    }
+
+    #[test]
+    fn test_return_money_after_purchase_itemizes_change() {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.load_cash(Money::from_cents(25), 1).unwrap();
+        machine.load_cash(Money::from_cents(10), 5).unwrap();
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        
+        let events = machine.return_money(now()).unwrap();
+        
+        // The single quarter cannot be combined with dimes to make 50 cents
+        let expected = ChangeBreakdown::new(vec![(Money::from_cents(10), 5)]);
+        assert_eq!(events[0], SodaMachineEvent::MoneyReturned { amount: Money::from_cents(50), breakdown: expected });
+        assert_eq!(machine.cash_box().count(Money::from_cents(25)), 1);
+    }
+
+    #[test]
+    fn test_return_money_none_inserted() {
+        let mut machine = create_test_machine();
+        
+        let result = machine.return_money(now());
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
+    }
+
+    #[test]
+    fn test_return_change() {
+        let mut machine = create_test_machine();
+        machine.load_cash(Money::from_cents(25), 2).unwrap();
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        
+        let change_amount = Money::from_dollars_cents(0, 50).unwrap();
+        let event = machine.return_change(change_amount, now()).unwrap();
+        
+        assert_eq!(machine.inserted_money(), Money::from_dollars_cents(1, 50).unwrap());
+        
+        match event {
+            SodaMachineEvent::ChangeReturned { amount, breakdown, .. } => {
+                assert_eq!(amount, change_amount);
+                assert_eq!(breakdown.items(), &[(Money::from_cents(25), 2)]);
+            },
+            _ => panic!("Expected ChangeReturned event"),
+        }
+    }
+
+    #[test]
+    fn test_return_change_exact_change_unavailable() {
+        let mut machine = create_test_machine();
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
+        
+        let result = machine.return_change(Money::from_dollars_cents(0, 50).unwrap(), now());
+        assert_eq!(result.unwrap_err(), SodaMachineError::ExactChangeUnavailable(Money::from_dollars_cents(0, 50).unwrap()));
+        assert_eq!(machine.inserted_money(), Money::from_dollars_cents(2, 00).unwrap());
+    }
+
+    fn create_stocked_machine() -> SodaMachine {
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.load_cash(Money::from_cents(25), 10).unwrap();
+        machine
+    }
+
+    #[test]
+    fn test_session_started_on_first_insert() {
+        let mut machine = create_test_machine();
+        assert!(machine.current_session().is_none());
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        let events = machine.insert_money(Money::from_cents(25), now().plus(Duration::from_secs(5))).unwrap();
+
+        // Only the first insert starts a session
+        assert_eq!(events, vec![SodaMachineEvent::MoneyInserted {
+            amount: Money::from_cents(25),
+            total_inserted: Money::from_cents(125),
+            inserted_at: now().plus(Duration::from_secs(5)),
+        }]);
+
+        let session = machine.current_session().unwrap();
+        assert_eq!(session.id(), SessionId::new(1));
+        assert_eq!(session.started_at(), now());
+        assert_eq!(session.last_activity_at(), now().plus(Duration::from_secs(5)));
+        assert_eq!(session.credit(), Money::from_cents(125));
+    }
+
+    #[test]
+    fn test_session_ids_are_sequential() {
+        let mut machine = create_test_machine();
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.return_money(now()).unwrap();
+        let events = machine.insert_money(Money::from_cents(100), now()).unwrap();
+
+        assert_eq!(events[0], SodaMachineEvent::SessionStarted { session_id: SessionId::new(2), started_at: now() });
+    }
+
+    #[test]
+    fn test_session_completes_when_credit_used_up() {
+        let mut machine = create_stocked_machine();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+
+        let events = machine.dispense_soda(SlotId::new(1), now()).unwrap();
+
+        assert!(machine.current_session().is_none());
+        match &events[..] {
+            [SodaMachineEvent::SodaDispensed { .. }, SodaMachineEvent::SessionCompleted { summary }] => {
+                assert_eq!(summary.session_id, SessionId::new(1));
+                assert_eq!(summary.total_inserted, Money::from_cents(150));
+                assert_eq!(summary.total_spent, Money::from_cents(150));
+                assert_eq!(summary.refunded, Money::zero());
+                assert_eq!(summary.purchases.len(), 1);
+            },
+            _ => panic!("Expected SodaDispensed and SessionCompleted events"),
+        }
+    }
+
+    #[test]
+    fn test_return_money_after_purchase_completes_session() {
+        let mut machine = create_stocked_machine();
+        machine.insert_money(Money::from_cents(200), now()).unwrap();
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+
+        let events = machine.return_money(now()).unwrap();
+
+        match &events[..] {
+            [SodaMachineEvent::MoneyReturned { amount, .. }, SodaMachineEvent::SessionCompleted { summary }] => {
+                assert_eq!(*amount, Money::from_cents(50));
+                assert_eq!(summary.total_spent, Money::from_cents(150));
+                assert_eq!(summary.refunded, Money::from_cents(50));
+            },
+            _ => panic!("Expected MoneyReturned and SessionCompleted events"),
+        }
+    }
+
+    #[test]
+    fn test_cancel_session() {
+        let mut machine = create_test_machine();
+        assert_eq!(machine.cancel_session(now()).unwrap_err(), SodaMachineError::NoActiveSession);
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        let events = machine.cancel_session(now()).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[1], SodaMachineEvent::SessionCancelled { summary } if summary.refunded == Money::from_cents(100)));
+        assert!(machine.cash_box().is_empty());
+    }
+
+    #[test]
+    fn test_complete_session_without_session() {
+        let mut machine = create_test_machine();
+        assert_eq!(machine.complete_session(now()).unwrap_err(), SodaMachineError::NoActiveSession);
+    }
+
+    #[test]
+    fn test_expire_idle_session() {
+        let mut machine = create_test_machine();
+        machine.load_cash(Money::from_cents(25), 4).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+
+        let not_yet = now().plus(DEFAULT_SESSION_TIMEOUT - Duration::from_millis(1));
+        assert!(machine.expire_idle_session(not_yet).unwrap().is_empty());
+        assert!(machine.current_session().is_some());
+
+        let later = now().plus(DEFAULT_SESSION_TIMEOUT);
+        let events = machine.expire_idle_session(later).unwrap();
+
+        assert!(machine.current_session().is_none());
+        assert_eq!(machine.inserted_money(), Money::zero());
+        assert_eq!(machine.cash_box().total(), Money::from_cents(100)); // Only the float is left
+        match &events[..] {
+            [SodaMachineEvent::MoneyReturned { amount, .. }, SodaMachineEvent::SessionExpired { summary }] => {
+                assert_eq!(*amount, Money::from_cents(100));
+                assert_eq!(summary.ended_at, later);
+                assert_eq!(summary.refunded, Money::from_cents(100));
+            },
+            _ => panic!("Expected MoneyReturned and SessionExpired events"),
+        }
+    }
+
+    #[test]
+    fn test_expire_idle_session_without_session() {
+        let mut machine = create_test_machine();
+        assert!(machine.expire_idle_session(now()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_idle_session_rejects_commands() {
+        let mut machine = create_stocked_machine();
+        machine.insert_money(Money::from_cents(200), now()).unwrap();
+        let later = now().plus(DEFAULT_SESSION_TIMEOUT);
+        let expired = SodaMachineError::SessionExpired(SessionId::new(1));
+
+        assert_eq!(machine.insert_money(Money::from_cents(25), later).unwrap_err(), expired);
+        assert_eq!(machine.dispense_soda(SlotId::new(1), later).unwrap_err(), expired);
+        assert_eq!(machine.return_money(later).unwrap_err(), expired);
+        assert_eq!(machine.return_change(Money::from_cents(25), later).unwrap_err(), expired);
+        assert_eq!(machine.inserted_money(), Money::from_cents(200));
+    }
+
+    #[test]
+    fn test_activity_keeps_session_alive() {
+        let mut machine = create_stocked_machine();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        let almost = now().plus(DEFAULT_SESSION_TIMEOUT - Duration::from_secs(1));
+        machine.insert_money(Money::from_cents(100), almost).unwrap();
+
+        let later = now().plus(DEFAULT_SESSION_TIMEOUT);
+        assert!(machine.expire_idle_session(later).unwrap().is_empty());
+        assert!(machine.dispense_soda(SlotId::new(1), later).is_ok());
+    }
+
+    #[test]
+    fn test_set_session_timeout() {
+        let mut machine = create_test_machine();
+        assert_eq!(machine.session_timeout(), DEFAULT_SESSION_TIMEOUT);
+
+        let event = machine.set_session_timeout(Duration::from_secs(30)).unwrap();
+        assert_eq!(event, SodaMachineEvent::SessionTimeoutChanged { timeout: Duration::from_secs(30) });
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        assert_eq!(machine.expire_idle_session(now().plus(Duration::from_secs(30))).unwrap().len(), 2);
+
+        assert_eq!(machine.set_session_timeout(Duration::ZERO).unwrap_err(), SodaMachineError::InvalidAmount);
+    }
+
+    #[test]
+    fn test_strict_mode_only_takes_single_accepted_denominations() {
+        let mut machine = create_test_machine();
+        machine.insert_money(Money::from_cents(5000), now()).unwrap();
+        machine.return_money(now()).unwrap();
+
+        machine.set_accepted_denominations(Some(AcceptedDenominations::standard(Currency::USD))).unwrap();
+
+        assert_eq!(machine.insert_money(Money::from_cents(7), now()).unwrap_err(), SodaMachineError::DenominationNotAccepted(Money::from_cents(7)));
+        assert_eq!(machine.insert_money(Money::from_cents(5000), now()).unwrap_err(), SodaMachineError::DenominationNotAccepted(Money::from_cents(5000)));
+        machine.insert_money(Money::from_cents(25), now()).unwrap();
+        assert_eq!(machine.inserted_money(), Money::from_cents(25));
+
+        assert_eq!(
+            machine.set_accepted_denominations(Some(AcceptedDenominations::standard(Currency::EUR))).unwrap_err(),
+            SodaMachineError::CurrencyMismatch { expected: Currency::USD, actual: Currency::EUR }
+        );
+
+        let rebuilt = SodaMachine::from_history(machine.take_uncommitted_events()).unwrap();
+        assert_eq!(rebuilt.accepted_denominations(), Some(&AcceptedDenominations::standard(Currency::USD)));
+
+        machine.set_accepted_denominations(None).unwrap();
+        machine.insert_money(Money::from_cents(5000), now()).unwrap();
+    }
+
+    #[test]
+    fn test_strict_mode_takes_denominations_only_as_their_kind() {
+        let mut machine = create_test_machine();
+        let dollar = Money::from_cents(100);
+        let dollar_coins_only = AcceptedDenominations::new(Currency::USD, &[dollar], &[]).unwrap();
+        machine.set_accepted_denominations(Some(dollar_coins_only)).unwrap();
+
+        assert_eq!(
+            machine.insert_denomination(DenominationKind::Bill, dollar, now()).unwrap_err(),
+            SodaMachineError::DenominationNotAccepted(dollar)
+        );
+        machine.insert_denomination(DenominationKind::Coin, dollar, now()).unwrap();
+        machine.insert_money(dollar, now()).unwrap();
+        assert_eq!(machine.inserted_money(), Money::from_cents(200));
+
+        machine.set_accepted_denominations(None).unwrap();
+        machine.insert_denomination(DenominationKind::Bill, dollar, now()).unwrap();
+        assert_eq!(machine.inserted_money(), Money::from_cents(300));
+    }
+
+    #[test]
+    fn test_return_change_without_session() {
+        let mut machine = create_test_machine();
+
+        let result = machine.return_change(Money::from_cents(25), now());
+        assert_eq!(result.unwrap_err(), SodaMachineError::InsufficientFunds {
+            required: Money::from_cents(25),
+            available: Money::zero(),
+        });
     }
 
     #[test]
-    fn test_machine_creation() {
-        let machine = create_test_machine();
-        
-        assert_eq!(machine.id().value(), 1);
-        assert_eq!(machine.slot_count(), 0);
-        assert_eq!(machine.max_slots(), 10);
-        assert!(machine.is_operational());
-        assert_eq!(machine.inserted_money(), Money::zero());
-        assert_eq!(machine.total_collected(), Money::zero());
+    fn test_eur_session_refund_keeps_currency() {
+        let euro = |amount| Money::from_minor_units(amount, Currency::EUR);
+        let mut machine = SodaMachine::new_with_currency(SodaMachineId::new(1), 10, Currency::EUR).unwrap();
+        machine.insert_money(euro(200), now()).unwrap();
+
+        machine.return_money(now()).unwrap();
+
+        assert_eq!(machine.inserted_money(), euro(0));
     }
 
     #[test]
-    fn test_machine_creation_zero_max_slots() {
-        let result = SodaMachine::new(SodaMachineId::new(1), 0);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
+    fn test_insert_money_fills_cash_box() {
+        let mut machine = create_test_machine();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(25), now()).unwrap();
+        
+        assert_eq!(machine.cash_box().count(Money::from_cents(100)), 1);
+        assert_eq!(machine.cash_box().count(Money::from_cents(25)), 1);
+        assert_eq!(machine.cash_box().total(), machine.inserted_money());
     }
 
     #[test]
-    fn test_add_slot() {
+    fn test_insert_money_takes_single_coins_and_bills_only() {
         let mut machine = create_test_machine();
-        let event = machine.add_slot(SlotId::new(1), 20).unwrap();
-        
-        assert_eq!(machine.slot_count(), 1);
-        assert!(machine.get_slot(SlotId::new(1)).is_some());
-        
-        match event {
-            SodaMachineEvent::SlotConfigured { slot_id, .. } => {
-                assert_eq!(slot_id, SlotId::new(1));
-            },
-            _ => panic!("Expected SlotConfigured event"),
+
+        for amount in [Money::from_cents(7), Money::from_cents(150), Money::from_cents(300)] {
+            assert_eq!(machine.insert_money(amount, now()).unwrap_err(), SodaMachineError::DenominationNotAccepted(amount));
         }
+        assert!(machine.current_session().is_none());
+
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+
+        assert_eq!(machine.inserted_money(), Money::from_cents(150));
+        assert_eq!(
+            machine.cash_box().denominations().iter().map(|(denomination, count)| (*denomination, *count)).collect::<Vec<_>>(),
+            vec![(Money::from_cents(50), 1), (Money::from_cents(100), 1)],
+            "The cash box holds the coins that went in, nothing else"
+        );
     }
 
     #[test]
-    fn test_add_slot_duplicate() {
+    fn test_load_cash_rejects_denominations_the_currency_lacks() {
         let mut machine = create_test_machine();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
-        
-        let result = machine.add_slot(SlotId::new(1), 15);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::SlotAlreadyExists(SlotId::new(1)));
+
+        let result = machine.load_cash(Money::from_cents(7), 10);
+
+        assert_eq!(result.unwrap_err(), SodaMachineError::CashBoxError(CashBoxError::InvalidDenomination(Money::from_cents(7))));
+        assert!(machine.cash_box().is_empty());
     }
 
     #[test]
-    fn test_add_slot_too_many() {
-        let mut machine = SodaMachine::new(SodaMachineId::new(1), 1).unwrap();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
+    fn test_load_cash() {
+        let mut machine = create_test_machine();
+        let event = machine.load_cash(Money::from_cents(25), 40).unwrap();
         
-        let result = machine.add_slot(SlotId::new(2), 15);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::TooManySlots);
+        assert_eq!(machine.cash_box().count(Money::from_cents(25)), 40);
+        assert_eq!(machine.cash_box().total(), Money::from_cents(1000));
+        assert_eq!(machine.inserted_money(), Money::zero());
+        assert_eq!(event, SodaMachineEvent::CashLoaded { denomination: Money::from_cents(25), count: 40 });
     }
 
     #[test]
-    fn test_configure_slot() {
+    fn test_load_cash_invalid() {
         let mut machine = create_test_machine();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
-        
-        let soda = create_test_soda();
-        let event = machine.configure_slot(SlotId::new(1), soda.clone()).unwrap();
         
-        let slot = machine.get_slot(SlotId::new(1)).unwrap();
-        assert!(slot.soda_type().is_some());
-        assert_eq!(slot.soda_type().unwrap().name(), "Coca-Cola");
+        let result = machine.load_cash(Money::from_cents(25), 0);
+        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
         
-        match event {
-            SodaMachineEvent::SlotConfigured { slot_id, soda_type } => {
-                assert_eq!(slot_id, SlotId::new(1));
-                assert_eq!(soda_type.name(), "Coca-Cola");
-            },
-            _ => panic!("Expected SlotConfigured event"),
-        }
+        let result = machine.load_cash(Money::zero(), 10);
+        assert_eq!(result.unwrap_err(), SodaMachineError::CashBoxError(CashBoxError::InvalidDenomination(Money::zero())));
     }
 
     #[test]
-    fn test_configure_slot_not_found() {
-        let mut machine = create_test_machine();
-        let soda = create_test_soda();
+    fn test_machine_with_currency() {
+        let machine = SodaMachine::new_with_currency(SodaMachineId::new(1), 10, Currency::EUR).unwrap();
         
-        let result = machine.configure_slot(SlotId::new(1), soda);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::SlotNotFound(SlotId::new(1)));
+        assert_eq!(machine.currency(), Currency::EUR);
+        assert_eq!(machine.inserted_money(), Money::zero_in(Currency::EUR));
+        assert_eq!(machine.total_collected(), Money::zero_in(Currency::EUR));
+        assert_eq!(machine.total_inventory_value(), Money::zero_in(Currency::EUR));
+        assert_eq!(machine.cash_box().currency(), Currency::EUR);
+        assert_eq!(create_test_machine().currency(), Currency::USD);
     }
 
     #[test]
-    fn test_refill_slot() {
+    fn test_insert_money_other_currency() {
         let mut machine = create_test_machine();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
-        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
         
-        let event = machine.refill_slot(SlotId::new(1), 10).unwrap();
-        
-        let slot = machine.get_slot(SlotId::new(1)).unwrap();
-        assert_eq!(slot.quantity(), 10);
-        
-        match event {
-            SodaMachineEvent::SlotRefilled { slot_id, quantity_added } => {
-                assert_eq!(slot_id, SlotId::new(1));
-                assert_eq!(quantity_added, 10);
-            },
-            _ => panic!("Expected SlotRefilled event"),
-        }
+        let result = machine.insert_money(Money::from_minor_units(200, Currency::EUR), now());
+        assert_eq!(result.unwrap_err(), SodaMachineError::CurrencyMismatch {
+            expected: Currency::USD,
+            actual: Currency::EUR,
+        });
+        assert_eq!(machine.inserted_money(), Money::zero());
+        assert!(machine.cash_box().is_empty());
     }
 
     #[test]
-    fn test_insert_money() {
+    fn test_configure_slot_other_currency() {
         let mut machine = create_test_machine();
-        let amount = Money::from_dollars_cents(2, 00).unwrap();
-        
-        let event = machine.insert_money(amount).unwrap();
-        
-        assert_eq!(machine.inserted_money(), amount);
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        let soda = create_test_soda().with_price(Money::from_minor_units(150, Currency::JPY)).unwrap();
         
-        match event {
-            SodaMachineEvent::MoneyInserted { amount: event_amount, total_inserted } => {
-                assert_eq!(event_amount, amount);
-                assert_eq!(total_inserted, amount);
-            },
-            _ => panic!("Expected MoneyInserted event"),
-        }
+        let result = machine.configure_slot(SlotId::new(1), soda);
+        assert_eq!(result.unwrap_err(), SodaMachineError::CurrencyMismatch {
+            expected: Currency::USD,
+            actual: Currency::JPY,
+        });
     }
 
     #[test]
-    fn test_insert_money_negative() {
+    fn test_load_cash_other_currency() {
         let mut machine = create_test_machine();
-        let amount = Money::from_dollars_cents(-1, 00).unwrap();
         
-        let result = machine.insert_money(amount);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
+        let result = machine.load_cash(Money::from_minor_units(100, Currency::GBP), 10);
+        assert_eq!(result.unwrap_err(), SodaMachineError::CurrencyMismatch {
+            expected: Currency::USD,
+            actual: Currency::GBP,
+        });
     }
 
     #[test]
-    fn test_dispense_soda() {
+    fn test_collect_cash_keeps_the_float() {
         let mut machine = create_test_machine();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
+        let operator = OperatorId::new("OP-7").unwrap();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
         machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
         machine.refill_slot(SlotId::new(1), 5).unwrap();
-        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap()).unwrap();
-        
-        let event = machine.dispense_soda(SlotId::new(1)).unwrap();
-        
-        assert_eq!(machine.inserted_money(), Money::from_dollars_cents(0, 50).unwrap()); // $2.00 - $1.50
-        assert_eq!(machine.total_collected(), Money::from_dollars_cents(1, 50).unwrap());
-        
-        let slot = machine.get_slot(SlotId::new(1)).unwrap();
-        assert_eq!(slot.quantity(), 4); // 5 - 1
-        
-        match event {
-            SodaMachineEvent::SodaDispensed { slot_id, soda } => {
-                assert_eq!(slot_id, SlotId::new(1));
-                assert_eq!(soda.name(), "Coca-Cola");
-            },
-            _ => panic!("Expected SodaDispensed event"),
-        }
+        machine.load_cash(Money::from_cents(25), 8).unwrap();
+        machine.insert_money(Money::from_cents(200), now()).unwrap();
+        machine.reserve_vend(SlotId::new(1), now()).unwrap();
+
+        assert_eq!(
+            machine.collect_cash(operator.clone(), Money::from_cents(400), now()).unwrap_err(),
+            SodaMachineError::VendInProgress(SlotId::new(1))
+        );
+        machine.confirm_vend(now()).unwrap();
+        assert_eq!(
+            machine.collect_cash(operator.clone(), Money::from_cents(400), now()).unwrap_err(),
+            SodaMachineError::SessionInProgress(SessionId::new(1))
+        );
+        machine.return_money(now()).unwrap();
+
+        let later = now().plus(Duration::from_secs(60));
+        let collection = machine.collect_cash(operator.clone(), Money::from_cents(175), later).unwrap();
+
+        assert_eq!(machine.uncommitted_events().last().map(|(_, event)| event), Some(&SodaMachineEvent::CashCollected { collection: collection.clone() }));
+        assert_eq!(collection.revenue, Money::from_cents(150));
+        assert_eq!(collection.float, Money::from_cents(200));
+        assert_eq!(collection.kept, Money::from_cents(150), "Two of the quarters went out as change");
+        assert_eq!(collection.period_start, None);
+        assert_eq!(collection.expected().unwrap(), Money::from_cents(200));
+        assert_eq!(collection.variance().unwrap(), Money::from_cents(-25));
+        assert_eq!(machine.cash_box().count(Money::from_cents(25)), 6);
+        assert_eq!(machine.cash_box().total(), Money::from_cents(150));
+        assert_eq!(machine.total_collected(), Money::zero());
+        assert_eq!(machine.last_collected_at(), Some(later));
+
+        let rebuilt = SodaMachine::from_history(machine.uncommitted_events().to_vec()).unwrap();
+        assert_eq!(rebuilt.state(), machine.state());
+
+        let collection = machine.collect_cash(operator, Money::zero(), later).unwrap();
+        assert_eq!(collection.period_start, Some(later));
+        assert_eq!(collection.expected().unwrap(), Money::zero());
+        assert_eq!(machine.cash_box().total(), Money::from_cents(150));
     }
 
     #[test]
-    fn test_dispense_soda_insufficient_funds() {
+    fn test_only_the_float_can_be_unloaded() {
         let mut machine = create_test_machine();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
+        let operator = OperatorId::new("OP-7").unwrap();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
         machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
         machine.refill_slot(SlotId::new(1), 5).unwrap();
-        machine.insert_money(Money::from_dollars_cents(1, 00).unwrap()).unwrap(); // Not enough
-        
-        let result = machine.dispense_soda(SlotId::new(1));
-        assert!(result.is_err());
-        
-        match result.unwrap_err() {
-            SodaMachineError::InsufficientFunds { required, available } => {
-                assert_eq!(required, Money::from_dollars_cents(1, 50).unwrap());
-                assert_eq!(available, Money::from_dollars_cents(1, 00).unwrap());
-            },
-            _ => panic!("Expected InsufficientFunds error"),
-        }
+        machine.load_cash(Money::from_cents(25), 2).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+
+        let revenue = machine.unload_cash(Money::from_cents(100), 1);
+        let missing = machine.unload_cash(Money::from_cents(25), 3);
+        machine.collect_cash(operator, Money::from_cents(150), now()).unwrap();
+        let unloaded = machine.unload_cash(Money::from_cents(25), 2).unwrap();
+
+        assert_eq!(revenue, Err(SodaMachineError::RevenueNotCollected(Money::from_cents(150))));
+        assert!(matches!(missing, Err(SodaMachineError::CashBoxError(CashBoxError::InsufficientCount { .. }))));
+        assert_eq!(unloaded, SodaMachineEvent::CashUnloaded { denomination: Money::from_cents(25), count: 2 });
+        assert!(machine.cash_box().is_empty());
+        assert_eq!(machine.decommission(), Ok(SodaMachineEvent::MachineDecommissioned));
     }
 
     #[test]
-    fn test_dispense_soda_empty_slot() {
+    fn test_confirmed_vend_charges_the_customer() {
         let mut machine = create_test_machine();
-        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
         machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
-        // Don't refill the slot
-        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap()).unwrap();
-        
-        let result = machine.dispense_soda(SlotId::new(1));
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::SlotError(SlotError::SlotEmpty));
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+
+        machine.reserve_vend(SlotId::new(1), now()).unwrap();
+
+        assert_eq!(machine.pending_vend().map(|vend| vend.price), Some(Money::from_cents(150)));
+        assert_eq!(machine.inserted_money(), Money::from_cents(150), "Nothing is charged before the soda drops");
+        assert_eq!(machine.reserve_vend(SlotId::new(1), now()).unwrap_err(), SodaMachineError::VendInProgress(SlotId::new(1)));
+        assert_eq!(machine.return_money(now()).unwrap_err(), SodaMachineError::VendInProgress(SlotId::new(1)));
+        let much_later = now().plus(Duration::from_secs(3600));
+        assert!(machine.expire_idle_session(much_later).unwrap().is_empty());
+
+        let events = machine.confirm_vend(now()).unwrap();
+
+        assert!(matches!(&events[..], [SodaMachineEvent::SodaDispensed { .. }, SodaMachineEvent::SessionCompleted { .. }]));
+        assert!(machine.pending_vend().is_none());
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().quantity(), 4);
+        assert_eq!(machine.total_collected(), Money::from_cents(150));
+        assert_eq!(machine.confirm_vend(now()).unwrap_err(), SodaMachineError::NoPendingVend);
     }
 
     #[test]
-    fn test_return_money() {
+    fn test_failed_vend_rolls_back_the_charge() {
         let mut machine = create_test_machine();
-        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap()).unwrap();
-        
-        let event = machine.return_money().unwrap();
-        
-        assert_eq!(machine.inserted_money(), Money::zero());
-        
-        match event {
-            SodaMachineEvent::MoneyReturned { amount } => {
-                assert_eq!(amount, Money::from_dollars_cents(2, 00).unwrap());
-            },
-            _ => panic!("Expected MoneyReturned event"),
-        }
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 2).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+
+        machine.reserve_vend(SlotId::new(1), now()).unwrap();
+        let events = machine.fail_vend(VendFailure::MotorFault, now()).unwrap();
+
+        assert!(matches!(&events[..], [SodaMachineEvent::VendFailed { failure: VendFailure::MotorFault, .. }]));
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().quantity(), 2, "The can is still in its place");
+        assert_eq!(machine.inserted_money(), Money::from_cents(150));
+
+        machine.reserve_vend(SlotId::new(1), now()).unwrap();
+        let events = machine.fail_vend(VendFailure::Jammed, now()).unwrap();
+
+        assert!(matches!(&events[..], [SodaMachineEvent::VendFailed { failure: VendFailure::Jammed, .. }]));
+        assert_eq!(machine.get_slot(SlotId::new(1)).unwrap().quantity(), 1, "The stuck can is quarantined");
+        assert_eq!(machine.total_collected(), Money::zero());
+
+        machine.reserve_vend(SlotId::new(1), now()).unwrap();
+        let events = machine.fail_vend(VendFailure::DropNotDetected, now()).unwrap();
+
+        assert!(matches!(&events[..], [SodaMachineEvent::VendFailed { .. }, SodaMachineEvent::SlotSoldOut { .. }]));
+        let refunded = machine.return_money(now()).unwrap();
+        assert!(matches!(&refunded[0], SodaMachineEvent::MoneyReturned { amount, .. } if *amount == Money::from_cents(150)));
+
+        let rebuilt = SodaMachine::from_history(machine.uncommitted_events().to_vec()).unwrap();
+        assert_eq!(rebuilt.state(), machine.state());
     }
 
     #[test]
-    fn test_return_money_none_inserted() {
+    fn test_refund_is_taken_off_the_revenue() {
         let mut machine = create_test_machine();
-        
-        let result = machine.return_money();
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), SodaMachineError::InvalidAmount);
+        let operator = OperatorId::new("OP-7").unwrap();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(25), now()).unwrap();
+        machine.insert_money(Money::from_cents(25), now()).unwrap();
+
+        assert_eq!(
+            machine.issue_refund(operator.clone(), Money::from_cents(150), "Can stuck", now()).unwrap_err(),
+            SodaMachineError::SessionInProgress(SessionId::new(1))
+        );
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+
+        assert_eq!(
+            machine.issue_refund(operator.clone(), Money::from_cents(175), "Can stuck", now()).unwrap_err(),
+            SodaMachineError::RefundExceedsRevenue { requested: Money::from_cents(175), revenue: Money::from_cents(150) }
+        );
+        assert_eq!(
+            machine.issue_refund(operator.clone(), Money::from_cents(50), "  ", now()).unwrap_err(),
+            SodaMachineError::RefundReasonMissing
+        );
+
+        let refund = machine.issue_refund(operator, Money::from_cents(50), " Can stuck ", now()).unwrap();
+
+        assert!(matches!(machine.uncommitted_events().last(), Some((_, SodaMachineEvent::RefundIssued { .. }))));
+        assert_eq!(refund.reason, "Can stuck");
+        assert_eq!(refund.breakdown.count(Money::from_cents(25)), 2);
+        assert_eq!(machine.total_collected(), Money::from_cents(100));
+        assert_eq!(machine.cash_box().total(), Money::from_cents(100));
     }
 
     #[test]
-    fn test_return_change() {
-        let mut machine = create_test_machine();
-        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap()).unwrap();
-        
-        let change_amount = Money::from_dollars_cents(0, 50).unwrap();
-        let event = machine.return_change(change_amount).unwrap();
+    fn test_yen_machine_sale() {
+        let yen = |amount| Money::from_minor_units(amount, Currency::JPY);
+        let mut machine = SodaMachine::new_with_currency(SodaMachineId::new(1), 10, Currency::JPY).unwrap();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda().with_price(yen(130)).unwrap()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.load_cash(yen(100), 5).unwrap();
+        machine.load_cash(yen(10), 10).unwrap();
+        machine.insert_money(yen(500), now()).unwrap();
         
-        assert_eq!(machine.inserted_money(), Money::from_dollars_cents(1, 50).unwrap());
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
         
-        match event {
-            SodaMachineEvent::ChangeReturned { amount } => {
-                assert_eq!(amount, change_amount);
-            },
-            _ => panic!("Expected ChangeReturned event"),
-        }
+        assert_eq!(machine.inserted_money(), yen(370));
+        assert_eq!(machine.total_collected(), yen(130));
+        assert!(machine.status_summary().contains("¥130 collected"));
     }
 
     #[test]
@@ -752,6 +3366,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decommission_refuses_a_machine_in_use() {
+        let operator = OperatorId::new("OP-1").unwrap();
+        let mut machine = create_test_machine();
+        machine.add_slot(SlotId::new(1), 20).unwrap();
+        machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.insert_money(Money::from_cents(50), now()).unwrap();
+        machine.reserve_vend(SlotId::new(1), now()).unwrap();
+
+        let delivering = machine.decommission();
+        machine.fail_vend(VendFailure::MotorFault, now()).unwrap();
+        let credit = machine.decommission();
+        machine.return_money(now()).unwrap();
+        machine.load_cash(Money::from_cents(25), 4).unwrap();
+        let cash = machine.decommission();
+        machine.collect_cash(operator, Money::zero(), now()).unwrap();
+        machine.unload_cash(Money::from_cents(25), 4).unwrap();
+        let version = machine.version();
+
+        assert!(matches!(delivering, Err(SodaMachineError::MachineInUse(reason)) if reason.contains("slot 1")));
+        assert!(matches!(credit, Err(SodaMachineError::MachineInUse(reason)) if reason.contains("$1.50")));
+        assert!(matches!(cash, Err(SodaMachineError::MachineInUse(reason)) if reason.contains("$1.00")));
+        assert_eq!(machine.decommission(), Ok(SodaMachineEvent::MachineDecommissioned));
+        assert_eq!(machine.version(), version + 1);
+        assert!(!machine.is_operational());
+    }
+
     #[test]
     fn test_total_inventory_value() {
         let mut machine = create_test_machine();
@@ -780,10 +3423,11 @@ mod tests {
         machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
         machine.refill_slot(SlotId::new(1), 5).unwrap();
         
-        let available = machine.get_available_sodas();
+        let available = machine.get_available_sodas(now());
         assert_eq!(available.len(), 1);
         assert_eq!(available[0].0, SlotId::new(1));
         assert_eq!(available[0].1.name(), "Coca-Cola");
+        assert_eq!(available[0].2, Money::from_cents(150));
     }
 
     #[test]
@@ -807,11 +3451,204 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), SodaMachineError::MachineNotOperational);
         
-        let result = machine.insert_money(Money::from_dollars_cents(1, 00).unwrap());
+        let result = machine.insert_money(Money::from_dollars_cents(1, 00).unwrap(), now());
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), SodaMachineError::MachineNotOperational);
     }
 
+    #[test]
+    fn test_commands_record_uncommitted_events() {
+        let mut machine = create_test_machine();
+        assert_eq!(machine.version(), 1);
+
+        let mut returned = vec![SodaMachineEvent::MachineCreated {
+            machine_id: SodaMachineId::new(1),
+            max_slots: 10,
+            currency: Currency::USD,
+        }];
+        returned.push(machine.add_slot(SlotId::new(1), 20).unwrap());
+        returned.push(machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap());
+        returned.extend(machine.refill_slot(SlotId::new(1), 5).unwrap());
+        returned.extend(machine.insert_money(Money::from_cents(100), now()).unwrap());
+        returned.extend(machine.insert_money(Money::from_cents(50), now()).unwrap());
+        returned.extend(machine.dispense_soda(SlotId::new(1), now()).unwrap());
+        returned.push(machine.disable());
+
+        // Every returned event is recorded once, numbered without gaps
+        let recorded = machine.uncommitted_events();
+        assert_eq!(recorded.len(), returned.len());
+        assert_eq!(machine.version(), returned.len() as u64);
+        for (index, (sequence, event)) in recorded.iter().enumerate() {
+            assert_eq!(*sequence, index as u64 + 1);
+            assert_eq!(event, &returned[index]);
+        }
+    }
+
+    #[test]
+    fn test_failed_command_records_nothing() {
+        let mut machine = create_test_machine();
+
+        assert!(machine.insert_money(Money::from_cents(-100), now()).is_err());
+        assert!(machine.refill_slot(SlotId::new(9), 5).is_err());
+
+        // Only the creation of the machine is recorded
+        assert_eq!(machine.uncommitted_events().len(), 1);
+        assert_eq!(machine.version(), 1);
+    }
+
+    #[test]
+    fn test_take_uncommitted_events() {
+        let mut machine = create_test_machine();
+        machine.load_cash(Money::from_cents(25), 4).unwrap();
+
+        let taken = machine.take_uncommitted_events();
+        assert_eq!(taken.len(), 2);
+        assert!(matches!(taken[0], (1, SodaMachineEvent::MachineCreated { .. })));
+        assert_eq!(taken[1], (2, SodaMachineEvent::CashLoaded { denomination: Money::from_cents(25), count: 4 }));
+        assert!(machine.uncommitted_events().is_empty());
+
+        // Numbering carries on after the events were handed over
+        machine.disable();
+        assert_eq!(machine.uncommitted_events(), &[(3, SodaMachineEvent::MachineDisabled)]);
+    }
+
+    #[test]
+    fn test_mark_events_committed() {
+        let mut machine = create_test_machine();
+        machine.disable();
+
+        machine.mark_events_committed();
+
+        assert!(machine.uncommitted_events().is_empty());
+        assert_eq!(machine.version(), 2);
+    }
+
+    #[test]
+    fn test_set_location() {
+        let mut machine = create_test_machine();
+        machine.disable();
+
+        let event = machine.set_location(Some("  Lobby, Building A ".to_string()));
+        assert_eq!(event, SodaMachineEvent::LocationChanged { location: Some("Lobby, Building A".to_string()) });
+        assert_eq!(machine.location(), Some("Lobby, Building A"));
+
+        machine.set_location(Some("   ".to_string()));
+        assert_eq!(machine.location(), None);
+    }
+
+    #[test]
+    fn test_has_low_stock() {
+        let mut machine = create_stocked_machine();
+        machine.add_slot(SlotId::new(2), 10).unwrap();
+
+        // The unconfigured slot does not count as low
+        assert!(!machine.has_low_stock(4));
+        assert!(machine.has_low_stock(5));
+    }
+
+    #[test]
+    fn test_committed_version() {
+        let mut machine = create_test_machine();
+        assert_eq!(machine.committed_version(), 0);
+
+        machine.mark_events_committed();
+        machine.disable();
+        machine.enable();
+
+        assert_eq!(machine.committed_version(), 1);
+        assert_eq!(machine.version(), 3);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut machine = create_stocked_machine();
+        machine.insert_money(Money::from_cents(200), now()).unwrap();
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        machine.disable();
+
+        let state = machine.state();
+        assert_eq!(state.version, machine.version());
+        let restored = SodaMachine::from_state(state.clone()).unwrap();
+
+        assert_eq!(restored.state(), state);
+        assert!(restored.uncommitted_events().is_empty());
+        assert!(!restored.is_operational());
+        assert_eq!(restored.status_summary(), machine.status_summary());
+    }
+
+    #[test]
+    fn test_from_state_rejects_slots_that_do_not_fit() {
+        let mut state = create_test_machine().state();
+        state.slots = vec![Slot::new(SlotId::new(1), 5).unwrap(), Slot::new(SlotId::new(1), 8).unwrap()];
+        assert_eq!(SodaMachine::from_state(state.clone()).unwrap_err(), SodaMachineError::SlotAlreadyExists(SlotId::new(1)));
+
+        state.max_slots = 1;
+        assert_eq!(SodaMachine::from_state(state).unwrap_err(), SodaMachineError::TooManySlots);
+    }
+
+    #[test]
+    fn test_from_history_rebuilds_state() {
+        let mut machine = create_stocked_machine();
+        machine.insert_money(Money::from_cents(200), now()).unwrap();
+        machine.dispense_soda(SlotId::new(1), now()).unwrap();
+        machine.insert_money(Money::from_cents(100), now()).unwrap();
+        machine.set_session_timeout(Duration::from_secs(30)).unwrap();
+
+        let rebuilt = SodaMachine::from_history(machine.uncommitted_events().to_vec()).unwrap();
+
+        assert_eq!(rebuilt.version(), machine.version());
+        assert!(rebuilt.uncommitted_events().is_empty());
+        assert_eq!(rebuilt.status_summary(), machine.status_summary());
+        assert_eq!(rebuilt.cash_box(), machine.cash_box());
+        assert_eq!(rebuilt.current_session(), machine.current_session());
+        assert_eq!(rebuilt.session_timeout(), Duration::from_secs(30));
+
+        // The rebuilt machine carries on numbering where the history stopped
+        let mut rebuilt = rebuilt;
+        rebuilt.return_money(now()).unwrap();
+        assert_eq!(rebuilt.uncommitted_events()[0].0, machine.version() + 1);
+        assert!(rebuilt.current_session().is_none());
+    }
+
+    #[test]
+    fn test_from_history_requires_machine_created() {
+        let result = SodaMachine::from_history(Vec::new());
+        assert!(matches!(result, Err(SodaMachineError::InvalidHistory(_))));
+
+        let result = SodaMachine::from_history(vec![(1, SodaMachineEvent::MachineDisabled)]);
+        assert!(matches!(result, Err(SodaMachineError::InvalidHistory(_))));
+    }
+
+    #[test]
+    fn test_replay_rejects_gaps() {
+        let mut machine = create_test_machine();
+        machine.mark_events_committed();
+
+        let result = machine.replay(3, &SodaMachineEvent::MachineDisabled);
+        assert!(matches!(result, Err(SodaMachineError::InvalidHistory(_))));
+        assert!(machine.is_operational());
+
+        machine.replay(2, &SodaMachineEvent::MachineDisabled).unwrap();
+        assert!(!machine.is_operational());
+        assert_eq!(machine.version(), 2);
+        assert!(machine.uncommitted_events().is_empty());
+    }
+
+    #[test]
+    fn test_apply_rejects_events_that_do_not_fit() {
+        let mut machine = create_test_machine();
+
+        let result = machine.apply(&SodaMachineEvent::SlotRefilled { slot_id: SlotId::new(7), quantity_added: 1 });
+        assert_eq!(result, Err(SodaMachineError::SlotNotFound(SlotId::new(7))));
+
+        let result = machine.apply(&SodaMachineEvent::MoneyInserted {
+            amount: Money::from_cents(100),
+            total_inserted: Money::from_cents(100),
+            inserted_at: now(),
+        });
+        assert_eq!(result, Err(SodaMachineError::NoActiveSession));
+    }
+
     #[test]
     fn test_soda_machine_id() {
         let id = SodaMachineId::new(42);
@@ -835,7 +3672,7 @@ mod tests {
         machine.add_slot(SlotId::new(1), 20).unwrap();
         machine.configure_slot(SlotId::new(1), create_test_soda()).unwrap();
         machine.refill_slot(SlotId::new(1), 5).unwrap();
-        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap()).unwrap();
+        machine.insert_money(Money::from_dollars_cents(2, 00).unwrap(), now()).unwrap();
         
         let summary = machine.status_summary();
         assert!(summary.contains("Machine 1"));
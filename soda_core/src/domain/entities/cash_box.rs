@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::domain::value_objects::money::{Currency, Money};
+use crate::domain::services::change_calculator::{ChangeBreakdown, ChangeCalculator, ChangeError};
+
+/// Represents the physical coins and bills held by a soda machine
+/// Each denomination is tracked by count so the machine knows exactly what change it can give
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CashBox {
+    /// The currency of every coin and bill held
+    currency: Currency,
+    /// Number of coins/bills held for each denomination
+    #[cfg_attr(feature = "serde", serde(with = "denomination_counts"))]
+    denominations: BTreeMap<Money, u32>,
+}
+
+/// Errors that can occur during cash box operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CashBoxError {
+    InvalidDenomination(Money),
+    CurrencyMismatch { expected: Currency, actual: Currency },
+    InsufficientCount { denomination: Money, requested: u32, available: u32 },
+    ExactChangeUnavailable(Money),
+    Overflow,
+}
+
+impl CashBox {
+    /// Creates a new empty cash box for a currency
+    ///
+    /// # Arguments
+    /// * `currency` - The currency of the coins and bills the cash box holds
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::entities::cash_box::CashBox;
+    /// use soda_core::domain::value_objects::money::Currency;
+    ///
+    /// let cash_box = CashBox::new(Currency::USD);
+    /// assert!(cash_box.is_empty());
+    /// ```
+    pub fn new(currency: Currency) -> Self {
+        CashBox {
+            currency,
+            denominations: BTreeMap::new(),
+        }
+    }
+
+    /// Gets the currency of the cash box
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Gets the number of coins/bills held for a denomination
+    pub fn count(&self, denomination: Money) -> u32 {
+        self.denominations.get(&denomination).copied().unwrap_or(0)
+    }
+
+    /// Gets all denominations held with their counts, smallest first
+    pub fn denominations(&self) -> &BTreeMap<Money, u32> {
+        &self.denominations
+    }
+
+    /// Checks if the cash box holds no money
+    pub fn is_empty(&self) -> bool {
+        self.denominations.is_empty()
+    }
+
+    /// Gets the total value of all coins and bills in the cash box
+    pub fn total(&self) -> Money {
+        self.denominations.iter()
+            .filter_map(|(denomination, count)| (*denomination * (*count as i64)).ok())
+            .fold(Money::zero_in(self.currency), |acc, value| {
+                (acc + value).unwrap_or(acc)
+            })
+    }
+
+    /// Adds coins/bills of a single denomination to the cash box
+    ///
+    /// # Arguments
+    /// * `denomination` - The face value of the coin or bill
+    /// * `count` - The number of coins/bills to add
+    ///
+    /// # Returns
+    /// * `Result<(), CashBoxError>` - Ok if successful, Err if the denomination is not a coin or bill
+    ///   issued in the cash box's currency
+    pub fn deposit(&mut self, denomination: Money, count: u32) -> Result<(), CashBoxError> {
+        if denomination.currency() != self.currency {
+            return Err(CashBoxError::CurrencyMismatch { expected: self.currency, actual: denomination.currency() });
+        }
+
+        if !self.currency.has_denomination(denomination) {
+            return Err(CashBoxError::InvalidDenomination(denomination));
+        }
+
+        if count == 0 {
+            return Ok(());
+        }
+
+        let held = self.denominations.entry(denomination).or_insert(0);
+        *held = held.checked_add(count).ok_or(CashBoxError::Overflow)?;
+        Ok(())
+    }
+
+    /// Removes coins/bills of a single denomination from the cash box
+    ///
+    /// # Arguments
+    /// * `denomination` - The face value of the coin or bill
+    /// * `count` - The number of coins/bills to remove
+    ///
+    /// # Returns
+    /// * `Result<(), CashBoxError>` - Ok if successful, Err if not enough coins/bills are held
+    pub fn withdraw(&mut self, denomination: Money, count: u32) -> Result<(), CashBoxError> {
+        let available = self.count(denomination);
+        if count > available {
+            return Err(CashBoxError::InsufficientCount { denomination, requested: count, available });
+        }
+
+        if count == available {
+            self.denominations.remove(&denomination);
+        } else if count > 0 {
+            self.denominations.insert(denomination, available - count);
+        }
+
+        Ok(())
+    }
+
+    /// Works out which coins/bills would be paid out for an amount, without removing them
+    ///
+    /// # Arguments
+    /// * `amount` - The amount of change to pay out
+    ///
+    /// # Returns
+    /// * `Result<ChangeBreakdown, CashBoxError>` - The fewest coins/bills that make the amount,
+    ///   or Err if the exact amount cannot be made from what is held
+    pub fn plan_change(&self, amount: Money) -> Result<ChangeBreakdown, CashBoxError> {
+        ChangeCalculator::calculate(&self.denominations, amount)
+            .map_err(CashBoxError::from)
+    }
+
+    /// Checks if the exact amount can be paid out from the coins/bills held
+    pub fn can_make_change(&self, amount: Money) -> bool {
+        self.plan_change(amount).is_ok()
+    }
+
+    /// Pays out an amount of change, removing the coins/bills from the cash box
+    ///
+    /// # Arguments
+    /// * `amount` - The amount of change to pay out
+    ///
+    /// # Returns
+    /// * `Result<ChangeBreakdown, CashBoxError>` - The coins/bills paid out,
+    ///   or Err if the exact amount cannot be made
+    pub fn pay_out(&mut self, amount: Money) -> Result<ChangeBreakdown, CashBoxError> {
+        let payout = self.plan_change(amount)?;
+        self.withdraw_breakdown(&payout)?;
+
+        Ok(payout)
+    }
+
+    /// Picks the coins/bills to leave in the cash box as change, smallest first
+    ///
+    /// Small coins are what change is made from, so they are kept before anything larger.
+    ///
+    /// # Arguments
+    /// * `at_most` - What the kept coins/bills may be worth, e.g. the float operators loaded
+    ///
+    /// # Returns
+    /// * `CashBox` - The coins/bills to keep, worth the amount or as close below it as they get
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::entities::cash_box::CashBox;
+    /// use soda_core::domain::value_objects::money::{Currency, Money};
+    ///
+    /// let mut cash_box = CashBox::new(Currency::USD);
+    /// cash_box.deposit(Money::from_cents(25), 3).unwrap();
+    /// cash_box.deposit(Money::from_cents(500), 1).unwrap();
+    ///
+    /// let kept = cash_box.float_up_to(Money::from_cents(100));
+    /// assert_eq!(kept.total(), Money::from_cents(75));
+    /// ```
+    pub fn float_up_to(&self, at_most: Money) -> CashBox {
+        let mut float = CashBox::new(self.currency);
+        let mut remaining = at_most.cents().max(0);
+
+        for (denomination, count) in &self.denominations {
+            let kept = (*count as i64).min(remaining / denomination.cents());
+            if kept > 0 {
+                float.denominations.insert(*denomination, kept as u32);
+                remaining -= kept * denomination.cents();
+            }
+        }
+
+        float
+    }
+
+    /// Removes every coin/bill of a breakdown, or nothing if any of them is missing
+    ///
+    /// # Arguments
+    /// * `breakdown` - The coins/bills to remove
+    ///
+    /// # Returns
+    /// * `Result<(), CashBoxError>` - Ok if all were removed, Err if the box does not hold them
+    pub fn withdraw_breakdown(&mut self, breakdown: &ChangeBreakdown) -> Result<(), CashBoxError> {
+        for (denomination, count) in breakdown.items() {
+            let available = self.count(*denomination);
+            if *count > available {
+                return Err(CashBoxError::InsufficientCount { denomination: *denomination, requested: *count, available });
+            }
+        }
+
+        for (denomination, count) in breakdown.items() {
+            self.withdraw(*denomination, *count)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CashBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "Cash box: empty");
+        }
+
+        let contents: Vec<String> = self.denominations.iter()
+            .rev()
+            .map(|(denomination, count)| format!("{} x {}", count, denomination))
+            .collect();
+
+        write!(f, "Cash box: {} ({})", self.total(), contents.join(", "))
+    }
+}
+
+impl fmt::Display for CashBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CashBoxError::InvalidDenomination(denomination) => write!(f, "Invalid denomination: {}", denomination),
+            CashBoxError::CurrencyMismatch { expected, actual } => {
+                write!(f, "Currency mismatch: cash box holds {}, got {}", expected, actual)
+            },
+            CashBoxError::InsufficientCount { denomination, requested, available } => {
+                write!(f, "Insufficient {} coins/bills: requested {}, have {}", denomination, requested, available)
+            },
+            CashBoxError::ExactChangeUnavailable(amount) => write!(f, "Cannot make exact change for {}", amount),
+            CashBoxError::Overflow => write!(f, "Cash box count overflow"),
+        }
+    }
+}
+
+impl std::error::Error for CashBoxError {}
+
+impl From<ChangeError> for CashBoxError {
+    fn from(err: ChangeError) -> Self {
+        match err {
+            ChangeError::InvalidAmount(amount) | ChangeError::NoExactSolution(amount) => {
+                CashBoxError::ExactChangeUnavailable(amount)
+            },
+        }
+    }
+}
+
+/// Stores the denominations as a list of (denomination, count) pairs,
+/// since formats like JSON only allow strings as map keys
+#[cfg(feature = "serde")]
+mod denomination_counts {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use crate::domain::value_objects::money::Money;
+
+    pub fn serialize<S: Serializer>(denominations: &BTreeMap<Money, u32>, serializer: S) -> Result<S::Ok, S::Error> {
+        denominations.iter().collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<Money, u32>, D::Error> {
+        Ok(Vec::<(Money, u32)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quarter() -> Money {
+        Money::from_cents(25)
+    }
+
+    fn dime() -> Money {
+        Money::from_cents(10)
+    }
+
+    fn dollar() -> Money {
+        Money::from_cents(100)
+    }
+
+    #[test]
+    fn test_float_keeps_the_smallest_coins() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(dime(), 3).unwrap();
+        cash_box.deposit(quarter(), 4).unwrap();
+        cash_box.deposit(dollar(), 2).unwrap();
+
+        let float = cash_box.float_up_to(Money::from_cents(150));
+
+        assert_eq!(float.count(dime()), 3);
+        assert_eq!(float.count(quarter()), 4);
+        assert_eq!(float.count(dollar()), 0);
+        assert_eq!(float.total(), Money::from_cents(130));
+        assert!(cash_box.float_up_to(Money::zero()).is_empty());
+        assert_eq!(cash_box.float_up_to(Money::from_cents(1000)), cash_box);
+    }
+
+    #[test]
+    fn test_cash_box_creation() {
+        let cash_box = CashBox::new(Currency::USD);
+
+        assert!(cash_box.is_empty());
+        assert_eq!(cash_box.total(), Money::zero());
+        assert_eq!(cash_box.count(quarter()), 0);
+    }
+
+    #[test]
+    fn test_deposit() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 4).unwrap();
+        cash_box.deposit(dollar(), 1).unwrap();
+        cash_box.deposit(quarter(), 2).unwrap();
+
+        assert_eq!(cash_box.count(quarter()), 6);
+        assert_eq!(cash_box.count(dollar()), 1);
+        assert_eq!(cash_box.total(), Money::from_cents(250));
+    }
+
+    #[test]
+    fn test_deposit_invalid_denomination() {
+        let mut cash_box = CashBox::new(Currency::USD);
+
+        let result = cash_box.deposit(Money::zero(), 1);
+        assert_eq!(result.unwrap_err(), CashBoxError::InvalidDenomination(Money::zero()));
+
+        let result = cash_box.deposit(Money::from_cents(-25), 1);
+        assert_eq!(result.unwrap_err(), CashBoxError::InvalidDenomination(Money::from_cents(-25)));
+    }
+
+    #[test]
+    fn test_deposit_other_currency() {
+        let mut cash_box = CashBox::new(Currency::USD);
+
+        let result = cash_box.deposit(Money::from_minor_units(100, Currency::EUR), 1);
+        assert_eq!(result.unwrap_err(), CashBoxError::CurrencyMismatch {
+            expected: Currency::USD,
+            actual: Currency::EUR,
+        });
+        assert!(cash_box.is_empty());
+    }
+
+    #[test]
+    fn test_total_in_cash_box_currency() {
+        let mut cash_box = CashBox::new(Currency::JPY);
+        assert_eq!(cash_box.total(), Money::zero_in(Currency::JPY));
+
+        cash_box.deposit(Money::from_minor_units(100, Currency::JPY), 3).unwrap();
+        assert_eq!(cash_box.total(), Money::from_minor_units(300, Currency::JPY));
+    }
+
+    #[test]
+    fn test_withdraw() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 4).unwrap();
+
+        cash_box.withdraw(quarter(), 3).unwrap();
+        assert_eq!(cash_box.count(quarter()), 1);
+
+        cash_box.withdraw(quarter(), 1).unwrap();
+        assert!(cash_box.is_empty());
+    }
+
+    #[test]
+    fn test_withdraw_insufficient_count() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 1).unwrap();
+
+        let result = cash_box.withdraw(quarter(), 2);
+        assert_eq!(result.unwrap_err(), CashBoxError::InsufficientCount {
+            denomination: quarter(),
+            requested: 2,
+            available: 1,
+        });
+        assert_eq!(cash_box.count(quarter()), 1);
+    }
+
+    #[test]
+    fn test_plan_change() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 4).unwrap();
+        cash_box.deposit(dime(), 5).unwrap();
+
+        let payout = cash_box.plan_change(Money::from_cents(70)).unwrap();
+        assert_eq!(payout.items(), &[(quarter(), 2), (dime(), 2)]);
+        // Planning does not remove anything
+        assert_eq!(cash_box.total(), Money::from_cents(150));
+    }
+
+    #[test]
+    fn test_plan_change_zero() {
+        let cash_box = CashBox::new(Currency::USD);
+        assert!(cash_box.plan_change(Money::zero()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_change_out_of_quarters() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(dollar(), 3).unwrap();
+
+        let result = cash_box.plan_change(Money::from_cents(50));
+        assert_eq!(result.unwrap_err(), CashBoxError::ExactChangeUnavailable(Money::from_cents(50)));
+        assert!(!cash_box.can_make_change(Money::from_cents(50)));
+        assert!(cash_box.can_make_change(Money::from_cents(200)));
+    }
+
+    #[test]
+    fn test_pay_out() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(dollar(), 1).unwrap();
+        cash_box.deposit(quarter(), 3).unwrap();
+
+        let payout = cash_box.pay_out(Money::from_cents(150)).unwrap();
+        assert_eq!(payout.items(), &[(dollar(), 1), (quarter(), 2)]);
+        assert_eq!(cash_box.count(dollar()), 0);
+        assert_eq!(cash_box.count(quarter()), 1);
+    }
+
+    #[test]
+    fn test_pay_out_uses_fewest_coins_when_greedy_fails() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 1).unwrap();
+        cash_box.deposit(dime(), 3).unwrap();
+
+        let payout = cash_box.pay_out(Money::from_cents(30)).unwrap();
+        assert_eq!(payout.items(), &[(dime(), 3)]);
+        assert_eq!(cash_box.count(quarter()), 1);
+        assert_eq!(cash_box.count(dime()), 0);
+    }
+
+    #[test]
+    fn test_pay_out_failure_leaves_cash_box_untouched() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 1).unwrap();
+
+        assert!(cash_box.pay_out(Money::from_cents(30)).is_err());
+        assert_eq!(cash_box.count(quarter()), 1);
+    }
+
+    #[test]
+    fn test_withdraw_breakdown() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 3).unwrap();
+        cash_box.deposit(dime(), 1).unwrap();
+
+        cash_box.withdraw_breakdown(&ChangeBreakdown::new(vec![(quarter(), 2), (dime(), 1)])).unwrap();
+        assert_eq!(cash_box.count(quarter()), 1);
+        assert_eq!(cash_box.count(dime()), 0);
+    }
+
+    #[test]
+    fn test_withdraw_breakdown_is_all_or_nothing() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        cash_box.deposit(quarter(), 3).unwrap();
+
+        let result = cash_box.withdraw_breakdown(&ChangeBreakdown::new(vec![(quarter(), 2), (dime(), 1)]));
+        assert_eq!(result.unwrap_err(), CashBoxError::InsufficientCount { denomination: dime(), requested: 1, available: 0 });
+        assert_eq!(cash_box.count(quarter()), 3);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut cash_box = CashBox::new(Currency::USD);
+        assert_eq!(format!("{}", cash_box), "Cash box: empty");
+
+        cash_box.deposit(quarter(), 2).unwrap();
+        cash_box.deposit(dollar(), 1).unwrap();
+        assert_eq!(format!("{}", cash_box), "Cash box: $1.50 (1 x $1.00, 2 x $0.25)");
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+use crate::domain::value_objects::money::{Money, MoneyError};
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// One visit of an operator taking the takings out of a machine's cash box, as it is kept for the auditors
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "StoredCashCollection"))]
+pub struct CashCollection {
+    pub operator_id: OperatorId,
+    pub collected_at: Timestamp,
+    /// When the cash was collected before, None if this is the machine's first collection
+    pub period_start: Option<Timestamp>,
+    /// What the sodas sold since the last collection brought in
+    pub revenue: Money,
+    /// Change loaded by operators that was still in the cash box
+    pub float: Money,
+    /// The part of the float left in the cash box as change for the next customers
+    pub kept: Money,
+    /// What the operator counted after taking the cash out
+    pub counted: Money,
+}
+
+/// A collection as it was stored before the float was kept in the cash box
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct StoredCashCollection {
+    operator_id: OperatorId,
+    collected_at: Timestamp,
+    period_start: Option<Timestamp>,
+    revenue: Money,
+    float: Money,
+    kept: Option<Money>,
+    counted: Money,
+}
+
+#[cfg(feature = "serde")]
+impl From<StoredCashCollection> for CashCollection {
+    fn from(stored: StoredCashCollection) -> Self {
+        CashCollection {
+            operator_id: stored.operator_id,
+            collected_at: stored.collected_at,
+            period_start: stored.period_start,
+            revenue: stored.revenue,
+            float: stored.float,
+            // Collections used to empty the whole cash box
+            kept: stored.kept.unwrap_or(Money::zero_in(stored.float.currency())),
+            counted: stored.counted,
+        }
+    }
+}
+
+/// Identifies the operator who collected the cash, e.g. a badge number
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperatorId(String);
+
+/// Errors that can occur when identifying an operator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperatorIdError {
+    Empty,
+}
+
+impl OperatorId {
+    /// Creates an operator ID
+    ///
+    /// # Arguments
+    /// * `id` - The ID, surrounding whitespace is ignored
+    ///
+    /// # Returns
+    /// * `Result<OperatorId, OperatorIdError>` - Ok(OperatorId) if valid, Err if blank
+    pub fn new(id: &str) -> Result<Self, OperatorIdError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(OperatorIdError::Empty);
+        }
+
+        Ok(OperatorId(id.to_string()))
+    }
+
+    /// Gets the ID as text
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl CashCollection {
+    /// Gets what the operator should have taken out, the revenue plus the float that was not kept
+    pub fn expected(&self) -> Result<Money, MoneyError> {
+        (self.revenue + self.float)? - self.kept
+    }
+
+    /// Gets how far the counted amount is off, negative if cash is missing
+    ///
+    /// # Returns
+    /// * `Result<Money, MoneyError>` - Ok(variance) the counted amount minus the expected amount
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::entities::cash_collection::{CashCollection, OperatorId};
+    /// use soda_core::domain::value_objects::money::Money;
+    /// use soda_core::domain::value_objects::timestamp::Timestamp;
+    ///
+    /// let collection = CashCollection {
+    ///     operator_id: OperatorId::new("OP-7").unwrap(),
+    ///     collected_at: Timestamp::from_secs(0),
+    ///     period_start: None,
+    ///     revenue: Money::from_cents(450),
+    ///     float: Money::from_cents(500),
+    ///     kept: Money::from_cents(500),
+    ///     counted: Money::from_cents(425),
+    /// };
+    /// assert_eq!(collection.variance().unwrap(), Money::from_cents(-25));
+    /// ```
+    pub fn variance(&self) -> Result<Money, MoneyError> {
+        self.counted - self.expected()?
+    }
+}
+
+impl fmt::Display for OperatorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for CashCollection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Collected by {} at {}: {} revenue, {} float, {} kept, {} counted",
+            self.operator_id,
+            self.collected_at,
+            self.revenue,
+            self.float,
+            self.kept,
+            self.counted
+        )
+    }
+}
+
+impl fmt::Display for OperatorIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperatorIdError::Empty => write!(f, "Operator ID cannot be empty"),
+        }
+    }
+}
+
+impl std::error::Error for OperatorIdError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::money::Currency;
+
+    #[test]
+    fn test_variance_compares_counted_with_expected() {
+        let collection = CashCollection {
+            operator_id: OperatorId::new(" OP-7 ").unwrap(),
+            collected_at: Timestamp::from_secs(100),
+            period_start: Some(Timestamp::from_secs(0)),
+            revenue: Money::from_cents(300),
+            float: Money::from_cents(200),
+            kept: Money::from_cents(150),
+            counted: Money::from_cents(375),
+        };
+
+        assert_eq!(collection.operator_id.as_str(), "OP-7");
+        assert_eq!(collection.expected().unwrap(), Money::from_cents(350));
+        assert_eq!(collection.variance().unwrap(), Money::from_cents(25));
+
+        let other_currency = CashCollection { counted: Money::from_minor_units(500, Currency::EUR), ..collection };
+        assert!(other_currency.variance().is_err());
+        assert_eq!(OperatorId::new("  "), Err(OperatorIdError::Empty));
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use crate::domain::entities::product::Sku;
+use crate::domain::value_objects::money::Money;
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// The prices a machine charges for catalog products instead of the price their slots were configured with
+/// Each product has a timeline of price changes, so a change can be set up ahead of the time it takes effect
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceSchedule {
+    /// Changes ordered by SKU, then by the time they take effect
+    changes: Vec<PriceChange>,
+}
+
+/// One change of the price a machine charges for a product
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceChange {
+    pub sku: Sku,
+    /// The price charged from `effective_at` on, None goes back to the price the slots were configured with
+    pub price: Option<Money>,
+    pub effective_at: Timestamp,
+}
+
+impl PriceSchedule {
+    /// Creates an empty schedule, every slot sells at the price it was configured with
+    pub fn new() -> Self {
+        PriceSchedule { changes: Vec::new() }
+    }
+
+    /// Gets every change, ordered by SKU and then by the time it takes effect
+    pub fn changes(&self) -> &[PriceChange] {
+        &self.changes
+    }
+
+    /// Checks if no price was ever changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Gets the price a product sells for at a point in time
+    ///
+    /// # Arguments
+    /// * `sku` - The product
+    /// * `at` - The point in time, e.g. when a soda is bought
+    ///
+    /// # Returns
+    /// * `Option<Money>` - Some(price) from the latest change that took effect, None if the slot's own price applies
+    pub fn price_at(&self, sku: &Sku, at: Timestamp) -> Option<Money> {
+        self.changes.iter()
+            .rev()
+            .find(|change| &change.sku == sku && change.effective_at <= at)
+            .and_then(|change| change.price)
+    }
+
+    /// Gets the changes still to take effect, ordered by SKU and then by time
+    pub fn upcoming(&self, now: Timestamp) -> Vec<&PriceChange> {
+        self.changes.iter()
+            .filter(|change| change.effective_at > now)
+            .collect()
+    }
+
+    /// Checks if a change of a product is set up for a point in time
+    pub fn contains(&self, sku: &Sku, effective_at: Timestamp) -> bool {
+        self.position(sku, effective_at).is_ok()
+    }
+
+    /// Changes a product's price right away, forgetting the changes that took effect before
+    ///
+    /// Changes set up for later still take effect when their time comes.
+    ///
+    /// # Arguments
+    /// * `sku` - The product
+    /// * `price` - The new price, None goes back to the price the slots were configured with
+    /// * `now` - When the price changes
+    pub fn set(&mut self, sku: &Sku, price: Option<Money>, now: Timestamp) {
+        self.changes.retain(|change| &change.sku != sku || change.effective_at > now);
+
+        if price.is_some() {
+            self.schedule(PriceChange { sku: sku.clone(), price, effective_at: now });
+        }
+    }
+
+    /// Sets up a change, replacing one of the same product at the same time
+    pub fn schedule(&mut self, change: PriceChange) {
+        match self.position(&change.sku, change.effective_at) {
+            Ok(index) => self.changes[index] = change,
+            Err(index) => self.changes.insert(index, change),
+        }
+    }
+
+    /// Drops a change that was set up, returning whether there was one
+    pub fn cancel(&mut self, sku: &Sku, effective_at: Timestamp) -> bool {
+        match self.position(sku, effective_at) {
+            Ok(index) => {
+                self.changes.remove(index);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    fn position(&self, sku: &Sku, effective_at: Timestamp) -> Result<usize, usize> {
+        self.changes.binary_search_by(|change| {
+            (&change.sku, change.effective_at).cmp(&(sku, effective_at))
+        })
+    }
+}
+
+impl fmt::Display for PriceChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.price {
+            Some(price) => write!(f, "{} at {} from {}", self.sku, price, self.effective_at),
+            None => write!(f, "{} back to the slot price from {}", self.sku, self.effective_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sku(sku: &str) -> Sku {
+        Sku::new(sku).unwrap()
+    }
+
+    #[test]
+    fn test_price_at_follows_the_timeline() {
+        let mut schedule = PriceSchedule::new();
+        schedule.schedule(PriceChange { sku: sku("COLA"), price: None, effective_at: Timestamp::from_secs(200) });
+        schedule.schedule(PriceChange { sku: sku("COLA"), price: Some(Money::from_cents(100)), effective_at: Timestamp::from_secs(100) });
+
+        assert_eq!(schedule.price_at(&sku("COLA"), Timestamp::from_secs(99)), None);
+        assert_eq!(schedule.price_at(&sku("COLA"), Timestamp::from_secs(100)), Some(Money::from_cents(100)));
+        assert_eq!(schedule.price_at(&sku("COLA"), Timestamp::from_secs(200)), None, "The promotion is over");
+        assert_eq!(schedule.price_at(&sku("LEMON"), Timestamp::from_secs(150)), None);
+        assert_eq!(schedule.upcoming(Timestamp::from_secs(100)).len(), 1);
+    }
+
+    #[test]
+    fn test_set_forgets_earlier_changes_only() {
+        let mut schedule = PriceSchedule::new();
+        schedule.set(&sku("COLA"), Some(Money::from_cents(175)), Timestamp::from_secs(10));
+        schedule.schedule(PriceChange { sku: sku("COLA"), price: Some(Money::from_cents(200)), effective_at: Timestamp::from_secs(100) });
+        schedule.set(&sku("LEMON"), Some(Money::from_cents(90)), Timestamp::from_secs(10));
+
+        schedule.set(&sku("COLA"), Some(Money::from_cents(180)), Timestamp::from_secs(50));
+
+        assert_eq!(schedule.changes().len(), 3);
+        assert_eq!(schedule.price_at(&sku("COLA"), Timestamp::from_secs(50)), Some(Money::from_cents(180)));
+        assert_eq!(schedule.price_at(&sku("COLA"), Timestamp::from_secs(100)), Some(Money::from_cents(200)));
+
+        schedule.set(&sku("LEMON"), None, Timestamp::from_secs(50));
+        assert_eq!(schedule.price_at(&sku("LEMON"), Timestamp::from_secs(50)), None);
+        assert_eq!(schedule.changes().len(), 2);
+    }
+
+    #[test]
+    fn test_schedule_replaces_and_cancel_removes() {
+        let mut schedule = PriceSchedule::new();
+        let at = Timestamp::from_secs(100);
+        schedule.schedule(PriceChange { sku: sku("COLA"), price: Some(Money::from_cents(100)), effective_at: at });
+        schedule.schedule(PriceChange { sku: sku("COLA"), price: Some(Money::from_cents(120)), effective_at: at });
+
+        assert_eq!(schedule.changes().len(), 1);
+        assert_eq!(schedule.price_at(&sku("COLA"), at), Some(Money::from_cents(120)));
+        assert!(schedule.contains(&sku("COLA"), at));
+        assert!(schedule.cancel(&sku("COLA"), at));
+        assert!(!schedule.cancel(&sku("COLA"), at));
+        assert!(schedule.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+use crate::domain::value_objects::money::Money;
+use crate::domain::value_objects::soda::Soda;
+
+/// A product of the catalog, managed centrally and referenced by the slots that sell it
+/// This is an entity identified by its SKU, its default price changes over time
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Product {
+    /// Stock keeping unit, unique within the catalog
+    sku: Sku,
+    /// Name, flavor, size, diet and caffeine of the product, priced at its default price
+    soda: Soda,
+    /// EAN or UPC printed on the can or bottle, if the product has one
+    barcode: Option<String>,
+}
+
+/// Stock keeping unit identifying a product, e.g. "COLA-12OZ"
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sku(String);
+
+/// Errors that can occur during product operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProductError {
+    InvalidSku(String),
+    InvalidBarcode(String),
+    InvalidPrice,
+}
+
+impl Sku {
+    /// Creates a SKU, stored in upper case so "cola-12oz" and "COLA-12OZ" are the same product
+    ///
+    /// # Arguments
+    /// * `sku` - Letters, digits, dashes and underscores
+    ///
+    /// # Returns
+    /// * `Result<Sku, ProductError>` - Ok(Sku) if valid, Err if empty or containing other characters
+    pub fn new(sku: &str) -> Result<Self, ProductError> {
+        let sku = sku.trim();
+        if sku.is_empty() {
+            return Err(ProductError::InvalidSku("SKU cannot be empty".to_string()));
+        }
+
+        if !sku.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(ProductError::InvalidSku(format!("'{}' may only contain letters, digits, '-' and '_'", sku)));
+        }
+
+        Ok(Sku(sku.to_ascii_uppercase()))
+    }
+
+    /// Gets the SKU as text
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Product {
+    /// Creates a catalog product
+    ///
+    /// # Arguments
+    /// * `sku` - The SKU identifying the product
+    /// * `soda` - The product itself, its price is the default price slots sell it for
+    /// * `barcode` - The EAN-8, UPC-A, EAN-13 or GTIN-14 on the package, if any
+    ///
+    /// # Returns
+    /// * `Result<Product, ProductError>` - Ok(Product) if valid, Err if the barcode is not 8 to 14 digits
+    pub fn new(sku: Sku, soda: Soda, barcode: Option<String>) -> Result<Self, ProductError> {
+        let barcode = barcode
+            .map(|barcode| barcode.trim().to_string())
+            .filter(|barcode| !barcode.is_empty());
+
+        if let Some(barcode) = &barcode
+            && (!(8..=14).contains(&barcode.len()) || !barcode.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(ProductError::InvalidBarcode(format!("'{}' is not 8 to 14 digits", barcode)));
+        }
+
+        Ok(Product { sku, soda, barcode })
+    }
+
+    /// Gets the SKU of the product
+    pub fn sku(&self) -> &Sku {
+        &self.sku
+    }
+
+    /// Gets the product as it is sold, at its default price
+    pub fn soda(&self) -> &Soda {
+        &self.soda
+    }
+
+    /// Gets the barcode on the package
+    pub fn barcode(&self) -> Option<&str> {
+        self.barcode.as_deref()
+    }
+
+    /// Gets the price slots sell the product for
+    pub fn default_price(&self) -> Money {
+        self.soda.price()
+    }
+
+    /// Changes the price slots sell the product for
+    ///
+    /// # Arguments
+    /// * `price` - The new price, in the product's currency
+    ///
+    /// # Returns
+    /// * `Result<(), ProductError>` - Ok if changed, Err if negative or in another currency
+    pub fn set_default_price(&mut self, price: Money) -> Result<(), ProductError> {
+        if !price.same_currency(&self.soda.price()) {
+            return Err(ProductError::InvalidPrice);
+        }
+
+        self.soda = self.soda.clone().with_price(price)
+            .map_err(|_| ProductError::InvalidPrice)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Sku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} at {}", self.sku, self.soda.description(), self.soda.price())
+    }
+}
+
+impl fmt::Display for ProductError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProductError::InvalidSku(msg) => write!(f, "Invalid SKU: {}", msg),
+            ProductError::InvalidBarcode(msg) => write!(f, "Invalid barcode: {}", msg),
+            ProductError::InvalidPrice => write!(f, "Invalid price"),
+        }
+    }
+}
+
+impl std::error::Error for ProductError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::money::Currency;
+    use crate::domain::value_objects::soda::{SodaFlavor, SodaSize};
+
+    fn create_test_soda() -> Soda {
+        Soda::new(
+            "Coca-Cola".to_string(),
+            SodaFlavor::Cola,
+            SodaSize::Medium,
+            Money::from_dollars_cents(1, 50).unwrap(),
+            false,
+            true,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_sku() {
+        assert_eq!(Sku::new(" cola-12oz ").unwrap(), Sku::new("COLA-12OZ").unwrap());
+        assert_eq!(Sku::new("cola_12oz").unwrap().as_str(), "COLA_12OZ");
+        assert!(matches!(Sku::new(""), Err(ProductError::InvalidSku(_))));
+        assert!(matches!(Sku::new("COLA 12OZ"), Err(ProductError::InvalidSku(_))));
+    }
+
+    #[test]
+    fn test_product_creation() {
+        let product = Product::new(Sku::new("COLA-12OZ").unwrap(), create_test_soda(), Some("5449000000996".to_string())).unwrap();
+
+        assert_eq!(product.sku().as_str(), "COLA-12OZ");
+        assert_eq!(product.barcode(), Some("5449000000996"));
+        assert_eq!(product.default_price(), Money::from_cents(150));
+    }
+
+    #[test]
+    fn test_product_barcode_must_be_digits() {
+        let sku = Sku::new("COLA-12OZ").unwrap();
+
+        assert_eq!(Product::new(sku.clone(), create_test_soda(), Some(" ".to_string())).unwrap().barcode(), None);
+        assert!(matches!(
+            Product::new(sku.clone(), create_test_soda(), Some("1234".to_string())),
+            Err(ProductError::InvalidBarcode(_))
+        ));
+        assert!(matches!(
+            Product::new(sku, create_test_soda(), Some("54490000A0996".to_string())),
+            Err(ProductError::InvalidBarcode(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_default_price() {
+        let mut product = Product::new(Sku::new("COLA-12OZ").unwrap(), create_test_soda(), None).unwrap();
+
+        product.set_default_price(Money::from_cents(175)).unwrap();
+        assert_eq!(product.default_price(), Money::from_cents(175));
+        assert_eq!(product.soda().name(), "Coca-Cola");
+
+        assert_eq!(product.set_default_price(Money::from_cents(-1)), Err(ProductError::InvalidPrice));
+        assert_eq!(
+            product.set_default_price(Money::from_minor_units(175, Currency::EUR)),
+            Err(ProductError::InvalidPrice)
+        );
+    }
+}
@@ -0,0 +1,461 @@
+use std::fmt;
+use crate::domain::entities::product::Sku;
+use crate::domain::value_objects::money::{Money, RoundingMode};
+use crate::domain::value_objects::soda::{Soda, SodaFlavor};
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// A discount a machine grants on the sodas it applies to, while it runs
+/// This is an entity identified by its ID, so marketing can run and end campaigns as data
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Promotion {
+    /// Identifies the promotion, and is the code customers enter for a coupon
+    id: PromotionId,
+    /// Name shown to customers, e.g. "Cherry summer"
+    name: String,
+    discount: Discount,
+    scope: PromotionScope,
+    /// When the promotion starts, None if it already runs
+    valid_from: Option<Timestamp>,
+    /// When the promotion ends, None if it runs until it is removed
+    valid_until: Option<Timestamp>,
+    /// The time of day the promotion runs at, None for all day
+    daily_hours: Option<DailyHours>,
+    /// Whether customers have to enter the promotion's ID before it applies
+    is_coupon: bool,
+}
+
+/// Code identifying a promotion, e.g. "HAPPY-HOUR"
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PromotionId(String);
+
+/// How much a promotion takes off the price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Discount {
+    /// A whole percentage off, from 1 to 100
+    PercentOff(u32),
+    /// A fixed amount off, never more than the price
+    AmountOff(Money),
+    /// After every N sodas bought in one session the next one is free
+    BuyNGetOneFree(u32),
+}
+
+/// Which sodas a promotion applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PromotionScope {
+    AllProducts,
+    Flavor(SodaFlavor),
+    /// One catalog product, only sold by slots it was assigned to
+    Product(Sku),
+}
+
+/// The time of day a promotion runs at in UTC, e.g. a happy hour from 16:00 to 18:00
+/// A window that ends before it starts runs over midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DailyHours {
+    /// First minute of the day the promotion runs
+    from_minute: u32,
+    /// Minute of the day the promotion stops running
+    until_minute: u32,
+}
+
+/// A promotion applied to a sale, and what it took off the price
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AppliedPromotion {
+    pub promotion_id: PromotionId,
+    pub discount: Money,
+}
+
+/// Errors that can occur when setting up a promotion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromotionError {
+    InvalidId(String),
+    InvalidName,
+    InvalidDiscount(String),
+    InvalidPeriod,
+    InvalidHours,
+}
+
+impl PromotionId {
+    /// Creates a promotion ID, stored in upper case so customers can type coupons in any case
+    ///
+    /// # Arguments
+    /// * `id` - Letters, digits, dashes and underscores
+    ///
+    /// # Returns
+    /// * `Result<PromotionId, PromotionError>` - Ok(PromotionId) if valid, Err if empty or containing other characters
+    pub fn new(id: &str) -> Result<Self, PromotionError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(PromotionError::InvalidId("ID cannot be empty".to_string()));
+        }
+
+        if !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(PromotionError::InvalidId(format!("'{}' may only contain letters, digits, '-' and '_'", id)));
+        }
+
+        Ok(PromotionId(id.to_ascii_uppercase()))
+    }
+
+    /// Gets the ID as text
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl DailyHours {
+    /// Creates the time of day a promotion runs at
+    ///
+    /// # Arguments
+    /// * `from_minute` - First minute of the day, e.g. 960 for 16:00
+    /// * `until_minute` - Minute the promotion stops, e.g. 1080 for 18:00
+    ///
+    /// # Returns
+    /// * `Result<DailyHours, PromotionError>` - Ok(DailyHours) if valid, Err if past the end of the day or empty
+    pub fn new(from_minute: u32, until_minute: u32) -> Result<Self, PromotionError> {
+        if from_minute >= 1440 || until_minute >= 1440 || from_minute == until_minute {
+            return Err(PromotionError::InvalidHours);
+        }
+
+        Ok(DailyHours { from_minute, until_minute })
+    }
+
+    /// Gets the first minute of the day the promotion runs
+    pub fn from_minute(&self) -> u32 {
+        self.from_minute
+    }
+
+    /// Gets the minute of the day the promotion stops running
+    pub fn until_minute(&self) -> u32 {
+        self.until_minute
+    }
+
+    /// Checks if a minute of the day falls within the hours
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.from_minute < self.until_minute {
+            (self.from_minute..self.until_minute).contains(&minute)
+        } else {
+            minute >= self.from_minute || minute < self.until_minute
+        }
+    }
+}
+
+impl Promotion {
+    /// Creates a promotion that applies to every soda, all day, until it is removed
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the promotion
+    /// * `name` - The name shown to customers
+    /// * `discount` - How much it takes off the price
+    ///
+    /// # Returns
+    /// * `Result<Promotion, PromotionError>` - Ok(Promotion) if valid, Err if the name is blank or the discount takes nothing off
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::entities::promotion::{Discount, Promotion, PromotionId};
+    /// use soda_core::domain::value_objects::soda::SodaFlavor;
+    ///
+    /// let promotion = Promotion::new(PromotionId::new("cherry-20").unwrap(), "Cherry summer".to_string(), Discount::PercentOff(20))
+    ///     .unwrap()
+    ///     .for_flavor(SodaFlavor::Cherry);
+    /// assert_eq!(promotion.id().as_str(), "CHERRY-20");
+    /// ```
+    pub fn new(id: PromotionId, name: String, discount: Discount) -> Result<Self, PromotionError> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(PromotionError::InvalidName);
+        }
+
+        match discount {
+            Discount::PercentOff(percent) if !(1..=100).contains(&percent) => {
+                return Err(PromotionError::InvalidDiscount("Percentage must be between 1 and 100".to_string()));
+            },
+            Discount::AmountOff(amount) if !amount.is_positive() => {
+                return Err(PromotionError::InvalidDiscount("Amount must be greater than 0".to_string()));
+            },
+            Discount::BuyNGetOneFree(0) => {
+                return Err(PromotionError::InvalidDiscount("At least one soda must be bought".to_string()));
+            },
+            _ => {},
+        }
+
+        Ok(Promotion {
+            id,
+            name,
+            discount,
+            scope: PromotionScope::AllProducts,
+            valid_from: None,
+            valid_until: None,
+            daily_hours: None,
+            is_coupon: false,
+        })
+    }
+
+    /// Applies the promotion to sodas of one flavor only
+    pub fn for_flavor(mut self, flavor: SodaFlavor) -> Self {
+        self.scope = PromotionScope::Flavor(flavor);
+        self
+    }
+
+    /// Applies the promotion to one catalog product only
+    pub fn for_product(mut self, sku: Sku) -> Self {
+        self.scope = PromotionScope::Product(sku);
+        self
+    }
+
+    /// Runs the promotion for a period only
+    ///
+    /// # Arguments
+    /// * `valid_from` - When it starts, None if it already runs
+    /// * `valid_until` - When it ends, None if it runs until it is removed
+    ///
+    /// # Returns
+    /// * `Result<Promotion, PromotionError>` - Ok(Promotion) if valid, Err if it would end before it starts
+    pub fn valid_between(mut self, valid_from: Option<Timestamp>, valid_until: Option<Timestamp>) -> Result<Self, PromotionError> {
+        if let (Some(from), Some(until)) = (valid_from, valid_until)
+            && from >= until
+        {
+            return Err(PromotionError::InvalidPeriod);
+        }
+
+        self.valid_from = valid_from;
+        self.valid_until = valid_until;
+        Ok(self)
+    }
+
+    /// Runs the promotion at a time of day only, e.g. for a happy hour
+    pub fn during(mut self, daily_hours: DailyHours) -> Self {
+        self.daily_hours = Some(daily_hours);
+        self
+    }
+
+    /// Makes customers enter the promotion's ID before it applies
+    pub fn as_coupon(mut self) -> Self {
+        self.is_coupon = true;
+        self
+    }
+
+    /// Gets the ID of the promotion
+    pub fn id(&self) -> &PromotionId {
+        &self.id
+    }
+
+    /// Gets the name shown to customers
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets how much the promotion takes off the price
+    pub fn discount(&self) -> Discount {
+        self.discount
+    }
+
+    /// Gets which sodas the promotion applies to
+    pub fn scope(&self) -> &PromotionScope {
+        &self.scope
+    }
+
+    /// Gets when the promotion starts
+    pub fn valid_from(&self) -> Option<Timestamp> {
+        self.valid_from
+    }
+
+    /// Gets when the promotion ends
+    pub fn valid_until(&self) -> Option<Timestamp> {
+        self.valid_until
+    }
+
+    /// Gets the time of day the promotion runs at
+    pub fn daily_hours(&self) -> Option<DailyHours> {
+        self.daily_hours
+    }
+
+    /// Checks if customers have to enter the promotion's ID
+    pub fn is_coupon(&self) -> bool {
+        self.is_coupon
+    }
+
+    /// Checks if the promotion runs at a point in time
+    pub fn is_active_at(&self, now: Timestamp) -> bool {
+        self.valid_from.is_none_or(|from| from <= now)
+            && self.valid_until.is_none_or(|until| now < until)
+            && self.daily_hours.is_none_or(|hours| hours.contains(now.minute_of_day()))
+    }
+
+    /// Checks if the promotion applies to a soda
+    ///
+    /// # Arguments
+    /// * `soda` - The soda
+    /// * `sku` - The catalog product the soda comes from, None for a soda configured by hand
+    pub fn applies_to(&self, soda: &Soda, sku: Option<&Sku>) -> bool {
+        match &self.scope {
+            PromotionScope::AllProducts => true,
+            PromotionScope::Flavor(flavor) => soda.flavor() == *flavor,
+            PromotionScope::Product(product) => sku == Some(product),
+        }
+    }
+
+    /// Works out what the promotion takes off a price
+    ///
+    /// # Arguments
+    /// * `price` - The price before the promotion
+    /// * `bought_before` - How many sodas the promotion applies to were bought earlier in the session,
+    ///   which is kept open at zero credit while the next soda is free
+    ///
+    /// # Returns
+    /// * `Money` - The discount, from zero up to the price
+    pub fn discount_on(&self, price: Money, bought_before: u32) -> Money {
+        let discount = match self.discount {
+            Discount::PercentOff(percent) => price.percentage(percent as i64, RoundingMode::Down)
+                .unwrap_or(Money::zero_in(price.currency())),
+            Discount::AmountOff(amount) if amount.same_currency(&price) => amount,
+            Discount::AmountOff(_) => Money::zero_in(price.currency()),
+            Discount::BuyNGetOneFree(n) if (bought_before + 1).is_multiple_of(n + 1) => price,
+            Discount::BuyNGetOneFree(_) => Money::zero_in(price.currency()),
+        };
+
+        discount.min(price).max(Money::zero_in(price.currency()))
+    }
+}
+
+impl fmt::Display for PromotionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Discount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discount::PercentOff(percent) => write!(f, "{}% off", percent),
+            Discount::AmountOff(amount) => write!(f, "{} off", amount),
+            Discount::BuyNGetOneFree(n) => write!(f, "buy {} get 1 free", n),
+        }
+    }
+}
+
+impl fmt::Display for DailyHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.from_minute / 60,
+            self.from_minute % 60,
+            self.until_minute / 60,
+            self.until_minute % 60
+        )
+    }
+}
+
+impl fmt::Display for Promotion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.id, self.name, self.discount)?;
+        match &self.scope {
+            PromotionScope::AllProducts => {},
+            PromotionScope::Flavor(flavor) => write!(f, " on {}", flavor)?,
+            PromotionScope::Product(sku) => write!(f, " on {}", sku)?,
+        }
+        if let Some(hours) = self.daily_hours {
+            write!(f, ", {} UTC", hours)?;
+        }
+        if self.is_coupon {
+            write!(f, " (coupon)")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PromotionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromotionError::InvalidId(msg) => write!(f, "Invalid promotion ID: {}", msg),
+            PromotionError::InvalidName => write!(f, "Promotion name cannot be empty"),
+            PromotionError::InvalidDiscount(msg) => write!(f, "Invalid discount: {}", msg),
+            PromotionError::InvalidPeriod => write!(f, "Promotion must end after it starts"),
+            PromotionError::InvalidHours => write!(f, "Daily hours must be two different minutes of the day"),
+        }
+    }
+}
+
+impl std::error::Error for PromotionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::soda::SodaSize;
+
+    fn promotion(discount: Discount) -> Promotion {
+        Promotion::new(PromotionId::new("TEST").unwrap(), "Test".to_string(), discount).unwrap()
+    }
+
+    fn cherry() -> Soda {
+        Soda::new("Cherry".to_string(), SodaFlavor::Cherry, SodaSize::Medium, Money::from_cents(150), false, false).unwrap()
+    }
+
+    #[test]
+    fn test_promotion_rejects_discounts_that_take_nothing_off() {
+        let id = PromotionId::new("test").unwrap();
+
+        assert!(matches!(Promotion::new(id.clone(), "Test".to_string(), Discount::PercentOff(0)), Err(PromotionError::InvalidDiscount(_))));
+        assert!(matches!(Promotion::new(id.clone(), "Test".to_string(), Discount::PercentOff(101)), Err(PromotionError::InvalidDiscount(_))));
+        assert!(matches!(Promotion::new(id.clone(), "Test".to_string(), Discount::AmountOff(Money::zero())), Err(PromotionError::InvalidDiscount(_))));
+        assert!(matches!(Promotion::new(id.clone(), "Test".to_string(), Discount::BuyNGetOneFree(0)), Err(PromotionError::InvalidDiscount(_))));
+        assert_eq!(Promotion::new(id, " ".to_string(), Discount::PercentOff(10)), Err(PromotionError::InvalidName));
+        assert!(matches!(PromotionId::new("SAVE 50"), Err(PromotionError::InvalidId(_))));
+    }
+
+    #[test]
+    fn test_discount_on() {
+        let price = Money::from_cents(155);
+
+        assert_eq!(promotion(Discount::PercentOff(20)).discount_on(price, 0), Money::from_cents(31));
+        assert_eq!(promotion(Discount::PercentOff(15)).discount_on(price, 0), Money::from_cents(23), "Rounded down to whole cents");
+        assert_eq!(promotion(Discount::AmountOff(Money::from_cents(50))).discount_on(price, 0), Money::from_cents(50));
+        assert_eq!(promotion(Discount::AmountOff(Money::from_cents(500))).discount_on(price, 0), price);
+
+        let buy_two = promotion(Discount::BuyNGetOneFree(2));
+        assert_eq!(buy_two.discount_on(price, 0), Money::zero());
+        assert_eq!(buy_two.discount_on(price, 1), Money::zero());
+        assert_eq!(buy_two.discount_on(price, 2), price);
+        assert_eq!(buy_two.discount_on(price, 5), price);
+    }
+
+    #[test]
+    fn test_is_active_at() {
+        let start = Timestamp::from_secs(1_709_251_200); // 2024-03-01T00:00:00Z
+        let happy_hour = promotion(Discount::PercentOff(50))
+            .valid_between(Some(start), Some(start.plus(std::time::Duration::from_secs(2 * 86_400))))
+            .unwrap()
+            .during(DailyHours::new(16 * 60, 18 * 60).unwrap());
+        let at = |hours: u64| start.plus(std::time::Duration::from_secs(hours * 3600));
+
+        assert!(!happy_hour.is_active_at(at(15)));
+        assert!(happy_hour.is_active_at(at(16)));
+        assert!(!happy_hour.is_active_at(at(18)));
+        assert!(happy_hour.is_active_at(at(24 + 17)));
+        assert!(!happy_hour.is_active_at(at(48 + 17)), "The campaign is over");
+        assert!(!happy_hour.is_active_at(Timestamp::from_secs(1_709_251_200 - 3600 * 7)));
+
+        let overnight = DailyHours::new(22 * 60, 2 * 60).unwrap();
+        assert!(overnight.contains(23 * 60) && overnight.contains(60) && !overnight.contains(12 * 60));
+        assert_eq!(overnight.to_string(), "22:00-02:00");
+        assert_eq!(promotion(Discount::PercentOff(5)).valid_between(Some(start), Some(start)).unwrap_err(), PromotionError::InvalidPeriod);
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let sku = Sku::new("CHERRY-12OZ").unwrap();
+
+        assert!(promotion(Discount::PercentOff(20)).applies_to(&cherry(), None));
+        assert!(promotion(Discount::PercentOff(20)).for_flavor(SodaFlavor::Cherry).applies_to(&cherry(), None));
+        assert!(!promotion(Discount::PercentOff(20)).for_flavor(SodaFlavor::Cola).applies_to(&cherry(), None));
+        assert!(promotion(Discount::PercentOff(20)).for_product(sku.clone()).applies_to(&cherry(), Some(&sku)));
+        assert!(!promotion(Discount::PercentOff(20)).for_product(sku).applies_to(&cherry(), None));
+    }
+}
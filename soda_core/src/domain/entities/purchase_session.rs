@@ -0,0 +1,522 @@
+use std::fmt;
+use std::time::Duration;
+use crate::domain::entities::promotion::{AppliedPromotion, PromotionId};
+use crate::domain::entities::slot::SlotId;
+use crate::domain::value_objects::money::{Currency, Money, MoneyError};
+use crate::domain::value_objects::soda::Soda;
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// Represents one customer's interaction with the machine, from the first inserted coin
+/// until the remaining credit is paid back
+/// This is an entity with identity and lifecycle, owned by the soda machine aggregate
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PurchaseSession {
+    /// Identifier of the session, unique within its machine
+    id: SessionId,
+    /// When the first coin or bill was inserted
+    started_at: Timestamp,
+    /// When the customer last inserted money, bought or got change back
+    last_activity_at: Timestamp,
+    /// Total money the customer has put in during the session
+    total_inserted: Money,
+    /// Credit the customer can still spend or get back
+    credit: Money,
+    /// Sodas bought during the session, in order
+    purchases: Vec<SessionPurchase>,
+    /// Coupon codes the customer entered, in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    coupons: Vec<PromotionId>,
+}
+
+/// Unique identifier for a purchase session within a machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionId(u64);
+
+/// A soda bought during a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionPurchase {
+    pub slot_id: SlotId,
+    pub soda: Soda,
+    /// The price charged, after the promotion
+    pub price: Money,
+    pub purchased_at: Timestamp,
+    /// The promotion that lowered the price, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub promotion: Option<AppliedPromotion>,
+}
+
+/// Final accounting of a session once it has ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionSummary {
+    pub session_id: SessionId,
+    pub started_at: Timestamp,
+    pub ended_at: Timestamp,
+    pub total_inserted: Money,
+    pub total_spent: Money,
+    pub refunded: Money,
+    pub purchases: Vec<SessionPurchase>,
+}
+
+/// Errors that can occur during session operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    InsufficientCredit { required: Money, available: Money },
+    MoneyError(MoneyError),
+}
+
+impl PurchaseSession {
+    /// Starts a new session with no credit
+    ///
+    /// # Arguments
+    /// * `id` - Identifier of the session
+    /// * `currency` - The currency of the machine the session runs on
+    /// * `now` - When the session starts
+    ///
+    /// # Returns
+    /// * `PurchaseSession` - The new session
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::entities::purchase_session::{PurchaseSession, SessionId};
+    /// use soda_core::domain::value_objects::money::Currency;
+    /// use soda_core::domain::value_objects::timestamp::Timestamp;
+    ///
+    /// let session = PurchaseSession::start(SessionId::new(1), Currency::USD, Timestamp::from_secs(0));
+    /// assert!(session.credit().is_zero());
+    /// ```
+    pub fn start(id: SessionId, currency: Currency, now: Timestamp) -> Self {
+        PurchaseSession {
+            id,
+            started_at: now,
+            last_activity_at: now,
+            total_inserted: Money::zero_in(currency),
+            credit: Money::zero_in(currency),
+            purchases: Vec::new(),
+            coupons: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a session from state a repository stored
+    ///
+    /// # Arguments
+    /// * `id` - The session ID
+    /// * `started_at` - When the session started
+    /// * `last_activity_at` - When the customer last did something
+    /// * `total_inserted` - Total money inserted during the session
+    /// * `credit` - Credit the customer can still spend or get back
+    /// * `purchases` - Sodas bought during the session, in order
+    /// * `coupons` - Coupon codes the customer entered, in order
+    ///
+    /// # Returns
+    /// * `PurchaseSession` - The session as it was stored
+    pub fn restore(
+        id: SessionId,
+        started_at: Timestamp,
+        last_activity_at: Timestamp,
+        total_inserted: Money,
+        credit: Money,
+        purchases: Vec<SessionPurchase>,
+        coupons: Vec<PromotionId>,
+    ) -> Self {
+        PurchaseSession { id, started_at, last_activity_at, total_inserted, credit, purchases, coupons }
+    }
+
+    /// Gets the session ID
+    pub fn id(&self) -> SessionId {
+        self.id
+    }
+
+    /// Gets when the session started
+    pub fn started_at(&self) -> Timestamp {
+        self.started_at
+    }
+
+    /// Gets when the customer last did something
+    pub fn last_activity_at(&self) -> Timestamp {
+        self.last_activity_at
+    }
+
+    /// Gets the total money inserted during the session
+    pub fn total_inserted(&self) -> Money {
+        self.total_inserted
+    }
+
+    /// Gets the credit the customer can still spend or get back
+    pub fn credit(&self) -> Money {
+        self.credit
+    }
+
+    /// Gets the sodas bought during the session
+    pub fn purchases(&self) -> &[SessionPurchase] {
+        &self.purchases
+    }
+
+    /// Gets the coupon codes the customer entered
+    pub fn coupons(&self) -> &[PromotionId] {
+        &self.coupons
+    }
+
+    /// Checks if a coupon was entered and has not been used on a purchase yet
+    pub fn has_unused_coupon(&self, code: &PromotionId) -> bool {
+        self.coupons.contains(code)
+            && !self.purchases.iter().any(|purchase| {
+                purchase.promotion.as_ref().is_some_and(|promotion| &promotion.promotion_id == code)
+            })
+    }
+
+    /// Gets the total spent on sodas during the session
+    pub fn total_spent(&self) -> Money {
+        self.purchases.iter()
+            .fold(Money::zero_in(self.credit.currency()), |acc, purchase| {
+                (acc + purchase.price).unwrap_or(acc)
+            })
+    }
+
+    /// Checks if the session has been idle for at least the timeout
+    ///
+    /// # Arguments
+    /// * `now` - The current time
+    /// * `timeout` - How long a session may stay idle
+    ///
+    /// # Returns
+    /// * `bool` - True if the session should be expired
+    pub fn is_idle(&self, now: Timestamp, timeout: Duration) -> bool {
+        now.duration_since(self.last_activity_at) >= timeout
+    }
+
+    /// Adds inserted money to the session's credit
+    ///
+    /// # Arguments
+    /// * `amount` - The amount inserted
+    /// * `now` - When the money was inserted
+    ///
+    /// # Returns
+    /// * `Result<Money, SessionError>` - Ok(credit) with the new credit, Err if invalid
+    pub fn add_credit(&mut self, amount: Money, now: Timestamp) -> Result<Money, SessionError> {
+        let credit = (self.credit + amount).map_err(SessionError::MoneyError)?;
+        let total_inserted = (self.total_inserted + amount).map_err(SessionError::MoneyError)?;
+
+        self.credit = credit;
+        self.total_inserted = total_inserted;
+        self.last_activity_at = now;
+        Ok(credit)
+    }
+
+    /// Remembers a coupon code the customer entered, for the next purchase it applies to
+    ///
+    /// # Arguments
+    /// * `code` - The coupon code
+    /// * `now` - When the code was entered
+    pub fn enter_coupon(&mut self, code: PromotionId, now: Timestamp) {
+        if !self.coupons.contains(&code) {
+            self.coupons.push(code);
+        }
+        self.last_activity_at = now;
+    }
+
+    /// Records a purchase, paying for it from the session's credit
+    ///
+    /// # Arguments
+    /// * `slot_id` - The slot the soda came from
+    /// * `soda` - The soda bought
+    /// * `price` - The price charged for it, after the promotion
+    /// * `promotion` - The promotion that lowered the price, if any
+    /// * `now` - When the soda was bought
+    ///
+    /// # Returns
+    /// * `Result<Money, SessionError>` - Ok(credit) with the remaining credit, Err if the credit does not cover the price
+    pub fn record_purchase(
+        &mut self,
+        slot_id: SlotId,
+        soda: Soda,
+        price: Money,
+        promotion: Option<AppliedPromotion>,
+        now: Timestamp,
+    ) -> Result<Money, SessionError> {
+        self.withdraw_credit(price)?;
+
+        self.purchases.push(SessionPurchase { slot_id, soda, price, purchased_at: now, promotion });
+        self.last_activity_at = now;
+        Ok(self.credit)
+    }
+
+    /// Takes part of the credit back out as change, keeping the session open
+    ///
+    /// # Arguments
+    /// * `amount` - The amount paid back
+    /// * `now` - When it was paid back
+    ///
+    /// # Returns
+    /// * `Result<Money, SessionError>` - Ok(credit) with the remaining credit, Err if the credit is too low
+    pub fn refund(&mut self, amount: Money, now: Timestamp) -> Result<Money, SessionError> {
+        self.withdraw_credit(amount)?;
+        self.last_activity_at = now;
+        Ok(self.credit)
+    }
+
+    /// Ends the session, summarizing it
+    ///
+    /// # Arguments
+    /// * `refunded` - The credit paid back to the customer when the session ended
+    /// * `now` - When the session ended
+    ///
+    /// # Returns
+    /// * `SessionSummary` - The final accounting of the session
+    pub fn end(self, refunded: Money, now: Timestamp) -> SessionSummary {
+        SessionSummary {
+            session_id: self.id,
+            started_at: self.started_at,
+            ended_at: now,
+            total_inserted: self.total_inserted,
+            total_spent: self.total_spent(),
+            refunded,
+            purchases: self.purchases,
+        }
+    }
+
+    fn withdraw_credit(&mut self, amount: Money) -> Result<(), SessionError> {
+        if amount > self.credit {
+            return Err(SessionError::InsufficientCredit { required: amount, available: self.credit });
+        }
+
+        self.credit = (self.credit - amount).map_err(SessionError::MoneyError)?;
+        Ok(())
+    }
+}
+
+impl SessionId {
+    /// Creates a new session ID
+    ///
+    /// # Arguments
+    /// * `id` - The numeric ID
+    ///
+    /// # Returns
+    /// * `SessionId` - The session ID
+    pub fn new(id: u64) -> Self {
+        SessionId(id)
+    }
+
+    /// Gets the numeric value of the session ID
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    /// Gets the ID that follows this one
+    pub fn next(&self) -> Self {
+        SessionId(self.0 + 1)
+    }
+}
+
+impl fmt::Display for PurchaseSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Session {}: {} credit, {} bought, started {}",
+            self.id,
+            self.credit,
+            self.purchases.len(),
+            self.started_at
+        )
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::InsufficientCredit { required, available } => {
+                write!(f, "Insufficient credit: need {}, have {}", required, available)
+            },
+            SessionError::MoneyError(err) => write!(f, "Money error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<MoneyError> for SessionError {
+    fn from(err: MoneyError) -> Self {
+        SessionError::MoneyError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::soda::{SodaFlavor, SodaSize};
+
+    fn create_test_soda() -> Soda {
+        Soda::new(
+            "Coca-Cola".to_string(),
+            SodaFlavor::Cola,
+            SodaSize::Medium,
+            Money::from_cents(150),
+            false,
+            true,
+        ).unwrap()
+    }
+
+    fn create_test_session() -> PurchaseSession {
+        PurchaseSession::start(SessionId::new(1), Currency::USD, Timestamp::from_secs(100))
+    }
+
+    #[test]
+    fn test_start() {
+        let session = create_test_session();
+
+        assert_eq!(session.id(), SessionId::new(1));
+        assert_eq!(session.started_at(), Timestamp::from_secs(100));
+        assert_eq!(session.last_activity_at(), Timestamp::from_secs(100));
+        assert_eq!(session.credit(), Money::zero());
+        assert_eq!(session.total_inserted(), Money::zero());
+        assert!(session.purchases().is_empty());
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(200), Timestamp::from_secs(110)).unwrap();
+        session.record_purchase(SlotId::new(1), create_test_soda(), Money::from_cents(150), None, Timestamp::from_secs(115)).unwrap();
+
+        let restored = PurchaseSession::restore(
+            session.id(),
+            session.started_at(),
+            session.last_activity_at(),
+            session.total_inserted(),
+            session.credit(),
+            session.purchases().to_vec(),
+            session.coupons().to_vec(),
+        );
+
+        assert_eq!(restored, session);
+    }
+
+    #[test]
+    fn test_add_credit() {
+        let mut session = create_test_session();
+
+        session.add_credit(Money::from_cents(100), Timestamp::from_secs(110)).unwrap();
+        let credit = session.add_credit(Money::from_cents(25), Timestamp::from_secs(120)).unwrap();
+
+        assert_eq!(credit, Money::from_cents(125));
+        assert_eq!(session.total_inserted(), Money::from_cents(125));
+        assert_eq!(session.last_activity_at(), Timestamp::from_secs(120));
+    }
+
+    #[test]
+    fn test_add_credit_other_currency() {
+        let mut session = create_test_session();
+        let result = session.add_credit(Money::from_minor_units(100, Currency::EUR), Timestamp::from_secs(110));
+
+        assert_eq!(result.unwrap_err(), SessionError::MoneyError(MoneyError::CurrencyMismatch));
+        assert_eq!(session.credit(), Money::zero());
+    }
+
+    #[test]
+    fn test_record_purchase() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(200), Timestamp::from_secs(110)).unwrap();
+
+        let credit = session.record_purchase(SlotId::new(1), create_test_soda(), Money::from_cents(150), None, Timestamp::from_secs(115)).unwrap();
+
+        assert_eq!(credit, Money::from_cents(50));
+        assert_eq!(session.total_spent(), Money::from_cents(150));
+        assert_eq!(session.purchases().len(), 1);
+        assert_eq!(session.purchases()[0].slot_id, SlotId::new(1));
+        assert_eq!(session.purchases()[0].purchased_at, Timestamp::from_secs(115));
+    }
+
+    #[test]
+    fn test_record_purchase_insufficient_credit() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(100), Timestamp::from_secs(110)).unwrap();
+
+        let result = session.record_purchase(SlotId::new(1), create_test_soda(), Money::from_cents(150), None, Timestamp::from_secs(115));
+
+        assert_eq!(result.unwrap_err(), SessionError::InsufficientCredit {
+            required: Money::from_cents(150),
+            available: Money::from_cents(100),
+        });
+        assert!(session.purchases().is_empty());
+    }
+
+    #[test]
+    fn test_refund() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(100), Timestamp::from_secs(110)).unwrap();
+
+        assert_eq!(session.refund(Money::from_cents(25), Timestamp::from_secs(111)).unwrap(), Money::from_cents(75));
+        assert!(session.refund(Money::from_cents(100), Timestamp::from_secs(112)).is_err());
+        assert_eq!(session.total_inserted(), Money::from_cents(100));
+    }
+
+    #[test]
+    fn test_is_idle() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(100), Timestamp::from_secs(110)).unwrap();
+        let timeout = Duration::from_secs(60);
+
+        assert!(!session.is_idle(Timestamp::from_secs(169), timeout));
+        assert!(session.is_idle(Timestamp::from_secs(170), timeout));
+    }
+
+    #[test]
+    fn test_end() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(200), Timestamp::from_secs(110)).unwrap();
+        session.record_purchase(SlotId::new(1), create_test_soda(), Money::from_cents(150), None, Timestamp::from_secs(115)).unwrap();
+
+        let summary = session.end(Money::from_cents(50), Timestamp::from_secs(120));
+
+        assert_eq!(summary.session_id, SessionId::new(1));
+        assert_eq!(summary.started_at, Timestamp::from_secs(100));
+        assert_eq!(summary.ended_at, Timestamp::from_secs(120));
+        assert_eq!(summary.total_inserted, Money::from_cents(200));
+        assert_eq!(summary.total_spent, Money::from_cents(150));
+        assert_eq!(summary.refunded, Money::from_cents(50));
+        assert_eq!(summary.purchases.len(), 1);
+    }
+
+    #[test]
+    fn test_coupon_is_used_by_the_purchase_it_discounts() {
+        let mut session = create_test_session();
+        let code = PromotionId::new("SAVE25").unwrap();
+        session.add_credit(Money::from_cents(200), Timestamp::from_secs(110)).unwrap();
+
+        session.enter_coupon(code.clone(), Timestamp::from_secs(112));
+        session.enter_coupon(code.clone(), Timestamp::from_secs(113));
+        assert_eq!(session.coupons().len(), 1);
+        assert!(session.has_unused_coupon(&code));
+
+        let promotion = AppliedPromotion { promotion_id: code.clone(), discount: Money::from_cents(25) };
+        session.record_purchase(SlotId::new(1), create_test_soda(), Money::from_cents(125), Some(promotion), Timestamp::from_secs(115)).unwrap();
+
+        assert!(!session.has_unused_coupon(&code));
+        assert_eq!(session.credit(), Money::from_cents(75));
+    }
+
+    #[test]
+    fn test_session_id() {
+        let id = SessionId::new(7);
+        assert_eq!(id.value(), 7);
+        assert_eq!(id.next(), SessionId::new(8));
+        assert_eq!(format!("{}", id), "7");
+    }
+
+    #[test]
+    fn test_display() {
+        let mut session = create_test_session();
+        session.add_credit(Money::from_cents(100), Timestamp::from_secs(110)).unwrap();
+
+        assert_eq!(format!("{}", session), "Session 1: $1.00 credit, 0 bought, started 1970-01-01T00:01:40.000Z");
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+use crate::domain::entities::cash_collection::OperatorId;
+use crate::domain::services::change_calculator::ChangeBreakdown;
+use crate::domain::value_objects::money::Money;
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// Money an operator paid back to a customer from the cash box, e.g. for a soda that never dropped
+///
+/// A refund is taken off the revenue since the last collection, so the till still reconciles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Refund {
+    pub operator_id: OperatorId,
+    pub amount: Money,
+    /// Why the customer got their money back, as the operator wrote it down
+    pub reason: String,
+    /// The coins and bills paid out
+    pub breakdown: ChangeBreakdown,
+    pub issued_at: Timestamp,
+}
+
+impl fmt::Display for Refund {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} refunded by {} at {}: {}", self.amount, self.operator_id, self.issued_at, self.reason)
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+use crate::domain::aggregates::soda_machine::{SodaMachineEvent, SodaMachineId};
+use crate::domain::entities::promotion::AppliedPromotion;
+use crate::domain::entities::slot::SlotId;
+use crate::domain::value_objects::money::Money;
+use crate::domain::value_objects::soda::Soda;
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// One soda sold, as it is kept in the sales ledger
+/// A sale is identified by its machine, the sequence number of its `SodaDispensed` event and when it was sold,
+/// as a machine created again under a deleted machine's ID restarts its sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sale {
+    pub machine_id: SodaMachineId,
+    /// Sequence number of the `SodaDispensed` event within its machine
+    pub sequence: u64,
+    pub slot_id: SlotId,
+    pub soda: Soda,
+    /// What the customer paid, after the promotion
+    pub price: Money,
+    pub promotion: Option<AppliedPromotion>,
+    pub sold_at: Timestamp,
+}
+
+impl Sale {
+    /// Gets the sale a `SodaDispensed` event records, None for every other event
+    ///
+    /// # Arguments
+    /// * `machine_id` - The machine that recorded the event
+    /// * `sequence` - The event's sequence number within the machine
+    /// * `event` - The event
+    ///
+    /// # Returns
+    /// * `Option<Sale>` - Some(sale) if the event dispensed a soda
+    pub fn from_event(machine_id: SodaMachineId, sequence: u64, event: &SodaMachineEvent) -> Option<Self> {
+        match event {
+            SodaMachineEvent::SodaDispensed { slot_id, soda, price, dispensed_at, promotion } => Some(Sale {
+                machine_id,
+                sequence,
+                slot_id: *slot_id,
+                soda: soda.clone(),
+                price: *price,
+                promotion: promotion.clone(),
+                sold_at: *dispensed_at,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Sale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} machine {} slot {}: {} for {}",
+            self.sold_at,
+            self.machine_id,
+            self.slot_id.value(),
+            self.soda.name(),
+            self.price
+        )?;
+        if let Some(promotion) = &self.promotion {
+            write!(f, " ({} off with {})", promotion.discount, promotion.promotion_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::soda::{SodaFlavor, SodaSize};
+
+    #[test]
+    fn test_sale_is_read_from_soda_dispensed_only() {
+        let soda = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let dispensed = SodaMachineEvent::SodaDispensed {
+            slot_id: SlotId::new(2),
+            soda: soda.clone(),
+            price: Money::from_cents(150),
+            dispensed_at: Timestamp::from_secs(1_709_296_200),
+            promotion: None,
+        };
+
+        let sale = Sale::from_event(SodaMachineId::new(1), 7, &dispensed).unwrap();
+
+        assert_eq!(sale.sequence, 7);
+        assert_eq!(sale.slot_id, SlotId::new(2));
+        assert_eq!(sale.soda, soda);
+        assert_eq!(sale.to_string(), "2024-03-01T12:30:00.000Z machine 1 slot 2: Cola for $1.50");
+        assert!(Sale::from_event(SodaMachineId::new(1), 8, &SodaMachineEvent::MachineEnabled).is_none());
+    }
+}
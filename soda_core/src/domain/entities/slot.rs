@@ -1,25 +1,34 @@
 use std::fmt;
+use crate::domain::entities::product::{Product, Sku};
 use crate::domain::value_objects::soda::Soda;
 use crate::domain::value_objects::money::Money;
 
 /// Represents a slot in the soda machine that can hold sodas
 /// This is an entity with identity and lifecycle
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Slot {
     /// Unique identifier for the slot
     id: SlotId,
     /// The type of soda this slot is configured for
     soda_type: Option<Soda>,
+    /// The catalog product the soda comes from, None for a soda configured by hand
+    #[cfg_attr(feature = "serde", serde(default))]
+    sku: Option<Sku>,
     /// Current quantity of sodas in the slot
     quantity: u32,
     /// Maximum capacity of the slot
     max_capacity: u32,
     /// Whether the slot is currently enabled/operational
     is_enabled: bool,
+    /// The slot counts as low on stock once it holds this many sodas or fewer, 0 only reports sold out
+    #[cfg_attr(feature = "serde", serde(default))]
+    low_stock_threshold: u32,
 }
 
 /// Unique identifier for a slot
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlotId(u32);
 
 /// Errors that can occur during slot operations
@@ -33,6 +42,7 @@ pub enum SlotError {
     SodaTypeMismatch,
     InsufficientQuantity,
     InvalidSlotId,
+    InvalidPrice,
 }
 
 impl Slot {
@@ -59,9 +69,11 @@ impl Slot {
         Ok(Slot {
             id,
             soda_type: None,
+            sku: None,
             quantity: 0,
             max_capacity,
             is_enabled: true,
+            low_stock_threshold: 0,
         })
     }
 
@@ -92,12 +104,37 @@ impl Slot {
         Ok(Slot {
             id,
             soda_type: Some(soda_type),
+            sku: None,
             quantity,
             max_capacity,
             is_enabled: true,
+            low_stock_threshold: 0,
         })
     }
 
+    /// Creates a slot selling a catalog product, e.g. when loading a stored machine
+    /// 
+    /// # Arguments
+    /// * `id` - Unique identifier for the slot
+    /// * `sku` - The catalog product the slot sells
+    /// * `soda_type` - The product as the slot sells it, at the slot's price
+    /// * `quantity` - Initial quantity of sodas
+    /// * `max_capacity` - Maximum number of sodas the slot can hold
+    /// 
+    /// # Returns
+    /// * `Result<Slot, SlotError>` - Ok(Slot) if valid, Err if invalid
+    pub fn new_with_product(
+        id: SlotId,
+        sku: Sku,
+        soda_type: Soda,
+        quantity: u32,
+        max_capacity: u32,
+    ) -> Result<Self, SlotError> {
+        let mut slot = Self::new_with_soda(id, soda_type, quantity, max_capacity)?;
+        slot.sku = Some(sku);
+        Ok(slot)
+    }
+
     /// Gets the slot ID
     pub fn id(&self) -> SlotId {
         self.id
@@ -108,6 +145,16 @@ impl Slot {
         self.soda_type.as_ref()
     }
 
+    /// Gets the SKU of the catalog product the slot sells
+    pub fn sku(&self) -> Option<&Sku> {
+        self.sku.as_ref()
+    }
+
+    /// Checks if the slot sells the given catalog product
+    pub fn sells(&self, sku: &Sku) -> bool {
+        self.sku.as_ref() == Some(sku)
+    }
+
     /// Gets the current quantity of sodas in the slot
     pub fn quantity(&self) -> u32 {
         self.quantity
@@ -123,6 +170,16 @@ impl Slot {
         self.is_enabled
     }
 
+    /// Gets how many sodas the slot may hold before it counts as low on stock
+    pub fn low_stock_threshold(&self) -> u32 {
+        self.low_stock_threshold
+    }
+
+    /// Checks if the slot is down to its low stock threshold, sold out included
+    pub fn is_low_on_stock(&self) -> bool {
+        self.quantity <= self.low_stock_threshold
+    }
+
     /// Checks if the slot is empty
     pub fn is_empty(&self) -> bool {
         self.quantity == 0
@@ -160,6 +217,43 @@ impl Slot {
         }
 
         self.soda_type = Some(soda_type);
+        self.sku = None;
+        Ok(())
+    }
+
+    /// Configures the slot to sell a catalog product at its default price
+    /// 
+    /// A slot that already sells the product only takes over the new price, so it
+    /// does not need to be emptied first.
+    /// 
+    /// # Arguments
+    /// * `product` - The catalog product to sell
+    /// 
+    /// # Returns
+    /// * `Result<(), SlotError>` - Ok if successful, Err if the slot still holds another soda
+    pub fn assign_product(&mut self, product: &Product) -> Result<(), SlotError> {
+        if self.sells(product.sku()) {
+            return self.reprice(product.default_price());
+        }
+
+        self.configure_soda_type(product.soda().clone())?;
+        self.sku = Some(product.sku().clone());
+        Ok(())
+    }
+
+    /// Changes the price the slot sells its soda for, even while it is stocked
+    /// 
+    /// # Arguments
+    /// * `price` - The new price
+    /// 
+    /// # Returns
+    /// * `Result<(), SlotError>` - Ok if successful, Err if no soda is configured or the price is negative
+    pub fn reprice(&mut self, price: Money) -> Result<(), SlotError> {
+        let soda = self.soda_type.as_ref().ok_or(SlotError::SlotEmpty)?;
+        let repriced = soda.clone().with_price(price)
+            .map_err(|_| SlotError::InvalidPrice)?;
+
+        self.soda_type = Some(repriced);
         Ok(())
     }
 
@@ -244,6 +338,22 @@ impl Slot {
         self.is_enabled = false;
     }
 
+    /// Sets how many sodas the slot may hold before it counts as low on stock
+    /// 
+    /// # Arguments
+    /// * `threshold` - The reorder level, must be below the capacity
+    /// 
+    /// # Returns
+    /// * `Result<(), SlotError>` - Ok if successful, Err if the slot would always be low on stock
+    pub fn set_low_stock_threshold(&mut self, threshold: u32) -> Result<(), SlotError> {
+        if threshold >= self.max_capacity {
+            return Err(SlotError::InvalidQuantity("Low stock threshold must be below capacity".to_string()));
+        }
+
+        self.low_stock_threshold = threshold;
+        Ok(())
+    }
+
     /// Updates the maximum capacity of the slot
     /// 
     /// # Arguments
@@ -260,6 +370,10 @@ impl Slot {
             return Err(SlotError::InvalidCapacity("Cannot reduce capacity below current quantity".to_string()));
         }
 
+        if self.low_stock_threshold >= new_capacity {
+            return Err(SlotError::InvalidCapacity("Cannot reduce capacity to the low stock threshold".to_string()));
+        }
+
         self.max_capacity = new_capacity;
         Ok(())
     }
@@ -271,12 +385,9 @@ impl Slot {
     pub fn total_value(&self) -> Option<Money> {
         if let Some(soda_type) = &self.soda_type {
             if self.quantity > 0 {
-                match soda_type.price() * (self.quantity as i64) {
-                    Ok(total) => Some(total),
-                    Err(_) => None,
-                }
+                (soda_type.price() * (self.quantity as i64)).ok()
             } else {
-                Some(Money::zero())
+                Some(Money::zero_in(soda_type.price().currency()))
             }
         } else {
             None
@@ -293,7 +404,7 @@ impl Slot {
     pub fn can_dispense(&self, soda: &Soda) -> bool {
         self.is_enabled && 
         !self.is_empty() && 
-        self.soda_type.as_ref().map_or(false, |slot_soda| slot_soda.is_same_type(soda))
+        self.soda_type.as_ref().is_some_and(|slot_soda| slot_soda.is_same_type(soda))
     }
 }
 
@@ -357,6 +468,7 @@ impl fmt::Display for SlotError {
             SlotError::SodaTypeMismatch => write!(f, "Soda type mismatch"),
             SlotError::InsufficientQuantity => write!(f, "Insufficient quantity"),
             SlotError::InvalidSlotId => write!(f, "Invalid slot ID"),
+            SlotError::InvalidPrice => write!(f, "Invalid price"),
         }
     }
 }
@@ -444,6 +556,49 @@ mod tests {
         assert_eq!(result.unwrap_err(), SlotError::SodaTypeMismatch);
     }
 
+    #[test]
+    fn test_assign_product() {
+        let sku = Sku::new("COLA-12OZ").unwrap();
+        let mut product = Product::new(sku.clone(), create_test_soda(), None).unwrap();
+        let mut slot = Slot::new(SlotId::new(1), 20).unwrap();
+
+        slot.assign_product(&product).unwrap();
+        slot.add_sodas(5).unwrap();
+        product.set_default_price(crate::domain::value_objects::money::Money::from_cents(175)).unwrap();
+        slot.assign_product(&product).unwrap();
+
+        assert!(slot.sells(&sku));
+        assert_eq!(slot.soda_type().unwrap().price().cents(), 175);
+        assert_eq!(slot.quantity(), 5);
+
+        let other = Product::new(Sku::new("COLA-16OZ").unwrap(), create_test_soda(), None).unwrap();
+        assert_eq!(slot.assign_product(&other), Err(SlotError::SodaTypeMismatch));
+    }
+
+    #[test]
+    fn test_configure_soda_type_forgets_product() {
+        let product = Product::new(Sku::new("COLA-12OZ").unwrap(), create_test_soda(), None).unwrap();
+        let mut slot = Slot::new(SlotId::new(1), 20).unwrap();
+        slot.assign_product(&product).unwrap();
+
+        slot.configure_soda_type(create_test_soda()).unwrap();
+
+        assert_eq!(slot.sku(), None);
+    }
+
+    #[test]
+    fn test_reprice() {
+        let mut slot = Slot::new(SlotId::new(1), 20).unwrap();
+        let price = crate::domain::value_objects::money::Money::from_cents(200);
+        assert_eq!(slot.reprice(price), Err(SlotError::SlotEmpty));
+
+        slot.configure_soda_type(create_test_soda()).unwrap();
+        slot.reprice(price).unwrap();
+        assert_eq!(slot.soda_type().unwrap().price(), price);
+        assert_eq!(slot.reprice(-price), Err(SlotError::InvalidPrice));
+        assert_eq!(slot.soda_type().unwrap().price(), price);
+    }
+
     #[test]
     fn test_add_sodas() {
         let mut slot = Slot::new(SlotId::new(1), 20).unwrap();
@@ -671,4 +826,29 @@ mod tests {
         
         assert_eq!(format!("{}", slot), "Slot 3: Coca-Cola (3 of 10) - Disabled");
     }
+
+    #[test]
+    fn test_low_stock_threshold() {
+        let soda = create_test_soda();
+        let mut slot = Slot::new_with_soda(SlotId::new(1), soda, 3, 10).unwrap();
+        assert_eq!(slot.low_stock_threshold(), 0);
+        assert!(!slot.is_low_on_stock());
+
+        slot.set_low_stock_threshold(3).unwrap();
+
+        assert_eq!(slot.low_stock_threshold(), 3);
+        assert!(slot.is_low_on_stock());
+        slot.add_sodas(1).unwrap();
+        assert!(!slot.is_low_on_stock());
+    }
+
+    #[test]
+    fn test_low_stock_threshold_must_be_below_capacity() {
+        let mut slot = Slot::new(SlotId::new(1), 10).unwrap();
+
+        assert!(slot.set_low_stock_threshold(10).is_err());
+        slot.set_low_stock_threshold(4).unwrap();
+        assert!(slot.set_capacity(4).is_err());
+        assert!(slot.set_capacity(5).is_ok());
+    }
 }
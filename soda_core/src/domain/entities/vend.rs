@@ -0,0 +1,77 @@
+use std::fmt;
+use crate::domain::entities::promotion::AppliedPromotion;
+use crate::domain::entities::slot::SlotId;
+use crate::domain::value_objects::money::Money;
+use crate::domain::value_objects::soda::Soda;
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// A soda the customer paid for that the vending mechanism is still delivering
+///
+/// The price stays in the session's credit until the mechanism confirms the soda dropped,
+/// so a failed vend leaves the customer with their money.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PendingVend {
+    pub slot_id: SlotId,
+    pub soda: Soda,
+    /// What the customer will pay, after the promotion
+    pub price: Money,
+    pub promotion: Option<AppliedPromotion>,
+    pub reserved_at: Timestamp,
+}
+
+/// What the vending mechanism reported when a soda did not reach the customer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VendFailure {
+    /// The motor did not turn, the can is still in its place
+    MotorFault,
+    /// The can got stuck between the slot and the delivery bin
+    Jammed,
+    /// The drop sensor saw nothing, the can may be stuck anywhere
+    DropNotDetected,
+}
+
+impl VendFailure {
+    /// Checks whether the can is still in its place and can be sold again
+    ///
+    /// Otherwise the can is quarantined: it no longer counts as stock until an operator frees it.
+    pub fn keeps_unit_in_slot(&self) -> bool {
+        matches!(self, VendFailure::MotorFault)
+    }
+
+    /// Gets the failure from its name, e.g. "jammed"
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "motorfault" => Some(VendFailure::MotorFault),
+            "jammed" | "jam" => Some(VendFailure::Jammed),
+            "dropnotdetected" => Some(VendFailure::DropNotDetected),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VendFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VendFailure::MotorFault => "motor fault",
+            VendFailure::Jammed => "jammed",
+            VendFailure::DropNotDetected => "drop not detected",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_a_motor_fault_keeps_the_unit() {
+        assert!(VendFailure::MotorFault.keeps_unit_in_slot());
+        assert!(!VendFailure::Jammed.keeps_unit_in_slot());
+        assert!(!VendFailure::DropNotDetected.keeps_unit_in_slot());
+        assert_eq!(VendFailure::from_string("Drop-Not-Detected"), Some(VendFailure::DropNotDetected));
+        assert_eq!(VendFailure::from_string("stuck"), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use uuid::Uuid;
+use crate::domain::aggregates::soda_machine::{SodaMachineEvent, SodaMachineId};
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// Version of the `SodaMachineEvent` layout, bumped whenever an event changes shape
+///
+/// * 1 - the first published layout
+/// * 2 - `SodaDispensed` records the promotion taken off the price, missing in version 1 envelopes
+/// * 3 - `CashCollected` records the float kept in the cash box, and `CashUnloaded` takes change out
+pub const EVENT_SCHEMA_VERSION: u32 = 3;
+
+/// A domain event together with the metadata downstream consumers need to order and trace it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventEnvelope {
+    /// The machine the event happened on
+    pub machine_id: SodaMachineId,
+    /// Position of the event in the machine's history, starting at 1 and without gaps
+    pub sequence: u64,
+    /// When the command that caused the event ran
+    pub occurred_at: Timestamp,
+    /// Version of the event layout, see `EVENT_SCHEMA_VERSION`
+    pub schema_version: u32,
+    /// Shared by all events caused by the same command
+    pub correlation_id: CorrelationId,
+    /// The event itself
+    pub event: SodaMachineEvent,
+}
+
+/// Identifies the command a group of events came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CorrelationId(String);
+
+impl EventEnvelope {
+    /// Wraps an event in an envelope with the current schema version
+    /// 
+    /// # Arguments
+    /// * `machine_id` - The machine the event happened on
+    /// * `sequence` - The event's sequence number within the machine
+    /// * `occurred_at` - When the event happened
+    /// * `correlation_id` - The command the event came from
+    /// * `event` - The event to wrap
+    /// 
+    /// # Returns
+    /// * `EventEnvelope` - The wrapped event
+    pub fn new(
+        machine_id: SodaMachineId,
+        sequence: u64,
+        occurred_at: Timestamp,
+        correlation_id: CorrelationId,
+        event: SodaMachineEvent,
+    ) -> Self {
+        EventEnvelope {
+            machine_id,
+            sequence,
+            occurred_at,
+            schema_version: EVENT_SCHEMA_VERSION,
+            correlation_id,
+            event,
+        }
+    }
+
+    /// Wraps the events of one command, as handed over by `SodaMachine::take_uncommitted_events`
+    /// 
+    /// # Arguments
+    /// * `machine_id` - The machine the events happened on
+    /// * `events` - The events with their sequence numbers
+    /// * `occurred_at` - When the command ran
+    /// * `correlation_id` - The command the events came from
+    /// 
+    /// # Returns
+    /// * `Vec<EventEnvelope>` - The wrapped events, in the same order
+    pub fn wrap_all(
+        machine_id: SodaMachineId,
+        events: Vec<(u64, SodaMachineEvent)>,
+        occurred_at: Timestamp,
+        correlation_id: &CorrelationId,
+    ) -> Vec<Self> {
+        events.into_iter()
+            .map(|(sequence, event)| {
+                EventEnvelope::new(machine_id, sequence, occurred_at, correlation_id.clone(), event)
+            })
+            .collect()
+    }
+}
+
+impl CorrelationId {
+    /// Creates a correlation ID from an existing value, e.g. one received from a caller
+    pub fn new(value: impl Into<String>) -> Self {
+        CorrelationId(value.into())
+    }
+
+    /// Creates a new random correlation ID
+    pub fn generate() -> Self {
+        CorrelationId(Uuid::new_v4().to_string())
+    }
+
+    /// Gets the correlation ID as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for EventEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] machine {} #{} (v{}, {}): {:?}",
+            self.occurred_at,
+            self.machine_id,
+            self.sequence,
+            self.schema_version,
+            self.correlation_id,
+            self.event
+        )
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let envelope = EventEnvelope::new(
+            SodaMachineId::new(1),
+            3,
+            Timestamp::from_secs(10),
+            CorrelationId::new("abc"),
+            SodaMachineEvent::MachineEnabled,
+        );
+
+        assert_eq!(envelope.machine_id, SodaMachineId::new(1));
+        assert_eq!(envelope.sequence, 3);
+        assert_eq!(envelope.occurred_at, Timestamp::from_secs(10));
+        assert_eq!(envelope.schema_version, EVENT_SCHEMA_VERSION);
+        assert_eq!(envelope.correlation_id.as_str(), "abc");
+    }
+
+    #[test]
+    fn test_wrap_all() {
+        let correlation_id = CorrelationId::new("abc");
+        let events = vec![(4, SodaMachineEvent::MachineDisabled), (5, SodaMachineEvent::MachineEnabled)];
+
+        let envelopes = EventEnvelope::wrap_all(SodaMachineId::new(2), events, Timestamp::from_secs(10), &correlation_id);
+
+        assert_eq!(envelopes.len(), 2);
+        assert_eq!(envelopes[0].sequence, 4);
+        assert_eq!(envelopes[0].event, SodaMachineEvent::MachineDisabled);
+        assert_eq!(envelopes[1].sequence, 5);
+        assert!(envelopes.iter().all(|envelope| envelope.correlation_id == correlation_id));
+    }
+
+    #[test]
+    fn test_generate_correlation_id() {
+        let first = CorrelationId::generate();
+        let second = CorrelationId::generate();
+
+        assert_ne!(first, second);
+        assert_eq!(first.as_str().len(), 36);
+    }
+
+    #[test]
+    fn test_display() {
+        let envelope = EventEnvelope::new(
+            SodaMachineId::new(1),
+            3,
+            Timestamp::EPOCH,
+            CorrelationId::new("abc"),
+            SodaMachineEvent::MachineEnabled,
+        );
+
+        assert_eq!(format!("{}", envelope), "[1970-01-01T00:00:00.000Z] machine 1 #3 (v3, abc): MachineEnabled");
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use crate::domain::value_objects::denomination::{AcceptedDenominations, DenominationKind};
+use crate::domain::value_objects::money::{Currency, Money};
+
+/// One message of a coin acceptor or bill validator
+///
+/// Devices report a channel code instead of an amount, each channel being programmed for one
+/// denomination. Messages are one line each, e.g. `COIN 3`, `BILL 2 COUNTERFEIT` or `COIN REJECT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptorEvent {
+    /// The device recognized money in one of its channels
+    Inserted {
+        kind: DenominationKind,
+        channel: u8,
+        /// The device doubts the money is genuine
+        counterfeit: bool,
+    },
+    /// The device could not recognize the money and handed it back by itself
+    Rejected { kind: DenominationKind },
+}
+
+/// Which denomination each channel of the coin acceptor and bill validator is programmed for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelTable {
+    currency: Currency,
+    coins: BTreeMap<u8, Money>,
+    bills: BTreeMap<u8, Money>,
+}
+
+/// What to do with money a device reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptorDecision {
+    /// Credit the coin or bill to the customer
+    Credit { kind: DenominationKind, amount: Money },
+    /// Hand the money back to the customer
+    Return { reason: String },
+}
+
+/// Turns device messages into money the machine may credit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoneyAcceptor {
+    channels: ChannelTable,
+    accepted: AcceptedDenominations,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptorProtocolError {
+    /// The line is not a message the devices send
+    InvalidMessage(String),
+}
+
+impl ChannelTable {
+    /// Creates a table with no channels programmed
+    pub fn new(currency: Currency) -> Self {
+        ChannelTable { currency, coins: BTreeMap::new(), bills: BTreeMap::new() }
+    }
+
+    /// Gets the channels the devices are shipped with, which recognize larger bills than most
+    /// machines take, e.g. US bills up to $100
+    pub fn standard(currency: Currency) -> Self {
+        let (coins, bills): (&[i64], &[i64]) = match currency {
+            Currency::USD => (&[5, 10, 25, 100], &[100, 500, 1000, 2000, 5000, 10000]),
+            Currency::EUR => (&[5, 10, 20, 50, 100, 200], &[500, 1000, 2000, 5000, 10000]),
+            Currency::GBP => (&[5, 10, 20, 50, 100, 200], &[500, 1000, 2000, 5000]),
+            Currency::JPY => (&[10, 50, 100, 500], &[1000, 2000, 5000, 10000]),
+        };
+
+        let mut table = ChannelTable::new(currency);
+        for (channel, value) in (1..).zip(coins) {
+            table = table.with_channel(DenominationKind::Coin, channel, Money::from_minor_units(*value, currency));
+        }
+        for (channel, value) in (1..).zip(bills) {
+            table = table.with_channel(DenominationKind::Bill, channel, Money::from_minor_units(*value, currency));
+        }
+        table
+    }
+
+    /// Programs a channel of the coin acceptor or bill validator for a denomination
+    pub fn with_channel(mut self, kind: DenominationKind, channel: u8, denomination: Money) -> Self {
+        match kind {
+            DenominationKind::Coin => self.coins.insert(channel, denomination),
+            DenominationKind::Bill => self.bills.insert(channel, denomination),
+        };
+        self
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Gets the denomination a channel is programmed for
+    pub fn denomination(&self, kind: DenominationKind, channel: u8) -> Option<Money> {
+        match kind {
+            DenominationKind::Coin => self.coins.get(&channel).copied(),
+            DenominationKind::Bill => self.bills.get(&channel).copied(),
+        }
+    }
+}
+
+impl MoneyAcceptor {
+    pub fn new(channels: ChannelTable, accepted: AcceptedDenominations) -> Self {
+        MoneyAcceptor { channels, accepted }
+    }
+
+    /// Uses the standard channels and takes the standard denominations of a currency
+    pub fn standard(currency: Currency) -> Self {
+        MoneyAcceptor::new(ChannelTable::standard(currency), AcceptedDenominations::standard(currency))
+    }
+
+    pub fn channels(&self) -> &ChannelTable {
+        &self.channels
+    }
+
+    pub fn accepted(&self) -> &AcceptedDenominations {
+        &self.accepted
+    }
+
+    /// Decides whether the money a device reported is credited or handed back
+    ///
+    /// # Arguments
+    /// * `event` - The message of the device
+    ///
+    /// # Returns
+    /// * `AcceptorDecision` - Credit for a genuine coin or bill the machine takes, Return otherwise
+    pub fn decide(&self, event: &AcceptorEvent) -> AcceptorDecision {
+        self.decide_with(event, &self.accepted)
+    }
+
+    /// Decides as `decide` does, with the given denominations instead of the configured ones,
+    /// e.g. those of a machine in strict mode
+    ///
+    /// # Arguments
+    /// * `event` - The message of the device
+    /// * `accepted` - The coins and bills that may be credited
+    ///
+    /// # Returns
+    /// * `AcceptorDecision` - Credit for a genuine coin or bill among the given ones, Return otherwise
+    pub fn decide_with(&self, event: &AcceptorEvent, accepted: &AcceptedDenominations) -> AcceptorDecision {
+        let (kind, channel) = match *event {
+            AcceptorEvent::Rejected { kind } => {
+                return AcceptorDecision::Return { reason: format!("{} not recognized", kind) };
+            },
+            AcceptorEvent::Inserted { kind, counterfeit: true, .. } => {
+                return AcceptorDecision::Return { reason: format!("{} flagged as counterfeit", kind) };
+            },
+            AcceptorEvent::Inserted { kind, channel, counterfeit: false } => (kind, channel),
+        };
+
+        match self.channels.denomination(kind, channel) {
+            None => AcceptorDecision::Return { reason: format!("unknown {} channel {}", kind, channel) },
+            Some(amount) if !accepted.accepts_as(kind, amount) => {
+                AcceptorDecision::Return { reason: format!("{} {}s are not accepted", amount, kind) }
+            },
+            Some(amount) => AcceptorDecision::Credit { kind, amount },
+        }
+    }
+}
+
+impl FromStr for AcceptorEvent {
+    type Err = AcceptorProtocolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AcceptorProtocolError::InvalidMessage(s.trim().to_string());
+        let tokens: Vec<String> = s.split_whitespace().map(|token| token.to_uppercase()).collect();
+        let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+
+        let kind = match tokens.first() {
+            Some(&"COIN") => DenominationKind::Coin,
+            Some(&"BILL") => DenominationKind::Bill,
+            _ => return Err(invalid()),
+        };
+
+        match tokens[1..] {
+            ["REJECT"] => Ok(AcceptorEvent::Rejected { kind }),
+            [channel] => Ok(AcceptorEvent::Inserted { kind, channel: channel.parse().map_err(|_| invalid())?, counterfeit: false }),
+            [channel, "COUNTERFEIT"] => {
+                Ok(AcceptorEvent::Inserted { kind, channel: channel.parse().map_err(|_| invalid())?, counterfeit: true })
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for AcceptorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let device = |kind: &DenominationKind| match kind {
+            DenominationKind::Coin => "COIN",
+            DenominationKind::Bill => "BILL",
+        };
+
+        match self {
+            AcceptorEvent::Inserted { kind, channel, counterfeit: false } => write!(f, "{} {}", device(kind), channel),
+            AcceptorEvent::Inserted { kind, channel, counterfeit: true } => {
+                write!(f, "{} {} COUNTERFEIT", device(kind), channel)
+            },
+            AcceptorEvent::Rejected { kind } => write!(f, "{} REJECT", device(kind)),
+        }
+    }
+}
+
+impl fmt::Display for AcceptorProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcceptorProtocolError::InvalidMessage(line) => write!(f, "Invalid acceptor message: '{}'", line),
+        }
+    }
+}
+
+impl std::error::Error for AcceptorProtocolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_parsed() {
+        assert_eq!(
+            "COIN 3".parse::<AcceptorEvent>().unwrap(),
+            AcceptorEvent::Inserted { kind: DenominationKind::Coin, channel: 3, counterfeit: false }
+        );
+        assert_eq!(
+            " bill 2  counterfeit ".parse::<AcceptorEvent>().unwrap(),
+            AcceptorEvent::Inserted { kind: DenominationKind::Bill, channel: 2, counterfeit: true }
+        );
+        assert_eq!("COIN REJECT".parse::<AcceptorEvent>().unwrap(), AcceptorEvent::Rejected { kind: DenominationKind::Coin });
+        assert_eq!("BILL 2 COUNTERFEIT".parse::<AcceptorEvent>().unwrap().to_string(), "BILL 2 COUNTERFEIT");
+
+        for line in ["", "COIN", "COIN 300", "TOKEN 1", "BILL 2 WORN", "COIN -1"] {
+            assert_eq!(line.parse::<AcceptorEvent>(), Err(AcceptorProtocolError::InvalidMessage(line.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_decide_credits_only_accepted_genuine_money() {
+        let acceptor = MoneyAcceptor::standard(Currency::USD);
+        let decide = |line: &str| acceptor.decide(&line.parse().unwrap());
+
+        assert_eq!(decide("COIN 3"), AcceptorDecision::Credit { kind: DenominationKind::Coin, amount: Money::from_cents(25) });
+        assert_eq!(decide("BILL 4"), AcceptorDecision::Credit { kind: DenominationKind::Bill, amount: Money::from_cents(2000) });
+        assert_eq!(decide("BILL 5"), AcceptorDecision::Return { reason: "$50.00 bills are not accepted".to_string() });
+        assert_eq!(decide("COIN 9"), AcceptorDecision::Return { reason: "unknown coin channel 9".to_string() });
+        assert_eq!(decide("BILL 1 COUNTERFEIT"), AcceptorDecision::Return { reason: "bill flagged as counterfeit".to_string() });
+        assert_eq!(decide("COIN REJECT"), AcceptorDecision::Return { reason: "coin not recognized".to_string() });
+
+        let quarters_only = MoneyAcceptor::new(
+            ChannelTable::standard(Currency::USD),
+            AcceptedDenominations::new(Currency::USD, &[Money::from_cents(25)], &[]).unwrap(),
+        );
+        assert_eq!(
+            quarters_only.decide(&"COIN 1".parse().unwrap()),
+            AcceptorDecision::Return { reason: "$0.05 coins are not accepted".to_string() }
+        );
+
+        let fifties = AcceptedDenominations::new(Currency::USD, &[], &[Money::from_cents(5000)]).unwrap();
+        assert_eq!(
+            quarters_only.decide_with(&"BILL 5".parse().unwrap(), &fifties),
+            AcceptorDecision::Credit { kind: DenominationKind::Bill, amount: Money::from_cents(5000) }
+        );
+        assert_eq!(
+            quarters_only.decide_with(&"COIN 3".parse().unwrap(), &fifties),
+            AcceptorDecision::Return { reason: "$0.25 coins are not accepted".to_string() }
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::domain::value_objects::money::Money;
+
+/// Represents an itemized amount of change: how many of each coin/bill to pay out
+/// Items are kept largest denomination first so the hardware can eject them in order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChangeBreakdown {
+    /// Denominations and their counts, largest denomination first
+    items: Vec<(Money, u32)>,
+}
+
+/// Errors that can occur while calculating change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeError {
+    InvalidAmount(Money),
+    NoExactSolution(Money),
+}
+
+/// Calculates the change to pay out from a limited set of coins and bills
+/// Uses dynamic programming so that non-canonical coin systems (where picking
+/// the largest coin first fails) still get the minimal number of coins
+pub struct ChangeCalculator;
+
+impl ChangeBreakdown {
+    /// Creates an empty breakdown (no change to pay out)
+    pub fn empty() -> Self {
+        ChangeBreakdown { items: Vec::new() }
+    }
+
+    /// Creates a breakdown from denominations and counts
+    ///
+    /// Zero counts are dropped and duplicate denominations are merged
+    ///
+    /// # Arguments
+    /// * `items` - The denominations and how many of each to pay out
+    ///
+    /// # Returns
+    /// * `ChangeBreakdown` - The breakdown, largest denomination first
+    pub fn new(items: Vec<(Money, u32)>) -> Self {
+        let mut merged: BTreeMap<Money, u32> = BTreeMap::new();
+        for (denomination, count) in items {
+            if count > 0 {
+                *merged.entry(denomination).or_insert(0) += count;
+            }
+        }
+
+        ChangeBreakdown {
+            items: merged.into_iter().rev().collect(),
+        }
+    }
+
+    /// Gets the denominations and counts, largest denomination first
+    pub fn items(&self) -> &[(Money, u32)] {
+        &self.items
+    }
+
+    /// Gets how many coins/bills of a denomination are in the breakdown
+    pub fn count(&self, denomination: Money) -> u32 {
+        self.items.iter()
+            .find(|(item, _)| *item == denomination)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Gets the total number of coins/bills in the breakdown
+    pub fn piece_count(&self) -> u32 {
+        self.items.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Gets the total value of the breakdown (zero US dollars when empty)
+    pub fn total(&self) -> Money {
+        let zero = self.items.first()
+            .map_or(Money::zero(), |(denomination, _)| Money::zero_in(denomination.currency()));
+
+        self.items.iter()
+            .filter_map(|(denomination, count)| (*denomination * (*count as i64)).ok())
+            .fold(zero, |acc, value| {
+                (acc + value).unwrap_or(acc)
+            })
+    }
+
+    /// Checks if the breakdown pays out nothing
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl ChangeCalculator {
+    /// Calculates the minimal-coin breakdown for an amount from the coins/bills available
+    ///
+    /// # Arguments
+    /// * `available` - The denominations available and how many of each can be used
+    /// * `amount` - The amount of change to pay out
+    ///
+    /// # Returns
+    /// * `Result<ChangeBreakdown, ChangeError>` - The breakdown using the fewest coins/bills,
+    ///   or Err if the exact amount cannot be made
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use soda_core::domain::services::change_calculator::ChangeCalculator;
+    /// use soda_core::domain::value_objects::money::Money;
+    ///
+    /// let available = BTreeMap::from([(Money::from_cents(25), 1), (Money::from_cents(10), 3)]);
+    /// let change = ChangeCalculator::calculate(&available, Money::from_cents(30)).unwrap();
+    /// assert_eq!(change.count(Money::from_cents(10)), 3);
+    /// ```
+    pub fn calculate(available: &BTreeMap<Money, u32>, amount: Money) -> Result<ChangeBreakdown, ChangeError> {
+        if amount.is_negative() {
+            return Err(ChangeError::InvalidAmount(amount));
+        }
+
+        if amount.is_zero() {
+            return Ok(ChangeBreakdown::empty());
+        }
+
+        let denominations: Vec<(i64, u32)> = available.iter()
+            .filter(|(denomination, count)| {
+                denomination.same_currency(&amount) && denomination.is_positive() && **count > 0
+            })
+            .map(|(denomination, count)| (denomination.cents(), *count))
+            .collect();
+
+        // Work in units of the greatest common divisor to keep the table small
+        let unit = denominations.iter().fold(0, |acc, (value, _)| gcd(acc, *value));
+        if unit == 0 || amount.cents() % unit != 0 {
+            return Err(ChangeError::NoExactSolution(amount));
+        }
+        let target = usize::try_from(amount.cents() / unit)
+            .map_err(|_| ChangeError::NoExactSolution(amount))?;
+
+        // Split each denomination's count into power-of-two bundles so every
+        // count from 0 up to the available count can be picked exactly once
+        let mut bundles: Vec<(usize, usize, u32)> = Vec::new(); // (denomination index, value, pieces)
+        for (index, (value, count)) in denominations.iter().enumerate() {
+            let value = (*value / unit) as usize;
+            let usable = (*count as usize).min(target / value) as u32;
+            let mut remaining = usable;
+            let mut size = 1u32;
+            while remaining > 0 {
+                let pieces = size.min(remaining);
+                bundles.push((index, value * pieces as usize, pieces));
+                remaining -= pieces;
+                size = size.saturating_mul(2);
+            }
+        }
+
+        // fewest[a] = fewest pieces that sum to exactly a; taken[b][a] records the choice
+        let mut fewest = vec![u32::MAX; target + 1];
+        fewest[0] = 0;
+        let mut taken = vec![vec![false; target + 1]; bundles.len()];
+
+        for (bundle, (_, value, pieces)) in bundles.iter().enumerate() {
+            for sum in (*value..=target).rev() {
+                let previous = fewest[sum - value];
+                if previous != u32::MAX && previous + pieces < fewest[sum] {
+                    fewest[sum] = previous + pieces;
+                    taken[bundle][sum] = true;
+                }
+            }
+        }
+
+        if fewest[target] == u32::MAX {
+            return Err(ChangeError::NoExactSolution(amount));
+        }
+
+        // Walk the choices backwards to recover which bundles were used
+        let mut counts = vec![0u32; denominations.len()];
+        let mut sum = target;
+        for (bundle, (index, value, pieces)) in bundles.iter().enumerate().rev() {
+            if taken[bundle][sum] {
+                counts[*index] += pieces;
+                sum -= value;
+            }
+        }
+
+        let items = denominations.iter()
+            .zip(counts)
+            .map(|((value, _), count)| (Money::from_minor_units(*value, amount.currency()), count))
+            .collect();
+
+        Ok(ChangeBreakdown::new(items))
+    }
+
+    /// Checks if an amount can be paid out exactly from the coins/bills available
+    pub fn can_make(available: &BTreeMap<Money, u32>, amount: Money) -> bool {
+        Self::calculate(available, amount).is_ok()
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+impl fmt::Display for ChangeBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no change");
+        }
+
+        let items: Vec<String> = self.items.iter()
+            .map(|(denomination, count)| format!("{} x {}", count, denomination))
+            .collect();
+
+        write!(f, "{}", items.join(", "))
+    }
+}
+
+impl fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeError::InvalidAmount(amount) => write!(f, "Invalid change amount: {}", amount),
+            ChangeError::NoExactSolution(amount) => write!(f, "No exact change possible for {}", amount),
+        }
+    }
+}
+
+impl std::error::Error for ChangeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::money::Currency;
+
+    fn coins(items: &[(i64, u32)]) -> BTreeMap<Money, u32> {
+        items.iter()
+            .map(|(cents, count)| (Money::from_cents(*cents), *count))
+            .collect()
+    }
+
+    #[test]
+    fn test_breakdown_new_merges_and_orders() {
+        let breakdown = ChangeBreakdown::new(vec![
+            (Money::from_cents(10), 1),
+            (Money::from_cents(25), 2),
+            (Money::from_cents(10), 2),
+            (Money::from_cents(5), 0),
+        ]);
+
+        assert_eq!(breakdown.items(), &[(Money::from_cents(25), 2), (Money::from_cents(10), 3)]);
+        assert_eq!(breakdown.piece_count(), 5);
+        assert_eq!(breakdown.total(), Money::from_cents(80));
+        assert_eq!(breakdown.count(Money::from_cents(5)), 0);
+    }
+
+
+    #[test]
+    fn test_calculate_zero() {
+        let breakdown = ChangeCalculator::calculate(&coins(&[(25, 4)]), Money::zero()).unwrap();
+        assert!(breakdown.is_empty());
+    }
+
+    #[test]
+    fn test_calculate_negative() {
+        let result = ChangeCalculator::calculate(&coins(&[(25, 4)]), Money::from_cents(-25));
+        assert_eq!(result.unwrap_err(), ChangeError::InvalidAmount(Money::from_cents(-25)));
+    }
+
+    #[test]
+    fn test_calculate_canonical() {
+        let available = coins(&[(100, 5), (25, 10), (10, 10), (5, 10)]);
+        let breakdown = ChangeCalculator::calculate(&available, Money::from_cents(165)).unwrap();
+
+        assert_eq!(breakdown.items(), &[
+            (Money::from_cents(100), 1),
+            (Money::from_cents(25), 2),
+            (Money::from_cents(10), 1),
+            (Money::from_cents(5), 1),
+        ]);
+        assert_eq!(breakdown.total(), Money::from_cents(165));
+    }
+
+    #[test]
+    fn test_calculate_non_canonical_system() {
+        // Greedy would pay 4 + 1 + 1; the optimum is 3 + 3
+        let available = coins(&[(4, 10), (3, 10), (1, 10)]);
+        let breakdown = ChangeCalculator::calculate(&available, Money::from_cents(6)).unwrap();
+
+        assert_eq!(breakdown.items(), &[(Money::from_cents(3), 2)]);
+        assert_eq!(breakdown.piece_count(), 2);
+    }
+
+    #[test]
+    fn test_calculate_limited_counts_where_greedy_fails() {
+        // Greedy takes the quarter and gets stuck with 5 cents to pay
+        let available = coins(&[(25, 1), (10, 3)]);
+        let breakdown = ChangeCalculator::calculate(&available, Money::from_cents(30)).unwrap();
+
+        assert_eq!(breakdown.items(), &[(Money::from_cents(10), 3)]);
+    }
+
+    #[test]
+    fn test_calculate_respects_counts() {
+        let available = coins(&[(25, 1), (10, 2), (5, 10)]);
+        let breakdown = ChangeCalculator::calculate(&available, Money::from_cents(75)).unwrap();
+
+        assert_eq!(breakdown.count(Money::from_cents(25)), 1);
+        assert_eq!(breakdown.count(Money::from_cents(10)), 2);
+        assert_eq!(breakdown.count(Money::from_cents(5)), 6);
+        assert_eq!(breakdown.total(), Money::from_cents(75));
+    }
+
+    #[test]
+    fn test_calculate_no_exact_solution() {
+        let available = coins(&[(25, 4), (10, 1)]);
+
+        let result = ChangeCalculator::calculate(&available, Money::from_cents(15));
+        assert_eq!(result.unwrap_err(), ChangeError::NoExactSolution(Money::from_cents(15)));
+        assert!(!ChangeCalculator::can_make(&available, Money::from_cents(15)));
+
+        let result = ChangeCalculator::calculate(&available, Money::from_cents(7));
+        assert_eq!(result.unwrap_err(), ChangeError::NoExactSolution(Money::from_cents(7)));
+    }
+
+    #[test]
+    fn test_calculate_nothing_available() {
+        let result = ChangeCalculator::calculate(&BTreeMap::new(), Money::from_cents(5));
+        assert_eq!(result.unwrap_err(), ChangeError::NoExactSolution(Money::from_cents(5)));
+    }
+
+    #[test]
+    fn test_calculate_large_amount() {
+        let available = coins(&[(2000, 10), (500, 10), (100, 50), (25, 200)]);
+        let breakdown = ChangeCalculator::calculate(&available, Money::from_cents(4375)).unwrap();
+
+        assert_eq!(breakdown.items(), &[
+            (Money::from_cents(2000), 2),
+            (Money::from_cents(100), 3),
+            (Money::from_cents(25), 3),
+        ]);
+    }
+
+    #[test]
+    fn test_calculate_ignores_other_currencies() {
+        let available = BTreeMap::from([
+            (Money::from_minor_units(100, Currency::JPY), 5),
+            (Money::from_minor_units(50, Currency::EUR), 5),
+        ]);
+        let amount = Money::from_minor_units(300, Currency::JPY);
+
+        let breakdown = ChangeCalculator::calculate(&available, amount).unwrap();
+        assert_eq!(breakdown.items(), &[(Money::from_minor_units(100, Currency::JPY), 3)]);
+        assert_eq!(breakdown.total(), amount);
+
+        let result = ChangeCalculator::calculate(&available, Money::from_minor_units(50, Currency::GBP));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let breakdown = ChangeBreakdown::new(vec![(Money::from_cents(25), 2), (Money::from_cents(100), 1)]);
+        assert_eq!(format!("{}", breakdown), "1 x $1.00, 2 x $0.25");
+        assert_eq!(format!("{}", ChangeBreakdown::empty()), "no change");
+    }
+}
@@ -0,0 +1,220 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+use crate::domain::entities::sale::Sale;
+use crate::domain::value_objects::money::{Currency, Money, MoneyError};
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// How long each line of a sales report covers, in UTC calendar terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportPeriod {
+    Daily,
+    /// Weeks start on Monday
+    Weekly,
+    Monthly,
+}
+
+/// What the sales of a report are grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportDimension {
+    /// The soda as it is stocked, e.g. "Cola Cola - 12 oz (Caffeinated)"
+    Product,
+    Flavor,
+    Size,
+    Machine,
+    /// A slot of a machine, e.g. "machine 1 slot 2"
+    Slot,
+}
+
+/// The units and revenue of one group of sales during one period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SalesReportLine {
+    pub period_start: Timestamp,
+    /// Start of the next period
+    pub period_end: Timestamp,
+    /// What the sales were grouped by, e.g. the flavor
+    pub key: String,
+    pub units: u32,
+    /// What the customers paid, after promotions
+    pub revenue: Money,
+}
+
+/// Sums up the sales ledger for the people deciding what to stock
+pub struct SalesReporter;
+
+impl ReportPeriod {
+    /// Gets the period from its name, e.g. "weekly"
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "daily" | "day" => Some(ReportPeriod::Daily),
+            "weekly" | "week" => Some(ReportPeriod::Weekly),
+            "monthly" | "month" => Some(ReportPeriod::Monthly),
+            _ => None,
+        }
+    }
+
+    /// Gets the start of the period a time falls in
+    pub fn start_of(&self, timestamp: Timestamp) -> Timestamp {
+        match self {
+            ReportPeriod::Daily => timestamp.start_of_day(),
+            ReportPeriod::Weekly => timestamp.start_of_week(),
+            ReportPeriod::Monthly => timestamp.start_of_month(),
+        }
+    }
+
+    /// Gets the start of the period following the one starting at `start`
+    pub fn next_start(&self, start: Timestamp) -> Timestamp {
+        const DAY: Duration = Duration::from_secs(86_400);
+        match self {
+            ReportPeriod::Daily => start.plus(DAY),
+            ReportPeriod::Weekly => start.plus(DAY * 7),
+            // Every month is shorter than 32 days
+            ReportPeriod::Monthly => start.start_of_month().plus(DAY * 32).start_of_month(),
+        }
+    }
+}
+
+impl ReportDimension {
+    /// Gets the dimension from its name, e.g. "flavor"
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "product" => Some(ReportDimension::Product),
+            "flavor" => Some(ReportDimension::Flavor),
+            "size" => Some(ReportDimension::Size),
+            "machine" => Some(ReportDimension::Machine),
+            "slot" => Some(ReportDimension::Slot),
+            _ => None,
+        }
+    }
+
+    /// Gets the group a sale belongs to
+    pub fn key_of(&self, sale: &Sale) -> String {
+        match self {
+            ReportDimension::Product => sale.soda.description(),
+            ReportDimension::Flavor => sale.soda.flavor().to_string(),
+            ReportDimension::Size => sale.soda.size().to_string(),
+            ReportDimension::Machine => format!("machine {}", sale.machine_id),
+            ReportDimension::Slot => format!("machine {} slot {}", sale.machine_id, sale.slot_id.value()),
+        }
+    }
+}
+
+impl SalesReporter {
+    /// Sums the sales into one line per period, group and currency
+    ///
+    /// # Arguments
+    /// * `sales` - The sales to report on, in any order
+    /// * `period` - How long each line covers
+    /// * `dimension` - What the sales are grouped by
+    ///
+    /// # Returns
+    /// * `Result<Vec<SalesReportLine>, MoneyError>` - Ok(lines) ordered by period, then by currency
+    ///   and the largest revenue first
+    pub fn summarize(sales: &[Sale], period: ReportPeriod, dimension: ReportDimension) -> Result<Vec<SalesReportLine>, MoneyError> {
+        let mut totals: BTreeMap<(Timestamp, Currency, String), (u32, Money)> = BTreeMap::new();
+        for sale in sales {
+            let currency = sale.price.currency();
+            let (units, revenue) = totals
+                .entry((period.start_of(sale.sold_at), currency, dimension.key_of(sale)))
+                .or_insert((0, Money::zero_in(currency)));
+            *units += 1;
+            *revenue = (*revenue + sale.price)?;
+        }
+
+        let mut lines: Vec<SalesReportLine> = totals.into_iter()
+            .map(|((period_start, _, key), (units, revenue))| SalesReportLine {
+                period_start,
+                period_end: period.next_start(period_start),
+                key,
+                units,
+                revenue,
+            })
+            .collect();
+        lines.sort_by_key(|line| (line.period_start, line.revenue.currency(), Reverse(line.revenue.cents())));
+
+        Ok(lines)
+    }
+}
+
+impl fmt::Display for ReportPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReportPeriod::Daily => "Daily",
+            ReportPeriod::Weekly => "Weekly",
+            ReportPeriod::Monthly => "Monthly",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ReportDimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReportDimension::Product => "Product",
+            ReportDimension::Flavor => "Flavor",
+            ReportDimension::Size => "Size",
+            ReportDimension::Machine => "Machine",
+            ReportDimension::Slot => "Slot",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::aggregates::soda_machine::SodaMachineId;
+    use crate::domain::entities::slot::SlotId;
+    use crate::domain::value_objects::soda::{Soda, SodaFlavor, SodaSize};
+
+    fn sale(machine_id: u32, slot_id: u32, flavor: SodaFlavor, price: Money, sold_at: &str) -> Sale {
+        Sale {
+            machine_id: SodaMachineId::new(machine_id),
+            sequence: 0,
+            slot_id: SlotId::new(slot_id),
+            soda: Soda::new(flavor.to_string(), flavor, SodaSize::Medium, price, false, false).unwrap(),
+            price,
+            promotion: None,
+            sold_at: sold_at.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_next_start_of_each_period() {
+        let start: Timestamp = "2024-01-31T00:00:00Z".parse().unwrap();
+
+        assert_eq!(ReportPeriod::Daily.next_start(start).to_string(), "2024-02-01T00:00:00.000Z");
+        assert_eq!(ReportPeriod::Weekly.next_start(start).to_string(), "2024-02-07T00:00:00.000Z");
+        assert_eq!(ReportPeriod::Monthly.next_start(start).to_string(), "2024-02-01T00:00:00.000Z");
+        assert_eq!(ReportPeriod::Monthly.next_start("2024-02-01T00:00:00Z".parse().unwrap()).to_string(), "2024-03-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_summarize_groups_by_period_and_key() {
+        let sales = vec![
+            sale(1, 1, SodaFlavor::Cola, Money::from_cents(150), "2024-03-04T09:00:00Z"),
+            sale(2, 1, SodaFlavor::Cola, Money::from_cents(150), "2024-03-05T18:00:00Z"),
+            sale(1, 2, SodaFlavor::Orange, Money::from_cents(400), "2024-03-06T12:00:00Z"),
+            sale(1, 1, SodaFlavor::Cola, Money::from_cents(150), "2024-03-11T09:00:00Z"),
+            sale(3, 1, SodaFlavor::Cola, Money::from_minor_units(200, Currency::EUR), "2024-03-04T10:00:00Z"),
+        ];
+
+        let lines = SalesReporter::summarize(&sales, ReportPeriod::Weekly, ReportDimension::Flavor).unwrap();
+
+        let summary: Vec<(String, &str, u32, Money)> = lines.iter()
+            .map(|line| (line.period_start.to_string(), line.key.as_str(), line.units, line.revenue))
+            .collect();
+        assert_eq!(summary, vec![
+            ("2024-03-04T00:00:00.000Z".to_string(), "Orange", 1, Money::from_cents(400)),
+            ("2024-03-04T00:00:00.000Z".to_string(), "Cola", 2, Money::from_cents(300)),
+            ("2024-03-04T00:00:00.000Z".to_string(), "Cola", 1, Money::from_minor_units(200, Currency::EUR)),
+            ("2024-03-11T00:00:00.000Z".to_string(), "Cola", 1, Money::from_cents(150)),
+        ]);
+        assert_eq!(lines[0].period_end.to_string(), "2024-03-11T00:00:00.000Z");
+
+        let by_slot = SalesReporter::summarize(&sales, ReportPeriod::Monthly, ReportDimension::Slot).unwrap();
+        let keys: Vec<(&str, u32)> = by_slot.iter().map(|line| (line.key.as_str(), line.units)).collect();
+        assert_eq!(keys, vec![("machine 1 slot 2", 1), ("machine 1 slot 1", 2), ("machine 2 slot 1", 1), ("machine 3 slot 1", 1)]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use crate::domain::value_objects::money::{Currency, Money};
+
+/// Whether money goes through the coin acceptor or the bill validator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DenominationKind {
+    Coin,
+    Bill,
+}
+
+/// The coins and bills a machine takes, one at a time, all in one currency
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AcceptedDenominations {
+    currency: Currency,
+    coins: BTreeSet<Money>,
+    bills: BTreeSet<Money>,
+}
+
+/// Errors that can occur when choosing the denominations a machine takes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DenominationError {
+    /// Neither a coin nor a bill was given
+    NoDenominations,
+    InvalidDenomination(Money),
+    CurrencyMismatch { expected: Currency, actual: Currency },
+}
+
+impl AcceptedDenominations {
+    /// Creates the set of denominations a machine takes
+    ///
+    /// # Arguments
+    /// * `currency` - The currency of every coin and bill
+    /// * `coins` - The coins taken, in any order
+    /// * `bills` - The bills taken, in any order
+    ///
+    /// # Returns
+    /// * `Result<AcceptedDenominations, DenominationError>` - Ok(denominations) if valid,
+    ///   Err naming the first coin or bill that is in another currency or not issued in this one
+    pub fn new(currency: Currency, coins: &[Money], bills: &[Money]) -> Result<Self, DenominationError> {
+        if coins.is_empty() && bills.is_empty() {
+            return Err(DenominationError::NoDenominations);
+        }
+
+        for denomination in coins.iter().chain(bills) {
+            if denomination.currency() != currency {
+                return Err(DenominationError::CurrencyMismatch { expected: currency, actual: denomination.currency() });
+            }
+            if !currency.has_denomination(*denomination) {
+                return Err(DenominationError::InvalidDenomination(*denomination));
+            }
+        }
+
+        Ok(AcceptedDenominations {
+            currency,
+            coins: coins.iter().copied().collect(),
+            bills: bills.iter().copied().collect(),
+        })
+    }
+
+    /// Gets the coins in circulation and the bills up to a size worth taking in a soda machine,
+    /// e.g. US coins from 5 cents and bills up to $20
+    pub fn standard(currency: Currency) -> Self {
+        let (coins, bills): (&[i64], &[i64]) = match currency {
+            Currency::USD => (&[5, 10, 25, 100], &[100, 500, 1000, 2000]),
+            Currency::EUR => (&[5, 10, 20, 50, 100, 200], &[500, 1000, 2000]),
+            Currency::GBP => (&[5, 10, 20, 50, 100, 200], &[500, 1000, 2000]),
+            Currency::JPY => (&[10, 50, 100, 500], &[1000, 2000]),
+        };
+
+        AcceptedDenominations {
+            currency,
+            coins: coins.iter().map(|value| Money::from_minor_units(*value, currency)).collect(),
+            bills: bills.iter().map(|value| Money::from_minor_units(*value, currency)).collect(),
+        }
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Gets the coins taken, smallest first
+    pub fn coins(&self) -> &BTreeSet<Money> {
+        &self.coins
+    }
+
+    /// Gets the bills taken, smallest first
+    pub fn bills(&self) -> &BTreeSet<Money> {
+        &self.bills
+    }
+
+    /// Checks if the amount is a single coin or bill the machine takes
+    pub fn accepts(&self, amount: Money) -> bool {
+        self.accepts_as(DenominationKind::Coin, amount) || self.accepts_as(DenominationKind::Bill, amount)
+    }
+
+    /// Checks if the amount is a coin, or a bill, the machine takes
+    pub fn accepts_as(&self, kind: DenominationKind, amount: Money) -> bool {
+        match kind {
+            DenominationKind::Coin => self.coins.contains(&amount),
+            DenominationKind::Bill => self.bills.contains(&amount),
+        }
+    }
+}
+
+impl fmt::Display for DenominationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DenominationKind::Coin => write!(f, "coin"),
+            DenominationKind::Bill => write!(f, "bill"),
+        }
+    }
+}
+
+impl fmt::Display for AcceptedDenominations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |denominations: &BTreeSet<Money>| {
+            denominations.iter().map(|denomination| denomination.to_string()).collect::<Vec<_>>().join(", ")
+        };
+        write!(f, "coins: {}; bills: {}", list(&self.coins), list(&self.bills))
+    }
+}
+
+impl fmt::Display for DenominationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DenominationError::NoDenominations => write!(f, "At least one coin or bill must be accepted"),
+            DenominationError::InvalidDenomination(amount) => write!(f, "{} is not a valid denomination", amount),
+            DenominationError::CurrencyMismatch { expected, actual } => {
+                write!(f, "Currency mismatch: expected {}, got {}", expected, actual)
+            },
+        }
+    }
+}
+
+impl std::error::Error for DenominationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_denominations_stop_at_twenty_dollar_bills() {
+        let denominations = AcceptedDenominations::standard(Currency::USD);
+
+        assert!(denominations.accepts(Money::from_cents(25)));
+        assert!(denominations.accepts(Money::from_cents(2000)));
+        assert!(!denominations.accepts(Money::from_cents(7)), "There is no 7 cent coin");
+        assert!(!denominations.accepts(Money::from_cents(5000)));
+        assert!(!denominations.accepts(Money::from_minor_units(25, Currency::EUR)));
+        assert!(denominations.accepts_as(DenominationKind::Coin, Money::from_cents(100)));
+        assert!(!denominations.accepts_as(DenominationKind::Bill, Money::from_cents(25)));
+        assert_eq!(
+            denominations.to_string(),
+            "coins: $0.05, $0.10, $0.25, $1.00; bills: $1.00, $5.00, $10.00, $20.00"
+        );
+    }
+
+    #[test]
+    fn test_new_checks_every_denomination() {
+        let quarter = Money::from_cents(25);
+
+        assert_eq!(AcceptedDenominations::new(Currency::USD, &[], &[]), Err(DenominationError::NoDenominations));
+        assert_eq!(
+            AcceptedDenominations::new(Currency::EUR, &[quarter], &[]),
+            Err(DenominationError::CurrencyMismatch { expected: Currency::EUR, actual: Currency::USD })
+        );
+        assert_eq!(
+            AcceptedDenominations::new(Currency::USD, &[quarter], &[Money::zero()]),
+            Err(DenominationError::InvalidDenomination(Money::zero()))
+        );
+        assert_eq!(
+            AcceptedDenominations::new(Currency::USD, &[Money::from_cents(7)], &[]),
+            Err(DenominationError::InvalidDenomination(Money::from_cents(7)))
+        );
+
+        let quarters_only = AcceptedDenominations::new(Currency::USD, &[quarter, quarter], &[]).unwrap();
+        assert_eq!(quarters_only.coins().len(), 1);
+        assert!(quarters_only.bills().is_empty());
+    }
+}
@@ -1,27 +1,53 @@
 use std::fmt;
-use std::ops::{Add, Sub, Mul, Div};
+use std::ops::{Add, Sub, Mul, Div, Neg};
+use std::str::FromStr;
 
 /// Represents a monetary amount with currency and precision
 /// This is a value object that ensures money operations are safe and consistent
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Money {
-    /// Amount in cents to avoid floating point precision issues
+    /// Amount in the currency's minor unit (e.g. cents) to avoid floating point precision issues
     cents: i64,
+    /// The currency the amount is expressed in
+    currency: Currency,
 }
 
 /// Currency types supported by the system
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Currency {
+    #[default]
     USD,
     EUR,
     GBP,
     JPY,
 }
 
+/// How to round a result that falls between two minor units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RoundingMode {
+    /// Round towards zero (truncate)
+    Down,
+    /// Round away from zero
+    Up,
+    /// Round towards negative infinity
+    Floor,
+    /// Round towards positive infinity
+    Ceiling,
+    /// Round to the nearest unit, halves away from zero
+    #[default]
+    HalfUp,
+    /// Round to the nearest unit, halves to the even neighbour (banker's rounding)
+    HalfEven,
+}
+
 /// Errors that can occur during money operations
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MoneyError {
     InvalidAmount(String),
+    InvalidFormat(String),
+    TooManyFractionalDigits { currency: Currency, found: usize },
     CurrencyMismatch,
     DivisionByZero,
     Overflow,
@@ -67,11 +93,14 @@ impl Money {
             None => return Err(MoneyError::Overflow),
         };
 
-        Ok(Money { cents: total_cents })
+        Ok(Money { cents: total_cents, currency: Currency::USD })
     }
 
     /// Creates a new Money instance from a decimal amount
     /// 
+    /// The amount goes through floating point; prefer parsing a string
+    /// (e.g. `"5.25".parse::<Money>()`) when the exact value matters.
+    /// 
     /// # Arguments
     /// * `amount` - The decimal amount (e.g., 5.25 for $5.25)
     /// 
@@ -85,10 +114,10 @@ impl Money {
         // Round to nearest cent to avoid floating point precision issues
         let rounded = (amount * 100.0).round() as i64;
         
-        Ok(Money { cents: rounded })
+        Ok(Money { cents: rounded, currency: Currency::USD })
     }
 
-    /// Creates a new Money instance from cents
+    /// Creates a new Money instance from cents (US dollars)
     /// 
     /// # Arguments
     /// * `cents` - The amount in cents
@@ -96,32 +125,79 @@ impl Money {
     /// # Returns
     /// * `Money` - The money instance
     pub fn from_cents(cents: i64) -> Self {
-        Money { cents }
+        Money { cents, currency: Currency::USD }
+    }
+
+    /// Creates a new Money instance from an amount in a currency's minor unit
+    /// 
+    /// # Arguments
+    /// * `minor_units` - The amount in minor units (cents for USD, yen for JPY)
+    /// * `currency` - The currency of the amount
+    /// 
+    /// # Returns
+    /// * `Money` - The money instance
+    /// 
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::value_objects::money::{Currency, Money};
+    /// 
+    /// let euros = Money::from_minor_units(250, Currency::EUR); // €2.50
+    /// let yen = Money::from_minor_units(150, Currency::JPY);   // ¥150
+    /// ```
+    pub fn from_minor_units(minor_units: i64, currency: Currency) -> Self {
+        Money { cents: minor_units, currency }
     }
 
-    /// Creates a zero amount
+    /// Creates a zero amount (US dollars)
     pub fn zero() -> Self {
-        Money { cents: 0 }
+        Money { cents: 0, currency: Currency::USD }
     }
 
-    /// Gets the total amount in cents
+    /// Creates a zero amount in a specific currency
+    pub fn zero_in(currency: Currency) -> Self {
+        Money { cents: 0, currency }
+    }
+
+    /// Gets the currency of the amount
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Gets the total amount in the currency's minor unit (cents for USD)
     pub fn cents(&self) -> i64 {
         self.cents
     }
 
-    /// Gets the dollar portion of the amount
+    /// Gets the major unit portion of the amount (dollars for USD)
     pub fn dollars(&self) -> i64 {
-        self.cents / 100
+        self.cents / self.currency.minor_units_per_major()
     }
 
-    /// Gets the cents portion of the amount (0-99)
+    /// Gets the minor unit portion of the amount (0-99 for USD, always 0 for JPY)
     pub fn cents_portion(&self) -> u8 {
-        (self.cents.abs() % 100) as u8
+        (self.cents.abs() % self.currency.minor_units_per_major()) as u8
     }
 
     /// Gets the amount as a decimal (e.g., 5.25 for $5.25)
     pub fn as_decimal(&self) -> f64 {
-        self.cents as f64 / 100.0
+        self.cents as f64 / self.currency.minor_units_per_major() as f64
+    }
+
+    /// Gets the amount as a decimal string without a currency symbol (e.g., "5.25", "-0.05", "150")
+    pub fn to_decimal_string(&self) -> String {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let exponent = self.currency.minor_unit_exponent() as usize;
+
+        if exponent == 0 {
+            format!("{}{}", sign, self.dollars().abs())
+        } else {
+            format!("{}{}.{:0width$}", sign, self.dollars().abs(), self.cents_portion(), width = exponent)
+        }
+    }
+
+    /// Checks if another amount is in the same currency
+    pub fn same_currency(&self, other: &Money) -> bool {
+        self.currency == other.currency
     }
 
     /// Checks if the amount is zero
@@ -141,12 +217,295 @@ impl Money {
 
     /// Returns the absolute value of the money
     pub fn abs(self) -> Self {
-        Money { cents: self.cents.abs() }
+        Money { cents: self.cents.abs(), currency: self.currency }
     }
 
-    /// Returns the negative of the money
-    pub fn neg(self) -> Self {
-        Money { cents: -self.cents }
+    /// Multiplies the amount by an exact ratio, rounding the result to a whole minor unit
+    /// 
+    /// # Arguments
+    /// * `numerator` - The numerator of the ratio
+    /// * `denominator` - The denominator of the ratio
+    /// * `rounding` - How to round a result that falls between two minor units
+    /// 
+    /// # Returns
+    /// * `Result<Money, MoneyError>` - Ok(Money) if valid, Err on division by zero or overflow
+    /// 
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::value_objects::money::{Money, RoundingMode};
+    /// 
+    /// let price = Money::from_cents(150);
+    /// let third = price.multiply_ratio(1, 3, RoundingMode::HalfUp).unwrap(); // $0.50
+    /// let larger = price.multiply_ratio(3, 2, RoundingMode::HalfUp).unwrap(); // $2.25
+    /// ```
+    pub fn multiply_ratio(self, numerator: i64, denominator: i64, rounding: RoundingMode) -> Result<Money, MoneyError> {
+        if denominator == 0 {
+            return Err(MoneyError::DivisionByZero);
+        }
+
+        let product = self.cents as i128 * numerator as i128;
+        let rounded = divide_rounded(product, denominator as i128, rounding);
+
+        match i64::try_from(rounded) {
+            Ok(cents) => Ok(Money { cents, currency: self.currency }),
+            Err(_) if rounded < 0 => Err(MoneyError::Underflow),
+            Err(_) => Err(MoneyError::Overflow),
+        }
+    }
+
+    /// Takes a whole percentage of the amount (e.g. 20 for 20%)
+    /// 
+    /// # Arguments
+    /// * `percent` - The percentage to take
+    /// * `rounding` - How to round a result that falls between two minor units
+    /// 
+    /// # Returns
+    /// * `Result<Money, MoneyError>` - Ok(Money) if valid, Err on overflow
+    pub fn percentage(self, percent: i64, rounding: RoundingMode) -> Result<Money, MoneyError> {
+        self.multiply_ratio(percent, 100, rounding)
+    }
+
+    /// Takes a percentage of the amount expressed in basis points (e.g. 1250 for 12.5%)
+    /// 
+    /// # Arguments
+    /// * `basis_points` - The percentage in hundredths of a percent
+    /// * `rounding` - How to round a result that falls between two minor units
+    /// 
+    /// # Returns
+    /// * `Result<Money, MoneyError>` - Ok(Money) if valid, Err on overflow
+    pub fn basis_points(self, basis_points: i64, rounding: RoundingMode) -> Result<Money, MoneyError> {
+        self.multiply_ratio(basis_points, 10_000, rounding)
+    }
+
+    /// Parses an exact amount like `FromStr`, reading amounts without a symbol or currency code
+    /// in the given currency instead of US dollars
+    /// 
+    /// # Arguments
+    /// * `s` - The amount, e.g. "1.50", "€1.50" or "1.50 EUR"
+    /// * `currency` - The currency of a bare amount
+    /// 
+    /// # Returns
+    /// * `Result<Money, MoneyError>` - Ok(Money) if valid, Err if the amount cannot be read
+    /// 
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::value_objects::money::{Currency, Money};
+    /// 
+    /// let euros = Money::parse_in("1.50", Currency::EUR).unwrap();
+    /// assert_eq!(euros, Money::from_minor_units(150, Currency::EUR));
+    /// ```
+    pub fn parse_in(s: &str, currency: Currency) -> Result<Money, MoneyError> {
+        let input = s.trim();
+        if input.is_empty() {
+            return Err(MoneyError::InvalidFormat("Amount cannot be empty".to_string()));
+        }
+
+        // An ISO code may come before or after the amount, separated by whitespace
+        let (code_currency, rest) = match input.split_once(char::is_whitespace) {
+            Some((first, second)) => {
+                let (first, second) = (first.trim(), second.trim());
+                if let Some(currency) = Currency::from_code(second) {
+                    (Some(currency), first)
+                } else if let Some(currency) = Currency::from_code(first) {
+                    (Some(currency), second)
+                } else {
+                    return Err(MoneyError::InvalidFormat(format!("Unknown currency in '{}'", input)));
+                }
+            },
+            None => (None, input),
+        };
+
+        // The sign may come before or after the symbol ("-$0.05" or "$-0.05")
+        let (mut negative, mut rest) = strip_sign(rest);
+        let mut symbol_currency = None;
+        for currency in [Currency::USD, Currency::EUR, Currency::GBP, Currency::JPY] {
+            if let Some(stripped) = rest.strip_prefix(currency.symbol()) {
+                symbol_currency = Some(currency);
+                rest = stripped;
+                if !negative {
+                    (negative, rest) = strip_sign(rest);
+                }
+                break;
+            }
+        }
+
+        let currency = match (code_currency, symbol_currency) {
+            (Some(code), Some(symbol)) if code != symbol => return Err(MoneyError::CurrencyMismatch),
+            (code, symbol) => code.or(symbol).unwrap_or(currency),
+        };
+
+        Money::parse_amount(rest, currency, negative)
+    }
+
+    /// Parses an amount string in a given currency, e.g. "1,234.56" or "-0.05"
+    fn parse_amount(input: &str, currency: Currency, negative: bool) -> Result<Money, MoneyError> {
+        let (whole, fraction) = match input.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (input, None),
+        };
+
+        if whole.is_empty() {
+            return Err(MoneyError::InvalidFormat(format!("Missing whole amount in '{}'", input)));
+        }
+
+        // Thousands separators must split the whole amount into groups of three
+        if whole.contains(',') {
+            let groups: Vec<&str> = whole.split(',').collect();
+            let first_ok = (1..=3).contains(&groups[0].len());
+            if !first_ok || groups[1..].iter().any(|group| group.len() != 3) {
+                return Err(MoneyError::InvalidFormat(format!("Misplaced thousands separator in '{}'", input)));
+            }
+        }
+
+        let digits: String = whole.chars().filter(|c| *c != ',').collect();
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(MoneyError::InvalidFormat(format!("'{}' is not a number", input)));
+        }
+
+        let exponent = currency.minor_unit_exponent() as usize;
+        let fraction = fraction.unwrap_or("");
+        if input.contains('.') && fraction.is_empty() {
+            return Err(MoneyError::InvalidFormat(format!("Missing digits after decimal point in '{}'", input)));
+        }
+        if !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(MoneyError::InvalidFormat(format!("'{}' is not a number", input)));
+        }
+        if fraction.len() > exponent {
+            return Err(MoneyError::TooManyFractionalDigits { currency, found: fraction.len() });
+        }
+
+        let whole_units: i64 = digits.parse().map_err(|_| MoneyError::Overflow)?;
+        let fraction_units: i64 = if fraction.is_empty() {
+            0
+        } else {
+            let padded = format!("{:0<width$}", fraction, width = exponent);
+            padded.parse().map_err(|_| MoneyError::Overflow)?
+        };
+
+        let minor_units = whole_units
+            .checked_mul(currency.minor_units_per_major())
+            .and_then(|units| units.checked_add(fraction_units))
+            .ok_or(MoneyError::Overflow)?;
+
+        Ok(Money {
+            cents: if negative { -minor_units } else { minor_units },
+            currency,
+        })
+    }
+}
+
+/// Divides with the given rounding mode instead of always truncating
+fn divide_rounded(numerator: i128, denominator: i128, rounding: RoundingMode) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+
+    if remainder == 0 {
+        return quotient;
+    }
+
+    // Direction away from zero for the exact result
+    let away = if (numerator < 0) != (denominator < 0) { -1 } else { 1 };
+    let twice_remainder = remainder.abs() * 2;
+    let divisor = denominator.abs();
+
+    match rounding {
+        RoundingMode::Down => quotient,
+        RoundingMode::Up => quotient + away,
+        RoundingMode::Floor => if away < 0 { quotient - 1 } else { quotient },
+        RoundingMode::Ceiling => if away > 0 { quotient + 1 } else { quotient },
+        RoundingMode::HalfUp => {
+            if twice_remainder >= divisor { quotient + away } else { quotient }
+        },
+        RoundingMode::HalfEven => {
+            if twice_remainder > divisor || (twice_remainder == divisor && quotient % 2 != 0) {
+                quotient + away
+            } else {
+                quotient
+            }
+        },
+    }
+}
+
+impl Currency {
+    /// Gets the ISO 4217 code of the currency
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::USD => "USD",
+            Currency::EUR => "EUR",
+            Currency::GBP => "GBP",
+            Currency::JPY => "JPY",
+        }
+    }
+
+    /// Gets the symbol used when displaying amounts
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::USD => "$",
+            Currency::EUR => "€",
+            Currency::GBP => "£",
+            Currency::JPY => "¥",
+        }
+    }
+
+    /// Gets the number of decimal digits of the minor unit (2 for cents, 0 for yen)
+    pub fn minor_unit_exponent(&self) -> u32 {
+        match self {
+            Currency::USD | Currency::EUR | Currency::GBP => 2,
+            Currency::JPY => 0,
+        }
+    }
+
+    /// Gets how many minor units make up one major unit (100 for USD, 1 for JPY)
+    pub fn minor_units_per_major(&self) -> i64 {
+        10_i64.pow(self.minor_unit_exponent())
+    }
+
+    /// Gets every coin and banknote issued in the currency, smallest first
+    pub fn denominations(&self) -> Vec<Money> {
+        let minor_units: &[i64] = match self {
+            Currency::USD => &[1, 5, 10, 25, 50, 100, 200, 500, 1000, 2000, 5000, 10000],
+            Currency::EUR => &[1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000, 10000, 20000, 50000],
+            Currency::GBP => &[1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000],
+            Currency::JPY => &[1, 5, 10, 50, 100, 500, 1000, 2000, 5000, 10000],
+        };
+        minor_units.iter().map(|value| Money::from_minor_units(*value, *self)).collect()
+    }
+
+    /// Checks if the amount is a single coin or banknote issued in the currency
+    pub fn has_denomination(&self, amount: Money) -> bool {
+        amount.currency() == *self && self.denominations().contains(&amount)
+    }
+
+    /// Gets the smallest banknote issued in the currency, anything smaller is a coin
+    pub fn smallest_bill(&self) -> Money {
+        let minor_units = match self {
+            Currency::USD => 100,
+            Currency::EUR | Currency::GBP => 500,
+            Currency::JPY => 1000,
+        };
+        Money::from_minor_units(minor_units, *self)
+    }
+
+    /// Gets the currency from its ISO 4217 code
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.to_uppercase().as_str() {
+            "USD" => Some(Currency::USD),
+            "EUR" => Some(Currency::EUR),
+            "GBP" => Some(Currency::GBP),
+            "JPY" => Some(Currency::JPY),
+            _ => None,
+        }
+    }
+
+    /// Gets the currency from its display symbol
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "$" => Some(Currency::USD),
+            "€" => Some(Currency::EUR),
+            "£" => Some(Currency::GBP),
+            "¥" => Some(Currency::JPY),
+            _ => None,
+        }
     }
 }
 
@@ -154,8 +513,12 @@ impl Add for Money {
     type Output = Result<Money, MoneyError>;
 
     fn add(self, other: Money) -> Self::Output {
+        if !self.same_currency(&other) {
+            return Err(MoneyError::CurrencyMismatch);
+        }
+
         match self.cents.checked_add(other.cents) {
-            Some(cents) => Ok(Money { cents }),
+            Some(cents) => Ok(Money { cents, currency: self.currency }),
             None => Err(MoneyError::Overflow),
         }
     }
@@ -165,8 +528,12 @@ impl Sub for Money {
     type Output = Result<Money, MoneyError>;
 
     fn sub(self, other: Money) -> Self::Output {
+        if !self.same_currency(&other) {
+            return Err(MoneyError::CurrencyMismatch);
+        }
+
         match self.cents.checked_sub(other.cents) {
-            Some(cents) => Ok(Money { cents }),
+            Some(cents) => Ok(Money { cents, currency: self.currency }),
             None => Err(MoneyError::Underflow),
         }
     }
@@ -177,7 +544,7 @@ impl Mul<i64> for Money {
 
     fn mul(self, multiplier: i64) -> Self::Output {
         match self.cents.checked_mul(multiplier) {
-            Some(cents) => Ok(Money { cents }),
+            Some(cents) => Ok(Money { cents, currency: self.currency }),
             None => Err(MoneyError::Overflow),
         }
     }
@@ -191,27 +558,82 @@ impl Div<i64> for Money {
             return Err(MoneyError::DivisionByZero);
         }
         
-        Ok(Money { cents: self.cents / divisor })
+        Ok(Money { cents: self.cents / divisor, currency: self.currency })
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    /// Returns the negative of the money
+    fn neg(self) -> Self::Output {
+        Money { cents: -self.cents, currency: self.currency }
     }
 }
 
 impl fmt::Display for Money {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let dollars = self.dollars();
-        let cents = self.cents_portion();
+        let symbol = self.currency.symbol();
+        let amount = self.abs().to_decimal_string();
         
         if self.is_negative() {
-            write!(f, "-${}.{:02}", dollars.abs(), cents)
+            write!(f, "-{}{}", symbol, amount)
         } else {
-            write!(f, "${}.{:02}", dollars, cents)
+            write!(f, "{}{}", symbol, amount)
         }
     }
 }
 
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    /// Parses an exact amount such as "2.50", "-0.05", "$1,234.56", "€2", "1.5 EUR" or "JPY 500"
+    /// 
+    /// Amounts without a symbol or currency code are US dollars.
+    /// 
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::value_objects::money::{Currency, Money};
+    /// 
+    /// let money: Money = "$1,234.56".parse().unwrap();
+    /// assert_eq!(money.cents(), 123456);
+    /// 
+    /// let euros: Money = "1.5 EUR".parse().unwrap();
+    /// assert_eq!(euros, Money::from_minor_units(150, Currency::EUR));
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Money::parse_in(s, Currency::default())
+    }
+}
+
+fn strip_sign(input: &str) -> (bool, &str) {
+    if let Some(rest) = input.strip_prefix('-') {
+        (true, rest)
+    } else if let Some(rest) = input.strip_prefix('+') {
+        (false, rest)
+    } else {
+        (false, input)
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
 impl fmt::Display for MoneyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             MoneyError::InvalidAmount(msg) => write!(f, "Invalid amount: {}", msg),
+            MoneyError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
+            MoneyError::TooManyFractionalDigits { currency, found } => write!(
+                f,
+                "Too many fractional digits: {} allows at most {}, got {}",
+                currency,
+                currency.minor_unit_exponent(),
+                found
+            ),
             MoneyError::CurrencyMismatch => write!(f, "Currency mismatch"),
             MoneyError::DivisionByZero => write!(f, "Division by zero"),
             MoneyError::Overflow => write!(f, "Arithmetic overflow"),
@@ -382,6 +804,230 @@ mod tests {
         assert_eq!(format!("{}", zero), "$0.00");
     }
 
+    #[test]
+    fn test_from_minor_units() {
+        let euros = Money::from_minor_units(250, Currency::EUR);
+        assert_eq!(euros.cents(), 250);
+        assert_eq!(euros.currency(), Currency::EUR);
+        assert_eq!(euros.dollars(), 2);
+        assert_eq!(euros.cents_portion(), 50);
+        assert_eq!(euros.as_decimal(), 2.5);
+    }
+
+    #[test]
+    fn test_default_currency_is_usd() {
+        assert_eq!(Money::from_cents(100).currency(), Currency::USD);
+        assert_eq!(Money::zero().currency(), Currency::USD);
+        assert_eq!(Money::from_dollars_cents(1, 0).unwrap().currency(), Currency::USD);
+        assert_eq!(Money::zero_in(Currency::GBP).currency(), Currency::GBP);
+    }
+
+    #[test]
+    fn test_jpy_has_no_minor_unit() {
+        let yen = Money::from_minor_units(150, Currency::JPY);
+        assert_eq!(yen.dollars(), 150);
+        assert_eq!(yen.cents_portion(), 0);
+        assert_eq!(yen.as_decimal(), 150.0);
+        assert_eq!(Currency::JPY.minor_unit_exponent(), 0);
+        assert_eq!(Currency::JPY.minor_units_per_major(), 1);
+        assert_eq!(Currency::EUR.minor_units_per_major(), 100);
+    }
+
+    #[test]
+    fn test_add_currency_mismatch() {
+        let dollars = Money::from_cents(100);
+        let euros = Money::from_minor_units(100, Currency::EUR);
+
+        assert_eq!((dollars + euros).unwrap_err(), MoneyError::CurrencyMismatch);
+        assert_eq!((Money::zero() + euros).unwrap_err(), MoneyError::CurrencyMismatch);
+    }
+
+    #[test]
+    fn test_sub_currency_mismatch() {
+        let pounds = Money::from_minor_units(500, Currency::GBP);
+        let yen = Money::from_minor_units(100, Currency::JPY);
+
+        assert_eq!((pounds - yen).unwrap_err(), MoneyError::CurrencyMismatch);
+    }
+
+    #[test]
+    fn test_arithmetic_keeps_currency() {
+        let euros = Money::from_minor_units(150, Currency::EUR);
+
+        assert_eq!((euros + euros).unwrap(), Money::from_minor_units(300, Currency::EUR));
+        assert_eq!((euros - euros).unwrap(), Money::zero_in(Currency::EUR));
+        assert_eq!((euros * 2).unwrap().currency(), Currency::EUR);
+        assert_eq!((euros / 3).unwrap().currency(), Currency::EUR);
+        assert_eq!((-euros).currency(), Currency::EUR);
+        assert_eq!((-euros).abs(), euros);
+    }
+
+    #[test]
+    fn test_equality_includes_currency() {
+        assert_ne!(Money::from_cents(100), Money::from_minor_units(100, Currency::EUR));
+        assert_ne!(Money::zero(), Money::zero_in(Currency::JPY));
+    }
+
+    #[test]
+    fn test_display_currency_symbols() {
+        assert_eq!(format!("{}", Money::from_minor_units(250, Currency::EUR)), "€2.50");
+        assert_eq!(format!("{}", Money::from_minor_units(-5, Currency::GBP)), "-£0.05");
+        assert_eq!(format!("{}", Money::from_minor_units(150, Currency::JPY)), "¥150");
+        assert_eq!(format!("{}", Money::from_minor_units(-1500, Currency::JPY)), "-¥1500");
+    }
+
+    #[test]
+    fn test_to_decimal_string() {
+        assert_eq!(Money::from_cents(525).to_decimal_string(), "5.25");
+        assert_eq!(Money::from_cents(-5).to_decimal_string(), "-0.05");
+        assert_eq!(Money::from_minor_units(150, Currency::JPY).to_decimal_string(), "150");
+    }
+
+    #[test]
+    fn test_currency_codes() {
+        assert_eq!(Currency::EUR.code(), "EUR");
+        assert_eq!(Currency::from_code("gbp"), Some(Currency::GBP));
+        assert_eq!(Currency::from_code("CHF"), None);
+        assert_eq!(Currency::from_symbol("¥"), Some(Currency::JPY));
+        assert_eq!(format!("{}", Currency::USD), "USD");
+    }
+
+    #[test]
+    fn test_denominations() {
+        assert!(Currency::USD.has_denomination(Money::from_cents(25)));
+        assert!(!Currency::USD.has_denomination(Money::from_cents(7)));
+        assert!(!Currency::USD.has_denomination(Money::from_minor_units(25, Currency::EUR)));
+        assert!(Currency::EUR.has_denomination(Money::from_minor_units(2, Currency::EUR)));
+        assert_eq!(Currency::JPY.denominations().first(), Some(&Money::from_minor_units(1, Currency::JPY)));
+    }
+
+    #[test]
+    fn test_smallest_bill() {
+        assert_eq!(Currency::USD.smallest_bill(), Money::from_cents(100));
+        assert_eq!(Currency::EUR.smallest_bill(), Money::from_minor_units(500, Currency::EUR));
+        assert_eq!(Currency::JPY.smallest_bill().to_string(), "¥1000");
+    }
+
+    #[test]
+    fn test_parse_plain() {
+        assert_eq!("2.50".parse::<Money>().unwrap(), Money::from_cents(250));
+        assert_eq!("2".parse::<Money>().unwrap(), Money::from_cents(200));
+        assert_eq!("0.1".parse::<Money>().unwrap(), Money::from_cents(10));
+        assert_eq!("  3.05  ".parse::<Money>().unwrap(), Money::from_cents(305));
+        assert_eq!("+1.00".parse::<Money>().unwrap(), Money::from_cents(100));
+    }
+
+    #[test]
+    fn test_parse_negative() {
+        assert_eq!("-0.05".parse::<Money>().unwrap(), Money::from_cents(-5));
+        assert_eq!("-$0.05".parse::<Money>().unwrap(), Money::from_cents(-5));
+        assert_eq!("$-2.50".parse::<Money>().unwrap(), Money::from_cents(-250));
+    }
+
+    #[test]
+    fn test_parse_symbol_and_thousands() {
+        assert_eq!("$1,234.56".parse::<Money>().unwrap(), Money::from_cents(123456));
+        assert_eq!("$1,234,567".parse::<Money>().unwrap(), Money::from_cents(123456700));
+        assert_eq!("€2".parse::<Money>().unwrap(), Money::from_minor_units(200, Currency::EUR));
+        assert_eq!("£0.99".parse::<Money>().unwrap(), Money::from_minor_units(99, Currency::GBP));
+        assert_eq!("¥500".parse::<Money>().unwrap(), Money::from_minor_units(500, Currency::JPY));
+    }
+
+    #[test]
+    fn test_parse_currency_code() {
+        assert_eq!("1.5 EUR".parse::<Money>().unwrap(), Money::from_minor_units(150, Currency::EUR));
+        assert_eq!("EUR 1.5".parse::<Money>().unwrap(), Money::from_minor_units(150, Currency::EUR));
+        assert_eq!("500 jpy".parse::<Money>().unwrap(), Money::from_minor_units(500, Currency::JPY));
+        assert_eq!("£3 GBP".parse::<Money>().unwrap(), Money::from_minor_units(300, Currency::GBP));
+    }
+
+    #[test]
+    fn test_parse_in_currency() {
+        assert_eq!(Money::parse_in("1.50", Currency::EUR).unwrap(), Money::from_minor_units(150, Currency::EUR));
+        assert_eq!(Money::parse_in("500", Currency::JPY).unwrap(), Money::from_minor_units(500, Currency::JPY));
+        assert_eq!(Money::parse_in("$1.50", Currency::EUR).unwrap(), Money::from_cents(150));
+        assert_eq!(
+            Money::parse_in("1.5", Currency::JPY).unwrap_err(),
+            MoneyError::TooManyFractionalDigits { currency: Currency::JPY, found: 1 }
+        );
+    }
+
+    #[test]
+    fn test_parse_too_many_fractional_digits() {
+        assert_eq!(
+            "2.505".parse::<Money>().unwrap_err(),
+            MoneyError::TooManyFractionalDigits { currency: Currency::USD, found: 3 }
+        );
+        assert_eq!(
+            "1.5 JPY".parse::<Money>().unwrap_err(),
+            MoneyError::TooManyFractionalDigits { currency: Currency::JPY, found: 1 }
+        );
+        assert_eq!(
+            format!("{}", "2.505".parse::<Money>().unwrap_err()),
+            "Too many fractional digits: USD allows at most 2, got 3"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for input in ["", "abc", "1.2.3", "1,23.00", ",123", "1.", ".50", "1.-5", "$", "1.5 CHF", "12a"] {
+            let result = input.parse::<Money>();
+            assert!(matches!(result, Err(MoneyError::InvalidFormat(_))), "'{}' should not parse: {:?}", input, result);
+        }
+        assert_eq!("€1.50 USD".parse::<Money>().unwrap_err(), MoneyError::CurrencyMismatch);
+        assert_eq!("99999999999999999999".parse::<Money>().unwrap_err(), MoneyError::Overflow);
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        for money in [Money::from_cents(525), Money::from_cents(-5), Money::from_minor_units(150, Currency::JPY)] {
+            assert_eq!(format!("{}", money).parse::<Money>().unwrap(), money);
+        }
+    }
+
+    #[test]
+    fn test_multiply_ratio() {
+        let price = Money::from_cents(150);
+
+        assert_eq!(price.multiply_ratio(3, 2, RoundingMode::HalfUp).unwrap(), Money::from_cents(225));
+        assert_eq!(price.multiply_ratio(1, 3, RoundingMode::HalfUp).unwrap(), Money::from_cents(50));
+        assert_eq!(price.multiply_ratio(1, 0, RoundingMode::HalfUp).unwrap_err(), MoneyError::DivisionByZero);
+        assert_eq!(Money::from_cents(i64::MAX).multiply_ratio(2, 1, RoundingMode::Down).unwrap_err(), MoneyError::Overflow);
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        let amount = Money::from_cents(125); // 125 / 10 = 12.5
+        let negative = Money::from_cents(-125);
+        let round = |money: Money, mode| money.multiply_ratio(1, 10, mode).unwrap().cents();
+
+        assert_eq!(round(amount, RoundingMode::Down), 12);
+        assert_eq!(round(amount, RoundingMode::Up), 13);
+        assert_eq!(round(amount, RoundingMode::Floor), 12);
+        assert_eq!(round(amount, RoundingMode::Ceiling), 13);
+        assert_eq!(round(amount, RoundingMode::HalfUp), 13);
+        assert_eq!(round(amount, RoundingMode::HalfEven), 12);
+        assert_eq!(round(Money::from_cents(135), RoundingMode::HalfEven), 14);
+
+        assert_eq!(round(negative, RoundingMode::Down), -12);
+        assert_eq!(round(negative, RoundingMode::Up), -13);
+        assert_eq!(round(negative, RoundingMode::Floor), -13);
+        assert_eq!(round(negative, RoundingMode::Ceiling), -12);
+        assert_eq!(round(negative, RoundingMode::HalfUp), -13);
+        assert_eq!(round(negative, RoundingMode::HalfEven), -12);
+    }
+
+    #[test]
+    fn test_percentage() {
+        let price = Money::from_cents(199);
+
+        assert_eq!(price.percentage(20, RoundingMode::HalfUp).unwrap(), Money::from_cents(40));
+        assert_eq!(price.percentage(20, RoundingMode::Down).unwrap(), Money::from_cents(39));
+        assert_eq!(price.basis_points(1250, RoundingMode::HalfEven).unwrap(), Money::from_cents(25));
+        assert_eq!(Money::from_minor_units(150, Currency::JPY).percentage(15, RoundingMode::HalfUp).unwrap(),
+            Money::from_minor_units(23, Currency::JPY));
+    }
+
     #[test]
     fn test_ordering() {
         let money1 = Money::from_cents(100);
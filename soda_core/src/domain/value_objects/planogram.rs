@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use std::fmt;
+use crate::domain::entities::product::Sku;
+use crate::domain::entities::slot::SlotId;
+use crate::domain::value_objects::soda::Soda;
+
+/// The layout of a machine: which slots it has, how much each holds and what it sells
+/// This is a value object, so the same layout can be rolled out to any number of machines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Planogram {
+    /// The slots of the layout, ordered by slot ID
+    slots: Vec<PlanogramSlot>,
+}
+
+/// One slot of a planogram
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanogramSlot {
+    pub slot_id: SlotId,
+    pub capacity: u32,
+    pub low_stock_threshold: u32,
+    /// The catalog product the soda comes from, None for a soda configured by hand
+    pub sku: Option<Sku>,
+    /// The soda the slot sells, None leaves the slot unconfigured
+    pub soda: Option<Soda>,
+}
+
+/// Errors that can occur when building a planogram
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanogramError {
+    DuplicateSlot(SlotId),
+    InvalidCapacity(SlotId),
+    InvalidThreshold(SlotId),
+    MissingSoda(SlotId),
+}
+
+impl Planogram {
+    /// Creates a planogram, checking every slot on its own before any machine sees it
+    ///
+    /// # Arguments
+    /// * `slots` - The slots of the layout, in any order
+    ///
+    /// # Returns
+    /// * `Result<Planogram, PlanogramError>` - Ok(planogram) if valid, Err naming the first invalid slot
+    pub fn new(mut slots: Vec<PlanogramSlot>) -> Result<Self, PlanogramError> {
+        let mut seen = HashSet::new();
+        for slot in &slots {
+            if !seen.insert(slot.slot_id) {
+                return Err(PlanogramError::DuplicateSlot(slot.slot_id));
+            }
+
+            if slot.capacity == 0 {
+                return Err(PlanogramError::InvalidCapacity(slot.slot_id));
+            }
+
+            if slot.low_stock_threshold >= slot.capacity {
+                return Err(PlanogramError::InvalidThreshold(slot.slot_id));
+            }
+
+            if slot.sku.is_some() && slot.soda.is_none() {
+                return Err(PlanogramError::MissingSoda(slot.slot_id));
+            }
+        }
+
+        slots.sort_by_key(|slot| slot.slot_id);
+        Ok(Planogram { slots })
+    }
+
+    /// Gets the slots of the layout, ordered by slot ID
+    pub fn slots(&self) -> &[PlanogramSlot] {
+        &self.slots
+    }
+}
+
+impl fmt::Display for PlanogramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanogramError::DuplicateSlot(slot_id) => write!(f, "Slot {} is listed more than once", slot_id),
+            PlanogramError::InvalidCapacity(slot_id) => {
+                write!(f, "Slot {}: capacity must be greater than 0", slot_id)
+            },
+            PlanogramError::InvalidThreshold(slot_id) => {
+                write!(f, "Slot {}: low stock threshold must be below the capacity", slot_id)
+            },
+            PlanogramError::MissingSoda(slot_id) => write!(f, "Slot {}: a SKU needs the soda it sells", slot_id),
+        }
+    }
+}
+
+impl std::error::Error for PlanogramError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(id: u32, capacity: u32, low_stock_threshold: u32) -> PlanogramSlot {
+        PlanogramSlot { slot_id: SlotId::new(id), capacity, low_stock_threshold, sku: None, soda: None }
+    }
+
+    #[test]
+    fn test_planogram_orders_slots() {
+        let planogram = Planogram::new(vec![slot(3, 10, 2), slot(1, 8, 0)]).unwrap();
+
+        let ids: Vec<SlotId> = planogram.slots().iter().map(|slot| slot.slot_id).collect();
+        assert_eq!(ids, vec![SlotId::new(1), SlotId::new(3)]);
+    }
+
+    #[test]
+    fn test_planogram_rejects_invalid_slots() {
+        assert_eq!(
+            Planogram::new(vec![slot(1, 10, 0), slot(1, 8, 0)]).unwrap_err(),
+            PlanogramError::DuplicateSlot(SlotId::new(1))
+        );
+        assert_eq!(Planogram::new(vec![slot(2, 0, 0)]).unwrap_err(), PlanogramError::InvalidCapacity(SlotId::new(2)));
+        assert_eq!(Planogram::new(vec![slot(2, 5, 5)]).unwrap_err(), PlanogramError::InvalidThreshold(SlotId::new(2)));
+    }
+}
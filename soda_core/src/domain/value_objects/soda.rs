@@ -1,9 +1,10 @@
 use std::fmt;
-use super::money::Money;
+use super::money::{Money, RoundingMode};
 
 /// Represents a type of soda with its properties
 /// This is a value object that ensures soda operations are consistent
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Soda {
     /// The brand/name of the soda
     name: String,
@@ -21,6 +22,7 @@ pub struct Soda {
 
 /// Available soda flavors
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SodaFlavor {
     Cola,
     Orange,
@@ -36,6 +38,7 @@ pub enum SodaFlavor {
 
 /// Available soda sizes
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SodaSize {
     Small,   // 8 oz
     Medium,  // 12 oz
@@ -149,16 +152,19 @@ impl Soda {
     /// 
     /// # Arguments
     /// * `new_size` - The new size for the soda
-    /// * `size_multiplier` - The price multiplier for the new size
+    /// * `price_percent` - The new price as a percentage of the current one (e.g. 150 for 1.5x)
+    /// 
+    /// The new price is rounded half-up to the nearest minor unit.
     /// 
     /// # Returns
     /// * `Result<Soda, SodaError>` - Ok(Soda) with new size and price, Err if invalid
-    pub fn with_size(self, new_size: SodaSize, size_multiplier: f64) -> Result<Self, SodaError> {
-        if size_multiplier <= 0.0 || size_multiplier.is_nan() || size_multiplier.is_infinite() {
+    pub fn with_size(self, new_size: SodaSize, price_percent: u32) -> Result<Self, SodaError> {
+        if price_percent == 0 {
             return Err(SodaError::InvalidPrice);
         }
 
-        let new_price = Money::from_decimal(self.price.as_decimal() * size_multiplier)
+        let new_price = self.price
+            .percentage(price_percent as i64, RoundingMode::HalfUp)
             .map_err(|_| SodaError::InvalidPrice)?;
 
         Ok(Soda {
@@ -206,7 +212,7 @@ impl Soda {
             "{}{} {} - {} oz{}",
             diet_text,
             self.name,
-            self.flavor.to_string(),
+            self.flavor,
             self.volume_ounces(),
             caffeine_text
         )
@@ -214,22 +220,6 @@ impl Soda {
 }
 
 impl SodaFlavor {
-    /// Gets a human-readable string representation of the flavor
-    pub fn to_string(&self) -> String {
-        match self {
-            SodaFlavor::Cola => "Cola".to_string(),
-            SodaFlavor::Orange => "Orange".to_string(),
-            SodaFlavor::LemonLime => "Lemon-Lime".to_string(),
-            SodaFlavor::RootBeer => "Root Beer".to_string(),
-            SodaFlavor::Grape => "Grape".to_string(),
-            SodaFlavor::Cherry => "Cherry".to_string(),
-            SodaFlavor::Vanilla => "Vanilla".to_string(),
-            SodaFlavor::Strawberry => "Strawberry".to_string(),
-            SodaFlavor::Peach => "Peach".to_string(),
-            SodaFlavor::Watermelon => "Watermelon".to_string(),
-        }
-    }
-
     /// Gets the flavor from a string representation
     pub fn from_string(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
@@ -246,19 +236,25 @@ impl SodaFlavor {
             _ => None,
         }
     }
-}
 
-impl SodaSize {
-    /// Gets a human-readable string representation of the size
-    pub fn to_string(&self) -> String {
+    /// Gets the short name of the flavor, as `from_string` reads it back
+    pub fn code(&self) -> &'static str {
         match self {
-            SodaSize::Small => "Small (8 oz)".to_string(),
-            SodaSize::Medium => "Medium (12 oz)".to_string(),
-            SodaSize::Large => "Large (16 oz)".to_string(),
-            SodaSize::XLarge => "X-Large (20 oz)".to_string(),
+            SodaFlavor::Cola => "cola",
+            SodaFlavor::Orange => "orange",
+            SodaFlavor::LemonLime => "lemon-lime",
+            SodaFlavor::RootBeer => "root beer",
+            SodaFlavor::Grape => "grape",
+            SodaFlavor::Cherry => "cherry",
+            SodaFlavor::Vanilla => "vanilla",
+            SodaFlavor::Strawberry => "strawberry",
+            SodaFlavor::Peach => "peach",
+            SodaFlavor::Watermelon => "watermelon",
         }
     }
+}
 
+impl SodaSize {
     /// Gets the size from a string representation
     pub fn from_string(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
@@ -270,6 +266,16 @@ impl SodaSize {
         }
     }
 
+    /// Gets the short name of the size, as `from_string` reads it back
+    pub fn code(&self) -> &'static str {
+        match self {
+            SodaSize::Small => "small",
+            SodaSize::Medium => "medium",
+            SodaSize::Large => "large",
+            SodaSize::XLarge => "x-large",
+        }
+    }
+
     /// Gets the volume in ounces for the size
     pub fn volume_ounces(&self) -> u8 {
         match self {
@@ -287,6 +293,38 @@ impl fmt::Display for Soda {
     }
 }
 
+impl fmt::Display for SodaFlavor {
+    /// Formats a human-readable representation of the flavor
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SodaFlavor::Cola => "Cola",
+            SodaFlavor::Orange => "Orange",
+            SodaFlavor::LemonLime => "Lemon-Lime",
+            SodaFlavor::RootBeer => "Root Beer",
+            SodaFlavor::Grape => "Grape",
+            SodaFlavor::Cherry => "Cherry",
+            SodaFlavor::Vanilla => "Vanilla",
+            SodaFlavor::Strawberry => "Strawberry",
+            SodaFlavor::Peach => "Peach",
+            SodaFlavor::Watermelon => "Watermelon",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for SodaSize {
+    /// Formats a human-readable representation of the size
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SodaSize::Small => "Small (8 oz)",
+            SodaSize::Medium => "Medium (12 oz)",
+            SodaSize::Large => "Large (16 oz)",
+            SodaSize::XLarge => "X-Large (20 oz)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl fmt::Display for SodaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -322,8 +360,8 @@ mod tests {
         assert_eq!(soda.flavor(), SodaFlavor::Cola);
         assert_eq!(soda.size(), SodaSize::Medium);
         assert_eq!(soda.price(), Money::from_dollars_cents(1, 50).unwrap());
-        assert_eq!(soda.is_diet(), false);
-        assert_eq!(soda.is_caffeinated(), true);
+        assert!(!soda.is_diet());
+        assert!(soda.is_caffeinated());
         assert_eq!(soda.volume_ounces(), 12);
     }
 
@@ -397,7 +435,7 @@ mod tests {
     #[test]
     fn test_with_size() {
         let soda = create_test_soda();
-        let large_soda = soda.with_size(SodaSize::Large, 1.5).unwrap();
+        let large_soda = soda.with_size(SodaSize::Large, 150).unwrap();
         
         assert_eq!(large_soda.size(), SodaSize::Large);
         assert_eq!(large_soda.price(), Money::from_dollars_cents(2, 25).unwrap()); // $1.50 * 1.5
@@ -407,12 +445,20 @@ mod tests {
     #[test]
     fn test_with_size_invalid_multiplier() {
         let soda = create_test_soda();
-        let result = soda.with_size(SodaSize::Large, -1.0);
+        let result = soda.with_size(SodaSize::Large, 0);
         
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), SodaError::InvalidPrice);
     }
 
+    #[test]
+    fn test_with_size_rounds_half_up() {
+        let soda = create_test_soda();
+        let small_soda = soda.with_size(SodaSize::Small, 75).unwrap();
+
+        assert_eq!(small_soda.price(), Money::from_cents(113)); // $1.125 rounds up to $1.13
+    }
+
     #[test]
     fn test_with_price() {
         let soda = create_test_soda();
@@ -557,6 +603,16 @@ mod tests {
         assert_eq!(SodaSize::from_string("invalid"), None);
     }
 
+    #[test]
+    fn test_codes_read_back() {
+        for flavor in [SodaFlavor::Cola, SodaFlavor::LemonLime, SodaFlavor::RootBeer, SodaFlavor::Watermelon] {
+            assert_eq!(SodaFlavor::from_string(flavor.code()), Some(flavor));
+        }
+        for size in [SodaSize::Small, SodaSize::Medium, SodaSize::Large, SodaSize::XLarge] {
+            assert_eq!(SodaSize::from_string(size.code()), Some(size));
+        }
+    }
+
     #[test]
     fn test_size_ordering() {
         assert!(SodaSize::Small < SodaSize::Medium);
@@ -0,0 +1,297 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Represents a point in time as milliseconds since the Unix epoch (UTC)
+/// This is a value object so domain logic can reason about time without a clock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamp(i64);
+
+/// Error returned when text is not an RFC 3339 time in UTC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampParseError(String);
+
+impl Timestamp {
+    /// The Unix epoch (1970-01-01T00:00:00Z)
+    pub const EPOCH: Timestamp = Timestamp(0);
+
+    /// Creates a timestamp from milliseconds since the Unix epoch
+    ///
+    /// # Arguments
+    /// * `millis` - Milliseconds since 1970-01-01T00:00:00Z
+    ///
+    /// # Returns
+    /// * `Timestamp` - The timestamp
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::value_objects::timestamp::Timestamp;
+    ///
+    /// let timestamp = Timestamp::from_millis(1_700_000_000_000);
+    /// assert_eq!(timestamp.to_string(), "2023-11-14T22:13:20.000Z");
+    /// ```
+    pub fn from_millis(millis: i64) -> Self {
+        Timestamp(millis)
+    }
+
+    /// Creates a timestamp from whole seconds since the Unix epoch
+    pub fn from_secs(secs: i64) -> Self {
+        Timestamp(secs.saturating_mul(1000))
+    }
+
+    /// Reads the current time from the operating system
+    pub fn now() -> Self {
+        let millis = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_millis() as i64,
+            Err(before) => -(before.duration().as_millis() as i64),
+        };
+        Timestamp(millis)
+    }
+
+    /// Gets the milliseconds since the Unix epoch
+    pub fn as_millis(&self) -> i64 {
+        self.0
+    }
+
+    /// Returns the timestamp moved forward by a duration
+    ///
+    /// # Arguments
+    /// * `duration` - How far to move forward
+    ///
+    /// # Returns
+    /// * `Timestamp` - The later timestamp, saturating at the end of the range
+    pub fn plus(self, duration: Duration) -> Self {
+        let millis = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+        Timestamp(self.0.saturating_add(millis))
+    }
+
+    /// Gets the minutes since midnight UTC, from 0 to 1439
+    pub fn minute_of_day(&self) -> u32 {
+        (self.0.rem_euclid(86_400_000) / 60_000) as u32
+    }
+
+    /// Gets midnight UTC of the timestamp's day
+    pub fn start_of_day(&self) -> Timestamp {
+        Timestamp(self.0.div_euclid(86_400_000) * 86_400_000)
+    }
+
+    /// Gets midnight UTC of the Monday starting the timestamp's week
+    pub fn start_of_week(&self) -> Timestamp {
+        let days = self.0.div_euclid(86_400_000);
+        // The Unix epoch was a Thursday
+        let days_since_monday = (days + 3).rem_euclid(7);
+        Timestamp((days - days_since_monday) * 86_400_000)
+    }
+
+    /// Gets midnight UTC of the first day of the timestamp's month
+    ///
+    /// # Examples
+    /// ```
+    /// use soda_core::domain::value_objects::timestamp::Timestamp;
+    ///
+    /// let timestamp: Timestamp = "2024-02-29T18:45:00Z".parse().unwrap();
+    /// assert_eq!(timestamp.start_of_month().to_string(), "2024-02-01T00:00:00.000Z");
+    /// ```
+    pub fn start_of_month(&self) -> Timestamp {
+        let days = self.0.div_euclid(86_400_000);
+        let (_, _, day) = civil_from_days(days);
+        Timestamp((days - (day as i64 - 1)) * 86_400_000)
+    }
+
+    /// Gets the time elapsed since an earlier timestamp
+    ///
+    /// # Arguments
+    /// * `earlier` - The earlier timestamp
+    ///
+    /// # Returns
+    /// * `Duration` - The elapsed time, or zero if `earlier` is actually later
+    pub fn duration_since(&self, earlier: Timestamp) -> Duration {
+        let millis = self.0.saturating_sub(earlier.0);
+        Duration::from_millis(millis.max(0) as u64)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Formats the timestamp as RFC 3339 in UTC, e.g. "2024-03-01T12:30:00.000Z"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0.div_euclid(86_400_000);
+        let millis_of_day = self.0.rem_euclid(86_400_000);
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            millis_of_day / 3_600_000,
+            millis_of_day / 60_000 % 60,
+            millis_of_day / 1000 % 60,
+            millis_of_day % 1000
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = TimestampParseError;
+
+    /// Reads an RFC 3339 time in UTC as `Display` writes it, e.g. "2024-03-01T12:30:00Z"
+    /// or "2024-03-01T12:30:00.000Z"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TimestampParseError(s.to_string());
+
+        let text = s.trim();
+        let text = text.strip_suffix('Z').or_else(|| text.strip_suffix('z')).ok_or_else(invalid)?;
+        let (date, time) = text.split_once(['T', 't']).ok_or_else(invalid)?;
+        let (time, millis) = match time.split_once('.') {
+            Some((time, fraction)) if (1..=3).contains(&fraction.len()) => {
+                let padded = format!("{:0<3}", fraction);
+                (time, padded.parse::<i64>().map_err(|_| invalid())?)
+            },
+            Some(_) => return Err(invalid()),
+            None => (time, 0),
+        };
+
+        let number = |part: Option<&str>, digits: usize| -> Result<i64, TimestampParseError> {
+            part.filter(|part| part.len() == digits && part.chars().all(|c| c.is_ascii_digit()))
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(invalid)
+        };
+
+        let mut date = date.split('-');
+        let (year, month, day) = (number(date.next(), 4)?, number(date.next(), 2)?, number(date.next(), 2)?);
+        let mut time = time.split(':');
+        let (hour, minute, second) = (number(time.next(), 2)?, number(time.next(), 2)?, number(time.next(), 2)?);
+        if date.next().is_some() || time.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+            return Err(invalid());
+        }
+
+        let days = days_from_civil(year, month as u32, day as u32).ok_or_else(invalid)?;
+        Ok(Timestamp(((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis))
+    }
+}
+
+impl fmt::Display for TimestampParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a UTC time like 2024-03-01T12:30:00Z", self.0)
+    }
+}
+
+impl std::error::Error for TimestampParseError {}
+
+/// Converts a date in the proleptic Gregorian calendar to days since the Unix epoch,
+/// None if the month or day does not exist
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    // Days past the end of the month roll over into the next one
+    (civil_from_days(days).2 == day).then_some(days)
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_millis() {
+        let timestamp = Timestamp::from_millis(1_500);
+        assert_eq!(timestamp.as_millis(), 1_500);
+        assert_eq!(Timestamp::from_secs(2).as_millis(), 2_000);
+    }
+
+    #[test]
+    fn test_plus() {
+        let timestamp = Timestamp::from_secs(10).plus(Duration::from_millis(250));
+        assert_eq!(timestamp.as_millis(), 10_250);
+        assert_eq!(Timestamp::from_millis(i64::MAX).plus(Duration::from_secs(1)).as_millis(), i64::MAX);
+    }
+
+    #[test]
+    fn test_duration_since() {
+        let start = Timestamp::from_secs(100);
+        let end = Timestamp::from_secs(130);
+
+        assert_eq!(end.duration_since(start), Duration::from_secs(30));
+        assert_eq!(start.duration_since(end), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Timestamp::from_secs(1) < Timestamp::from_secs(2));
+        assert_eq!(Timestamp::default(), Timestamp::EPOCH);
+    }
+
+    #[test]
+    fn test_now_is_after_2020() {
+        assert!(Timestamp::now() > Timestamp::from_secs(1_577_836_800));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Timestamp::EPOCH.to_string(), "1970-01-01T00:00:00.000Z");
+        assert_eq!(Timestamp::from_millis(951_782_400_123).to_string(), "2000-02-29T00:00:00.123Z");
+        assert_eq!(Timestamp::from_secs(1_709_296_200).to_string(), "2024-03-01T12:30:00.000Z");
+        assert_eq!(Timestamp::from_millis(-1).to_string(), "1969-12-31T23:59:59.999Z");
+    }
+
+    #[test]
+    fn test_parse_reads_display_back() {
+        for millis in [0, 951_782_400_123, 1_709_296_200_000, -1] {
+            let timestamp = Timestamp::from_millis(millis);
+            assert_eq!(timestamp.to_string().parse::<Timestamp>(), Ok(timestamp));
+        }
+        assert_eq!("2024-03-01T12:30:00Z".parse::<Timestamp>(), Ok(Timestamp::from_secs(1_709_296_200)));
+        assert_eq!("2024-03-01T12:30:00.5Z".parse::<Timestamp>(), Ok(Timestamp::from_millis(1_709_296_200_500)));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_times() {
+        for text in ["", "2024-03-01", "2024-03-01T12:30:00", "2024-02-30T12:30:00Z", "2024-03-01T24:00:00Z", "2024-3-01T12:30:00Z"] {
+            assert!(text.parse::<Timestamp>().is_err(), "{} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn test_start_of_day_week_and_month() {
+        // A Wednesday
+        let timestamp: Timestamp = "2024-03-06T12:30:00Z".parse().unwrap();
+
+        assert_eq!(timestamp.start_of_day().to_string(), "2024-03-06T00:00:00.000Z");
+        assert_eq!(timestamp.start_of_week().to_string(), "2024-03-04T00:00:00.000Z");
+        assert_eq!(timestamp.start_of_month().to_string(), "2024-03-01T00:00:00.000Z");
+        assert_eq!(Timestamp::EPOCH.start_of_week().to_string(), "1969-12-29T00:00:00.000Z");
+        assert_eq!(Timestamp::from_millis(-1).start_of_month().to_string(), "1969-12-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_minute_of_day() {
+        assert_eq!(Timestamp::from_secs(1_709_296_200).minute_of_day(), 12 * 60 + 30);
+        assert_eq!(Timestamp::from_millis(-1).minute_of_day(), 1439);
+    }
+}
@@ -1,27 +1,60 @@
 pub mod domain {
     pub mod value_objects {
+        pub mod denomination;
         pub mod money;
+        pub mod planogram;
         pub mod soda;
+        pub mod timestamp;
     }
     pub mod entities {
         pub mod slot;
+        pub mod product;
+        pub mod cash_box;
+        pub mod cash_collection;
+        pub mod refund;
+        pub mod price_schedule;
+        pub mod promotion;
+        pub mod sale;
+        pub mod vend;
+        pub mod purchase_session;
     }
     pub mod aggregates {
         pub mod soda_machine;
     }
+    pub mod services {
+        pub mod acceptor_protocol;
+        pub mod change_calculator;
+        pub mod sales_report;
+    }
+    pub mod events {
+        pub mod event_envelope;
+    }
 }
 
 pub mod application {
+    pub mod acceptor_service;
+    pub mod catalog_service;
     pub mod customer_service;
     pub mod operator_service;
+    mod outbox;
+    pub mod reporting_service;
 }
 
 pub mod ports {
     pub mod driving {
+        pub mod acceptor_port;
+        pub mod catalog_port;
         pub mod customer_port;
         pub mod operator_port;
+        pub mod reporting_port;
     }
     pub mod driven {
         pub mod soda_machine_repository_port;
+        pub mod product_catalog_port;
+        pub mod clock_port;
+        pub mod event_publisher_port;
+        pub mod notification_port;
+        pub mod sales_ledger_port;
+        pub mod hardware_port;
     }
 }
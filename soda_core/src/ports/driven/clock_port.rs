@@ -0,0 +1,17 @@
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// Tells the application what time it is
+/// Services read the time through this port so tests can control it
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// Clock backed by the operating system's wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+
+use crate::domain::events::event_envelope::EventEnvelope;
+
+#[derive(Debug)]
+pub enum PublishError {
+    ConnectionError(String),
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
+            PublishError::Other(err) => write!(f, "Publish error: {}", err),
+        }
+    }
+}
+
+impl Error for PublishError {}
+
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(&self, events: &[EventEnvelope]) -> Result<(), PublishError>;
+}
+
+/// Publisher that drops every event, used when nobody listens
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullEventPublisher;
+
+#[async_trait]
+impl EventPublisher for NullEventPublisher {
+    async fn publish(&self, _events: &[EventEnvelope]) -> Result<(), PublishError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::aggregates::soda_machine::SodaMachineId;
+use crate::domain::entities::slot::SlotId;
+use crate::domain::entities::vend::VendFailure;
+use crate::domain::value_objects::money::Money;
+
+/// What the motor of a slot reports after turning its spiral
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VendOutcome {
+    /// The drop sensor saw the soda fall into the tray
+    Dropped,
+    Failed(VendFailure),
+}
+
+/// What a coin acceptor or bill validator did with the money the customer put in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Acceptance {
+    /// The money is held in escrow until it is credited or ejected
+    Accepted,
+    /// The money was handed back straight away, e.g. a worn coin or a crumpled bill
+    Rejected { reason: String },
+}
+
+#[derive(Debug)]
+pub enum HardwareError {
+    /// The device did not answer in time
+    Timeout(String),
+    DeviceFault(String),
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for HardwareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HardwareError::Timeout(device) => write!(f, "Hardware timeout: {} did not answer", device),
+            HardwareError::DeviceFault(msg) => write!(f, "Device fault: {}", msg),
+            HardwareError::Other(err) => write!(f, "Hardware error: {}", err),
+        }
+    }
+}
+
+impl Error for HardwareError {}
+
+/// Turns the spiral of a slot to push one soda out
+#[async_trait]
+pub trait VendingMotor: Send + Sync {
+    async fn vend(&self, machine_id: SodaMachineId, slot_id: SlotId) -> Result<VendOutcome, HardwareError>;
+}
+
+/// Checks the coins customers put in and holds them until they are credited
+#[async_trait]
+pub trait CoinAcceptor: Send + Sync {
+    async fn accept(&self, machine_id: SodaMachineId, coin: Money) -> Result<Acceptance, HardwareError>;
+    /// Drops a coin held in escrow back into the return cup
+    async fn eject(&self, machine_id: SodaMachineId, coin: Money) -> Result<(), HardwareError>;
+}
+
+/// Checks the bills customers feed in and holds them until they are credited
+#[async_trait]
+pub trait BillValidator: Send + Sync {
+    async fn validate(&self, machine_id: SodaMachineId, bill: Money) -> Result<Acceptance, HardwareError>;
+    /// Pushes a bill held in escrow back out of the slot
+    async fn eject(&self, machine_id: SodaMachineId, bill: Money) -> Result<(), HardwareError>;
+}
+
+/// The screen on the front of the machine
+#[async_trait]
+pub trait CustomerDisplay: Send + Sync {
+    async fn show(&self, machine_id: SodaMachineId, message: &str) -> Result<(), HardwareError>;
+}
+
+/// Tells whether an operator has the front door open
+#[async_trait]
+pub trait DoorSensor: Send + Sync {
+    async fn is_open(&self, machine_id: SodaMachineId) -> Result<bool, HardwareError>;
+}
+
+/// The devices of a machine the application services drive around the domain's commands
+#[derive(Clone)]
+pub struct VendingHardware {
+    pub motor: Arc<dyn VendingMotor>,
+    pub coin_acceptor: Arc<dyn CoinAcceptor>,
+    pub bill_validator: Arc<dyn BillValidator>,
+    pub display: Arc<dyn CustomerDisplay>,
+    pub door: Arc<dyn DoorSensor>,
+}
+
+impl VendingHardware {
+    /// Uses one driver for every device, e.g. a board that controls the whole machine
+    ///
+    /// # Arguments
+    /// * `driver` - The driver of all the devices
+    ///
+    /// # Returns
+    /// * `VendingHardware` - The devices, all backed by the driver
+    pub fn from_driver<D>(driver: Arc<D>) -> Self
+    where
+        D: VendingMotor + CoinAcceptor + BillValidator + CustomerDisplay + DoorSensor + 'static,
+    {
+        VendingHardware {
+            motor: driver.clone(),
+            coin_acceptor: driver.clone(),
+            bill_validator: driver.clone(),
+            display: driver.clone(),
+            door: driver,
+        }
+    }
+}
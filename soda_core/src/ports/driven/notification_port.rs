@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+
+use crate::domain::aggregates::soda_machine::{SodaMachineEvent, SodaMachineId};
+use crate::domain::entities::slot::SlotId;
+use crate::domain::entities::vend::VendFailure;
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// What happened to the stock of a slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StockAlertKind {
+    LowStock { quantity: u32, threshold: u32 },
+    SoldOut,
+    Restocked { quantity: u32 },
+    /// A soda did not reach the customer, a stuck can needs to be freed
+    VendFailed { failure: VendFailure },
+}
+
+/// Tells whoever refills the machines that a slot needs (or no longer needs) a visit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StockAlert {
+    pub machine_id: SodaMachineId,
+    pub slot_id: SlotId,
+    pub kind: StockAlertKind,
+    pub raised_at: Timestamp,
+}
+
+impl StockAlert {
+    /// Gets the alert a stock event reports, None for every other event
+    pub fn from_event(machine_id: SodaMachineId, event: &SodaMachineEvent, raised_at: Timestamp) -> Option<Self> {
+        let (slot_id, kind) = match event {
+            SodaMachineEvent::SlotLowStock { slot_id, quantity, threshold } => {
+                (*slot_id, StockAlertKind::LowStock { quantity: *quantity, threshold: *threshold })
+            },
+            SodaMachineEvent::SlotSoldOut { slot_id } => (*slot_id, StockAlertKind::SoldOut),
+            SodaMachineEvent::SlotRestocked { slot_id, quantity } => {
+                (*slot_id, StockAlertKind::Restocked { quantity: *quantity })
+            },
+            SodaMachineEvent::VendFailed { vend, failure, .. } => {
+                (vend.slot_id, StockAlertKind::VendFailed { failure: *failure })
+            },
+            _ => return None,
+        };
+
+        Some(StockAlert { machine_id, slot_id, kind, raised_at })
+    }
+}
+
+impl fmt::Display for StockAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} machine {} slot {}: ", self.raised_at, self.machine_id, self.slot_id.value())?;
+        match self.kind {
+            StockAlertKind::LowStock { quantity, threshold } => {
+                write!(f, "low on stock, {} left (threshold {})", quantity, threshold)
+            },
+            StockAlertKind::SoldOut => write!(f, "sold out"),
+            StockAlertKind::Restocked { quantity } => write!(f, "restocked, {} in stock", quantity),
+            StockAlertKind::VendFailed { failure } => write!(f, "vend failed ({})", failure),
+        }
+    }
+}
+
+/// Something a saved command could not hand over, reported instead of failing the command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryFailure {
+    /// The notifier did not take a stock alert, it is not sent again
+    StockAlert { alert: StockAlert, reason: String },
+    /// The sales ledger did not take a batch of sales, they are sent again after the next command
+    Sales { count: usize, reason: String },
+    /// The publisher did not take a batch of events, they are sent again after the next command
+    Events { count: usize, reason: String },
+}
+
+impl fmt::Display for DeliveryFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryFailure::StockAlert { alert, reason } => {
+                write!(f, "Failed to send stock alert '{}': {}", alert, reason)
+            },
+            DeliveryFailure::Sales { count, reason } => {
+                write!(f, "Failed to record {} sales, they are kept for the next command: {}", count, reason)
+            },
+            DeliveryFailure::Events { count, reason } => {
+                write!(f, "Failed to publish {} events, they are kept for the next command: {}", count, reason)
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NotificationError {
+    ConnectionError(String),
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for NotificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotificationError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
+            NotificationError::Other(err) => write!(f, "Notification error: {}", err),
+        }
+    }
+}
+
+impl Error for NotificationError {}
+
+/// Sends stock alerts to whoever refills the machines
+///
+/// Alerts are sent after the command that raised them was saved, so a failed alert is reported
+/// through `report` but never undoes or fails the command.
+#[async_trait]
+pub trait NotificationPort: Send + Sync {
+    async fn notify(&self, alert: &StockAlert) -> Result<(), NotificationError>;
+
+    /// Reports what a saved command could not hand over, e.g. an alert `notify` refused
+    ///
+    /// Reporting is the last resort, so it cannot fail. Notifiers that have nowhere to keep
+    /// the report drop it.
+    async fn report(&self, _failure: &DeliveryFailure) {}
+}
+
+/// Notifier that drops every alert, used when nobody needs to be told
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullNotifier;
+
+#[async_trait]
+impl NotificationPort for NullNotifier {
+    async fn notify(&self, _alert: &StockAlert) -> Result<(), NotificationError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+
+use crate::domain::entities::product::{Product, Sku};
+use crate::ports::driven::soda_machine_repository_port::RepositoryError;
+
+/// Stores the product catalog, shared by every machine of the fleet
+#[async_trait]
+pub trait ProductCatalogRepository: Send + Sync {
+    async fn find_product(&self, sku: &Sku) -> Result<Option<Product>, RepositoryError>;
+
+    /// Adds the product, or replaces the stored product with the same SKU
+    async fn save_product(&self, product: &Product) -> Result<(), RepositoryError>;
+
+    /// Gets every product, ordered by SKU
+    async fn list_products(&self) -> Result<Vec<Product>, RepositoryError>;
+
+    /// Removes a product, returning whether it existed
+    async fn delete_product(&self, sku: &Sku) -> Result<bool, RepositoryError>;
+}
@@ -0,0 +1,30 @@
+use async_trait::async_trait;
+
+use crate::domain::entities::sale::Sale;
+use crate::domain::value_objects::timestamp::Timestamp;
+use crate::ports::driven::soda_machine_repository_port::RepositoryError;
+
+/// Keeps every soda sold by the fleet, so sales can be reported on after the machines moved on
+#[async_trait]
+pub trait SalesLedger: Send + Sync {
+    /// Adds the sales, a sale already in the ledger (same machine and sequence) is kept as it is
+    async fn record(&self, sales: &[Sale]) -> Result<(), RepositoryError>;
+
+    /// Gets the sales made from `from` until just before `until`, oldest first
+    async fn sales_between(&self, from: Timestamp, until: Timestamp) -> Result<Vec<Sale>, RepositoryError>;
+}
+
+/// Ledger that forgets every sale, used when nobody reports on sales
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullSalesLedger;
+
+#[async_trait]
+impl SalesLedger for NullSalesLedger {
+    async fn record(&self, _sales: &[Sale]) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    async fn sales_between(&self, _from: Timestamp, _until: Timestamp) -> Result<Vec<Sale>, RepositoryError> {
+        Ok(Vec::new())
+    }
+}
@@ -3,10 +3,13 @@ use std::error::Error;
 use std::fmt;
 
 use crate::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
+use crate::domain::entities::product::Sku;
 
 #[derive(Debug)]
 pub enum RepositoryError {
     ConnectionError(String),
+    /// The machine was saved by someone else since it was loaded
+    ConcurrencyConflict { machine_id: SodaMachineId, expected_version: u64, actual_version: u64 },
     Other(Box<dyn Error + Send + Sync>),
 }
 
@@ -14,6 +17,11 @@ impl fmt::Display for RepositoryError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             RepositoryError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
+            RepositoryError::ConcurrencyConflict { machine_id, expected_version, actual_version } => write!(
+                f,
+                "Concurrency conflict: machine {} was loaded at version {} but is now at version {}",
+                machine_id, expected_version, actual_version
+            ),
             RepositoryError::Other(err) => write!(f, "Repository error: {}", err),
         }
     }
@@ -21,9 +29,88 @@ impl fmt::Display for RepositoryError {
 
 impl Error for RepositoryError {}
 
+/// Criteria to find machines by, a machine matches if it meets every criterion that is set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachineFilter {
+    /// Only machines that are (or are not) operational
+    pub operational: Option<bool>,
+    /// Only machines with a stocked slot down to this many sodas or fewer
+    pub low_stock_at_or_below: Option<u32>,
+    /// Only machines whose location contains this text, ignoring case
+    pub location: Option<String>,
+    /// Only machines with a slot selling this catalog product
+    pub product: Option<Sku>,
+}
+
+impl MachineFilter {
+    /// A filter that matches every machine
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches machines that are (or are not) operational
+    pub fn operational(mut self, operational: bool) -> Self {
+        self.operational = Some(operational);
+        self
+    }
+
+    /// Only matches machines with a stocked slot down to `threshold` sodas or fewer
+    pub fn low_stock(mut self, threshold: u32) -> Self {
+        self.low_stock_at_or_below = Some(threshold);
+        self
+    }
+
+    /// Only matches machines whose location contains `location`, ignoring case
+    pub fn location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Only matches machines with a slot selling the catalog product `sku`
+    pub fn sells(mut self, sku: Sku) -> Self {
+        self.product = Some(sku);
+        self
+    }
+
+    /// Checks if a machine meets every criterion of the filter
+    pub fn matches(&self, machine: &SodaMachine) -> bool {
+        let operational = self.operational
+            .is_none_or(|operational| machine.is_operational() == operational);
+        let low_stock = self.low_stock_at_or_below
+            .is_none_or(|threshold| machine.has_low_stock(threshold));
+        let location = self.location.as_ref().is_none_or(|wanted| {
+            machine.location()
+                .is_some_and(|location| location.to_lowercase().contains(&wanted.to_lowercase()))
+        });
+
+        let product = self.product.as_ref().is_none_or(|sku| machine.sells(sku));
+
+        operational && low_stock && location && product
+    }
+}
+
 #[async_trait]
 pub trait SodaMachineRepository: Send + Sync {
     async fn find_by_id(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError>;
+    /// Saves a machine, failing with `ConcurrencyConflict` if the stored machine is no longer
+    /// at the version it was loaded at (`SodaMachine::committed_version`)
     async fn save(&self, machine: &SodaMachine) -> Result<(), RepositoryError>;
     async fn create(&self, machine: &SodaMachine) -> Result<(), RepositoryError>;
+
+    /// Gets every machine, ordered by ID
+    async fn list(&self) -> Result<Vec<SodaMachine>, RepositoryError>;
+
+    /// Removes a machine, returning whether it existed
+    async fn delete(&self, id: SodaMachineId) -> Result<bool, RepositoryError>;
+
+    /// Checks if a machine is stored
+    async fn exists(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        Ok(self.find_by_id(id).await?.is_some())
+    }
+
+    /// Gets the machines matching a filter, ordered by ID
+    async fn query(&self, filter: &MachineFilter) -> Result<Vec<SodaMachine>, RepositoryError> {
+        let machines = self.list().await?;
+        Ok(machines.into_iter().filter(|machine| filter.matches(machine)).collect())
+    }
 }
\ No newline at end of file
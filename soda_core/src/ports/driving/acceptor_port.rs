@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use crate::domain::value_objects::money::{Currency, Money};
+use crate::ports::driving::customer_port::CustomerError;
+
+/// What became of the money a coin acceptor or bill validator reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptorOutcomeDTO {
+    /// The money was credited to the customer
+    Credited { amount: Money },
+    /// The device must hand the money back
+    Returned { reason: String },
+}
+
+#[derive(Debug)]
+pub enum AcceptorError {
+    /// The device sent a line that is not a message it knows, any money stays in escrow
+    InvalidMessage(String),
+    /// The money could not be credited, the device must hand it back
+    CreditFailure(CustomerError),
+    /// The devices are programmed for another currency than the machine takes, the device must hand the money back
+    CurrencyMismatch { machine: Currency, acceptor: Currency },
+}
+
+impl std::fmt::Display for AcceptorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AcceptorError::InvalidMessage(msg) => write!(f, "{}", msg),
+            AcceptorError::CreditFailure(e) => write!(f, "Credit failure: {}", e),
+            AcceptorError::CurrencyMismatch { machine, acceptor } => {
+                write!(f, "The acceptor is set up for {} but the machine takes {}", acceptor, machine)
+            },
+        }
+    }
+}
+
+impl std::error::Error for AcceptorError {}
+
+#[async_trait]
+pub trait AcceptorPort {
+    /// Handles one message of a machine's coin acceptor or bill validator, e.g. `COIN 3`
+    async fn handle_device_message(&self, machine_id: u32, message: &str) -> Result<AcceptorOutcomeDTO, AcceptorError>;
+}
@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use crate::domain::aggregates::soda_machine::{SodaMachineError, SodaMachineId};
+use crate::domain::entities::product::{Product, ProductError, Sku};
+use crate::domain::value_objects::money::Money;
+
+/// One product of the catalog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductDTO {
+    pub sku: String,
+    pub name: String,
+    /// The product as it is loaded on the truck, e.g. "Cola Cola - 12 oz (Caffeinated)"
+    pub description: String,
+    pub barcode: Option<String>,
+    pub default_price: Money,
+}
+
+/// What a catalog-wide price change reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceUpdateDTO {
+    pub sku: String,
+    pub price: Money,
+    /// IDs of the machines whose slots now sell at the new price
+    pub machines: Vec<u32>,
+    pub slots_repriced: usize,
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    ProductError(ProductError),
+    ProductNotFound(Sku),
+    ProductAlreadyExists(Sku),
+    /// The product is still sold by the listed machines
+    ProductInUse { sku: Sku, machines: Vec<SodaMachineId> },
+    MachineError(SodaMachineError),
+    SodaMachineNotFound(SodaMachineId),
+    RepositoryUnavailable(String),
+    RepositoryFailure(String),
+    ConcurrencyConflict(SodaMachineId),
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::ProductError(e) => write!(f, "Product error: {}", e),
+            CatalogError::ProductNotFound(sku) => write!(f, "Product not found: {}", sku),
+            CatalogError::ProductAlreadyExists(sku) => write!(f, "Product {} is already in the catalog", sku),
+            CatalogError::ProductInUse { sku, machines } => {
+                let ids: Vec<String> = machines.iter().map(|id| id.to_string()).collect();
+                write!(f, "Product {} is still sold by machines {}", sku, ids.join(", "))
+            },
+            CatalogError::MachineError(e) => write!(f, "Machine error: {}", e),
+            CatalogError::SodaMachineNotFound(id) => write!(f, "Soda machine not found: {:?}", id),
+            CatalogError::RepositoryUnavailable(msg) => write!(f, "Repository unavailable: {}", msg),
+            CatalogError::RepositoryFailure(msg) => write!(f, "Repository failure: {}", msg),
+            CatalogError::ConcurrencyConflict(id) => {
+                write!(f, "Soda machine {} kept changing while saving, please try again", id)
+            },
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+#[async_trait]
+pub trait CatalogPort {
+    async fn add_product(&self, product: Product) -> Result<(), CatalogError>;
+    async fn get_product(&self, sku: &str) -> Result<ProductDTO, CatalogError>;
+    async fn list_products(&self) -> Result<Vec<ProductDTO>, CatalogError>;
+    async fn remove_product(&self, sku: &str) -> Result<(), CatalogError>;
+    /// Changes a product's default price and passes it on to every slot selling the product
+    async fn update_price(&self, sku: &str, price: Money) -> Result<PriceUpdateDTO, CatalogError>;
+    /// Configures a slot to sell a catalog product, adding the slot if the machine does not have it yet
+    async fn assign_product(&self, machine_id: u32, slot_id: u32, capacity: u32, sku: &str) -> Result<(), CatalogError>;
+}
@@ -1,12 +1,23 @@
 use async_trait::async_trait;
 use crate::domain::aggregates::soda_machine::{SodaMachineError, SodaMachineId};
-use crate::domain::value_objects::money::Money;
+use crate::domain::entities::vend::VendFailure;
+use crate::domain::value_objects::denomination::{AcceptedDenominations, DenominationKind};
+use crate::domain::value_objects::money::{Currency, Money};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct AvailableSodaDTO {
     pub slot_id: u32,
     pub soda_name: String,
     pub price: String,
+    pub currency: Currency,
+}
+
+/// The money a machine takes, as its coin acceptor and bill validator need to know it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcceptedMoneyDTO {
+    pub currency: Currency,
+    /// The only coins and bills a machine in strict mode takes, None if it takes any of its currency
+    pub denominations: Option<AcceptedDenominations>,
 }
 
 #[derive(Debug)]
@@ -15,7 +26,15 @@ pub enum CustomerError {
     SodaMachineNotFound(SodaMachineId),
     RepositoryUnavailable(String),
     RepositoryFailure(String),
+    ConcurrencyConflict(SodaMachineId),
     Validation(String),
+    /// The coin acceptor or bill validator handed the money back
+    MoneyRejected { amount: Money, reason: String },
+    /// The soda did not reach the customer, who keeps their credit
+    VendFailed(VendFailure),
+    /// An operator is servicing the machine
+    DoorOpen(SodaMachineId),
+    HardwareFailure(String),
 }
 
 impl std::fmt::Display for CustomerError {
@@ -25,7 +44,16 @@ impl std::fmt::Display for CustomerError {
             CustomerError::SodaMachineNotFound(id) => write!(f, "Soda machine not found: {:?}", id),
             CustomerError::RepositoryUnavailable(msg) => write!(f, "Repository unavailable: {}", msg),
             CustomerError::RepositoryFailure(msg) => write!(f, "Repository failure: {}", msg),
+            CustomerError::ConcurrencyConflict(id) => {
+                write!(f, "Soda machine {} kept changing while saving, please try again", id)
+            },
             CustomerError::Validation(msg) => write!(f, "Validation error: {}", msg),
+            CustomerError::MoneyRejected { amount, reason } => write!(f, "{} was rejected: {}", amount, reason),
+            CustomerError::VendFailed(failure) => {
+                write!(f, "The soda could not be vended ({}), your credit is kept", failure)
+            },
+            CustomerError::DoorOpen(id) => write!(f, "Soda machine {} is being serviced, please wait", id),
+            CustomerError::HardwareFailure(msg) => write!(f, "Hardware failure: {}", msg),
         }
     }
 }
@@ -36,6 +64,19 @@ impl std::error::Error for CustomerError {}
 pub trait CustomerPort {
     async fn list_available_sodas(&self, machine_id: u32) -> Result<Vec<AvailableSodaDTO>, CustomerError>;
     async fn insert_money(&self, machine_id: u32, amount: Money) -> Result<(), CustomerError>;
+    /// Gets the currency of a machine and, in strict mode, the coins and bills it takes
+    async fn accepted_money(&self, machine_id: u32) -> Result<AcceptedMoneyDTO, CustomerError>;
+    /// Credits a single coin or bill the coin acceptor or the bill validator already took, as its kind says,
+    /// handing it back through that device if the machine refuses it
+    async fn insert_denomination(&self, machine_id: u32, kind: DenominationKind, amount: Money) -> Result<(), CustomerError>;
+    /// Enters a coupon code, taken off the next soda of the session it applies to
+    async fn enter_coupon(&self, machine_id: u32, code: &str) -> Result<(), CustomerError>;
     async fn buy_soda(&self, machine_id: u32, slot_id: u32) -> Result<(), CustomerError>;
+    /// Starts buying a soda from a mechanism that reports whether it dropped, holding its price from the credit
+    async fn reserve_soda(&self, machine_id: u32, slot_id: u32) -> Result<(), CustomerError>;
+    /// Charges the reserved soda, reported by the mechanism once it saw the soda drop
+    async fn confirm_vend(&self, machine_id: u32) -> Result<(), CustomerError>;
+    /// Gives the customer back the credit held for the reserved soda, reported by the mechanism
+    async fn report_vend_failure(&self, machine_id: u32, failure: VendFailure) -> Result<(), CustomerError>;
     async fn request_money_back(&self, machine_id: u32) -> Result<Money, CustomerError>;
 }
@@ -1,6 +1,346 @@
+use std::time::Duration;
 use async_trait::async_trait;
 use crate::domain::value_objects::soda::Soda;
+use crate::domain::value_objects::money::{Currency, Money};
+use crate::domain::value_objects::timestamp::Timestamp;
 use crate::domain::aggregates::soda_machine::{SodaMachineError, SodaMachineId};
+use crate::domain::entities::vend::VendFailure;
+use crate::ports::driven::soda_machine_repository_port::MachineFilter;
+
+/// One line of the machine overview, so operators can pick a machine without knowing its ID
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineSummaryDTO {
+    pub machine_id: u32,
+    pub location: Option<String>,
+    pub is_operational: bool,
+    pub slot_count: usize,
+    pub total_sodas: u32,
+    pub currency: Currency,
+}
+
+/// Contents of one slot of a machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotStatusDTO {
+    pub slot_id: u32,
+    /// Name of the soda the slot is configured for, None if it is not configured yet
+    pub soda_name: Option<String>,
+    /// SKU of the catalog product the slot sells, None for a soda configured by hand
+    pub sku: Option<String>,
+    /// The price a customer pays right now, including the machine's own price for the product
+    pub price: Option<Money>,
+    pub quantity: u32,
+    pub max_capacity: u32,
+    /// How full the slot is, from 0.0 to 1.0
+    pub fill_percentage: f64,
+    /// The slot is reported as low on stock once it is down to this many sodas
+    pub low_stock_threshold: u32,
+    pub is_enabled: bool,
+}
+
+/// Full status of one machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineStatusDTO {
+    pub machine_id: u32,
+    pub location: Option<String>,
+    pub currency: Currency,
+    pub is_operational: bool,
+    /// Every slot, ordered by ID
+    pub slots: Vec<SlotStatusDTO>,
+    /// Number of slots a customer can buy a soda from
+    pub available_sodas: usize,
+    pub total_sodas: u32,
+    pub inventory_value: Money,
+    /// Credit of the customer session in progress
+    pub inserted_money: Money,
+    /// Revenue since the cash was last collected
+    pub total_collected: Money,
+    /// Coins and bills available to pay out change
+    pub cash_in_box: Money,
+    /// The soda the vending mechanism is still delivering
+    pub pending_vend: Option<PendingVendDTO>,
+}
+
+/// A soda whose vend was reserved but neither confirmed nor failed yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingVendDTO {
+    pub slot_id: u32,
+    pub soda_name: String,
+    /// What the customer is charged once the vend is confirmed
+    pub price: Money,
+    pub reserved_at: Timestamp,
+}
+
+impl std::fmt::Display for MachineStatusDTO {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Machine {}", self.machine_id)?;
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+        write!(
+            f,
+            ": {} slots, {} available sodas ({} total), {} inventory value, {} inserted, {} collected, {} in cash box - {}",
+            self.slots.len(),
+            self.available_sodas,
+            self.total_sodas,
+            self.inventory_value,
+            self.inserted_money,
+            self.total_collected,
+            self.cash_in_box,
+            if self.is_operational { "Operational" } else { "Out of Service" }
+        )?;
+
+        for slot in &self.slots {
+            write!(f, "\n  Slot {}: ", slot.slot_id)?;
+            match (&slot.soda_name, slot.price) {
+                (Some(name), Some(price)) => write!(f, "{} at {}", name, price)?,
+                _ => write!(f, "not configured")?,
+            }
+            write!(f, ", {}/{} ({:.0}%)", slot.quantity, slot.max_capacity, slot.fill_percentage * 100.0)?;
+            if !slot.is_enabled {
+                write!(f, ", disabled")?;
+            }
+        }
+
+        if let Some(vend) = &self.pending_vend {
+            write!(f, "\n  Vend pending: {} from slot {} for {} since {}", vend.soda_name, vend.slot_id, vend.price, vend.reserved_at)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What a route planner needs to know about one machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineDashboardDTO {
+    pub machine_id: u32,
+    pub location: Option<String>,
+    pub is_operational: bool,
+    pub total_collected: Money,
+    pub inventory_value: Money,
+    /// Credit of the customer session in progress
+    pub inserted_money: Money,
+    /// Stocked slots that are sold out, ordered by ID
+    pub empty_slots: Vec<u32>,
+    /// Stocked slots down to the low stock threshold but not sold out, ordered by ID
+    pub low_slots: Vec<u32>,
+}
+
+impl MachineDashboardDTO {
+    /// Checks if someone has to visit the machine, because it is out of service or running out of sodas
+    pub fn needs_visit(&self) -> bool {
+        !self.is_operational || !self.empty_slots.is_empty() || !self.low_slots.is_empty()
+    }
+}
+
+/// Fleet totals of all machines operating in one currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrencyTotalsDTO {
+    pub currency: Currency,
+    pub machine_count: usize,
+    pub total_collected: Money,
+    pub inventory_value: Money,
+    pub inserted_money: Money,
+}
+
+/// Overview of every machine in the fleet
+#[derive(Debug, Clone, PartialEq)]
+pub struct FleetDashboardDTO {
+    /// Every machine, ordered by ID
+    pub machines: Vec<MachineDashboardDTO>,
+    /// Totals per currency, since amounts in different currencies cannot be added up
+    pub totals: Vec<CurrencyTotalsDTO>,
+    /// IDs of the machines that are out of service
+    pub out_of_service: Vec<u32>,
+    /// IDs of the machines with empty or low slots
+    pub needs_refill: Vec<u32>,
+}
+
+/// How many sodas go into one slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotRestockDTO {
+    pub slot_id: u32,
+    /// The product as it is loaded on the truck, e.g. "Cola Cola - 16 oz (Caffeinated)"
+    pub product: String,
+    pub units: u32,
+}
+
+/// The sodas one machine of a route needs, or got
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineRestockDTO {
+    pub machine_id: u32,
+    pub location: Option<String>,
+    /// Slots that need (or got) sodas, ordered by ID
+    pub slots: Vec<SlotRestockDTO>,
+}
+
+impl MachineRestockDTO {
+    /// Total number of sodas for the machine
+    pub fn total_units(&self) -> u32 {
+        self.slots.iter().map(|slot| slot.units).sum()
+    }
+}
+
+/// How many units of a product to load on the truck
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickListItemDTO {
+    pub product: String,
+    pub units: u32,
+}
+
+/// Everything a route of machines needs to be filled up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestockPlanDTO {
+    /// What to load on the truck, ordered by product
+    pub pick_list: Vec<PickListItemDTO>,
+    /// What each machine needs, in route order
+    pub machines: Vec<MachineRestockDTO>,
+}
+
+/// Reconciliation of one cash collection, comparing what the operator counted with what the machine expected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CashReconciliationDTO {
+    pub machine_id: u32,
+    pub operator_id: String,
+    pub collected_at: Timestamp,
+    /// When the cash was collected before, None for the machine's first collection
+    pub period_start: Option<Timestamp>,
+    /// What the sodas sold during the period brought in
+    pub revenue: Money,
+    /// Change loaded by operators that was still in the cash box
+    pub float: Money,
+    /// The part of the float left in the cash box as change for the next customers
+    pub kept: Money,
+    /// The revenue plus the float, less what was kept in the cash box
+    pub expected: Money,
+    pub counted: Money,
+    /// The counted amount minus the expected amount, negative if cash is missing
+    pub variance: Money,
+}
+
+impl std::fmt::Display for CashReconciliationDTO {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Machine {} collected by {} at {}", self.machine_id, self.operator_id, self.collected_at)?;
+        match self.period_start {
+            Some(period_start) => writeln!(f, "  Period:   since {}", period_start)?,
+            None => writeln!(f, "  Period:   since the machine was set up")?,
+        }
+        writeln!(f, "  Revenue:  {}", self.revenue)?;
+        writeln!(f, "  Float:    {}", self.float)?;
+        writeln!(f, "  Kept:     {}", self.kept)?;
+        writeln!(f, "  Expected: {}", self.expected)?;
+        writeln!(f, "  Counted:  {}", self.counted)?;
+        write!(f, "  Variance: {}", self.variance)
+    }
+}
+
+/// Money an operator paid back to a customer from a machine's cash box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefundDTO {
+    pub machine_id: u32,
+    pub operator_id: String,
+    pub amount: Money,
+    pub reason: String,
+    pub issued_at: Timestamp,
+    /// The coins and bills to hand over, largest first
+    pub coins: Vec<(Money, u32)>,
+    /// Revenue since the last collection, after the refund
+    pub total_collected: Money,
+}
+
+/// A price a machine charges for a catalog product, now or from a later time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceChangeDTO {
+    pub sku: String,
+    /// None goes back to the price the product's slots were configured with
+    pub price: Option<Money>,
+    pub effective_at: Timestamp,
+    /// Whether the change is still to take effect
+    pub is_upcoming: bool,
+}
+
+/// A promotion as it is written to and read from a campaign file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PromotionDTO {
+    /// Identifies the promotion on each machine, and is the code customers enter for a coupon
+    pub id: String,
+    pub name: String,
+    pub discount: DiscountDTO,
+    /// Only sodas of this flavor get the discount, e.g. "cherry"
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub flavor: Option<String>,
+    /// Only this catalog product gets the discount
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub sku: Option<String>,
+    /// When the promotion starts in UTC, e.g. "2024-06-01T00:00:00Z"
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub valid_from: Option<String>,
+    /// When the promotion ends in UTC
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub valid_until: Option<String>,
+    /// Time of day the promotion runs at in UTC, e.g. "16:00-18:00"
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub hours: Option<String>,
+    /// Whether customers have to enter the ID before the promotion applies
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_coupon: bool,
+    /// Whether the promotion runs right now, ignored when a promotion is launched
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_active: bool,
+}
+
+/// The discount of a campaign file, e.g. `{"percent_off": 20}` or `{"amount_off": "0.50"}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DiscountDTO {
+    PercentOff(u32),
+    /// Amount in the machine's currency, e.g. "0.50"
+    AmountOff(String),
+    BuyNGetOneFree(u32),
+}
+
+/// A machine layout as it is written to and read from a planogram file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanogramDTO {
+    /// The currency the prices are in, must be the machine's currency
+    pub currency: Currency,
+    pub slots: Vec<PlanogramSlotDTO>,
+}
+
+/// One slot of a planogram file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanogramSlotDTO {
+    pub slot_id: u32,
+    pub capacity: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub low_stock_threshold: u32,
+    /// The product the slot sells, left out for a slot that is not configured yet
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub product: Option<PlanogramProductDTO>,
+}
+
+/// A product of a planogram file, spelled the way operators type it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanogramProductDTO {
+    /// The catalog product, left out for a soda configured by hand
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub sku: Option<String>,
+    pub name: String,
+    /// e.g. "cola", "root beer" or "lemon-lime"
+    pub flavor: String,
+    /// "small", "medium", "large" or "x-large"
+    pub size: String,
+    /// Amount in the planogram's currency, e.g. "1.50"
+    pub price: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_diet: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_caffeinated: bool,
+}
 
 #[derive(Debug)]
 pub enum OperatorError {
@@ -8,6 +348,7 @@ pub enum OperatorError {
     SodaMachineNotFound(SodaMachineId),
     RepositoryUnavailable(String),
     RepositoryFailure(String),
+    ConcurrencyConflict(SodaMachineId),
     Validation(String),
 }
 
@@ -18,6 +359,9 @@ impl std::fmt::Display for OperatorError {
             OperatorError::SodaMachineNotFound(id) => write!(f, "Soda machine not found: {:?}", id),
             OperatorError::RepositoryUnavailable(msg) => write!(f, "Repository unavailable: {}", msg),
             OperatorError::RepositoryFailure(msg) => write!(f, "Repository failure: {}", msg),
+            OperatorError::ConcurrencyConflict(id) => {
+                write!(f, "Soda machine {} kept changing while saving, please try again", id)
+            },
             OperatorError::Validation(msg) => write!(f, "Validation error: {}", msg),
         }
     }
@@ -28,6 +372,7 @@ impl std::error::Error for OperatorError {}
 #[async_trait]
 pub trait OperatorPort {
     async fn create_new_machine(&self, machine_id: u32, max_slots: u32) -> Result<(), OperatorError>;
+    async fn create_new_machine_with_currency(&self, machine_id: u32, max_slots: u32, currency: Currency) -> Result<(), OperatorError>;
     async fn configure_slot(
         &self,
         machine_id: u32,
@@ -36,5 +381,46 @@ pub trait OperatorPort {
         soda: Soda
     ) -> Result<(), OperatorError>;
     async fn refill_slot(&self, machine_id: u32, slot_id: u32, quantity: u32) -> Result<(), OperatorError>;
-    async fn get_machine_status(&self, machine_id: u32) -> Result<String, OperatorError>;
+    async fn set_low_stock_threshold(&self, machine_id: u32, slot_id: u32, threshold: u32) -> Result<(), OperatorError>;
+    async fn import_planogram(&self, machine_id: u32, planogram: PlanogramDTO) -> Result<(), OperatorError>;
+    async fn export_planogram(&self, machine_id: u32) -> Result<PlanogramDTO, OperatorError>;
+    /// Makes a machine charge its own price for a catalog product from now on, None goes back to the slot price
+    async fn set_price_override(&self, machine_id: u32, sku: &str, price: Option<Money>) -> Result<(), OperatorError>;
+    /// Sets up a change of a machine's price for a catalog product, taking effect at a later time
+    async fn schedule_price_change(&self, machine_id: u32, sku: &str, price: Option<Money>, effective_at: Timestamp) -> Result<(), OperatorError>;
+    async fn cancel_price_change(&self, machine_id: u32, sku: &str, effective_at: Timestamp) -> Result<(), OperatorError>;
+    /// Gets the machine's own prices in effect now and the changes still to come, ordered by SKU and time
+    async fn list_price_changes(&self, machine_id: u32) -> Result<Vec<PriceChangeDTO>, OperatorError>;
+    /// Starts running a promotion on a machine, amounts are read in the machine's currency
+    async fn add_promotion(&self, machine_id: u32, promotion: PromotionDTO) -> Result<(), OperatorError>;
+    async fn remove_promotion(&self, machine_id: u32, promotion_id: &str) -> Result<(), OperatorError>;
+    /// Gets the promotions a machine runs, in the order they were added
+    async fn list_promotions(&self, machine_id: u32) -> Result<Vec<PromotionDTO>, OperatorError>;
+    async fn plan_restock(&self, machine_ids: &[u32]) -> Result<RestockPlanDTO, OperatorError>;
+    async fn restock_machine(&self, machine_id: u32) -> Result<MachineRestockDTO, OperatorError>;
+    async fn load_cash(&self, machine_id: u32, denomination: Money, count: u32) -> Result<(), OperatorError>;
+    /// Takes change out of a machine's cash box, never the revenue that has not been collected yet
+    async fn unload_cash(&self, machine_id: u32, denomination: Money, count: u32) -> Result<(), OperatorError>;
+    /// Records an operator taking the takings out of a machine's cash box, leaving the float as
+    /// change, and reconciles the amount they counted
+    async fn collect_cash(&self, machine_id: u32, operator_id: &str, counted: Money) -> Result<CashReconciliationDTO, OperatorError>;
+    /// Pays money back to a customer from the cash box and takes it off the machine's revenue
+    async fn issue_refund(&self, machine_id: u32, operator_id: &str, amount: Money, reason: &str) -> Result<RefundDTO, OperatorError>;
+    /// Settles a vend the machine never finished, e.g. after a power cut: None charges the soda the
+    /// operator found delivered, Some(failure) gives the customer their credit back
+    async fn resolve_pending_vend(&self, machine_id: u32, failure: Option<VendFailure>) -> Result<(), OperatorError>;
+    async fn set_session_timeout(&self, machine_id: u32, timeout: Duration) -> Result<(), OperatorError>;
+    /// Puts the machine in strict mode, where customers may only insert single coins and bills from the lists
+    async fn set_accepted_denominations(&self, machine_id: u32, coins: &[Money], bills: &[Money]) -> Result<(), OperatorError>;
+    /// Leaves strict mode, the machine takes any coin or bill of its currency again
+    async fn accept_any_denomination(&self, machine_id: u32) -> Result<(), OperatorError>;
+    async fn expire_idle_session(&self, machine_id: u32) -> Result<Money, OperatorError>;
+    async fn get_machine_status(&self, machine_id: u32) -> Result<MachineStatusDTO, OperatorError>;
+    async fn get_fleet_dashboard(&self, low_stock_threshold: u32) -> Result<FleetDashboardDTO, OperatorError>;
+    async fn list_machines(&self) -> Result<Vec<MachineSummaryDTO>, OperatorError>;
+    async fn find_machines(&self, filter: MachineFilter) -> Result<Vec<MachineSummaryDTO>, OperatorError>;
+    async fn machine_exists(&self, machine_id: u32) -> Result<bool, OperatorError>;
+    /// Decommissions an empty machine and removes it, refused with `MachineInUse` while it holds credit, cash or a vend
+    async fn delete_machine(&self, machine_id: u32) -> Result<(), OperatorError>;
+    async fn set_machine_location(&self, machine_id: u32, location: Option<String>) -> Result<(), OperatorError>;
 }
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use crate::domain::services::sales_report::{ReportDimension, ReportPeriod};
+use crate::domain::value_objects::money::{Currency, Money};
+use crate::domain::value_objects::timestamp::Timestamp;
+
+/// The units and revenue of one group of sales during one period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SalesReportLineDTO {
+    pub period_start: Timestamp,
+    /// Start of the next period
+    pub period_end: Timestamp,
+    /// The product, flavor, size, machine or slot the line is about
+    pub key: String,
+    pub units: u32,
+    pub revenue: Money,
+}
+
+/// Everything sold in one currency during a report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SalesTotalsDTO {
+    pub currency: Currency,
+    pub units: u32,
+    pub revenue: Money,
+}
+
+/// Revenue and units sold, grouped by period and by what the report asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SalesReportDTO {
+    pub from: Timestamp,
+    pub until: Timestamp,
+    pub period: ReportPeriod,
+    pub dimension: ReportDimension,
+    /// Ordered by period, then by currency and the largest revenue first
+    pub lines: Vec<SalesReportLineDTO>,
+    /// One entry per currency sold in, ordered by currency
+    pub totals: Vec<SalesTotalsDTO>,
+}
+
+impl std::fmt::Display for SalesReportDTO {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} sales by {} from {} until {}", self.period, self.dimension, self.from, self.until)?;
+
+        let mut period_start = None;
+        for line in &self.lines {
+            if period_start != Some(line.period_start) {
+                write!(f, "\n  {} - {}", line.period_start, line.period_end)?;
+                period_start = Some(line.period_start);
+            }
+            write!(f, "\n    {}: {} sold, {}", line.key, line.units, line.revenue)?;
+        }
+
+        for totals in &self.totals {
+            write!(f, "\n  Total {}: {} sold, {}", totals.currency.code(), totals.units, totals.revenue)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ReportingError {
+    /// The report would end before it starts
+    InvalidWindow { from: Timestamp, until: Timestamp },
+    RepositoryUnavailable(String),
+    RepositoryFailure(String),
+    Validation(String),
+}
+
+impl std::fmt::Display for ReportingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportingError::InvalidWindow { from, until } => {
+                write!(f, "A report from {} cannot end at {}", from, until)
+            },
+            ReportingError::RepositoryUnavailable(msg) => write!(f, "Repository unavailable: {}", msg),
+            ReportingError::RepositoryFailure(msg) => write!(f, "Repository failure: {}", msg),
+            ReportingError::Validation(msg) => write!(f, "Validation error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ReportingError {}
+
+#[async_trait]
+pub trait ReportingPort {
+    /// Reports the sales made from `from` until just before `until`
+    ///
+    /// Lines are labelled with whole periods, so the first and last period only count the sales
+    /// inside the window.
+    async fn sales_report(
+        &self,
+        dimension: ReportDimension,
+        period: ReportPeriod,
+        from: Timestamp,
+        until: Timestamp,
+    ) -> Result<SalesReportDTO, ReportingError>;
+}
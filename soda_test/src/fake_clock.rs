@@ -0,0 +1,39 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use soda_core::domain::value_objects::timestamp::Timestamp;
+use soda_core::ports::driven::clock_port::Clock;
+
+/// Clock that only moves when a test tells it to
+pub struct FakeClock {
+    now: Mutex<Timestamp>,
+}
+
+impl FakeClock {
+    pub fn new(start: Timestamp) -> Self {
+        FakeClock { now: Mutex::new(start) }
+    }
+
+    /// Moves the clock forward
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now = now.plus(duration);
+    }
+
+    /// Jumps the clock to a given time
+    pub fn set(&self, timestamp: Timestamp) {
+        *self.now.lock().unwrap() = timestamp;
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::new(Timestamp::from_secs(1_700_000_000))
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Timestamp {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use soda_core::domain::entities::sale::Sale;
+use soda_core::domain::events::event_envelope::EventEnvelope;
+use soda_core::domain::value_objects::timestamp::Timestamp;
+use soda_core::ports::driven::event_publisher_port::{EventPublisher, PublishError};
+use soda_core::ports::driven::sales_ledger_port::SalesLedger;
+use soda_core::ports::driven::soda_machine_repository_port::RepositoryError;
+
+/// Sales ledger that is down for its first few calls, then hands everything to the wrapped ledger
+pub struct FlakySalesLedger {
+    inner: Arc<dyn SalesLedger>,
+    failures_left: AtomicU32,
+}
+
+impl FlakySalesLedger {
+    pub fn new(inner: Arc<dyn SalesLedger>, failures: u32) -> Self {
+        FlakySalesLedger { inner, failures_left: AtomicU32::new(failures) }
+    }
+}
+
+#[async_trait]
+impl SalesLedger for FlakySalesLedger {
+    async fn record(&self, sales: &[Sale]) -> Result<(), RepositoryError> {
+        if take_failure(&self.failures_left) {
+            return Err(RepositoryError::ConnectionError("ledger unreachable".to_string()));
+        }
+        self.inner.record(sales).await
+    }
+
+    async fn sales_between(&self, from: Timestamp, until: Timestamp) -> Result<Vec<Sale>, RepositoryError> {
+        self.inner.sales_between(from, until).await
+    }
+}
+
+/// Event publisher that is down for its first few calls, then hands everything to the wrapped publisher
+pub struct FlakyEventPublisher {
+    inner: Arc<dyn EventPublisher>,
+    failures_left: AtomicU32,
+}
+
+impl FlakyEventPublisher {
+    pub fn new(inner: Arc<dyn EventPublisher>, failures: u32) -> Self {
+        FlakyEventPublisher { inner, failures_left: AtomicU32::new(failures) }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for FlakyEventPublisher {
+    async fn publish(&self, events: &[EventEnvelope]) -> Result<(), PublishError> {
+        if take_failure(&self.failures_left) {
+            return Err(PublishError::ConnectionError("broker unreachable".to_string()));
+        }
+        self.inner.publish(events).await
+    }
+}
+
+/// Uses up one of the failures still to come, telling whether there was one
+fn take_failure(failures_left: &AtomicU32) -> bool {
+    failures_left.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok()
+}
@@ -1,26 +1,63 @@
+pub mod fake_clock;
+pub mod flaky_adapters;
+pub mod racing_repository;
+pub mod unreachable_notifier;
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
-    use memory_repository::InMemorySodaMachineRepository;
+    use std::time::Duration;
+    use json_repository::{JsonFileProductCatalog, JsonFileSalesLedger, JsonFileSodaMachineRepository, JSON_SCHEMA_VERSION};
+    use sqlite_repository::{SqliteSodaMachineRepository, MIGRATIONS};
+    use log_notifier::LogFileNotifier;
+    use simulated_hardware::SimulatedHardware;
+    use memory_repository::{EventSourcedSodaMachineRepository, InMemoryEventPublisher, InMemoryProductCatalog, InMemorySalesLedger, InMemorySodaMachineRepository};
+    use crate::fake_clock::FakeClock;
+    use crate::flaky_adapters::{FlakyEventPublisher, FlakySalesLedger};
+    use crate::racing_repository::RacingRepository;
+    use crate::unreachable_notifier::UnreachableNotifier;
     use soda_core::{
         application::{
+            acceptor_service::AcceptorService,
+            catalog_service::CatalogService,
             customer_service::CustomerService,
             operator_service::OperatorService,
+            reporting_service::ReportingService,
         },
         domain::{
             value_objects::{
-                money::Money,
+                denomination::AcceptedDenominations,
+                money::{Currency, Money},
                 soda::{Soda, SodaFlavor, SodaSize},
+                timestamp::Timestamp,
+            },
+            aggregates::soda_machine::{SodaMachine, SodaMachineError, SodaMachineEvent, SodaMachineId},
+            events::event_envelope::{CorrelationId, EventEnvelope, EVENT_SCHEMA_VERSION},
+            entities::{
+                price_schedule::PriceChange,
+                product::{Product, Sku},
+                promotion::{AppliedPromotion, Discount, Promotion, PromotionId},
+                sale::Sale,
+                slot::SlotId,
+                vend::VendFailure,
             },
-            aggregates::soda_machine::{SodaMachine, SodaMachineId},
-            entities::slot::SlotId,
+            services::sales_report::{ReportDimension, ReportPeriod},
         },
         ports::{
             driving::{
-                customer_port::CustomerPort,
-                operator_port::OperatorPort,
+                acceptor_port::{AcceptorError, AcceptorOutcomeDTO, AcceptorPort},
+                catalog_port::{CatalogError, CatalogPort},
+                customer_port::{CustomerError, CustomerPort},
+                operator_port::{DiscountDTO, OperatorError, OperatorPort, PlanogramDTO, PromotionDTO},
+                reporting_port::{ReportingError, ReportingPort},
+            },
+            driven::{
+                hardware_port::VendingHardware,
+                notification_port::{DeliveryFailure, StockAlert, StockAlertKind},
+                product_catalog_port::ProductCatalogRepository,
+                sales_ledger_port::SalesLedger,
+                soda_machine_repository_port::{MachineFilter, RepositoryError, SodaMachineRepository},
             },
-            driven::soda_machine_repository_port::SodaMachineRepository,
         },
     };
 
@@ -48,6 +85,7 @@ mod tests {
         ).unwrap();
         machine.configure_slot(SlotId::new(slot_id), cola).unwrap();
         machine.refill_slot(SlotId::new(slot_id), 5).unwrap();
+        machine.load_cash(Money::from_cents(25), 10).unwrap();
         
         // Store the configured machine
         repository.create(&machine).await.unwrap();
@@ -69,6 +107,2070 @@ mod tests {
         assert_eq!(remaining_money, expected_change);
     }
 
+    #[tokio::test]
+    async fn test_sale_refused_when_exact_change_unavailable() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = Arc::new(OperatorService::new(repository.clone()));
+        let customer_service = Arc::new(CustomerService::new(repository.clone()));
+        let machine_id = 1;
+        let slot_id = 1;
+
+        operator_service.create_new_machine(machine_id, 10).await.unwrap();
+        let cola = Soda::new(
+            "Cola".to_string(),
+            SodaFlavor::Cola,
+            SodaSize::Medium,
+            Money::from_cents(150),
+            false,
+            true,
+        ).unwrap();
+        operator_service.configure_slot(machine_id, slot_id, 10, cola).await.unwrap();
+        operator_service.refill_slot(machine_id, slot_id, 5).await.unwrap();
+        operator_service.load_cash(machine_id, Money::from_cents(25), 1).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(200)).await.unwrap();
+
+        // Act: only one quarter is available but two are needed
+        let buy_result = customer_service.buy_soda(machine_id, slot_id).await;
+
+        // Assert
+        assert!(buy_result.is_err());
+        let available_sodas = customer_service.list_available_sodas(machine_id).await.unwrap();
+        assert_eq!(available_sodas.len(), 1);
+
+        // Once the operator loads more quarters the sale goes through
+        operator_service.load_cash(machine_id, Money::from_cents(25), 1).await.unwrap();
+        assert!(customer_service.buy_soda(machine_id, slot_id).await.is_ok());
+        let change = customer_service.request_money_back(machine_id).await.unwrap();
+        assert_eq!(change, Money::from_cents(50));
+    }
+
+    #[tokio::test]
+    async fn test_machine_only_accepts_its_own_currency() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = Arc::new(OperatorService::new(repository.clone()));
+        let customer_service = Arc::new(CustomerService::new(repository.clone()));
+        let machine_id = 7;
+        let slot_id = 1;
+        let price = Money::from_minor_units(180, Currency::EUR);
+
+        operator_service.create_new_machine_with_currency(machine_id, 10, Currency::EUR).await.unwrap();
+        let orange = Soda::new(
+            "Fanta".to_string(),
+            SodaFlavor::Orange,
+            SodaSize::Medium,
+            price,
+            false,
+            false,
+        ).unwrap();
+        operator_service.configure_slot(machine_id, slot_id, 10, orange).await.unwrap();
+        operator_service.refill_slot(machine_id, slot_id, 2).await.unwrap();
+
+        // Act
+        let dollars = customer_service.insert_money(machine_id, Money::from_cents(200)).await;
+        let euros = customer_service.insert_money(machine_id, Money::from_minor_units(100, Currency::EUR)).await;
+        for cents in [50, 20, 10] {
+            customer_service.insert_money(machine_id, Money::from_minor_units(cents, Currency::EUR)).await.unwrap();
+        }
+
+        // Assert
+        assert!(dollars.is_err());
+        assert!(euros.is_ok());
+        assert!(customer_service.buy_soda(machine_id, slot_id).await.is_ok());
+
+        let available_sodas = customer_service.list_available_sodas(machine_id).await.unwrap();
+        assert_eq!(available_sodas[0].price, "1.80");
+        assert_eq!(available_sodas[0].currency, Currency::EUR);
+
+        let status = operator_service.get_machine_status(machine_id).await.unwrap();
+        assert_eq!(status.total_collected, Money::from_minor_units(180, Currency::EUR));
+        assert_eq!(status.currency, Currency::EUR);
+        assert!(status.to_string().contains("€1.80 collected"), "Status should show euros, got: {}", status);
+    }
+
+    #[tokio::test]
+    async fn test_money_back_closes_customer_session() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = Arc::new(OperatorService::new(repository.clone()));
+        let customer_service = Arc::new(CustomerService::new(repository.clone()));
+        let machine_id = 1;
+
+        operator_service.create_new_machine(machine_id, 10).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(25)).await.unwrap();
+
+        let machine = repository.find_by_id(SodaMachineId::new(machine_id)).await.unwrap().unwrap();
+        assert_eq!(machine.current_session().unwrap().credit(), Money::from_cents(125));
+
+        // Act
+        let returned = customer_service.request_money_back(machine_id).await.unwrap();
+
+        // Assert
+        assert_eq!(returned, Money::from_cents(125));
+        let machine = repository.find_by_id(SodaMachineId::new(machine_id)).await.unwrap().unwrap();
+        assert!(machine.current_session().is_none());
+        assert!(machine.cash_box().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_session_is_refunded_before_next_customer() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let customer_service = Arc::new(CustomerService::new(repository.clone()));
+        let machine_id = 1;
+
+        // A customer inserted money long ago and walked away
+        let mut machine = SodaMachine::new(SodaMachineId::new(machine_id), 10).unwrap();
+        machine.insert_money(Money::from_cents(100), Timestamp::from_secs(0)).unwrap();
+        repository.create(&machine).await.unwrap();
+
+        // Act
+        customer_service.insert_money(machine_id, Money::from_cents(25)).await.unwrap();
+
+        // Assert
+        let machine = repository.find_by_id(SodaMachineId::new(machine_id)).await.unwrap().unwrap();
+        let session = machine.current_session().unwrap();
+        assert_eq!(session.id().value(), 2, "The next customer should get a fresh session");
+        assert_eq!(session.credit(), Money::from_cents(25));
+        assert_eq!(machine.cash_box().total(), Money::from_cents(25), "The abandoned dollar should have been refunded");
+    }
+
+    #[tokio::test]
+    async fn test_session_expires_after_idle_timeout() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let clock = Arc::new(FakeClock::default());
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone()).with_clock(clock.clone());
+        let machine_id = 1;
+
+        operator_service.create_new_machine(machine_id, 10).await.unwrap();
+        operator_service.set_session_timeout(machine_id, Duration::from_secs(60)).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+
+        // Act & Assert: nothing expires before the timeout
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(operator_service.expire_idle_session(machine_id).await.unwrap(), Money::zero());
+
+        // Activity restarts the idle timer
+        customer_service.insert_money(machine_id, Money::from_cents(25)).await.unwrap();
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(operator_service.expire_idle_session(machine_id).await.unwrap(), Money::zero());
+
+        clock.advance(Duration::from_secs(1));
+        let refunded = operator_service.expire_idle_session(machine_id).await.unwrap();
+
+        assert_eq!(refunded, Money::from_cents(125));
+        let machine = repository.find_by_id(SodaMachineId::new(machine_id)).await.unwrap().unwrap();
+        assert!(machine.current_session().is_none());
+        assert!(machine.cash_box().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_money_back_after_timeout_reports_refund() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let clock = Arc::new(FakeClock::default());
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone()).with_clock(clock.clone());
+        let machine_id = 1;
+
+        operator_service.create_new_machine(machine_id, 10).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+        clock.advance(Duration::from_secs(3600));
+
+        // Act
+        let returned = customer_service.request_money_back(machine_id).await.unwrap();
+
+        // Assert
+        assert_eq!(returned, Money::from_cents(100));
+        let machine = repository.find_by_id(SodaMachineId::new(machine_id)).await.unwrap().unwrap();
+        assert!(machine.current_session().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_every_command_publishes_enveloped_events() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let clock = Arc::new(FakeClock::default());
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let operator_service = OperatorService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_event_publisher(publisher.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_event_publisher(publisher.clone());
+        let machine_id = 7;
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        // Act
+        operator_service.create_new_machine(machine_id, 10).await.unwrap();
+        operator_service.configure_slot(machine_id, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(machine_id, 1, 5).await.unwrap();
+        clock.advance(Duration::from_secs(10));
+        customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(machine_id, 1).await.unwrap();
+
+        // Assert
+        let published = publisher.published();
+        let sequences: Vec<u64> = published.iter().map(|envelope| envelope.sequence).collect();
+        assert_eq!(sequences, (1..=published.len() as u64).collect::<Vec<_>>(), "Sequence numbers should have no gaps");
+        assert!(published.iter().all(|envelope| envelope.machine_id == SodaMachineId::new(machine_id)));
+        assert!(published.iter().all(|envelope| envelope.schema_version == EVENT_SCHEMA_VERSION));
+
+        // The first insert starts a session, both events share the command's correlation id and time
+        let session_started = published.iter()
+            .find(|envelope| matches!(envelope.event, SodaMachineEvent::SessionStarted { .. }))
+            .unwrap();
+        let first_insert: Vec<_> = published.iter()
+            .filter(|envelope| envelope.correlation_id == session_started.correlation_id)
+            .collect();
+        assert_eq!(first_insert.len(), 2);
+        assert!(matches!(first_insert[1].event, SodaMachineEvent::MoneyInserted { .. }));
+        assert_eq!(first_insert[1].occurred_at, published[0].occurred_at.plus(Duration::from_secs(10)));
+
+        let second_insert = &published[first_insert[1].sequence as usize];
+        assert!(matches!(second_insert.event, SodaMachineEvent::MoneyInserted { .. }));
+        assert_ne!(second_insert.correlation_id, session_started.correlation_id);
+
+        // The exact purchase ends the session
+        assert!(matches!(published.last().unwrap().event, SodaMachineEvent::SessionCompleted { .. }));
+
+        // Nothing is left waiting in the stored machine
+        let machine = repository.find_by_id(SodaMachineId::new(machine_id)).await.unwrap().unwrap();
+        assert!(machine.uncommitted_events().is_empty());
+        assert_eq!(machine.version(), published.len() as u64);
+    }
+
+    #[test]
+    fn test_version_1_envelopes_are_still_read() {
+        // Arrange
+        let published_before_promotions = r#"{
+            "machine_id": 1,
+            "sequence": 7,
+            "occurred_at": 1700000000000,
+            "schema_version": 1,
+            "correlation_id": "abc",
+            "event": {
+                "SodaDispensed": {
+                    "slot_id": 2,
+                    "soda": {
+                        "name": "Cola", "flavor": "Cola", "size": "Medium",
+                        "price": { "cents": 150, "currency": "USD" },
+                        "is_diet": false, "is_caffeinated": true
+                    },
+                    "price": { "cents": 150, "currency": "USD" },
+                    "dispensed_at": 1700000000000
+                }
+            }
+        }"#;
+
+        // Act
+        let envelope: EventEnvelope = serde_json::from_str(published_before_promotions).unwrap();
+
+        // Assert
+        assert_eq!(EVENT_SCHEMA_VERSION, 3);
+        assert_eq!(envelope.schema_version, 1, "The envelope keeps the layout it was written in");
+        assert_eq!(envelope.correlation_id, CorrelationId::new("abc"));
+        assert!(matches!(
+            envelope.event,
+            SodaMachineEvent::SodaDispensed { slot_id, price, promotion: None, .. }
+                if slot_id == SlotId::new(2) && price == Money::from_cents(150)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_event_sourced_machine_is_rebuilt_from_its_history() {
+        // Arrange
+        let repository = Arc::new(EventSourcedSodaMachineRepository::new().with_snapshot_interval(5));
+        let clock = Arc::new(FakeClock::default());
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone()).with_clock(clock.clone());
+        let machine_id = SodaMachineId::new(3);
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        // Act
+        operator_service.create_new_machine(machine_id.value(), 10).await.unwrap();
+        operator_service.configure_slot(machine_id.value(), 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(machine_id.value(), 1, 5).await.unwrap();
+        operator_service.load_cash(machine_id.value(), Money::from_cents(25), 8).await.unwrap();
+        customer_service.insert_money(machine_id.value(), Money::from_cents(200)).await.unwrap();
+        customer_service.buy_soda(machine_id.value(), 1).await.unwrap();
+        let refund = customer_service.request_money_back(machine_id.value()).await.unwrap();
+
+        // Assert
+        assert_eq!(refund, Money::from_cents(50));
+
+        let machine = repository.find_by_id(machine_id).await.unwrap().unwrap();
+        let history = repository.events_for(machine_id).unwrap();
+        assert_eq!(machine.version(), history.len() as u64);
+        assert!(machine.uncommitted_events().is_empty());
+        assert_eq!(machine.total_soda_count(), 4);
+        assert_eq!(machine.total_collected(), Money::from_cents(150));
+        assert_eq!(machine.cash_box().total(), Money::from_cents(350));
+        assert!(machine.current_session().is_none());
+
+        // The whole history is kept for auditing, starting with the machine's creation
+        assert!(matches!(history[0], (1, SodaMachineEvent::MachineCreated { .. })));
+        assert!(history.iter().any(|(_, event)| matches!(event, SodaMachineEvent::SodaDispensed { .. })));
+
+        // Loading starts from the latest snapshot instead of replaying everything
+        let snapshot_version = repository.snapshot_version(machine_id).unwrap().unwrap();
+        assert!(snapshot_version >= 5 && snapshot_version <= machine.version());
+    }
+
+    #[tokio::test]
+    async fn test_event_sourced_repository_rejects_stale_machine() {
+        // Arrange
+        let repository = EventSourcedSodaMachineRepository::new();
+        let machine = SodaMachine::new(SodaMachineId::new(1), 10).unwrap();
+        repository.create(&machine).await.unwrap();
+
+        let mut first = repository.find_by_id(machine.id()).await.unwrap().unwrap();
+        let mut second = repository.find_by_id(machine.id()).await.unwrap().unwrap();
+        first.disable();
+        second.add_slot(SlotId::new(1), 10).unwrap();
+
+        // Act
+        repository.save(&first).await.unwrap();
+        let result = repository.save(&second).await;
+
+        // Assert
+        assert!(matches!(result, Err(RepositoryError::ConcurrencyConflict { expected_version: 1, actual_version: 2, .. })));
+        let machine = repository.find_by_id(machine.id()).await.unwrap().unwrap();
+        assert!(!machine.is_operational());
+        assert_eq!(machine.slot_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stale_machine_is_not_saved_over_newer_one() {
+        // Arrange
+        let repository = InMemorySodaMachineRepository::new();
+        let machine = SodaMachine::new(SodaMachineId::new(1), 10).unwrap();
+        repository.create(&machine).await.unwrap();
+
+        let mut first_terminal = repository.find_by_id(machine.id()).await.unwrap().unwrap();
+        let mut second_terminal = repository.find_by_id(machine.id()).await.unwrap().unwrap();
+        first_terminal.load_cash(Money::from_cents(25), 4).unwrap();
+        second_terminal.load_cash(Money::from_cents(100), 1).unwrap();
+
+        // Act
+        repository.save(&first_terminal).await.unwrap();
+        let result = repository.save(&second_terminal).await;
+
+        // Assert
+        assert!(matches!(result, Err(RepositoryError::ConcurrencyConflict { .. })));
+        let machine = repository.find_by_id(machine.id()).await.unwrap().unwrap();
+        assert_eq!(machine.cash_box().total(), Money::from_cents(100));
+        assert_eq!(machine.version(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_purchase_is_retried_instead_of_lost() {
+        // Arrange
+        let inner = Arc::new(InMemorySodaMachineRepository::new());
+        let repository = Arc::new(RacingRepository::new(inner.clone()));
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let machine_id = SodaMachineId::new(1);
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(machine_id.value(), 10).await.unwrap();
+        operator_service.configure_slot(machine_id.value(), 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(machine_id.value(), 1, 5).await.unwrap();
+        operator_service.load_cash(machine_id.value(), Money::from_cents(25), 4).await.unwrap();
+        customer_service.insert_money(machine_id.value(), Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(machine_id.value(), Money::from_cents(50)).await.unwrap();
+
+        // An operator refills the same machine from another terminal while this purchase is being saved
+        repository.interfere_once(|machine| {
+            machine.refill_slot(SlotId::new(1), 2).unwrap();
+        });
+
+        // Act
+        customer_service.buy_soda(machine_id.value(), 1).await.unwrap();
+
+        // Assert
+        let machine = inner.find_by_id(machine_id).await.unwrap().unwrap();
+        assert_eq!(machine.total_soda_count(), 6, "Neither the refill nor the sale may be lost");
+        assert_eq!(machine.total_collected(), Money::from_cents(150));
+    }
+
+    #[tokio::test]
+    async fn test_json_repository_round_trips_every_field() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let repository = JsonFileSodaMachineRepository::new(directory.path()).unwrap();
+        let now = Timestamp::from_secs(1_700_000_000);
+        let euro = |amount| Money::from_minor_units(amount, Currency::EUR);
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, euro(150), false, true).unwrap();
+
+        let mut machine = SodaMachine::new_with_currency(SodaMachineId::new(4), 6, Currency::EUR).unwrap();
+        repository.create(&machine).await.unwrap();
+        machine.mark_events_committed();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), cola.clone()).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.add_slot(SlotId::new(2), 8).unwrap();
+        machine.set_low_stock_threshold(SlotId::new(2), 3).unwrap();
+        machine.assign_product(SlotId::new(2), &Product::new(Sku::new("COLA-330ML").unwrap(), cola, None).unwrap()).unwrap();
+        machine.load_cash(euro(20), 10).unwrap();
+        machine.override_price(&Sku::new("COLA-330ML").unwrap(), Some(euro(180)), now).unwrap();
+        machine.add_promotion(
+            Promotion::new(PromotionId::new("COLA-50").unwrap(), "Cola week".to_string(), Discount::AmountOff(euro(50)))
+                .unwrap()
+                .for_flavor(SodaFlavor::Cola)
+        ).unwrap();
+        machine.set_session_timeout(Duration::from_secs(45)).unwrap();
+        machine.insert_money(euro(200), now).unwrap();
+        machine.dispense_soda(SlotId::new(1), now).unwrap();
+        machine.insert_money(euro(100), now).unwrap();
+        machine.disable();
+
+        // Act
+        repository.save(&machine).await.unwrap();
+        let reopened = JsonFileSodaMachineRepository::new(directory.path()).unwrap();
+        let loaded = reopened.find_by_id(machine.id()).await.unwrap().unwrap();
+
+        // Assert
+        assert_eq!(loaded.id(), machine.id());
+        assert_eq!(loaded.currency(), Currency::EUR);
+        assert_eq!(loaded.max_slots(), 6);
+        assert_eq!(loaded.slot_count(), 2);
+        assert_eq!(loaded.get_slot(SlotId::new(1)), machine.get_slot(SlotId::new(1)));
+        assert_eq!(loaded.get_slot(SlotId::new(2)), machine.get_slot(SlotId::new(2)));
+        assert_eq!(loaded.total_collected(), euro(100));
+        assert!(!loaded.is_operational());
+        assert_eq!(loaded.cash_box(), machine.cash_box());
+        assert_eq!(loaded.price_schedule(), machine.price_schedule());
+        assert_eq!(loaded.promotions(), machine.promotions());
+        assert_eq!(loaded.current_session(), machine.current_session());
+        assert_eq!(loaded.session_timeout(), Duration::from_secs(45));
+        assert_eq!(loaded.version(), machine.version());
+        assert!(loaded.uncommitted_events().is_empty());
+        assert_eq!(loaded.status_summary(), machine.status_summary());
+    }
+
+    #[tokio::test]
+    async fn test_json_repository_writes_versioned_files_atomically() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let repository = JsonFileSodaMachineRepository::new(directory.path().join("machines")).unwrap();
+        let mut machine = SodaMachine::new(SodaMachineId::new(9), 4).unwrap();
+
+        // Act
+        repository.create(&machine).await.unwrap();
+        machine.mark_events_committed();
+        machine.load_cash(Money::from_cents(10), 3).unwrap();
+        repository.save(&machine).await.unwrap();
+
+        // Assert
+        let files: Vec<_> = std::fs::read_dir(repository.directory()).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(files, vec!["machine-9.json".to_string()], "No temporary file should be left behind");
+
+        let contents = std::fs::read_to_string(repository.path_for(machine.id())).unwrap();
+        assert!(contents.contains(&format!("\"schema_version\": {}", JSON_SCHEMA_VERSION)));
+
+        assert!(repository.create(&machine).await.is_err(), "A machine can only be created once");
+        assert!(repository.find_by_id(SodaMachineId::new(10)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_json_repository_rejects_stale_machine() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let repository = JsonFileSodaMachineRepository::new(directory.path()).unwrap();
+        repository.create(&SodaMachine::new(SodaMachineId::new(1), 4).unwrap()).await.unwrap();
+
+        let mut first_terminal = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        let mut second_terminal = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        first_terminal.disable();
+        second_terminal.add_slot(SlotId::new(1), 10).unwrap();
+
+        // Act
+        repository.save(&first_terminal).await.unwrap();
+        let result = repository.save(&second_terminal).await;
+
+        // Assert
+        assert!(matches!(result, Err(RepositoryError::ConcurrencyConflict { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_repository_rebuilds_the_whole_machine() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = SqliteSodaMachineRepository::open(&path).unwrap();
+        let now = Timestamp::from_secs(1_700_000_000);
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let root_beer = Soda::new("Root Beer".to_string(), SodaFlavor::RootBeer, SodaSize::XLarge, Money::from_cents(100), true, false).unwrap();
+
+        let mut machine = SodaMachine::new(SodaMachineId::new(2), 5).unwrap();
+        repository.create(&machine).await.unwrap();
+        machine.mark_events_committed();
+        machine.add_slot(SlotId::new(1), 10).unwrap();
+        machine.configure_slot(SlotId::new(1), cola).unwrap();
+        machine.refill_slot(SlotId::new(1), 5).unwrap();
+        machine.add_slot(SlotId::new(2), 6).unwrap();
+        machine.configure_slot(SlotId::new(2), root_beer.clone()).unwrap();
+        machine.refill_slot(SlotId::new(2), 2).unwrap();
+        machine.add_slot(SlotId::new(3), 4).unwrap();
+        machine.assign_product(SlotId::new(3), &Product::new(Sku::new("ROOT-BEER-XL").unwrap(), root_beer, None).unwrap()).unwrap();
+        machine.set_low_stock_threshold(SlotId::new(2), 1).unwrap();
+        machine.load_cash(Money::from_cents(25), 10).unwrap();
+        machine.override_price(&Sku::new("ROOT-BEER-XL").unwrap(), Some(Money::from_cents(120)), now).unwrap();
+        machine.schedule_price_change(PriceChange {
+            sku: Sku::new("ROOT-BEER-XL").unwrap(),
+            price: None,
+            effective_at: now.plus(Duration::from_secs(3600)),
+        }, now).unwrap();
+        machine.add_promotion(
+            Promotion::new(PromotionId::new("ROOT-BEER-25").unwrap(), "Root beer float".to_string(), Discount::PercentOff(25))
+                .unwrap()
+                .for_flavor(SodaFlavor::RootBeer)
+                .valid_between(Some(now), None)
+                .unwrap()
+        ).unwrap();
+        machine.add_promotion(
+            Promotion::new(PromotionId::new("WELCOME").unwrap(), "Welcome".to_string(), Discount::AmountOff(Money::from_cents(25)))
+                .unwrap()
+                .for_product(Sku::new("COLA-12OZ").unwrap())
+                .as_coupon()
+        ).unwrap();
+        machine.set_session_timeout(Duration::from_secs(90)).unwrap();
+        machine.insert_money(Money::from_cents(200), now).unwrap();
+        machine.insert_money(Money::from_cents(100), now).unwrap();
+        machine.enter_coupon(&PromotionId::new("WELCOME").unwrap(), now).unwrap();
+        machine.dispense_soda(SlotId::new(1), now).unwrap();
+        machine.dispense_soda(SlotId::new(2), now.plus(Duration::from_secs(3))).unwrap();
+        machine.disable();
+
+        // Act
+        repository.save(&machine).await.unwrap();
+        let reopened = SqliteSodaMachineRepository::open(&path).unwrap();
+        let loaded = reopened.find_by_id(machine.id()).await.unwrap().unwrap();
+
+        // Assert
+        assert_eq!(loaded.state(), machine.state());
+        assert_eq!(loaded.total_collected(), Money::from_cents(225));
+        assert!(!loaded.is_operational());
+        assert!(loaded.uncommitted_events().is_empty());
+        assert_eq!(loaded.current_session().unwrap().purchases().len(), 2);
+        assert!(reopened.find_by_id(SodaMachineId::new(3)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_sales_can_be_reported_with_sql() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let orange = Soda::new("Fanta".to_string(), SodaFlavor::Orange, SodaSize::Small, Money::from_cents(100), false, false).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        operator_service.configure_slot(1, 2, 10, orange).await.unwrap();
+        operator_service.refill_slot(1, 2, 5).await.unwrap();
+
+        // Act
+        for slot_id in [1, 1, 2] {
+            let coins: &[i64] = if slot_id == 1 { &[100, 50] } else { &[100] };
+            for &cents in coins {
+                customer_service.insert_money(1, Money::from_cents(cents)).await.unwrap();
+            }
+            customer_service.buy_soda(1, slot_id).await.unwrap();
+        }
+
+        // Assert
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let mut statement = connection.prepare(
+            "SELECT d.name, COUNT(*), SUM(s.price) FROM sales s JOIN sodas d ON d.id = s.soda_id
+             WHERE s.machine_id = 1 GROUP BY d.name ORDER BY d.name",
+        ).unwrap();
+        let report: Vec<(String, i64, i64)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(report, vec![("Cola".to_string(), 2, 300), ("Fanta".to_string(), 1, 100)]);
+
+        let schema_version: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(schema_version as usize, MIGRATIONS.len());
+        assert_eq!(repository.schema_version().unwrap() as usize, MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_repository_rejects_stale_machine() {
+        // Arrange
+        let repository = SqliteSodaMachineRepository::open_in_memory().unwrap();
+        repository.create(&SodaMachine::new(SodaMachineId::new(1), 4).unwrap()).await.unwrap();
+
+        let mut first_terminal = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        let mut second_terminal = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        first_terminal.load_cash(Money::from_cents(25), 4).unwrap();
+        second_terminal.add_slot(SlotId::new(1), 10).unwrap();
+
+        // Act
+        repository.save(&first_terminal).await.unwrap();
+        let result = repository.save(&second_terminal).await;
+
+        // Assert
+        assert!(matches!(result, Err(RepositoryError::ConcurrencyConflict { .. })));
+        let machine = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        assert_eq!(machine.slot_count(), 0, "The rejected save must not be partially applied");
+        assert_eq!(machine.cash_box().total(), Money::from_cents(100));
+    }
+
+    #[tokio::test]
+    async fn test_every_repository_lists_finds_and_deletes_machines() {
+        let directory = tempfile::tempdir().unwrap();
+        let repositories: Vec<(&str, Arc<dyn SodaMachineRepository>)> = vec![
+            ("in-memory", Arc::new(InMemorySodaMachineRepository::new())),
+            ("event-sourced", Arc::new(EventSourcedSodaMachineRepository::new())),
+            ("json", Arc::new(JsonFileSodaMachineRepository::new(directory.path()).unwrap())),
+            ("sqlite", Arc::new(SqliteSodaMachineRepository::open_in_memory().unwrap())),
+        ];
+
+        for (name, repository) in repositories {
+            // Arrange
+            for id in [3, 1, 2] {
+                let mut machine = SodaMachine::new(SodaMachineId::new(id), 4).unwrap();
+                repository.create(&machine).await.unwrap();
+                machine.mark_events_committed();
+                machine.set_location(Some(format!("Building {}", id)));
+                repository.save(&machine).await.unwrap();
+            }
+
+            // Act
+            let listed = repository.list().await.unwrap();
+            let found = repository.query(&MachineFilter::new().location("building 2")).await.unwrap();
+            let deleted = repository.delete(SodaMachineId::new(2)).await.unwrap();
+            let deleted_again = repository.delete(SodaMachineId::new(2)).await.unwrap();
+
+            // Assert
+            let ids: Vec<u32> = listed.iter().map(|machine| machine.id().value()).collect();
+            assert_eq!(ids, vec![1, 2, 3], "{} should list machines ordered by ID", name);
+            assert_eq!(listed[0].location(), Some("Building 1"), "{} should keep the location", name);
+            assert_eq!(found.len(), 1, "{} should find machines by location", name);
+            assert_eq!(found[0].id(), SodaMachineId::new(2));
+            assert!(deleted, "{} should report the deleted machine", name);
+            assert!(!deleted_again, "{} should report nothing to delete", name);
+            assert!(!repository.exists(SodaMachineId::new(2)).await.unwrap(), "{}", name);
+            assert!(repository.exists(SodaMachineId::new(3)).await.unwrap(), "{}", name);
+            assert_eq!(repository.list().await.unwrap().len(), 2, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_operator_finds_machines_that_need_attention() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        for machine_id in [1, 2, 3] {
+            operator_service.create_new_machine(machine_id, 5).await.unwrap();
+            operator_service.configure_slot(machine_id, 1, 10, cola.clone()).await.unwrap();
+        }
+        operator_service.refill_slot(1, 1, 10).await.unwrap();
+        operator_service.refill_slot(2, 1, 1).await.unwrap();
+        operator_service.refill_slot(3, 1, 8).await.unwrap();
+        operator_service.set_machine_location(3, Some("  Lobby, Building B ".to_string())).await.unwrap();
+
+        let mut out_of_service = repository.find_by_id(SodaMachineId::new(3)).await.unwrap().unwrap();
+        out_of_service.disable();
+        repository.save(&out_of_service).await.unwrap();
+
+        // Act
+        let all = operator_service.list_machines().await.unwrap();
+        let low_stock = operator_service.find_machines(MachineFilter::new().low_stock(2)).await.unwrap();
+        let disabled = operator_service.find_machines(MachineFilter::new().operational(false)).await.unwrap();
+        let in_lobby = operator_service.find_machines(MachineFilter::new().location("lobby")).await.unwrap();
+
+        // Assert
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].machine_id, 1);
+        assert_eq!(all[0].total_sodas, 10);
+        assert_eq!(all[0].slot_count, 1);
+        assert_eq!(all[2].location.as_deref(), Some("Lobby, Building B"));
+        assert_eq!(low_stock.iter().map(|machine| machine.machine_id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(disabled.iter().map(|machine| machine.machine_id).collect::<Vec<_>>(), vec![3]);
+        assert!(!disabled[0].is_operational);
+        assert_eq!(in_lobby.iter().map(|machine| machine.machine_id).collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_operator_deletes_machines() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let operator_service = OperatorService::new(repository.clone()).with_event_publisher(publisher.clone());
+        let customer_service = CustomerService::new(repository);
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 4).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+
+        // Act
+        let with_credit = operator_service.delete_machine(1).await;
+        customer_service.request_money_back(1).await.unwrap();
+        let with_cash = operator_service.delete_machine(1).await;
+        operator_service.collect_cash(1, "OP-7", Money::zero()).await.unwrap();
+        let with_float = operator_service.delete_machine(1).await;
+        operator_service.unload_cash(1, Money::from_cents(25), 4).await.unwrap();
+        let deleted = operator_service.delete_machine(1).await;
+        let deleted_again = operator_service.delete_machine(1).await;
+
+        // Assert
+        assert!(matches!(with_credit, Err(OperatorError::MachineError(SodaMachineError::MachineInUse(_)))));
+        assert!(matches!(with_cash, Err(OperatorError::MachineError(SodaMachineError::MachineInUse(_)))));
+        assert!(matches!(with_float, Err(OperatorError::MachineError(SodaMachineError::MachineInUse(_)))));
+        assert!(deleted.is_ok());
+        assert!(matches!(deleted_again, Err(OperatorError::SodaMachineNotFound(_))));
+        assert!(!operator_service.machine_exists(1).await.unwrap());
+        assert!(operator_service.list_machines().await.unwrap().is_empty());
+        assert_eq!(publisher.published().last().unwrap().event, SodaMachineEvent::MachineDecommissioned);
+    }
+
+    #[tokio::test]
+    async fn test_event_sourced_repository_keeps_the_history_of_deleted_machines() {
+        // Arrange
+        let repository = Arc::new(EventSourcedSodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let machine_id = SodaMachineId::new(1);
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.set_machine_location(1, Some("Lobby".to_string())).await.unwrap();
+        operator_service.delete_machine(1).await.unwrap();
+
+        // Act
+        operator_service.create_new_machine(1, 8).await.unwrap();
+        let unchecked_delete = repository.delete(machine_id).await.unwrap();
+
+        // Assert
+        assert!(unchecked_delete);
+        assert!(!repository.exists(machine_id).await.unwrap());
+
+        let histories = repository.deleted_histories(machine_id).unwrap();
+        assert_eq!(histories.len(), 2, "Each machine created under the ID keeps its own history");
+        assert_eq!(histories[0].len(), 3);
+        assert!(matches!(histories[0][1], (2, SodaMachineEvent::LocationChanged { .. })));
+        assert_eq!(histories[0][2], (3, SodaMachineEvent::MachineDecommissioned));
+        assert_eq!(
+            histories[1].last(),
+            Some(&(2, SodaMachineEvent::MachineDecommissioned)),
+            "A machine deleted straight from the repository still gets its tombstone"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_machine_status_details_every_slot() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 2, 8, cola).await.unwrap();
+        operator_service.refill_slot(1, 2, 2).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 8).await.unwrap();
+        operator_service.set_machine_location(1, Some("Lobby".to_string())).await.unwrap();
+
+        let mut machine = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        machine.add_slot(SlotId::new(1), 4).unwrap();
+        repository.save(&machine).await.unwrap();
+
+        // Act
+        let status = operator_service.get_machine_status(1).await.unwrap();
+
+        // Assert
+        assert_eq!(status.location.as_deref(), Some("Lobby"));
+        assert!(status.is_operational);
+        assert_eq!(status.cash_in_box, Money::from_cents(200));
+        assert_eq!(status.slots.iter().map(|slot| slot.slot_id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let empty = &status.slots[0];
+        assert_eq!(empty.soda_name, None);
+        assert_eq!(empty.price, None);
+        assert_eq!((empty.quantity, empty.max_capacity), (0, 4));
+
+        let stocked = &status.slots[1];
+        assert_eq!(stocked.soda_name.as_deref(), Some("Cola"));
+        assert_eq!(stocked.price, Some(Money::from_cents(150)));
+        assert_eq!((stocked.quantity, stocked.max_capacity), (2, 8));
+        assert_eq!(stocked.fill_percentage, 0.25);
+        assert!(stocked.is_enabled);
+
+        let rendered = status.to_string();
+        assert!(rendered.starts_with("Machine 1 (Lobby): 2 slots, 1 available sodas (2 total)"), "got: {}", rendered);
+        assert!(rendered.contains("Slot 1: not configured, 0/4 (0%)"), "got: {}", rendered);
+        assert!(rendered.contains("Slot 2: Cola at $1.50, 2/8 (25%)"), "got: {}", rendered);
+    }
+
+    #[tokio::test]
+    async fn test_stock_alerts_are_written_to_the_log_file() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let log_path = directory.path().join("logs").join("alerts.log");
+        let notifier = Arc::new(LogFileNotifier::new(&log_path).unwrap());
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let opened_at = Timestamp::from_secs(1_700_000_000);
+        let clock = Arc::new(FakeClock::new(opened_at));
+        let operator_service = OperatorService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_notifier(notifier.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_clock(clock)
+            .with_notifier(notifier);
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(100), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.set_low_stock_threshold(1, 1, 1).await.unwrap();
+        operator_service.refill_slot(1, 1, 2).await.unwrap();
+
+        // Act
+        for _ in 0..2 {
+            customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+            customer_service.buy_soda(1, 1).await.unwrap();
+        }
+        operator_service.refill_slot(1, 1, 1).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+
+        // Assert
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines, vec![
+            "2023-11-14T22:13:20.000Z machine 1 slot 1: restocked, 2 in stock",
+            "2023-11-14T22:13:20.000Z machine 1 slot 1: low on stock, 1 left (threshold 1)",
+            "2023-11-14T22:13:20.000Z machine 1 slot 1: sold out",
+            "2023-11-14T22:13:20.000Z machine 1 slot 1: restocked, 6 in stock",
+        ], "Refilling to the threshold is not a restock");
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.slots[0].low_stock_threshold, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stock_alerts_that_cannot_be_sent_do_not_fail_the_command() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let notifier = Arc::new(UnreachableNotifier::new());
+        let jammed = Arc::new(SimulatedHardware::new().with_jam_rate(1.0));
+        let operator_service = OperatorService::new(repository.clone()).with_notifier(notifier.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_notifier(notifier.clone())
+            .with_hardware(VendingHardware::from_driver(jammed));
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+
+        // Act
+        let refilled = operator_service.refill_slot(1, 1, 1).await;
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        let failed = customer_service.buy_soda(1, 1).await;
+
+        // Assert
+        assert!(refilled.is_ok(), "The refill was saved, the alert failing does not change that");
+        assert!(matches!(failed, Err(CustomerError::VendFailed(VendFailure::Jammed))), "got: {:?}", failed);
+
+        let kinds: Vec<StockAlertKind> = notifier.attempted().into_iter().map(|alert| alert.kind).collect();
+        assert_eq!(kinds, vec![
+            StockAlertKind::Restocked { quantity: 1 },
+            StockAlertKind::VendFailed { failure: VendFailure::Jammed },
+            StockAlertKind::SoldOut,
+        ], "Every alert is tried even after one failed");
+
+        let reported: Vec<StockAlert> = notifier.reported().into_iter()
+            .map(|failure| match failure {
+                DeliveryFailure::StockAlert { alert, reason } => {
+                    assert!(reason.contains("alert service unreachable"), "got: {}", reason);
+                    alert
+                },
+                other => panic!("Only alerts were undelivered, got: {}", other),
+            })
+            .collect();
+        assert_eq!(reported, notifier.attempted(), "Every alert that could not be sent is reported");
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.slots[0].quantity, 0);
+        assert_eq!(status.inserted_money, Money::from_cents(150));
+    }
+
+    #[tokio::test]
+    async fn test_sales_and_events_the_ledger_and_publisher_missed_are_delivered_later() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let ledger = Arc::new(InMemorySalesLedger::new());
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let operator_service = OperatorService::new(repository.clone())
+            .with_event_publisher(Arc::new(FlakyEventPublisher::new(publisher.clone(), 1)));
+        let customer_service = CustomerService::new(repository.clone())
+            .with_sales_ledger(Arc::new(FlakySalesLedger::new(ledger.clone(), 1)))
+            .with_event_publisher(Arc::new(FlakyEventPublisher::new(publisher.clone(), 2)));
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        // Act
+        let created = operator_service.create_new_machine(1, 5).await;
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        let first_sale = customer_service.buy_soda(1, 1).await;
+        let sales_while_down = ledger.sales();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+
+        // Assert
+        assert!(created.is_ok(), "The machine was created, the publisher being down does not change that");
+        assert!(first_sale.is_ok(), "The sale was saved, the ledger being down does not change that");
+        assert!(sales_while_down.is_empty());
+
+        let sequences: Vec<u64> = ledger.sales().iter().map(|sale| sale.sequence).collect();
+        assert_eq!(sequences.len(), 2, "The sale the ledger missed is recorded with the next one");
+        assert!(sequences[0] < sequences[1]);
+
+        let machine = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        let published: Vec<u64> = publisher.published().iter().map(|envelope| envelope.sequence).collect();
+        assert_eq!(published, (1..=machine.version()).collect::<Vec<_>>(), "Every event is published once, in order");
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_sales_and_events_are_reported_and_lost_when_the_service_stops() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let ledger = Arc::new(InMemorySalesLedger::new());
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let notifier = Arc::new(UnreachableNotifier::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+
+        let stopped_service = CustomerService::new(repository.clone())
+            .with_sales_ledger(Arc::new(FlakySalesLedger::new(ledger.clone(), 1)))
+            .with_event_publisher(Arc::new(FlakyEventPublisher::new(publisher.clone(), 3)))
+            .with_notifier(notifier.clone());
+        stopped_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        stopped_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        stopped_service.buy_soda(1, 1).await.unwrap();
+        drop(stopped_service);
+        let version_at_stop = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap().version();
+
+        // Act
+        let restarted_service = CustomerService::new(repository.clone())
+            .with_sales_ledger(ledger.clone())
+            .with_event_publisher(publisher.clone())
+            .with_notifier(notifier.clone());
+        restarted_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        restarted_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        restarted_service.buy_soda(1, 1).await.unwrap();
+
+        // Assert
+        assert!(matches!(
+            notifier.reported().as_slice(),
+            [
+                DeliveryFailure::Events { count: 2, .. },
+                DeliveryFailure::Events { .. },
+                DeliveryFailure::Sales { count: 1, .. },
+                DeliveryFailure::Events { .. },
+            ]
+        ), "Every failed handover is reported");
+
+        let sales = ledger.sales();
+        assert_eq!(sales.len(), 1, "The sale kept by the stopped service is never recorded");
+        assert!(sales[0].sequence > version_at_stop);
+
+        let published = publisher.published();
+        assert!(!published.is_empty());
+        assert!(published.iter().all(|envelope| envelope.sequence > version_at_stop), "The events kept by the stopped service are never published");
+    }
+
+    #[tokio::test]
+    async fn test_restock_route_is_planned_and_applied_per_machine() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(100), false, true).unwrap();
+        let pricier_cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(125), false, true).unwrap();
+        let orange = Soda::new("Fanta".to_string(), SodaFlavor::Orange, SodaSize::Small, Money::from_cents(100), false, false).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola.clone()).await.unwrap();
+        operator_service.refill_slot(1, 1, 4).await.unwrap();
+        operator_service.configure_slot(1, 2, 6, orange.clone()).await.unwrap();
+        operator_service.refill_slot(1, 2, 6).await.unwrap();
+
+        operator_service.create_new_machine(2, 5).await.unwrap();
+        operator_service.configure_slot(2, 1, 8, pricier_cola).await.unwrap();
+        operator_service.configure_slot(2, 3, 5, orange).await.unwrap();
+        operator_service.refill_slot(2, 3, 1).await.unwrap();
+
+        // Act
+        let plan = operator_service.plan_restock(&[2, 1]).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.buy_soda(1, 2).await.unwrap();
+        let report = operator_service.restock_machine(1).await.unwrap();
+
+        // Assert
+        assert_eq!(plan.machines.iter().map(|machine| machine.machine_id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(plan.machines[0].total_units(), 12);
+        assert_eq!(plan.machines[1].slots.len(), 1, "Full slots are not in the plan");
+        assert_eq!(plan.pick_list.len(), 2);
+        assert_eq!(plan.pick_list[0].product, cola.description());
+        assert_eq!(plan.pick_list[0].units, 6 + 8, "The same product at another price goes in the same crate");
+        assert_eq!(plan.pick_list[1].units, 4);
+
+        assert_eq!(report.slots.iter().map(|slot| (slot.slot_id, slot.units)).collect::<Vec<_>>(), vec![(1, 6), (2, 1)],
+            "The soda sold after planning is restocked too");
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert!(status.slots.iter().all(|slot| slot.quantity == slot.max_capacity));
+        assert!(matches!(
+            operator_service.plan_restock(&[1, 9]).await,
+            Err(OperatorError::SodaMachineNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_planogram_is_rolled_out_to_several_machines() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let planogram: PlanogramDTO = serde_json::from_str(r#"{
+            "currency": "EUR",
+            "slots": [
+                { "slot_id": 2, "capacity": 6 },
+                { "slot_id": 1, "capacity": 10, "low_stock_threshold": 2,
+                  "product": { "name": "Cola", "flavor": "cola", "size": "medium", "price": "1.50", "is_caffeinated": true } }
+            ]
+        }"#).unwrap();
+
+        operator_service.create_new_machine_with_currency(1, 4, Currency::EUR).await.unwrap();
+        operator_service.create_new_machine_with_currency(2, 4, Currency::EUR).await.unwrap();
+        operator_service.create_new_machine(3, 4).await.unwrap();
+
+        // Act
+        operator_service.import_planogram(1, planogram.clone()).await.unwrap();
+        operator_service.import_planogram(2, planogram.clone()).await.unwrap();
+        let dollars = operator_service.import_planogram(3, planogram.clone()).await;
+        let exported = operator_service.export_planogram(2).await.unwrap();
+
+        // Assert
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.slots.len(), 2);
+        assert_eq!(status.slots[0].soda_name.as_deref(), Some("Cola"));
+        assert_eq!(status.slots[0].price, Some(Money::from_minor_units(150, Currency::EUR)));
+        assert_eq!(status.slots[0].low_stock_threshold, 2);
+        assert_eq!(status.slots[1].soda_name, None);
+
+        assert_eq!(exported.slots.iter().map(|slot| slot.slot_id).collect::<Vec<_>>(), vec![1, 2]);
+        let mut sorted = planogram.clone();
+        sorted.slots.sort_by_key(|slot| slot.slot_id);
+        assert_eq!(exported, sorted, "The export reads back as the planogram it came from");
+
+        assert!(matches!(dollars, Err(OperatorError::MachineError(_))));
+        assert_eq!(operator_service.get_machine_status(3).await.unwrap().slots.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_planogram_leaves_machine_untouched() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 2).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        let before = operator_service.export_planogram(1).await.unwrap();
+
+        let mut unknown_flavor = before.clone();
+        unknown_flavor.slots[0].product.as_mut().unwrap().flavor = "licorice".to_string();
+        let mut blank_name = before.clone();
+        blank_name.slots[0].product.as_mut().unwrap().name = " ".to_string();
+        let mut too_many_slots = before.clone();
+        for slot_id in [2, 3] {
+            let mut slot = before.slots[0].clone();
+            slot.slot_id = slot_id;
+            too_many_slots.slots.push(slot);
+        }
+
+        // Act
+        let results = [
+            operator_service.import_planogram(1, unknown_flavor).await,
+            operator_service.import_planogram(1, blank_name).await,
+            operator_service.import_planogram(1, too_many_slots).await,
+        ];
+
+        // Assert
+        assert!(matches!(results[0], Err(OperatorError::Validation(_))));
+        assert!(matches!(results[1], Err(OperatorError::Validation(_))));
+        assert!(matches!(results[2], Err(OperatorError::MachineError(_))));
+        assert_eq!(operator_service.export_planogram(1).await.unwrap(), before,
+            "Slot 2 fits but is not added when slot 3 does not");
+    }
+
+    #[tokio::test]
+    async fn test_every_catalog_stores_products() {
+        let directory = tempfile::tempdir().unwrap();
+        let catalogs: Vec<(&str, Arc<dyn ProductCatalogRepository>)> = vec![
+            ("in-memory", Arc::new(InMemoryProductCatalog::new())),
+            ("json", Arc::new(JsonFileProductCatalog::new(directory.path().join("catalog.json")).unwrap())),
+            ("sqlite", Arc::new(SqliteSodaMachineRepository::open_in_memory().unwrap())),
+        ];
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let lemon = Soda::new("Lemon".to_string(), SodaFlavor::LemonLime, SodaSize::Small, Money::from_cents(120), true, false).unwrap();
+
+        for (name, catalog) in catalogs {
+            // Arrange
+            let mut cola = Product::new(Sku::new("COLA-12OZ").unwrap(), cola.clone(), Some("5449000000996".to_string())).unwrap();
+            let lemon = Product::new(Sku::new("LEMON-8OZ").unwrap(), lemon.clone(), None).unwrap();
+            catalog.save_product(&lemon).await.unwrap();
+            catalog.save_product(&cola).await.unwrap();
+
+            // Act
+            cola.set_default_price(Money::from_cents(175)).unwrap();
+            catalog.save_product(&cola).await.unwrap();
+            let found = catalog.find_product(cola.sku()).await.unwrap();
+            let listed = catalog.list_products().await.unwrap();
+            let deleted = catalog.delete_product(lemon.sku()).await.unwrap();
+            let deleted_again = catalog.delete_product(lemon.sku()).await.unwrap();
+
+            // Assert
+            assert_eq!(found, Some(cola.clone()), "{} should keep every field of the product", name);
+            assert_eq!(listed, vec![cola.clone(), lemon.clone()], "{} should list products ordered by SKU", name);
+            assert!(deleted, "{} should report the deleted product", name);
+            assert!(!deleted_again, "{} should report nothing to delete", name);
+            assert_eq!(catalog.find_product(lemon.sku()).await.unwrap(), None, "{}", name);
+            assert_eq!(catalog.list_products().await.unwrap().len(), 1, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_sales_ledger_keeps_sales() {
+        let directory = tempfile::tempdir().unwrap();
+        let ledgers: Vec<(&str, Arc<dyn SalesLedger>)> = vec![
+            ("in-memory", Arc::new(InMemorySalesLedger::new())),
+            ("json", Arc::new(JsonFileSalesLedger::new(directory.path().join("sales.jsonl")).unwrap())),
+            ("sqlite", Arc::new(SqliteSodaMachineRepository::open_in_memory().unwrap())),
+        ];
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let sale = |machine_id: u32, sequence: u64, secs: i64, promotion: Option<AppliedPromotion>| Sale {
+            machine_id: SodaMachineId::new(machine_id),
+            sequence,
+            slot_id: SlotId::new(1),
+            soda: cola.clone(),
+            price: Money::from_cents(150),
+            promotion,
+            sold_at: Timestamp::from_secs(secs),
+        };
+
+        for (name, ledger) in ledgers {
+            // Arrange
+            let discounted = AppliedPromotion { promotion_id: PromotionId::new("welcome").unwrap(), discount: Money::from_cents(25) };
+            let first = Sale { price: Money::from_cents(125), ..sale(1, 4, 100, Some(discounted)) };
+            let second = sale(2, 4, 200, None);
+            let third = sale(1, 9, 300, None);
+
+            // Act
+            ledger.record(&[third.clone(), first.clone()]).await.unwrap();
+            ledger.record(&[first.clone(), second.clone()]).await.unwrap();
+            let all = ledger.sales_between(Timestamp::EPOCH, Timestamp::from_secs(1_000)).await.unwrap();
+            let window = ledger.sales_between(Timestamp::from_secs(100), Timestamp::from_secs(300)).await.unwrap();
+
+            // Assert
+            assert_eq!(all, vec![first.clone(), second.clone(), third.clone()], "{} should keep each sale once, oldest first", name);
+            assert_eq!(window, vec![first, second], "{} should leave out sales at the end of the window", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_catalog_price_change_reaches_every_slot_selling_the_product() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let catalog_service = CatalogService::new(Arc::new(InMemoryProductCatalog::new()), repository.clone());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        catalog_service.add_product(Product::new(Sku::new("COLA-12OZ").unwrap(), cola.clone(), None).unwrap()).await.unwrap();
+        operator_service.create_new_machine(1, 4).await.unwrap();
+        operator_service.create_new_machine(2, 4).await.unwrap();
+        operator_service.create_new_machine(3, 4).await.unwrap();
+        catalog_service.assign_product(1, 1, 10, "cola-12oz").await.unwrap();
+        catalog_service.assign_product(1, 2, 10, "COLA-12OZ").await.unwrap();
+        catalog_service.assign_product(2, 1, 10, "COLA-12OZ").await.unwrap();
+        operator_service.configure_slot(3, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(2, 1, 5).await.unwrap();
+
+        // Act
+        let update = catalog_service.update_price("COLA-12OZ", Money::from_cents(175)).await.unwrap();
+        customer_service.insert_money(2, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(2, Money::from_cents(50)).await.unwrap();
+        let short = customer_service.buy_soda(2, 1).await;
+        customer_service.insert_money(2, Money::from_cents(25)).await.unwrap();
+        customer_service.buy_soda(2, 1).await.unwrap();
+
+        // Assert
+        assert_eq!(update.machines, vec![1, 2]);
+        assert_eq!(update.slots_repriced, 3);
+        assert_eq!(catalog_service.get_product("COLA-12OZ").await.unwrap().default_price, Money::from_cents(175));
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert!(status.slots.iter().all(|slot| slot.price == Some(Money::from_cents(175))));
+        assert_eq!(status.slots[0].sku.as_deref(), Some("COLA-12OZ"));
+        assert!(short.is_err(), "The old price no longer buys the soda");
+        assert_eq!(operator_service.get_machine_status(2).await.unwrap().slots[0].quantity, 4);
+
+        let hand_configured = operator_service.get_machine_status(3).await.unwrap();
+        assert_eq!(hand_configured.slots[0].price, Some(Money::from_cents(150)));
+        assert_eq!(hand_configured.slots[0].sku, None);
+
+        let removed = catalog_service.remove_product("COLA-12OZ").await;
+        assert!(matches!(removed, Err(CatalogError::ProductInUse { machines, .. }) if machines.len() == 2));
+    }
+
+    #[tokio::test]
+    async fn test_machine_prices_override_the_catalog_price() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let opened_at = Timestamp::from_secs(1_700_000_000);
+        let clock = Arc::new(FakeClock::new(opened_at));
+        let catalog_service = CatalogService::new(Arc::new(InMemoryProductCatalog::new()), repository.clone());
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone()).with_clock(clock.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let happy_hour = opened_at.plus(Duration::from_secs(3600));
+        let closing_time = opened_at.plus(Duration::from_secs(7200));
+
+        catalog_service.add_product(Product::new(Sku::new("COLA-12OZ").unwrap(), cola, None).unwrap()).await.unwrap();
+        for (machine_id, location) in [(1, "Airport, gate 12"), (2, "Office, 3rd floor")] {
+            operator_service.create_new_machine(machine_id, 4).await.unwrap();
+            operator_service.set_machine_location(machine_id, Some(location.to_string())).await.unwrap();
+            catalog_service.assign_product(machine_id, 1, 10, "COLA-12OZ").await.unwrap();
+            operator_service.refill_slot(machine_id, 1, 5).await.unwrap();
+        }
+        operator_service.load_cash(1, Money::from_cents(25), 10).await.unwrap();
+
+        // Act
+        operator_service.set_price_override(1, "cola-12oz", Some(Money::from_cents(200))).await.unwrap();
+        operator_service.schedule_price_change(2, "COLA-12OZ", Some(Money::from_cents(100)), happy_hour).await.unwrap();
+        operator_service.schedule_price_change(2, "COLA-12OZ", None, closing_time).await.unwrap();
+        catalog_service.update_price("COLA-12OZ", Money::from_cents(160)).await.unwrap();
+
+        // Assert
+        let price_now = |machine_id| {
+            let customer_service = &customer_service;
+            async move { customer_service.list_available_sodas(machine_id).await.unwrap()[0].price.clone() }
+        };
+        assert_eq!(price_now(1).await, "2.00", "The airport charges its own price");
+        assert_eq!(price_now(2).await, "1.60", "The office follows the catalog");
+        assert_eq!(operator_service.get_machine_status(1).await.unwrap().slots[0].price, Some(Money::from_cents(200)));
+
+        clock.set(happy_hour);
+        assert_eq!(price_now(2).await, "1.00");
+        assert_eq!(price_now(1).await, "2.00");
+        customer_service.insert_money(1, Money::from_cents(200)).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+        let airport = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        assert_eq!(airport.total_collected(), Money::from_cents(200));
+
+        clock.set(closing_time);
+        assert_eq!(price_now(2).await, "1.60");
+
+        let changes = operator_service.list_price_changes(2).await.unwrap();
+        assert!(changes.is_empty(), "Happy hour is over and nothing else is planned: {:?}", changes);
+        let changes = operator_service.list_price_changes(1).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].price, Some(Money::from_cents(200)));
+        assert!(!changes[0].is_upcoming);
+
+        let in_the_past = operator_service.schedule_price_change(2, "COLA-12OZ", Some(Money::from_cents(90)), happy_hour).await;
+        assert!(matches!(in_the_past, Err(OperatorError::MachineError(SodaMachineError::PriceChangeInPast(_)))));
+        let blank_sku = operator_service.set_price_override(2, " ", Some(Money::from_cents(90))).await;
+        assert!(matches!(blank_sku, Err(OperatorError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_promotion_campaign_discounts_sales_on_every_machine() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let opened_at = Timestamp::from_secs(1_700_000_000); // 22:13 UTC
+        let clock = Arc::new(FakeClock::new(opened_at));
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_event_publisher(publisher.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let cherry = Soda::new("Cherry".to_string(), SodaFlavor::Cherry, SodaSize::Medium, Money::from_cents(150), false, false).unwrap();
+        let campaign: Vec<PromotionDTO> = serde_json::from_str(r#"[
+            { "id": "cherry-20", "name": "Cherry week", "discount": { "percent_off": 20 }, "flavor": "cherry" },
+            { "id": "late-night", "name": "Late night", "discount": { "percent_off": 50 }, "hours": "23:00-01:00" },
+            { "id": "third-free", "name": "Third one free", "discount": { "buy_n_get_one_free": 2 } },
+            { "id": "welcome", "name": "Welcome", "discount": { "amount_off": "0.50" }, "is_coupon": true,
+              "valid_until": "2023-12-01T00:00:00Z" }
+        ]"#).unwrap();
+
+        for machine_id in [1, 2] {
+            operator_service.create_new_machine(machine_id, 4).await.unwrap();
+            operator_service.configure_slot(machine_id, 1, 10, cola.clone()).await.unwrap();
+            operator_service.refill_slot(machine_id, 1, 5).await.unwrap();
+            operator_service.configure_slot(machine_id, 2, 10, cherry.clone()).await.unwrap();
+            operator_service.refill_slot(machine_id, 2, 5).await.unwrap();
+            operator_service.load_cash(machine_id, Money::from_cents(25), 20).await.unwrap();
+            operator_service.load_cash(machine_id, Money::from_cents(5), 10).await.unwrap();
+        }
+
+        // Act
+        for machine_id in [1, 2] {
+            for promotion in &campaign {
+                operator_service.add_promotion(machine_id, promotion.clone()).await.unwrap();
+            }
+        }
+        customer_service.insert_money(1, Money::from_cents(500)).await.unwrap();
+        customer_service.buy_soda(1, 2).await.unwrap();
+        customer_service.enter_coupon(1, "Welcome").await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+
+        clock.set(opened_at.plus(Duration::from_secs(3600)));
+        customer_service.insert_money(2, Money::from_cents(100)).await.unwrap();
+        customer_service.buy_soda(2, 1).await.unwrap();
+
+        // Assert
+        let sales: Vec<(u32, Money, Option<String>)> = publisher.published().iter()
+            .filter_map(|envelope| match &envelope.event {
+                SodaMachineEvent::SodaDispensed { price, promotion, .. } => Some((
+                    envelope.machine_id.value(),
+                    *price,
+                    promotion.as_ref().map(|promotion| promotion.promotion_id.to_string()),
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sales, vec![
+            (1, Money::from_cents(120), Some("CHERRY-20".to_string())),
+            (1, Money::from_cents(100), Some("WELCOME".to_string())),
+            (1, Money::zero(), Some("THIRD-FREE".to_string())),
+            (2, Money::from_cents(75), Some("LATE-NIGHT".to_string())),
+        ]);
+        let machine = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        assert_eq!(machine.total_collected(), Money::from_cents(220));
+
+        let promotions = operator_service.list_promotions(2).await.unwrap();
+        assert_eq!(promotions.len(), 4);
+        assert!(promotions.iter().all(|promotion| promotion.is_active));
+        assert_eq!(promotions[3].discount, DiscountDTO::AmountOff("0.50".to_string()));
+        assert_eq!(promotions[3].valid_until.as_deref(), Some("2023-12-01T00:00:00.000Z"));
+
+        operator_service.remove_promotion(2, "late-night").await.unwrap();
+        assert_eq!(operator_service.list_promotions(2).await.unwrap().len(), 3);
+        assert!(matches!(
+            operator_service.remove_promotion(2, "late-night").await,
+            Err(OperatorError::MachineError(SodaMachineError::PromotionNotFound(_)))
+        ));
+        assert!(matches!(
+            operator_service.add_promotion(2, campaign[0].clone()).await,
+            Err(OperatorError::MachineError(SodaMachineError::PromotionAlreadyExists(_)))
+        ));
+        let bubblegum = PromotionDTO { id: "gum".to_string(), flavor: Some("bubblegum".to_string()), ..campaign[0].clone() };
+        let too_late = PromotionDTO { id: "late".to_string(), hours: Some("25:00-26:00".to_string()), ..campaign[1].clone() };
+        assert!(matches!(operator_service.add_promotion(2, bubblegum).await, Err(OperatorError::Validation(_))));
+        assert!(matches!(operator_service.add_promotion(2, too_late).await, Err(OperatorError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fleet_dashboard_shows_which_machines_to_visit() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let euro_cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_minor_units(200, Currency::EUR), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola.clone()).await.unwrap();
+        operator_service.configure_slot(1, 2, 10, cola.clone()).await.unwrap();
+        operator_service.configure_slot(1, 3, 10, cola.clone()).await.unwrap();
+        operator_service.refill_slot(1, 1, 10).await.unwrap();
+        operator_service.refill_slot(1, 2, 2).await.unwrap();
+
+        operator_service.create_new_machine(2, 5).await.unwrap();
+        operator_service.configure_slot(2, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(2, 1, 5).await.unwrap();
+        customer_service.insert_money(2, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(2, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(2, 1).await.unwrap();
+        customer_service.insert_money(2, Money::from_cents(25)).await.unwrap();
+
+        operator_service.create_new_machine_with_currency(3, 5, Currency::EUR).await.unwrap();
+        operator_service.configure_slot(3, 1, 10, euro_cola).await.unwrap();
+        operator_service.refill_slot(3, 1, 6).await.unwrap();
+        let mut broken = repository.find_by_id(SodaMachineId::new(3)).await.unwrap().unwrap();
+        broken.disable();
+        repository.save(&broken).await.unwrap();
+
+        // Act
+        let dashboard = operator_service.get_fleet_dashboard(2).await.unwrap();
+
+        // Assert
+        assert_eq!(dashboard.machines.len(), 3);
+        assert_eq!(dashboard.machines[0].empty_slots, vec![3]);
+        assert_eq!(dashboard.machines[0].low_slots, vec![2]);
+        assert_eq!(dashboard.machines[1].inserted_money, Money::from_cents(25));
+        assert!(!dashboard.machines[1].needs_visit());
+        assert!(dashboard.machines[2].needs_visit());
+        assert_eq!(dashboard.out_of_service, vec![3]);
+        assert_eq!(dashboard.needs_refill, vec![1]);
+
+        assert_eq!(dashboard.totals.len(), 2);
+        let dollars = &dashboard.totals[0];
+        assert_eq!(dollars.currency, Currency::USD);
+        assert_eq!(dollars.machine_count, 2);
+        assert_eq!(dollars.total_collected, Money::from_cents(150));
+        assert_eq!(dollars.inventory_value, Money::from_cents(150 * 16));
+        assert_eq!(dollars.inserted_money, Money::from_cents(25));
+        let euros = &dashboard.totals[1];
+        assert_eq!(euros.currency, Currency::EUR);
+        assert_eq!(euros.machine_count, 1);
+        assert_eq!(euros.inventory_value, Money::from_minor_units(1200, Currency::EUR));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_sales_outlive_deleted_machine() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+        operator_service.collect_cash(1, "OP-7", Money::from_cents(150)).await.unwrap();
+
+        // Act
+        operator_service.delete_machine(1).await.unwrap();
+
+        // Assert
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let count = |table: &str| -> u32 {
+            connection.query_row(&format!("SELECT COUNT(*) FROM {} WHERE machine_id = 1", table), [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(count("sales"), 1, "The sales ledger keeps the machine's sales");
+        assert_eq!(count("slots"), 0);
+        assert_eq!(count("cash_box"), 0);
+        assert!(!repository.exists(SodaMachineId::new(1)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_machine_created_again_under_a_deleted_id_keeps_its_own_ledger() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        let sell_refund_and_collect = || async {
+            operator_service.create_new_machine(1, 5).await.unwrap();
+            operator_service.configure_slot(1, 1, 10, cola.clone()).await.unwrap();
+            operator_service.refill_slot(1, 1, 5).await.unwrap();
+            customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+            customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+            customer_service.buy_soda(1, 1).await.unwrap();
+            operator_service.issue_refund(1, "OP-7", Money::from_cents(50), "Soda was warm").await.unwrap();
+            operator_service.collect_cash(1, "OP-7", Money::from_cents(100)).await.unwrap();
+        };
+        sell_refund_and_collect().await;
+        operator_service.delete_machine(1).await.unwrap();
+
+        // Act
+        sell_refund_and_collect().await;
+
+        // Assert
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let incarnations = |table: &str| -> Vec<(i64, i64)> {
+            connection
+                .prepare(&format!("SELECT incarnation, sequence FROM {} WHERE machine_id = 1 ORDER BY incarnation", table))
+                .unwrap()
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .map(|row| row.unwrap())
+                .collect()
+        };
+        for table in ["sales", "refunds", "cash_collections"] {
+            let rows = incarnations(table);
+            assert_eq!(rows.len(), 2, "Both machines are in the {} ledger", table);
+            assert_eq!(rows[0].1, rows[1].1, "The machine created again restarted its sequence");
+            assert_eq!((rows[0].0, rows[1].0), (1, 2));
+        }
+
+        let sales = repository.sales_between(Timestamp::EPOCH, Timestamp::from_secs(i32::MAX as i64)).await.unwrap();
+        assert_eq!(sales.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_sale_retried_after_its_machine_was_created_again_keeps_its_incarnation() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let clock = Arc::new(FakeClock::new(Timestamp::from_secs(1_700_000_000)));
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_sales_ledger(Arc::new(FlakySalesLedger::new(repository.clone(), 1)));
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        let set_up = || async {
+            operator_service.create_new_machine(1, 5).await.unwrap();
+            operator_service.configure_slot(1, 1, 10, cola.clone()).await.unwrap();
+            operator_service.refill_slot(1, 1, 5).await.unwrap();
+        };
+        set_up().await;
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+        operator_service.collect_cash(1, "OP-7", Money::from_cents(150)).await.unwrap();
+        operator_service.delete_machine(1).await.unwrap();
+
+        clock.advance(Duration::from_secs(3600));
+        set_up().await;
+
+        // Act
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        let bought = customer_service.buy_soda(1, 1).await;
+
+        // Assert
+        assert!(bought.is_ok(), "The retried sale does not take the sequence of the new machine's sale: {:?}", bought);
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let rows: Vec<(i64, i64)> = connection
+            .prepare("SELECT incarnation, sequence FROM sales WHERE machine_id = 1 ORDER BY incarnation")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2, "The retried sale is not recorded twice");
+        assert_eq!((rows[0].0, rows[1].0), (1, 2));
+        assert_eq!(rows[0].1, rows[1].1);
+    }
+
+    #[tokio::test]
+    async fn test_cash_collection_reconciles_each_operator_visit() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let clock = Arc::new(FakeClock::new(Timestamp::from_secs(1_700_000_000)));
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone()).with_clock(clock.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 20).await.unwrap();
+        for _ in 0..2 {
+            customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+            customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+            customer_service.buy_soda(1, 1).await.unwrap();
+        }
+
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        let busy = operator_service.collect_cash(1, "OP-7", Money::from_cents(900)).await;
+        assert!(matches!(busy, Err(OperatorError::MachineError(SodaMachineError::SessionInProgress(_)))));
+        customer_service.request_money_back(1).await.unwrap();
+
+        // Act
+        clock.advance(Duration::from_secs(3600));
+        let first_visit = operator_service.collect_cash(1, "OP-7", Money::from_cents(275)).await.unwrap();
+
+        clock.advance(Duration::from_secs(3600));
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+        let second_visit = operator_service.collect_cash(1, " OP-9 ", Money::from_cents(150)).await.unwrap();
+
+        // Assert
+        assert_eq!(first_visit.period_start, None);
+        assert_eq!(first_visit.revenue, Money::from_cents(300));
+        assert_eq!(first_visit.float, Money::from_cents(500));
+        assert_eq!(first_visit.kept, Money::from_cents(500));
+        assert_eq!(first_visit.expected, Money::from_cents(300));
+        assert_eq!(first_visit.variance, Money::from_cents(-25), "A quarter went missing");
+
+        assert_eq!(second_visit.operator_id, "OP-9");
+        assert_eq!(second_visit.period_start, Some(first_visit.collected_at));
+        assert_eq!(second_visit.revenue, Money::from_cents(150));
+        assert_eq!(second_visit.float, Money::from_cents(500));
+        assert_eq!(second_visit.kept, Money::from_cents(500));
+        assert_eq!(second_visit.variance, Money::zero());
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.total_collected, Money::zero());
+        assert_eq!(status.cash_in_box, Money::from_cents(500), "The quarters stay in the cash box as change");
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let mut statement = connection.prepare(
+            "SELECT operator_id, revenue, float, kept, counted FROM cash_collections WHERE machine_id = 1 ORDER BY sequence",
+        ).unwrap();
+        let ledger: Vec<(String, i64, i64, i64, i64)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(ledger, vec![("OP-7".to_string(), 300, 500, 500, 275), ("OP-9".to_string(), 150, 500, 500, 150)]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_vend_keeps_the_credit_and_quarantines_the_unit() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let log_path = directory.path().join("alerts.log");
+        let notifier = Arc::new(LogFileNotifier::new(&log_path).unwrap());
+        let repository = Arc::new(SqliteSodaMachineRepository::open(directory.path().join("soda.db")).unwrap());
+        let clock = Arc::new(FakeClock::new(Timestamp::from_secs(1_700_000_000)));
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_notifier(notifier);
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 4).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(200)).await.unwrap();
+
+        // Act
+        customer_service.reserve_soda(1, 1).await.unwrap();
+        let pending = repository.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        let busy = customer_service.request_money_back(1).await;
+        customer_service.report_vend_failure(1, VendFailure::Jammed).await.unwrap();
+        let after_failure = operator_service.get_machine_status(1).await.unwrap();
+
+        customer_service.reserve_soda(1, 1).await.unwrap();
+        customer_service.confirm_vend(1).await.unwrap();
+
+        // Assert
+        assert_eq!(pending.pending_vend().map(|vend| vend.slot_id), Some(SlotId::new(1)), "The reservation is stored");
+        assert!(matches!(busy, Err(CustomerError::MachineError(SodaMachineError::VendInProgress(_)))));
+
+        assert_eq!(after_failure.inserted_money, Money::from_cents(200), "Nothing is charged for a failed vend");
+        assert_eq!(after_failure.total_collected, Money::zero());
+        assert_eq!(after_failure.slots[0].quantity, 4, "The jammed unit is taken off the slot");
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.inserted_money, Money::from_cents(50));
+        assert_eq!(status.total_collected, Money::from_cents(150));
+        assert_eq!(status.slots[0].quantity, 3);
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("machine 1 slot 1: vend failed (jammed)"), "got: {}", log);
+    }
+
+    #[tokio::test]
+    async fn test_operator_resolves_a_vend_the_controller_never_finished() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let ledger = Arc::new(InMemorySalesLedger::new());
+        let operator_service = OperatorService::new(repository.clone()).with_sales_ledger(ledger.clone());
+        let customer_service = CustomerService::new(repository.clone()).with_sales_ledger(ledger.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 6).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(200)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+
+        // The controller reserves a vend and goes down before it hears back from the motor
+        customer_service.reserve_soda(1, 1).await.unwrap();
+        let stuck_buy = customer_service.buy_soda(1, 1).await;
+        let stuck_refund = customer_service.request_money_back(1).await;
+        let stuck_collection = operator_service.collect_cash(1, "OP-7", Money::zero()).await;
+        let stuck = operator_service.get_machine_status(1).await.unwrap();
+
+        // Act
+        operator_service.resolve_pending_vend(1, Some(VendFailure::Jammed)).await.unwrap();
+        let after_failure = operator_service.get_machine_status(1).await.unwrap();
+        customer_service.reserve_soda(1, 1).await.unwrap();
+        operator_service.resolve_pending_vend(1, None).await.unwrap();
+        let nothing_pending = operator_service.resolve_pending_vend(1, None).await;
+
+        // Assert
+        assert!(matches!(stuck_buy, Err(CustomerError::MachineError(SodaMachineError::VendInProgress(_)))));
+        assert!(matches!(stuck_refund, Err(CustomerError::MachineError(SodaMachineError::VendInProgress(_)))));
+        assert!(matches!(stuck_collection, Err(OperatorError::MachineError(SodaMachineError::VendInProgress(_)))));
+        let pending = stuck.pending_vend.unwrap();
+        assert_eq!((pending.slot_id, pending.soda_name.as_str(), pending.price), (1, "Cola", Money::from_cents(150)));
+
+        assert!(after_failure.pending_vend.is_none());
+        assert_eq!(after_failure.inserted_money, Money::from_cents(300), "A failed vend charges nothing");
+        assert_eq!(after_failure.slots[0].quantity, 4, "The jammed can is quarantined");
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert!(status.pending_vend.is_none());
+        assert_eq!(status.total_collected, Money::from_cents(150), "The delivered soda is charged");
+        assert_eq!(status.slots[0].quantity, 3);
+
+        let sales = ledger.sales();
+        assert_eq!(sales.len(), 1, "The delivered soda is in the sales ledger, the failed one is not");
+        assert_eq!((sales[0].slot_id, sales[0].soda.name(), sales[0].price), (SlotId::new(1), "Cola", Money::from_cents(150)));
+        assert!(matches!(nothing_pending, Err(OperatorError::MachineError(SodaMachineError::NoPendingVend))));
+        assert_eq!(customer_service.request_money_back(1).await.unwrap(), Money::from_cents(150));
+    }
+
+    #[tokio::test]
+    async fn test_refund_is_paid_from_the_cash_box_and_kept_in_the_ledger() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = CustomerService::new(repository.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 20).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(50)).await.unwrap();
+        customer_service.buy_soda(1, 1).await.unwrap();
+
+        // Act
+        let too_much = operator_service.issue_refund(1, "OP-7", Money::from_cents(200), "Soda was warm").await;
+        let no_reason = operator_service.issue_refund(1, "OP-7", Money::from_cents(50), "  ").await;
+        let refund = operator_service.issue_refund(1, "OP-7", Money::from_cents(50), "Soda was warm").await.unwrap();
+
+        // Assert
+        assert!(matches!(too_much, Err(OperatorError::MachineError(SodaMachineError::RefundExceedsRevenue { .. }))));
+        assert!(matches!(no_reason, Err(OperatorError::MachineError(SodaMachineError::RefundReasonMissing))));
+
+        assert_eq!(refund.amount, Money::from_cents(50));
+        assert_eq!(refund.coins, vec![(Money::from_cents(50), 1)], "The $1.50 went in as a dollar and a half dollar");
+        assert_eq!(refund.total_collected, Money::from_cents(100));
+
+        let reconciliation = operator_service.collect_cash(1, "OP-7", Money::from_cents(100)).await.unwrap();
+        assert_eq!(reconciliation.revenue, Money::from_cents(100));
+        assert_eq!(reconciliation.variance, Money::zero(), "The refund is already taken off the revenue");
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let ledger: (String, i64, String) = connection.query_row(
+            "SELECT operator_id, amount, reason FROM refunds WHERE machine_id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!(ledger, ("OP-7".to_string(), 50, "Soda was warm".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_simulated_hardware_only_charges_sodas_that_dropped() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let jammed = Arc::new(SimulatedHardware::new().with_jam_rate(1.0));
+        let working = Arc::new(SimulatedHardware::new().with_motor_latency(Duration::from_millis(5)));
+        let operator_service = OperatorService::new(repository.clone());
+        let jammed_service = CustomerService::new(repository.clone()).with_hardware(VendingHardware::from_driver(jammed.clone()));
+        let working_service = CustomerService::new(repository.clone()).with_hardware(VendingHardware::from_driver(working.clone()));
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.configure_slot(1, 1, 10, cola).await.unwrap();
+        operator_service.refill_slot(1, 1, 5).await.unwrap();
+        operator_service.load_cash(1, Money::from_cents(25), 4).await.unwrap();
+
+        // Act
+        jammed_service.insert_money(1, Money::from_cents(200)).await.unwrap();
+        let failed = jammed_service.buy_soda(1, 1).await;
+        let after_failure = operator_service.get_machine_status(1).await.unwrap();
+        working_service.buy_soda(1, 1).await.unwrap();
+
+        // Assert
+        assert!(matches!(failed, Err(CustomerError::VendFailed(VendFailure::Jammed))));
+        assert_eq!(after_failure.inserted_money, Money::from_cents(200), "The customer keeps their credit");
+        assert_eq!(after_failure.slots[0].quantity, 4, "The jammed unit is quarantined");
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.total_collected, Money::from_cents(150));
+        assert_eq!(status.slots[0].quantity, 3);
+
+        let jammed_display: Vec<String> = jammed.messages().into_iter().map(|(_, message)| message).collect();
+        assert_eq!(jammed_display, vec!["Credit: $2.00", "Sorry, Cola could not be vended, please choose again"]);
+        assert_eq!(working.messages(), vec![(SodaMachineId::new(1), "Enjoy your Cola".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_money_is_only_credited_once_the_hardware_took_it() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let picky = Arc::new(SimulatedHardware::new().with_coin_reject_rate(1.0));
+        let hardware = Arc::new(SimulatedHardware::new().with_acceptor_latency(Duration::from_millis(5)));
+        let operator_service = OperatorService::new(repository.clone());
+        let picky_service = CustomerService::new(repository.clone()).with_hardware(VendingHardware::from_driver(picky.clone()));
+        let customer_service = CustomerService::new(repository.clone()).with_hardware(VendingHardware::from_driver(hardware.clone()));
+        let machine_id = SodaMachineId::new(1);
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+
+        // Act
+        let rejected_coin = picky_service.insert_money(1, Money::from_cents(25)).await;
+        let bill_past_picky_acceptor = picky_service.insert_money(1, Money::from_cents(500)).await;
+
+        hardware.open_door(machine_id);
+        let while_servicing = customer_service.insert_money(1, Money::from_cents(25)).await;
+        hardware.close_door(machine_id);
+
+        let wrong_currency = customer_service.insert_money(1, Money::from_minor_units(100, Currency::EUR)).await;
+        customer_service.insert_money(1, Money::from_cents(25)).await.unwrap();
+
+        // Assert
+        assert!(matches!(rejected_coin, Err(CustomerError::MoneyRejected { .. })));
+        assert!(bill_past_picky_acceptor.is_ok(), "Bills go through the bill validator");
+        assert!(matches!(while_servicing, Err(CustomerError::DoorOpen(_))));
+        assert!(matches!(wrong_currency, Err(CustomerError::MachineError(_))));
+        assert_eq!(hardware.ejected(), vec![(machine_id, Money::from_minor_units(100, Currency::EUR))], "Refused money is handed back");
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.inserted_money, Money::from_cents(525));
+        assert_eq!(hardware.take_messages(), vec![(machine_id, "Credit: $5.25".to_string())]);
+        assert!(hardware.take_messages().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_messages_only_credit_accepted_genuine_money() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = Arc::new(CustomerService::new(repository.clone()));
+        let acceptor_service = AcceptorService::new(customer_service.clone(), Currency::USD);
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.set_accepted_denominations(1, &[Money::from_cents(25)], &[Money::from_cents(100), Money::from_cents(500)]).await.unwrap();
+
+        // Act
+        let quarter = acceptor_service.handle_device_message(1, "COIN 3").await.unwrap();
+        let five = acceptor_service.handle_device_message(1, "bill 2").await.unwrap();
+        let fifty = acceptor_service.handle_device_message(1, "BILL 5").await.unwrap();
+        let counterfeit = acceptor_service.handle_device_message(1, "BILL 1 COUNTERFEIT").await.unwrap();
+        let rejected = acceptor_service.handle_device_message(1, "COIN REJECT").await.unwrap();
+        let unknown_channel = acceptor_service.handle_device_message(1, "COIN 9").await.unwrap();
+        let dime_in_strict_mode = acceptor_service.handle_device_message(1, "COIN 2").await.unwrap();
+        let garbage = acceptor_service.handle_device_message(1, "TOKEN 1").await;
+
+        // Assert
+        assert_eq!(quarter, AcceptorOutcomeDTO::Credited { amount: Money::from_cents(25) });
+        assert_eq!(five, AcceptorOutcomeDTO::Credited { amount: Money::from_cents(500) });
+        assert_eq!(fifty, AcceptorOutcomeDTO::Returned { reason: "$50.00 bills are not accepted".to_string() });
+        assert_eq!(counterfeit, AcceptorOutcomeDTO::Returned { reason: "bill flagged as counterfeit".to_string() });
+        assert_eq!(rejected, AcceptorOutcomeDTO::Returned { reason: "coin not recognized".to_string() });
+        assert_eq!(unknown_channel, AcceptorOutcomeDTO::Returned { reason: "unknown coin channel 9".to_string() });
+        assert!(
+            matches!(&dime_in_strict_mode, AcceptorOutcomeDTO::Returned { reason } if reason.contains("$0.10")),
+            "The machine's own strict mode refuses the dime, got: {:?}", dime_in_strict_mode
+        );
+        assert!(matches!(garbage, Err(AcceptorError::InvalidMessage(_))));
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.inserted_money, Money::from_cents(525));
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_messages_are_checked_once_against_the_machine() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let rejects_bills = Arc::new(SimulatedHardware::new().with_bill_reject_rate(1.0));
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = Arc::new(CustomerService::new(repository.clone()).with_hardware(VendingHardware::from_driver(rejects_bills.clone())));
+        let acceptor_service = AcceptorService::new(customer_service, Currency::USD);
+        let machine_id = SodaMachineId::new(1);
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        operator_service.set_accepted_denominations(1, &[Money::from_cents(100)], &[Money::from_cents(5000)]).await.unwrap();
+
+        // Act
+        let fifty = acceptor_service.handle_device_message(1, "BILL 5").await.unwrap();
+        let dollar_coin = acceptor_service.handle_device_message(1, "COIN 4").await.unwrap();
+        let dollar_bill = acceptor_service.handle_device_message(1, "BILL 1").await.unwrap();
+
+        let mut machine = repository.find_by_id(machine_id).await.unwrap().unwrap();
+        machine.disable();
+        repository.save(&machine).await.unwrap();
+        let out_of_service = acceptor_service.handle_device_message(1, "COIN 4").await.unwrap();
+
+        // Assert
+        assert_eq!(
+            fifty,
+            AcceptorOutcomeDTO::Credited { amount: Money::from_cents(5000) },
+            "The machine's own bills count, and the bill validator that took the bill is not asked again"
+        );
+        assert_eq!(dollar_coin, AcceptorOutcomeDTO::Credited { amount: Money::from_cents(100) });
+        assert_eq!(dollar_bill, AcceptorOutcomeDTO::Returned { reason: "$1.00 bills are not accepted".to_string() });
+        assert!(matches!(out_of_service, AcceptorOutcomeDTO::Returned { .. }), "got: {:?}", out_of_service);
+        assert_eq!(
+            rejects_bills.ejected(),
+            vec![(machine_id, Money::from_cents(100))],
+            "Only the coin the machine refused after it was taken is ejected, the device hands back the bill it was told to return"
+        );
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.inserted_money, Money::from_cents(5100));
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_refuses_a_machine_of_another_currency() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let operator_service = OperatorService::new(repository.clone());
+        let customer_service = Arc::new(CustomerService::new(repository.clone()));
+        let acceptor_service = AcceptorService::new(customer_service, Currency::USD);
+        operator_service.create_new_machine_with_currency(1, 5, Currency::EUR).await.unwrap();
+
+        // Act
+        let mismatch = acceptor_service.handle_device_message(1, "COIN 3").await;
+        let unknown_machine = acceptor_service.handle_device_message(2, "COIN 3").await;
+
+        // Assert
+        assert!(
+            matches!(mismatch, Err(AcceptorError::CurrencyMismatch { machine: Currency::EUR, acceptor: Currency::USD })),
+            "got: {:?}", mismatch
+        );
+        assert!(matches!(unknown_machine, Err(AcceptorError::CreditFailure(CustomerError::SodaMachineNotFound(_)))));
+
+        let status = operator_service.get_machine_status(1).await.unwrap();
+        assert_eq!(status.inserted_money, Money::zero_in(Currency::EUR));
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_survives_a_restart() {
+        // Arrange
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("soda.db");
+        let repository = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let operator_service = OperatorService::new(repository.clone());
+        let standard = AcceptedDenominations::standard(Currency::USD);
+        let coins: Vec<Money> = standard.coins().iter().copied().collect();
+        let bills: Vec<Money> = standard.bills().iter().copied().collect();
+
+        operator_service.create_new_machine(1, 5).await.unwrap();
+        let nothing = operator_service.set_accepted_denominations(1, &[], &[]).await;
+        operator_service.set_accepted_denominations(1, &coins, &bills).await.unwrap();
+
+        // Act
+        let reopened = Arc::new(SqliteSodaMachineRepository::open(&path).unwrap());
+        let customer_service = CustomerService::new(reopened.clone());
+        let seven_cents = customer_service.insert_money(1, Money::from_cents(7)).await;
+        customer_service.insert_money(1, Money::from_cents(2000)).await.unwrap();
+
+        OperatorService::new(reopened.clone()).accept_any_denomination(1).await.unwrap();
+        customer_service.insert_money(1, Money::from_cents(5000)).await.unwrap();
+
+        // Assert
+        assert!(matches!(nothing, Err(OperatorError::Validation(_))));
+        assert!(matches!(
+            seven_cents,
+            Err(CustomerError::MachineError(SodaMachineError::DenominationNotAccepted(amount))) if amount == Money::from_cents(7)
+        ));
+
+        let machine = reopened.find_by_id(SodaMachineId::new(1)).await.unwrap().unwrap();
+        assert_eq!(machine.accepted_denominations(), None);
+        assert_eq!(machine.inserted_money(), Money::from_cents(7000));
+    }
+
+    #[tokio::test]
+    async fn test_sales_report_shows_revenue_by_flavor_and_machine() {
+        // Arrange
+        let repository = Arc::new(InMemorySodaMachineRepository::new());
+        let ledger = Arc::new(InMemorySalesLedger::new());
+        let clock = Arc::new(FakeClock::new("2024-03-04T09:00:00Z".parse().unwrap()));
+        let operator_service = OperatorService::new(repository.clone()).with_clock(clock.clone());
+        let customer_service = CustomerService::new(repository.clone())
+            .with_clock(clock.clone())
+            .with_sales_ledger(ledger.clone());
+        let reporting_service = ReportingService::new(ledger.clone());
+        let cola = Soda::new("Cola".to_string(), SodaFlavor::Cola, SodaSize::Medium, Money::from_cents(150), false, true).unwrap();
+        let orange = Soda::new("Fanta".to_string(), SodaFlavor::Orange, SodaSize::Small, Money::from_cents(100), false, false).unwrap();
+
+        for machine_id in [1, 2] {
+            operator_service.create_new_machine(machine_id, 5).await.unwrap();
+            operator_service.configure_slot(machine_id, 1, 10, cola.clone()).await.unwrap();
+            operator_service.refill_slot(machine_id, 1, 5).await.unwrap();
+            operator_service.configure_slot(machine_id, 2, 10, orange.clone()).await.unwrap();
+            operator_service.refill_slot(machine_id, 2, 5).await.unwrap();
+        }
+
+        let buy = |machine_id: u32, slot_id: u32, coins: &'static [i64]| {
+            let customer_service = &customer_service;
+            async move {
+                for &cents in coins {
+                    customer_service.insert_money(machine_id, Money::from_cents(cents)).await.unwrap();
+                }
+                customer_service.buy_soda(machine_id, slot_id).await.unwrap();
+            }
+        };
+        buy(1, 1, &[100, 50]).await;
+        buy(1, 2, &[100]).await;
+        clock.advance(Duration::from_secs(86_400));
+        buy(2, 1, &[100, 50]).await;
+        clock.advance(Duration::from_secs(7 * 86_400));
+        buy(1, 1, &[100, 50]).await;
+
+        let from: Timestamp = "2024-03-01T00:00:00Z".parse().unwrap();
+        let until: Timestamp = "2024-04-01T00:00:00Z".parse().unwrap();
+
+        // Act
+        let by_flavor = reporting_service.sales_report(ReportDimension::Flavor, ReportPeriod::Weekly, from, until).await.unwrap();
+        let by_machine = reporting_service.sales_report(ReportDimension::Machine, ReportPeriod::Monthly, from, until).await.unwrap();
+        let backwards = reporting_service.sales_report(ReportDimension::Flavor, ReportPeriod::Daily, until, from).await;
+
+        // Assert
+        let weekly: Vec<(String, &str, u32, Money)> = by_flavor.lines.iter()
+            .map(|line| (line.period_start.to_string(), line.key.as_str(), line.units, line.revenue))
+            .collect();
+        assert_eq!(weekly, vec![
+            ("2024-03-04T00:00:00.000Z".to_string(), "Cola", 2, Money::from_cents(300)),
+            ("2024-03-04T00:00:00.000Z".to_string(), "Orange", 1, Money::from_cents(100)),
+            ("2024-03-11T00:00:00.000Z".to_string(), "Cola", 1, Money::from_cents(150)),
+        ]);
+        assert_eq!(by_flavor.totals.len(), 1);
+        assert_eq!(by_flavor.totals[0].units, 4);
+        assert_eq!(by_flavor.totals[0].revenue, Money::from_cents(550));
+
+        let monthly: Vec<(&str, u32, Money)> = by_machine.lines.iter()
+            .map(|line| (line.key.as_str(), line.units, line.revenue))
+            .collect();
+        assert_eq!(monthly, vec![("machine 1", 3, Money::from_cents(400)), ("machine 2", 1, Money::from_cents(150))]);
+        assert_eq!(by_machine.lines[0].period_end.to_string(), "2024-04-01T00:00:00.000Z");
+
+        assert!(matches!(backwards, Err(ReportingError::InvalidWindow { .. })));
+        assert_eq!(ledger.sales().len(), 4);
+    }
+
     #[tokio::test]
     async fn test_operator_can_refill_slot() {
         // Arrange
@@ -99,12 +2201,13 @@ mod tests {
         
         // Verify initial machine status
         let status = operator_service.get_machine_status(machine_id).await.unwrap();
-        assert!(status.contains(&format!("Machine {}", machine_id)), "Status should show machine ID");
-        assert!(status.contains("1 slots"), "Status should show number of slots");
-        assert!(status.contains(&format!("{} total", initial_capacity)), 
-            "Status should show total sodas {}, got: {}", initial_capacity, status);
-        assert!(status.contains(&format!("${:.2}", soda_price.as_decimal() * initial_capacity as f64)), 
-            "Status should show inventory value for {} sodas, got: {}", initial_capacity, status);
+        assert_eq!(status.machine_id, machine_id, "Status should show machine ID");
+        assert_eq!(status.slots.len(), 1, "Status should show number of slots");
+        assert_eq!(status.total_sodas, initial_capacity, "Status should show total sodas");
+        assert_eq!(status.inventory_value, (soda_price * initial_capacity as i64).unwrap(),
+            "Status should show inventory value for {} sodas", initial_capacity);
+        assert_eq!(status.slots[0].soda_name.as_deref(), Some("Cola"));
+        assert_eq!(status.slots[0].fill_percentage, 1.0);
 
         // Verify initial available sodas
         let available_sodas = customer_service.list_available_sodas(machine_id).await.unwrap();
@@ -115,16 +2218,16 @@ mod tests {
         
         // Buy all sodas to empty the slot
         for i in 0..initial_capacity {
-            customer_service.insert_money(machine_id, soda_price).await.unwrap();
+            customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+            customer_service.insert_money(machine_id, Money::from_cents(50)).await.unwrap();
             customer_service.buy_soda(machine_id, slot_id).await.unwrap();
             
             // Check status after each purchase
             let status = operator_service.get_machine_status(machine_id).await.unwrap();
             let remaining = initial_capacity - (i + 1);
-            assert!(status.contains(&format!("{} total", remaining)), 
-                "Status should show {} total sodas, status: {}", remaining, status);
-            assert!(status.contains(&format!("${:.2}", soda_price.as_decimal() * remaining as f64)),
-                "Status should show updated inventory value, got: {}", status);
+            assert_eq!(status.total_sodas, remaining, "Status should show {} total sodas", remaining);
+            assert_eq!(status.inventory_value, (soda_price * remaining as i64).unwrap(),
+                "Status should show updated inventory value");
         }
 
         // Verify slot is empty by checking available sodas
@@ -133,11 +2236,14 @@ mod tests {
 
         // Verify empty slot in machine status
         let status = operator_service.get_machine_status(machine_id).await.unwrap();
-        assert!(status.contains("0 total"), "Status should show no sodas");
-        assert!(status.contains("$0.00 inventory"), "Status should show zero inventory value");
+        assert_eq!(status.total_sodas, 0, "Status should show no sodas");
+        assert!(status.inventory_value.is_zero(), "Status should show zero inventory value");
+        assert_eq!(status.available_sodas, 0);
+        assert_eq!(status.slots[0].fill_percentage, 0.0);
 
         // Additional verification by trying to buy one more soda
-        customer_service.insert_money(machine_id, soda_price).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(50)).await.unwrap();
         assert!(customer_service.buy_soda(machine_id, slot_id).await.is_err());
         customer_service.request_money_back(machine_id).await.unwrap();
 
@@ -149,10 +2255,9 @@ mod tests {
 
         // Verify machine status after refill
         let status = operator_service.get_machine_status(machine_id).await.unwrap();
-        assert!(status.contains(&format!("{} total", refill_amount)), 
-            "Status should show {} total sodas after refill, status: {}", refill_amount, status);
-        assert!(status.contains(&format!("${:.2}", soda_price.as_decimal() * refill_amount as f64)),
-            "Status should show updated inventory value after refill, got: {}", status);
+        assert_eq!(status.total_sodas, refill_amount, "Status should show {} total sodas after refill", refill_amount);
+        assert_eq!(status.inventory_value, (soda_price * refill_amount as i64).unwrap(),
+            "Status should show updated inventory value after refill");
 
         // Verify available sodas after refill
         let available_sodas = customer_service.list_available_sodas(machine_id).await.unwrap();
@@ -162,7 +2267,8 @@ mod tests {
         
         // Verify we can now buy exactly refill_amount sodas
         for _ in 0..refill_amount {
-            customer_service.insert_money(machine_id, soda_price).await.unwrap();
+            customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+            customer_service.insert_money(machine_id, Money::from_cents(50)).await.unwrap();
             assert!(customer_service.buy_soda(machine_id, slot_id).await.is_ok());
         }
 
@@ -171,7 +2277,8 @@ mod tests {
         assert_eq!(available_sodas.len(), 0, "Should have no available sodas after buying all refilled sodas");
 
         // Additional verification by trying to buy one more soda
-        customer_service.insert_money(machine_id, soda_price).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(100)).await.unwrap();
+        customer_service.insert_money(machine_id, Money::from_cents(50)).await.unwrap();
         assert!(customer_service.buy_soda(machine_id, slot_id).await.is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineId};
+use soda_core::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+
+type Interference = Box<dyn FnOnce(&mut SodaMachine) + Send>;
+
+/// Repository that lets another terminal change a machine right before a save goes through,
+/// so tests can reproduce a lost update without relying on timing
+pub struct RacingRepository {
+    inner: Arc<dyn SodaMachineRepository>,
+    interference: Mutex<Option<Interference>>,
+}
+
+impl RacingRepository {
+    pub fn new(inner: Arc<dyn SodaMachineRepository>) -> Self {
+        RacingRepository { inner, interference: Mutex::new(None) }
+    }
+
+    /// Before the next save, another terminal loads the machine, applies `change` and saves it
+    pub fn interfere_once(&self, change: impl FnOnce(&mut SodaMachine) + Send + 'static) {
+        *self.interference.lock().unwrap() = Some(Box::new(change));
+    }
+}
+
+#[async_trait]
+impl SodaMachineRepository for RacingRepository {
+    async fn find_by_id(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn save(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let interference = self.interference.lock().unwrap().take();
+
+        if let Some(change) = interference {
+            let mut other = self.inner.find_by_id(machine.id()).await?
+                .expect("the machine exists");
+            change(&mut other);
+            self.inner.save(&other).await?;
+        }
+
+        self.inner.save(machine).await
+    }
+
+    async fn create(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        self.inner.create(machine).await
+    }
+
+    async fn list(&self) -> Result<Vec<SodaMachine>, RepositoryError> {
+        self.inner.list().await
+    }
+
+    async fn delete(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        self.inner.delete(id).await
+    }
+}
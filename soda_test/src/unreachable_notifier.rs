@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+use soda_core::ports::driven::notification_port::{DeliveryFailure, NotificationError, NotificationPort, StockAlert};
+
+/// Notifier whose backend is down, it fails every alert but remembers which ones were tried
+/// and what the services reported
+pub struct UnreachableNotifier {
+    attempted: Mutex<Vec<StockAlert>>,
+    reported: Mutex<Vec<DeliveryFailure>>,
+}
+
+impl UnreachableNotifier {
+    pub fn new() -> Self {
+        UnreachableNotifier { attempted: Mutex::new(Vec::new()), reported: Mutex::new(Vec::new()) }
+    }
+
+    /// Gets every alert that was sent, oldest first
+    pub fn attempted(&self) -> Vec<StockAlert> {
+        self.attempted.lock().unwrap().clone()
+    }
+
+    /// Gets every failure that was reported, oldest first
+    pub fn reported(&self) -> Vec<DeliveryFailure> {
+        self.reported.lock().unwrap().clone()
+    }
+}
+
+impl Default for UnreachableNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NotificationPort for UnreachableNotifier {
+    async fn notify(&self, alert: &StockAlert) -> Result<(), NotificationError> {
+        self.attempted.lock().unwrap().push(alert.clone());
+        Err(NotificationError::ConnectionError("alert service unreachable".to_string()))
+    }
+
+    async fn report(&self, failure: &DeliveryFailure) {
+        self.reported.lock().unwrap().push(failure.clone());
+    }
+}
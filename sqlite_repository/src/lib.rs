@@ -0,0 +1,922 @@
+use async_trait::async_trait;
+use rusqlite::{Connection, OptionalExtension, Row, Transaction, params};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use soda_core::domain::aggregates::soda_machine::{SodaMachine, SodaMachineEvent, SodaMachineId, SodaMachineState};
+use soda_core::domain::entities::cash_box::CashBox;
+use soda_core::domain::entities::price_schedule::{PriceChange, PriceSchedule};
+use soda_core::domain::entities::product::{Product, Sku};
+use soda_core::domain::entities::promotion::{AppliedPromotion, DailyHours, Discount, Promotion, PromotionId, PromotionScope};
+use soda_core::domain::entities::sale::Sale;
+use soda_core::domain::entities::purchase_session::{PurchaseSession, SessionId, SessionPurchase};
+use soda_core::domain::entities::slot::{Slot, SlotId};
+use soda_core::domain::entities::vend::PendingVend;
+use soda_core::domain::value_objects::denomination::{AcceptedDenominations, DenominationKind};
+use soda_core::domain::value_objects::money::{Currency, Money};
+use soda_core::domain::value_objects::soda::{Soda, SodaFlavor, SodaSize};
+use soda_core::domain::value_objects::timestamp::Timestamp;
+use soda_core::ports::driven::product_catalog_port::ProductCatalogRepository;
+use soda_core::ports::driven::sales_ledger_port::SalesLedger;
+use soda_core::ports::driven::soda_machine_repository_port::{SodaMachineRepository, RepositoryError};
+
+mod migrations;
+pub use migrations::MIGRATIONS;
+
+/// Stores machines in normalized tables of an embedded SQLite database
+///
+/// Every sold soda is also written to the `sales` table, so sales can be reported on with plain SQL.
+/// The product catalog lives in the same database, so the repository is also the `ProductCatalogRepository`,
+/// and reading the `sales` table back makes it the `SalesLedger`.
+pub struct SqliteSodaMachineRepository {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteSodaMachineRepository {
+    /// Opens (or creates) the database file and brings its schema up to date
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RepositoryError> {
+        let connection = Connection::open(path)
+            .map_err(|e| RepositoryError::ConnectionError(e.to_string()))?;
+        Self::with_connection(connection)
+    }
+
+    /// Opens a database that only lives as long as the repository, e.g. for tests
+    pub fn open_in_memory() -> Result<Self, RepositoryError> {
+        let connection = Connection::open_in_memory()
+            .map_err(|e| RepositoryError::ConnectionError(e.to_string()))?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self, RepositoryError> {
+        connection.pragma_update(None, "foreign_keys", true).map_err(sql_error)?;
+        migrations::migrate(&mut connection).map_err(RepositoryError::ConnectionError)?;
+
+        Ok(SqliteSodaMachineRepository { connection: Mutex::new(connection) })
+    }
+
+    /// Gets how many schema migrations the database has run
+    pub fn schema_version(&self) -> Result<u32, RepositoryError> {
+        migrations::schema_version(&*self.lock()?).map_err(sql_error)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Connection>, RepositoryError> {
+        self.connection.lock().map_err(|e| {
+            RepositoryError::ConnectionError(format!("Mutex poisoned: {}", e))
+        })
+    }
+}
+
+#[async_trait]
+impl SodaMachineRepository for SqliteSodaMachineRepository {
+    async fn find_by_id(&self, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sql_error)?;
+        read_machine(&transaction, id)
+    }
+
+    async fn save(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sql_error)?;
+
+        if let Some(stored_version) = stored_version(&transaction, machine.id())?
+            && stored_version != machine.committed_version()
+        {
+            return Err(RepositoryError::ConcurrencyConflict {
+                machine_id: machine.id(),
+                expected_version: machine.committed_version(),
+                actual_version: stored_version,
+            });
+        }
+
+        write_machine(&transaction, machine)?;
+        transaction.commit().map_err(sql_error)
+    }
+
+    async fn create(&self, machine: &SodaMachine) -> Result<(), RepositoryError> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sql_error)?;
+
+        if stored_version(&transaction, machine.id())?.is_some() {
+            return Err(RepositoryError::Other(Box::new(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "Machine already exists",
+            ))));
+        }
+
+        // A deleted machine's ledger rows are kept, so a machine created under its ID starts a new incarnation
+        transaction.execute(
+            "UPDATE machine_incarnations SET incarnation = incarnation + 1 WHERE machine_id = ?1",
+            [machine.id().value()],
+        ).map_err(sql_error)?;
+
+        write_machine(&transaction, machine)?;
+        transaction.commit().map_err(sql_error)
+    }
+
+    async fn list(&self) -> Result<Vec<SodaMachine>, RepositoryError> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sql_error)?;
+
+        let ids = {
+            let mut statement = transaction.prepare("SELECT id FROM machines ORDER BY id").map_err(sql_error)?;
+            statement.query_map([], |row| row.get::<_, u32>(0))
+                .map_err(sql_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(sql_error)?
+        };
+
+        let mut machines = Vec::new();
+        for id in ids {
+            machines.extend(read_machine(&transaction, SodaMachineId::new(id))?);
+        }
+
+        Ok(machines)
+    }
+
+    /// Deletes the machine with its slots, cash and session, its sales, cash collections and refunds
+    /// stay in the ledgers under the machine's current incarnation
+    async fn delete(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let connection = self.lock()?;
+        let deleted = connection
+            .execute("DELETE FROM machines WHERE id = ?1", [id.value()])
+            .map_err(sql_error)?;
+
+        Ok(deleted > 0)
+    }
+
+    async fn exists(&self, id: SodaMachineId) -> Result<bool, RepositoryError> {
+        let connection = self.lock()?;
+        connection
+            .query_row("SELECT EXISTS (SELECT 1 FROM machines WHERE id = ?1)", [id.value()], |row| row.get(0))
+            .map_err(sql_error)
+    }
+}
+
+#[async_trait]
+impl ProductCatalogRepository for SqliteSodaMachineRepository {
+    async fn find_product(&self, sku: &Sku) -> Result<Option<Product>, RepositoryError> {
+        let connection = self.lock()?;
+        connection
+            .query_row(
+                &format!("SELECT p.sku, p.barcode, {} FROM products p JOIN sodas d ON d.id = p.soda_id WHERE p.sku = ?1", SODA_COLUMNS),
+                [sku.as_str()],
+                |row| Ok(read_product(row)),
+            )
+            .optional()
+            .map_err(sql_error)?
+            .transpose()
+    }
+
+    async fn save_product(&self, product: &Product) -> Result<(), RepositoryError> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sql_error)?;
+
+        transaction.execute(
+            "INSERT INTO products (sku, soda_id, barcode) VALUES (?1, ?2, ?3)
+             ON CONFLICT (sku) DO UPDATE SET soda_id = excluded.soda_id, barcode = excluded.barcode",
+            params![product.sku().as_str(), soda_id(&transaction, product.soda())?, product.barcode()],
+        ).map_err(sql_error)?;
+
+        transaction.commit().map_err(sql_error)
+    }
+
+    async fn list_products(&self) -> Result<Vec<Product>, RepositoryError> {
+        let connection = self.lock()?;
+        let mut statement = connection.prepare(&format!(
+            "SELECT p.sku, p.barcode, {} FROM products p JOIN sodas d ON d.id = p.soda_id ORDER BY p.sku",
+            SODA_COLUMNS
+        )).map_err(sql_error)?;
+
+        let mut rows = statement.query([]).map_err(sql_error)?;
+        let mut products = Vec::new();
+        while let Some(row) = rows.next().map_err(sql_error)? {
+            products.push(read_product(row)?);
+        }
+
+        Ok(products)
+    }
+
+    async fn delete_product(&self, sku: &Sku) -> Result<bool, RepositoryError> {
+        let connection = self.lock()?;
+        let deleted = connection
+            .execute("DELETE FROM products WHERE sku = ?1", [sku.as_str()])
+            .map_err(sql_error)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+#[async_trait]
+impl SalesLedger for SqliteSodaMachineRepository {
+    /// Saving a machine already adds its sales, so this only adds the sales the ledger has not seen yet
+    ///
+    /// A sale is looked up by its machine, sequence number and time in every incarnation: a sale
+    /// retried after its machine was deleted and created again is already kept under the old one.
+    async fn record(&self, sales: &[Sale]) -> Result<(), RepositoryError> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sql_error)?;
+
+        for sale in sales {
+            if sale_recorded(&transaction, sale)? {
+                continue;
+            }
+
+            transaction.execute(
+                "INSERT OR IGNORE INTO sales (machine_id, incarnation, sequence, slot_id, soda_id, price, currency, sold_at, promotion_id, discount)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    sale.machine_id.value(),
+                    incarnation(&transaction, sale.machine_id)?,
+                    sale.sequence as i64,
+                    sale.slot_id.value(),
+                    soda_id(&transaction, &sale.soda)?,
+                    sale.price.cents(),
+                    sale.price.currency().code(),
+                    sale.sold_at.as_millis(),
+                    sale.promotion.as_ref().map(|promotion| promotion.promotion_id.as_str()),
+                    sale.promotion.as_ref().map(|promotion| promotion.discount.cents()),
+                ],
+            ).map_err(sql_error)?;
+        }
+
+        transaction.commit().map_err(sql_error)
+    }
+
+    async fn sales_between(&self, from: Timestamp, until: Timestamp) -> Result<Vec<Sale>, RepositoryError> {
+        let connection = self.lock()?;
+        let mut statement = connection.prepare(&format!(
+            "SELECT s.machine_id, s.sequence, s.slot_id, s.price, s.currency, s.sold_at, s.promotion_id, s.discount, {}
+             FROM sales s JOIN sodas d ON d.id = s.soda_id
+             WHERE s.sold_at >= ?1 AND s.sold_at < ?2 ORDER BY s.sold_at, s.machine_id, s.sequence",
+            SODA_COLUMNS
+        )).map_err(sql_error)?;
+
+        let mut rows = statement.query([from.as_millis(), until.as_millis()]).map_err(sql_error)?;
+        let mut sales = Vec::new();
+        while let Some(row) = rows.next().map_err(sql_error)? {
+            let currency = parse_currency(&row.get::<_, String>(4).map_err(sql_error)?)?;
+            let promotion = read_applied_promotion(row.get(6).map_err(sql_error)?, row.get(7).map_err(sql_error)?, currency)?;
+
+            sales.push(Sale {
+                machine_id: SodaMachineId::new(row.get(0).map_err(sql_error)?),
+                sequence: row.get::<_, i64>(1).map_err(sql_error)? as u64,
+                slot_id: SlotId::new(row.get(2).map_err(sql_error)?),
+                price: Money::from_minor_units(row.get(3).map_err(sql_error)?, currency),
+                sold_at: Timestamp::from_millis(row.get(5).map_err(sql_error)?),
+                soda: read_soda(row, 8)?,
+                promotion,
+            });
+        }
+
+        Ok(sales)
+    }
+}
+
+fn stored_version(transaction: &Transaction<'_>, id: SodaMachineId) -> Result<Option<u64>, RepositoryError> {
+    transaction
+        .query_row("SELECT version FROM machines WHERE id = ?1", [id.value()], |row| row.get::<_, i64>(0))
+        .optional()
+        .map(|version| version.map(|version| version as u64))
+        .map_err(sql_error)
+}
+
+/// Replaces everything stored for the machine and adds its new sales, cash collections and refunds to the ledgers
+fn write_machine(transaction: &Transaction<'_>, machine: &SodaMachine) -> Result<(), RepositoryError> {
+    let state = machine.state();
+    let id = state.id.value();
+
+    transaction.execute(
+        "INSERT INTO machines (id, currency, max_slots, is_operational, total_collected, session_timeout_ms, next_session_id, version, location, last_collected_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT (id) DO UPDATE SET
+             currency = excluded.currency,
+             max_slots = excluded.max_slots,
+             is_operational = excluded.is_operational,
+             total_collected = excluded.total_collected,
+             session_timeout_ms = excluded.session_timeout_ms,
+             next_session_id = excluded.next_session_id,
+             version = excluded.version,
+             location = excluded.location,
+             last_collected_at = excluded.last_collected_at",
+        params![
+            id,
+            state.currency.code(),
+            state.max_slots,
+            state.is_operational,
+            state.total_collected.cents(),
+            state.session_timeout.as_millis() as i64,
+            state.next_session_id.value() as i64,
+            state.version as i64,
+            state.location,
+            state.last_collected_at.map(|at| at.as_millis()),
+        ],
+    ).map_err(sql_error)?;
+
+    transaction.execute("DELETE FROM slots WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    for slot in &state.slots {
+        let soda_id = slot.soda_type().map(|soda| soda_id(transaction, soda)).transpose()?;
+        transaction.execute(
+            "INSERT INTO slots (machine_id, slot_id, soda_id, quantity, max_capacity, is_enabled, low_stock_threshold, sku)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                id,
+                slot.id().value(),
+                soda_id,
+                slot.quantity(),
+                slot.max_capacity(),
+                slot.is_enabled(),
+                slot.low_stock_threshold(),
+                slot.sku().map(Sku::as_str),
+            ],
+        ).map_err(sql_error)?;
+    }
+
+    transaction.execute("DELETE FROM cash_box WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    for (denomination, count) in state.cash_box.denominations() {
+        transaction.execute(
+            "INSERT INTO cash_box (machine_id, denomination, count) VALUES (?1, ?2, ?3)",
+            params![id, denomination.cents(), count],
+        ).map_err(sql_error)?;
+    }
+
+    transaction.execute("DELETE FROM accepted_denominations WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    if let Some(accepted) = &state.accepted_denominations {
+        let coins = accepted.coins().iter().map(|coin| (DenominationKind::Coin, coin));
+        let bills = accepted.bills().iter().map(|bill| (DenominationKind::Bill, bill));
+        for (kind, denomination) in coins.chain(bills) {
+            transaction.execute(
+                "INSERT INTO accepted_denominations (machine_id, kind, denomination) VALUES (?1, ?2, ?3)",
+                params![id, kind.to_string(), denomination.cents()],
+            ).map_err(sql_error)?;
+        }
+    }
+
+    transaction.execute("DELETE FROM price_changes WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    for change in state.price_schedule.changes() {
+        transaction.execute(
+            "INSERT INTO price_changes (machine_id, sku, price, effective_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, change.sku.as_str(), change.price.map(|price| price.cents()), change.effective_at.as_millis()],
+        ).map_err(sql_error)?;
+    }
+
+    transaction.execute("DELETE FROM promotions WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    for (position, promotion) in state.promotions.iter().enumerate() {
+        let (discount_kind, discount_value) = match promotion.discount() {
+            Discount::PercentOff(percent) => ("percent_off", percent as i64),
+            Discount::AmountOff(amount) => ("amount_off", amount.cents()),
+            Discount::BuyNGetOneFree(n) => ("buy_n_get_one_free", n as i64),
+        };
+        let (flavor, sku) = match promotion.scope() {
+            PromotionScope::AllProducts => (None, None),
+            PromotionScope::Flavor(flavor) => (Some(flavor_code(*flavor)), None),
+            PromotionScope::Product(sku) => (None, Some(sku.as_str())),
+        };
+
+        transaction.execute(
+            "INSERT INTO promotions (machine_id, position, id, name, discount_kind, discount_value, flavor, sku, valid_from, valid_until, hours_from, hours_until, is_coupon)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                id,
+                position as i64,
+                promotion.id().as_str(),
+                promotion.name(),
+                discount_kind,
+                discount_value,
+                flavor,
+                sku,
+                promotion.valid_from().map(|time| time.as_millis()),
+                promotion.valid_until().map(|time| time.as_millis()),
+                promotion.daily_hours().map(|hours| hours.from_minute()),
+                promotion.daily_hours().map(|hours| hours.until_minute()),
+                promotion.is_coupon(),
+            ],
+        ).map_err(sql_error)?;
+    }
+
+    transaction.execute("DELETE FROM session_coupons WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    transaction.execute("DELETE FROM session_purchases WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    transaction.execute("DELETE FROM sessions WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    if let Some(session) = &state.session {
+        transaction.execute(
+            "INSERT INTO sessions (machine_id, session_id, started_at, last_activity_at, total_inserted, credit) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                session.id().value() as i64,
+                session.started_at().as_millis(),
+                session.last_activity_at().as_millis(),
+                session.total_inserted().cents(),
+                session.credit().cents(),
+            ],
+        ).map_err(sql_error)?;
+
+        for (position, purchase) in session.purchases().iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO session_purchases (machine_id, position, slot_id, soda_id, price, purchased_at, promotion_id, discount)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    id,
+                    position as i64,
+                    purchase.slot_id.value(),
+                    soda_id(transaction, &purchase.soda)?,
+                    purchase.price.cents(),
+                    purchase.purchased_at.as_millis(),
+                    purchase.promotion.as_ref().map(|promotion| promotion.promotion_id.as_str()),
+                    purchase.promotion.as_ref().map(|promotion| promotion.discount.cents()),
+                ],
+            ).map_err(sql_error)?;
+        }
+
+        for (position, code) in session.coupons().iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO session_coupons (machine_id, position, code) VALUES (?1, ?2, ?3)",
+                params![id, position as i64, code.as_str()],
+            ).map_err(sql_error)?;
+        }
+    }
+
+    transaction.execute("DELETE FROM pending_vends WHERE machine_id = ?1", [id]).map_err(sql_error)?;
+    if let Some(vend) = &state.pending_vend {
+        transaction.execute(
+            "INSERT INTO pending_vends (machine_id, slot_id, soda_id, price, promotion_id, discount, reserved_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id,
+                vend.slot_id.value(),
+                soda_id(transaction, &vend.soda)?,
+                vend.price.cents(),
+                vend.promotion.as_ref().map(|promotion| promotion.promotion_id.as_str()),
+                vend.promotion.as_ref().map(|promotion| promotion.discount.cents()),
+                vend.reserved_at.as_millis(),
+            ],
+        ).map_err(sql_error)?;
+    }
+
+    let incarnation = incarnation(transaction, state.id)?;
+    for (sequence, event) in machine.uncommitted_events() {
+        if let SodaMachineEvent::SodaDispensed { slot_id, soda, price, dispensed_at, promotion } = event {
+            transaction.execute(
+                "INSERT INTO sales (machine_id, incarnation, sequence, slot_id, soda_id, price, currency, sold_at, promotion_id, discount)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    id,
+                    incarnation,
+                    *sequence as i64,
+                    slot_id.value(),
+                    soda_id(transaction, soda)?,
+                    price.cents(),
+                    price.currency().code(),
+                    dispensed_at.as_millis(),
+                    promotion.as_ref().map(|promotion| promotion.promotion_id.as_str()),
+                    promotion.as_ref().map(|promotion| promotion.discount.cents()),
+                ],
+            ).map_err(sql_error)?;
+        }
+
+        if let SodaMachineEvent::RefundIssued { refund } = event {
+            transaction.execute(
+                "INSERT INTO refunds (machine_id, incarnation, sequence, operator_id, amount, currency, reason, issued_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    id,
+                    incarnation,
+                    *sequence as i64,
+                    refund.operator_id.as_str(),
+                    refund.amount.cents(),
+                    refund.amount.currency().code(),
+                    refund.reason,
+                    refund.issued_at.as_millis(),
+                ],
+            ).map_err(sql_error)?;
+        }
+
+        if let SodaMachineEvent::CashCollected { collection } = event {
+            transaction.execute(
+                "INSERT INTO cash_collections (machine_id, incarnation, sequence, operator_id, collected_at, period_start, revenue, float, kept, counted, currency)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    id,
+                    incarnation,
+                    *sequence as i64,
+                    collection.operator_id.as_str(),
+                    collection.collected_at.as_millis(),
+                    collection.period_start.map(|at| at.as_millis()),
+                    collection.revenue.cents(),
+                    collection.float.cents(),
+                    collection.kept.cents(),
+                    collection.counted.cents(),
+                    collection.counted.currency().code(),
+                ],
+            ).map_err(sql_error)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Tells whether the ledger holds a sale under any incarnation of its machine ID
+fn sale_recorded(transaction: &Transaction<'_>, sale: &Sale) -> Result<bool, RepositoryError> {
+    transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sales WHERE machine_id = ?1 AND sequence = ?2 AND sold_at = ?3)",
+        params![sale.machine_id.value(), sale.sequence as i64, sale.sold_at.as_millis()],
+        |row| row.get(0),
+    ).map_err(sql_error)
+}
+
+/// Gets the incarnation of a machine ID its ledger rows are written under, starting at 1 the first time the ID is seen
+fn incarnation(transaction: &Transaction<'_>, id: SodaMachineId) -> Result<i64, RepositoryError> {
+    transaction.execute(
+        "INSERT OR IGNORE INTO machine_incarnations (machine_id, incarnation) VALUES (?1, 1)",
+        [id.value()],
+    ).map_err(sql_error)?;
+
+    transaction
+        .query_row("SELECT incarnation FROM machine_incarnations WHERE machine_id = ?1", [id.value()], |row| row.get(0))
+        .map_err(sql_error)
+}
+
+/// Gets the ID of a soda product, adding the product the first time it is seen
+fn soda_id(transaction: &Transaction<'_>, soda: &Soda) -> Result<i64, RepositoryError> {
+    let values = params![
+        soda.name(),
+        flavor_code(soda.flavor()),
+        size_code(soda.size()),
+        soda.price().cents(),
+        soda.price().currency().code(),
+        soda.is_diet(),
+        soda.is_caffeinated(),
+    ];
+
+    transaction.execute(
+        "INSERT OR IGNORE INTO sodas (name, flavor, size, price, currency, is_diet, is_caffeinated) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        values,
+    ).map_err(sql_error)?;
+
+    transaction.query_row(
+        "SELECT id FROM sodas
+         WHERE name = ?1 AND flavor = ?2 AND size = ?3 AND price = ?4 AND currency = ?5 AND is_diet = ?6 AND is_caffeinated = ?7",
+        values,
+        |row| row.get(0),
+    ).map_err(sql_error)
+}
+
+/// Rebuilds the whole aggregate from its rows
+fn read_machine(transaction: &Transaction<'_>, id: SodaMachineId) -> Result<Option<SodaMachine>, RepositoryError> {
+    let machine_row = transaction.query_row(
+        "SELECT currency, max_slots, is_operational, total_collected, session_timeout_ms, next_session_id, version, location, last_collected_at FROM machines WHERE id = ?1",
+        [id.value()],
+        |row| Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, u32>(1)?,
+            row.get::<_, bool>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+            row.get::<_, i64>(6)?,
+            row.get::<_, Option<String>>(7)?,
+            row.get::<_, Option<i64>>(8)?,
+        )),
+    ).optional().map_err(sql_error)?;
+
+    let Some((currency, max_slots, is_operational, total_collected, session_timeout_ms, next_session_id, version, location, last_collected_at)) = machine_row else {
+        return Ok(None);
+    };
+    let currency = parse_currency(&currency)?;
+
+    let state = SodaMachineState {
+        id,
+        currency,
+        max_slots,
+        location,
+        slots: read_slots(transaction, id)?,
+        session: read_session(transaction, id, currency)?,
+        next_session_id: SessionId::new(next_session_id as u64),
+        session_timeout: Duration::from_millis(session_timeout_ms as u64),
+        total_collected: Money::from_minor_units(total_collected, currency),
+        last_collected_at: last_collected_at.map(Timestamp::from_millis),
+        cash_box: read_cash_box(transaction, id, currency)?,
+        price_schedule: read_price_schedule(transaction, id, currency)?,
+        promotions: read_promotions(transaction, id, currency)?,
+        pending_vend: read_pending_vend(transaction, id, currency)?,
+        accepted_denominations: read_accepted_denominations(transaction, id, currency)?,
+        is_operational,
+        version: version as u64,
+    };
+
+    SodaMachine::from_state(state).map(Some).map_err(invalid_data)
+}
+
+/// Columns selected for a soda joined from the `sodas` table
+const SODA_COLUMNS: &str = "d.name, d.flavor, d.size, d.price, d.currency, d.is_diet, d.is_caffeinated";
+
+fn read_slots(transaction: &Transaction<'_>, id: SodaMachineId) -> Result<Vec<Slot>, RepositoryError> {
+    let mut statement = transaction.prepare(&format!(
+        "SELECT s.slot_id, s.quantity, s.max_capacity, s.is_enabled, s.low_stock_threshold, s.soda_id, s.sku, {}
+         FROM slots s LEFT JOIN sodas d ON d.id = s.soda_id
+         WHERE s.machine_id = ?1 ORDER BY s.slot_id",
+        SODA_COLUMNS
+    )).map_err(sql_error)?;
+
+    let mut rows = statement.query([id.value()]).map_err(sql_error)?;
+    let mut slots = Vec::new();
+    while let Some(row) = rows.next().map_err(sql_error)? {
+        let slot_id = SlotId::new(row.get(0).map_err(sql_error)?);
+        let quantity: u32 = row.get(1).map_err(sql_error)?;
+        let max_capacity: u32 = row.get(2).map_err(sql_error)?;
+        let is_enabled: bool = row.get(3).map_err(sql_error)?;
+        let low_stock_threshold: u32 = row.get(4).map_err(sql_error)?;
+        let has_soda = row.get::<_, Option<i64>>(5).map_err(sql_error)?.is_some();
+        let sku = row.get::<_, Option<String>>(6).map_err(sql_error)?
+            .map(|sku| Sku::new(&sku).map_err(invalid_data))
+            .transpose()?;
+
+        let mut slot = match (has_soda, sku) {
+            (true, Some(sku)) => Slot::new_with_product(slot_id, sku, read_soda(row, 7)?, quantity, max_capacity),
+            (true, None) => Slot::new_with_soda(slot_id, read_soda(row, 7)?, quantity, max_capacity),
+            (false, _) => Slot::new(slot_id, max_capacity),
+        }.map_err(invalid_data)?;
+        slot.set_low_stock_threshold(low_stock_threshold).map_err(invalid_data)?;
+
+        if !is_enabled {
+            slot.disable();
+        }
+        slots.push(slot);
+    }
+
+    Ok(slots)
+}
+
+fn read_cash_box(transaction: &Transaction<'_>, id: SodaMachineId, currency: Currency) -> Result<CashBox, RepositoryError> {
+    let mut statement = transaction
+        .prepare("SELECT denomination, count FROM cash_box WHERE machine_id = ?1")
+        .map_err(sql_error)?;
+    let denominations = statement
+        .query_map([id.value()], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?)))
+        .map_err(sql_error)?;
+
+    let mut cash_box = CashBox::new(currency);
+    for denomination in denominations {
+        let (denomination, count) = denomination.map_err(sql_error)?;
+        cash_box.deposit(Money::from_minor_units(denomination, currency), count).map_err(invalid_data)?;
+    }
+
+    Ok(cash_box)
+}
+
+fn read_accepted_denominations(transaction: &Transaction<'_>, id: SodaMachineId, currency: Currency) -> Result<Option<AcceptedDenominations>, RepositoryError> {
+    let mut statement = transaction
+        .prepare("SELECT kind, denomination FROM accepted_denominations WHERE machine_id = ?1")
+        .map_err(sql_error)?;
+    let rows = statement
+        .query_map([id.value()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(sql_error)?;
+
+    let mut coins = Vec::new();
+    let mut bills = Vec::new();
+    for row in rows {
+        let (kind, denomination) = row.map_err(sql_error)?;
+        let denomination = Money::from_minor_units(denomination, currency);
+        match kind.as_str() {
+            "coin" => coins.push(denomination),
+            "bill" => bills.push(denomination),
+            other => return Err(invalid_data(format!("Unknown denomination kind '{}'", other))),
+        }
+    }
+
+    if coins.is_empty() && bills.is_empty() {
+        return Ok(None);
+    }
+    AcceptedDenominations::new(currency, &coins, &bills).map(Some).map_err(invalid_data)
+}
+
+fn read_price_schedule(transaction: &Transaction<'_>, id: SodaMachineId, currency: Currency) -> Result<PriceSchedule, RepositoryError> {
+    let mut statement = transaction
+        .prepare("SELECT sku, price, effective_at FROM price_changes WHERE machine_id = ?1")
+        .map_err(sql_error)?;
+    let changes = statement
+        .query_map([id.value()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, i64>(2)?)))
+        .map_err(sql_error)?;
+
+    let mut price_schedule = PriceSchedule::new();
+    for change in changes {
+        let (sku, price, effective_at) = change.map_err(sql_error)?;
+        price_schedule.schedule(PriceChange {
+            sku: Sku::new(&sku).map_err(invalid_data)?,
+            price: price.map(|price| Money::from_minor_units(price, currency)),
+            effective_at: Timestamp::from_millis(effective_at),
+        });
+    }
+
+    Ok(price_schedule)
+}
+
+fn read_promotions(transaction: &Transaction<'_>, id: SodaMachineId, currency: Currency) -> Result<Vec<Promotion>, RepositoryError> {
+    let mut statement = transaction.prepare(
+        "SELECT id, name, discount_kind, discount_value, flavor, sku, valid_from, valid_until, hours_from, hours_until, is_coupon
+         FROM promotions WHERE machine_id = ?1 ORDER BY position"
+    ).map_err(sql_error)?;
+
+    let mut rows = statement.query([id.value()]).map_err(sql_error)?;
+    let mut promotions = Vec::new();
+    while let Some(row) = rows.next().map_err(sql_error)? {
+        let promotion_id: String = row.get(0).map_err(sql_error)?;
+        let name: String = row.get(1).map_err(sql_error)?;
+        let discount_kind: String = row.get(2).map_err(sql_error)?;
+        let discount_value: i64 = row.get(3).map_err(sql_error)?;
+        let flavor: Option<String> = row.get(4).map_err(sql_error)?;
+        let sku: Option<String> = row.get(5).map_err(sql_error)?;
+        let valid_from: Option<i64> = row.get(6).map_err(sql_error)?;
+        let valid_until: Option<i64> = row.get(7).map_err(sql_error)?;
+        let hours_from: Option<u32> = row.get(8).map_err(sql_error)?;
+        let hours_until: Option<u32> = row.get(9).map_err(sql_error)?;
+        let is_coupon: bool = row.get(10).map_err(sql_error)?;
+
+        let discount = match discount_kind.as_str() {
+            "percent_off" => Discount::PercentOff(discount_value as u32),
+            "amount_off" => Discount::AmountOff(Money::from_minor_units(discount_value, currency)),
+            "buy_n_get_one_free" => Discount::BuyNGetOneFree(discount_value as u32),
+            other => return Err(invalid_data(format!("Unknown discount {}", other))),
+        };
+
+        let mut promotion = Promotion::new(PromotionId::new(&promotion_id).map_err(invalid_data)?, name, discount)
+            .and_then(|promotion| {
+                promotion.valid_between(valid_from.map(Timestamp::from_millis), valid_until.map(Timestamp::from_millis))
+            })
+            .map_err(invalid_data)?;
+        if let Some(flavor) = flavor {
+            let flavor = SodaFlavor::from_string(&flavor)
+                .ok_or_else(|| invalid_data(format!("Unknown soda flavor {}", flavor)))?;
+            promotion = promotion.for_flavor(flavor);
+        }
+        if let Some(sku) = sku {
+            promotion = promotion.for_product(Sku::new(&sku).map_err(invalid_data)?);
+        }
+        if let (Some(from), Some(until)) = (hours_from, hours_until) {
+            promotion = promotion.during(DailyHours::new(from, until).map_err(invalid_data)?);
+        }
+        if is_coupon {
+            promotion = promotion.as_coupon();
+        }
+
+        promotions.push(promotion);
+    }
+
+    Ok(promotions)
+}
+
+fn read_session(transaction: &Transaction<'_>, id: SodaMachineId, currency: Currency) -> Result<Option<PurchaseSession>, RepositoryError> {
+    let session_row = transaction.query_row(
+        "SELECT session_id, started_at, last_activity_at, total_inserted, credit FROM sessions WHERE machine_id = ?1",
+        [id.value()],
+        |row| Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+        )),
+    ).optional().map_err(sql_error)?;
+
+    let Some((session_id, started_at, last_activity_at, total_inserted, credit)) = session_row else {
+        return Ok(None);
+    };
+
+    let mut statement = transaction.prepare(&format!(
+        "SELECT p.slot_id, p.price, p.purchased_at, p.promotion_id, p.discount, {}
+         FROM session_purchases p JOIN sodas d ON d.id = p.soda_id
+         WHERE p.machine_id = ?1 ORDER BY p.position",
+        SODA_COLUMNS
+    )).map_err(sql_error)?;
+
+    let mut rows = statement.query([id.value()]).map_err(sql_error)?;
+    let mut purchases = Vec::new();
+    while let Some(row) = rows.next().map_err(sql_error)? {
+        let promotion = read_applied_promotion(row.get(3).map_err(sql_error)?, row.get(4).map_err(sql_error)?, currency)?;
+
+        purchases.push(SessionPurchase {
+            slot_id: SlotId::new(row.get(0).map_err(sql_error)?),
+            price: Money::from_minor_units(row.get(1).map_err(sql_error)?, currency),
+            purchased_at: Timestamp::from_millis(row.get(2).map_err(sql_error)?),
+            soda: read_soda(row, 5)?,
+            promotion,
+        });
+    }
+
+    let mut statement = transaction
+        .prepare("SELECT code FROM session_coupons WHERE machine_id = ?1 ORDER BY position")
+        .map_err(sql_error)?;
+    let coupons = statement
+        .query_map([id.value()], |row| row.get::<_, String>(0))
+        .map_err(sql_error)?
+        .map(|code| PromotionId::new(&code.map_err(sql_error)?).map_err(invalid_data))
+        .collect::<Result<Vec<_>, RepositoryError>>()?;
+
+    Ok(Some(PurchaseSession::restore(
+        SessionId::new(session_id as u64),
+        Timestamp::from_millis(started_at),
+        Timestamp::from_millis(last_activity_at),
+        Money::from_minor_units(total_inserted, currency),
+        Money::from_minor_units(credit, currency),
+        purchases,
+        coupons,
+    )))
+}
+
+fn read_pending_vend(transaction: &Transaction<'_>, id: SodaMachineId, currency: Currency) -> Result<Option<PendingVend>, RepositoryError> {
+    let mut statement = transaction.prepare(&format!(
+        "SELECT v.slot_id, v.price, v.promotion_id, v.discount, v.reserved_at, {}
+         FROM pending_vends v JOIN sodas d ON d.id = v.soda_id
+         WHERE v.machine_id = ?1",
+        SODA_COLUMNS
+    )).map_err(sql_error)?;
+
+    let mut rows = statement.query([id.value()]).map_err(sql_error)?;
+    let Some(row) = rows.next().map_err(sql_error)? else {
+        return Ok(None);
+    };
+
+    Ok(Some(PendingVend {
+        slot_id: SlotId::new(row.get(0).map_err(sql_error)?),
+        price: Money::from_minor_units(row.get(1).map_err(sql_error)?, currency),
+        promotion: read_applied_promotion(row.get(2).map_err(sql_error)?, row.get(3).map_err(sql_error)?, currency)?,
+        reserved_at: Timestamp::from_millis(row.get(4).map_err(sql_error)?),
+        soda: read_soda(row, 5)?,
+    }))
+}
+
+/// Reads the promotion a sale got from its ID and discount columns, which are both NULL without one
+fn read_applied_promotion(promotion_id: Option<String>, discount: Option<i64>, currency: Currency) -> Result<Option<AppliedPromotion>, RepositoryError> {
+    match (promotion_id, discount) {
+        (Some(promotion_id), Some(discount)) => Ok(Some(AppliedPromotion {
+            promotion_id: PromotionId::new(&promotion_id).map_err(invalid_data)?,
+            discount: Money::from_minor_units(discount, currency),
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Reads the `SODA_COLUMNS` starting at column `first`
+fn read_soda(row: &Row<'_>, first: usize) -> Result<Soda, RepositoryError> {
+    let name: String = row.get(first).map_err(sql_error)?;
+    let flavor: String = row.get(first + 1).map_err(sql_error)?;
+    let size: String = row.get(first + 2).map_err(sql_error)?;
+    let price: i64 = row.get(first + 3).map_err(sql_error)?;
+    let currency: String = row.get(first + 4).map_err(sql_error)?;
+    let is_diet: bool = row.get(first + 5).map_err(sql_error)?;
+    let is_caffeinated: bool = row.get(first + 6).map_err(sql_error)?;
+
+    let flavor = SodaFlavor::from_string(&flavor)
+        .ok_or_else(|| invalid_data(format!("Unknown soda flavor {}", flavor)))?;
+    let size = SodaSize::from_string(&size)
+        .ok_or_else(|| invalid_data(format!("Unknown soda size {}", size)))?;
+    let price = Money::from_minor_units(price, parse_currency(&currency)?);
+
+    Soda::new(name, flavor, size, price, is_diet, is_caffeinated).map_err(invalid_data)
+}
+
+/// Reads a catalog product from its SKU, its barcode and the `SODA_COLUMNS`
+fn read_product(row: &Row<'_>) -> Result<Product, RepositoryError> {
+    let sku: String = row.get(0).map_err(sql_error)?;
+    let barcode: Option<String> = row.get(1).map_err(sql_error)?;
+
+    Product::new(Sku::new(&sku).map_err(invalid_data)?, read_soda(row, 2)?, barcode).map_err(invalid_data)
+}
+
+/// How a flavor is stored, readable by `SodaFlavor::from_string`
+fn flavor_code(flavor: SodaFlavor) -> &'static str {
+    match flavor {
+        SodaFlavor::Cola => "cola",
+        SodaFlavor::Orange => "orange",
+        SodaFlavor::LemonLime => "lemon-lime",
+        SodaFlavor::RootBeer => "root beer",
+        SodaFlavor::Grape => "grape",
+        SodaFlavor::Cherry => "cherry",
+        SodaFlavor::Vanilla => "vanilla",
+        SodaFlavor::Strawberry => "strawberry",
+        SodaFlavor::Peach => "peach",
+        SodaFlavor::Watermelon => "watermelon",
+    }
+}
+
+/// How a size is stored, readable by `SodaSize::from_string`
+fn size_code(size: SodaSize) -> &'static str {
+    match size {
+        SodaSize::Small => "small",
+        SodaSize::Medium => "medium",
+        SodaSize::Large => "large",
+        SodaSize::XLarge => "x-large",
+    }
+}
+
+fn parse_currency(code: &str) -> Result<Currency, RepositoryError> {
+    Currency::from_code(code).ok_or_else(|| invalid_data(format!("Unknown currency {}", code)))
+}
+
+fn sql_error(err: rusqlite::Error) -> RepositoryError {
+    RepositoryError::Other(Box::new(err))
+}
+
+/// Stored rows that do not make a valid machine
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> RepositoryError {
+    RepositoryError::Other(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, err)))
+}
@@ -0,0 +1,326 @@
+use rusqlite::{Connection, Transaction};
+
+/// Schema changes in the order they are applied, the database's `user_version` counts how many ran
+///
+/// Amounts are stored in the currency's minor unit and times as milliseconds since the Unix epoch.
+pub const MIGRATIONS: &[&str] = &[
+    // 1: machines, their slots and cash, the soda products and the sales ledger
+    "
+    CREATE TABLE machines (
+        id                 INTEGER PRIMARY KEY,
+        currency           TEXT NOT NULL,
+        max_slots          INTEGER NOT NULL,
+        is_operational     INTEGER NOT NULL,
+        total_collected    INTEGER NOT NULL,
+        session_timeout_ms INTEGER NOT NULL,
+        next_session_id    INTEGER NOT NULL,
+        version            INTEGER NOT NULL
+    );
+
+    CREATE TABLE sodas (
+        id             INTEGER PRIMARY KEY AUTOINCREMENT,
+        name           TEXT NOT NULL,
+        flavor         TEXT NOT NULL,
+        size           TEXT NOT NULL,
+        price          INTEGER NOT NULL,
+        currency       TEXT NOT NULL,
+        is_diet        INTEGER NOT NULL,
+        is_caffeinated INTEGER NOT NULL,
+        UNIQUE (name, flavor, size, price, currency, is_diet, is_caffeinated)
+    );
+
+    CREATE TABLE slots (
+        machine_id   INTEGER NOT NULL REFERENCES machines (id) ON DELETE CASCADE,
+        slot_id      INTEGER NOT NULL,
+        soda_id      INTEGER REFERENCES sodas (id),
+        quantity     INTEGER NOT NULL,
+        max_capacity INTEGER NOT NULL,
+        is_enabled   INTEGER NOT NULL,
+        PRIMARY KEY (machine_id, slot_id)
+    );
+
+    CREATE TABLE cash_box (
+        machine_id   INTEGER NOT NULL REFERENCES machines (id) ON DELETE CASCADE,
+        denomination INTEGER NOT NULL,
+        count        INTEGER NOT NULL,
+        PRIMARY KEY (machine_id, denomination)
+    );
+
+    CREATE TABLE sessions (
+        machine_id       INTEGER PRIMARY KEY REFERENCES machines (id) ON DELETE CASCADE,
+        session_id       INTEGER NOT NULL,
+        started_at       INTEGER NOT NULL,
+        last_activity_at INTEGER NOT NULL,
+        total_inserted   INTEGER NOT NULL,
+        credit           INTEGER NOT NULL
+    );
+
+    CREATE TABLE session_purchases (
+        machine_id   INTEGER NOT NULL REFERENCES sessions (machine_id) ON DELETE CASCADE,
+        position     INTEGER NOT NULL,
+        slot_id      INTEGER NOT NULL,
+        soda_id      INTEGER NOT NULL REFERENCES sodas (id),
+        price        INTEGER NOT NULL,
+        purchased_at INTEGER NOT NULL,
+        PRIMARY KEY (machine_id, position)
+    );
+
+    CREATE TABLE sales (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id INTEGER NOT NULL REFERENCES machines (id),
+        sequence   INTEGER NOT NULL,
+        slot_id    INTEGER NOT NULL,
+        soda_id    INTEGER NOT NULL REFERENCES sodas (id),
+        price      INTEGER NOT NULL,
+        currency   TEXT NOT NULL,
+        sold_at    INTEGER NOT NULL,
+        UNIQUE (machine_id, sequence)
+    );
+
+    CREATE INDEX sales_by_soda ON sales (soda_id);
+    CREATE INDEX sales_by_time ON sales (sold_at);
+    ",
+    // 2: where machines are installed, and sales that outlive deleted machines
+    "
+    ALTER TABLE machines ADD COLUMN location TEXT;
+
+    CREATE TABLE sales_kept (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id INTEGER NOT NULL,
+        sequence   INTEGER NOT NULL,
+        slot_id    INTEGER NOT NULL,
+        soda_id    INTEGER NOT NULL REFERENCES sodas (id),
+        price      INTEGER NOT NULL,
+        currency   TEXT NOT NULL,
+        sold_at    INTEGER NOT NULL,
+        UNIQUE (machine_id, sequence)
+    );
+
+    INSERT INTO sales_kept SELECT * FROM sales;
+    DROP TABLE sales;
+    ALTER TABLE sales_kept RENAME TO sales;
+
+    CREATE INDEX sales_by_soda ON sales (soda_id);
+    CREATE INDEX sales_by_time ON sales (sold_at);
+    ",
+    // 3: per-slot low stock thresholds
+    "
+    ALTER TABLE slots ADD COLUMN low_stock_threshold INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 4: the product catalog, and which catalog product a slot sells
+    "
+    CREATE TABLE products (
+        sku     TEXT PRIMARY KEY,
+        soda_id INTEGER NOT NULL REFERENCES sodas (id),
+        barcode TEXT
+    );
+
+    ALTER TABLE slots ADD COLUMN sku TEXT;
+    ",
+    // 5: the prices a machine charges for catalog products, now and from later times
+    "
+    CREATE TABLE price_changes (
+        machine_id   INTEGER NOT NULL REFERENCES machines (id) ON DELETE CASCADE,
+        sku          TEXT NOT NULL,
+        price        INTEGER,
+        effective_at INTEGER NOT NULL,
+        PRIMARY KEY (machine_id, sku, effective_at)
+    );
+    ",
+    // 6: promotions, the coupons customers entered and the promotion each sale got
+    "
+    CREATE TABLE promotions (
+        machine_id     INTEGER NOT NULL REFERENCES machines (id) ON DELETE CASCADE,
+        position       INTEGER NOT NULL,
+        id             TEXT NOT NULL,
+        name           TEXT NOT NULL,
+        discount_kind  TEXT NOT NULL,
+        discount_value INTEGER NOT NULL,
+        flavor         TEXT,
+        sku            TEXT,
+        valid_from     INTEGER,
+        valid_until    INTEGER,
+        hours_from     INTEGER,
+        hours_until    INTEGER,
+        is_coupon      INTEGER NOT NULL,
+        PRIMARY KEY (machine_id, id)
+    );
+
+    CREATE TABLE session_coupons (
+        machine_id INTEGER NOT NULL REFERENCES sessions (machine_id) ON DELETE CASCADE,
+        position   INTEGER NOT NULL,
+        code       TEXT NOT NULL,
+        PRIMARY KEY (machine_id, position)
+    );
+
+    ALTER TABLE session_purchases ADD COLUMN promotion_id TEXT;
+    ALTER TABLE session_purchases ADD COLUMN discount INTEGER;
+
+    ALTER TABLE sales ADD COLUMN promotion_id TEXT;
+    ALTER TABLE sales ADD COLUMN discount INTEGER;
+    ",
+    // 7: cash collections, kept like sales after the machine is deleted
+    "
+    ALTER TABLE machines ADD COLUMN last_collected_at INTEGER;
+
+    CREATE TABLE cash_collections (
+        id           INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id   INTEGER NOT NULL,
+        sequence     INTEGER NOT NULL,
+        operator_id  TEXT NOT NULL,
+        collected_at INTEGER NOT NULL,
+        period_start INTEGER,
+        revenue      INTEGER NOT NULL,
+        float        INTEGER NOT NULL,
+        counted      INTEGER NOT NULL,
+        currency     TEXT NOT NULL,
+        UNIQUE (machine_id, sequence)
+    );
+    ",
+    // 8: the soda a two-phase vend is delivering, and the refunds operators paid out
+    "
+    CREATE TABLE pending_vends (
+        machine_id   INTEGER PRIMARY KEY REFERENCES machines (id) ON DELETE CASCADE,
+        slot_id      INTEGER NOT NULL,
+        soda_id      INTEGER NOT NULL REFERENCES sodas (id),
+        price        INTEGER NOT NULL,
+        promotion_id TEXT,
+        discount     INTEGER,
+        reserved_at  INTEGER NOT NULL
+    );
+
+    CREATE TABLE refunds (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id  INTEGER NOT NULL,
+        sequence    INTEGER NOT NULL,
+        operator_id TEXT NOT NULL,
+        amount      INTEGER NOT NULL,
+        currency    TEXT NOT NULL,
+        reason      TEXT NOT NULL,
+        issued_at   INTEGER NOT NULL,
+        UNIQUE (machine_id, sequence)
+    );
+    ",
+    // 9: the coins and bills a machine in strict mode takes, no rows takes any amount
+    "
+    CREATE TABLE accepted_denominations (
+        machine_id   INTEGER NOT NULL REFERENCES machines (id) ON DELETE CASCADE,
+        kind         TEXT NOT NULL,
+        denomination INTEGER NOT NULL,
+        PRIMARY KEY (machine_id, kind, denomination)
+    );
+    ",
+    // 10: ledger rows keyed by the incarnation of their machine, so a machine created again under
+    // a deleted machine's ID starts its sequence afresh without clashing with the kept rows
+    "
+    CREATE TABLE machine_incarnations (
+        machine_id  INTEGER PRIMARY KEY,
+        incarnation INTEGER NOT NULL
+    );
+
+    INSERT INTO machine_incarnations (machine_id, incarnation)
+        SELECT id, 1 FROM machines
+        UNION SELECT machine_id, 1 FROM sales
+        UNION SELECT machine_id, 1 FROM cash_collections
+        UNION SELECT machine_id, 1 FROM refunds;
+
+    CREATE TABLE sales_by_incarnation (
+        id           INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id   INTEGER NOT NULL,
+        incarnation  INTEGER NOT NULL,
+        sequence     INTEGER NOT NULL,
+        slot_id      INTEGER NOT NULL,
+        soda_id      INTEGER NOT NULL REFERENCES sodas (id),
+        price        INTEGER NOT NULL,
+        currency     TEXT NOT NULL,
+        sold_at      INTEGER NOT NULL,
+        promotion_id TEXT,
+        discount     INTEGER,
+        UNIQUE (machine_id, incarnation, sequence)
+    );
+
+    INSERT INTO sales_by_incarnation (id, machine_id, incarnation, sequence, slot_id, soda_id, price, currency, sold_at, promotion_id, discount)
+        SELECT id, machine_id, 1, sequence, slot_id, soda_id, price, currency, sold_at, promotion_id, discount FROM sales;
+    DROP TABLE sales;
+    ALTER TABLE sales_by_incarnation RENAME TO sales;
+
+    CREATE INDEX sales_by_soda ON sales (soda_id);
+    CREATE INDEX sales_by_time ON sales (sold_at);
+
+    CREATE TABLE cash_collections_by_incarnation (
+        id           INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id   INTEGER NOT NULL,
+        incarnation  INTEGER NOT NULL,
+        sequence     INTEGER NOT NULL,
+        operator_id  TEXT NOT NULL,
+        collected_at INTEGER NOT NULL,
+        period_start INTEGER,
+        revenue      INTEGER NOT NULL,
+        float        INTEGER NOT NULL,
+        counted      INTEGER NOT NULL,
+        currency     TEXT NOT NULL,
+        UNIQUE (machine_id, incarnation, sequence)
+    );
+
+    INSERT INTO cash_collections_by_incarnation (id, machine_id, incarnation, sequence, operator_id, collected_at, period_start, revenue, float, counted, currency)
+        SELECT id, machine_id, 1, sequence, operator_id, collected_at, period_start, revenue, float, counted, currency FROM cash_collections;
+    DROP TABLE cash_collections;
+    ALTER TABLE cash_collections_by_incarnation RENAME TO cash_collections;
+
+    CREATE TABLE refunds_by_incarnation (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        machine_id  INTEGER NOT NULL,
+        incarnation INTEGER NOT NULL,
+        sequence    INTEGER NOT NULL,
+        operator_id TEXT NOT NULL,
+        amount      INTEGER NOT NULL,
+        currency    TEXT NOT NULL,
+        reason      TEXT NOT NULL,
+        issued_at   INTEGER NOT NULL,
+        UNIQUE (machine_id, incarnation, sequence)
+    );
+
+    INSERT INTO refunds_by_incarnation (id, machine_id, incarnation, sequence, operator_id, amount, currency, reason, issued_at)
+        SELECT id, machine_id, 1, sequence, operator_id, amount, currency, reason, issued_at FROM refunds;
+    DROP TABLE refunds;
+    ALTER TABLE refunds_by_incarnation RENAME TO refunds;
+    ",
+    // 11: the part of the float a collection left in the cash box, nothing before collections kept it
+    "
+    ALTER TABLE cash_collections ADD COLUMN kept INTEGER NOT NULL DEFAULT 0;
+    ",
+];
+
+/// Gets how many migrations the database has run
+pub fn schema_version(connection: &Connection) -> rusqlite::Result<u32> {
+    connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the schema up to date, running each missing migration in its own transaction
+///
+/// # Returns
+/// * `Result<u32, String>` - Ok(version) the schema is at, Err if the database is newer than this code
+pub fn migrate(connection: &mut Connection) -> Result<u32, String> {
+    let current = schema_version(connection).map_err(|e| e.to_string())? as usize;
+
+    if current > MIGRATIONS.len() {
+        return Err(format!(
+            "Database schema version {} is newer than the supported version {}",
+            current,
+            MIGRATIONS.len()
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let transaction = connection.transaction().map_err(|e| e.to_string())?;
+        apply(&transaction, migration, index + 1).map_err(|e| format!("Migration {} failed: {}", index + 1, e))?;
+        transaction.commit().map_err(|e| e.to_string())?;
+    }
+
+    Ok(MIGRATIONS.len() as u32)
+}
+
+fn apply(transaction: &Transaction<'_>, migration: &str, version: usize) -> rusqlite::Result<()> {
+    transaction.execute_batch(migration)?;
+    transaction.pragma_update(None, "user_version", version as i64)
+}